@@ -0,0 +1,355 @@
+use std::{any::Any, collections::HashMap};
+
+/// The result of ticking a behavior tree node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Status {
+    Success,
+    Failure,
+    /// The node has not finished yet, and is ticked again on the next tick of the tree.
+    Running,
+}
+
+/// Values shared between the nodes of a behavior tree, such as the current target of an NPC.
+#[derive(Default)]
+pub struct Blackboard {
+    values: HashMap<String, Box<dyn Any>>,
+}
+
+impl Blackboard {
+    /// The value for `key`, or `None` if there is no value or it is not a `T`.
+    pub fn get<T: 'static>(&self, key: &str) -> Option<&T> {
+        self.values.get(key)?.downcast_ref()
+    }
+
+    pub fn get_mut<T: 'static>(&mut self, key: &str) -> Option<&mut T> {
+        self.values.get_mut(key)?.downcast_mut()
+    }
+
+    pub fn set<T: 'static>(&mut self, key: &str, value: T) {
+        self.values.insert(key.to_string(), Box::new(value));
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        self.values.remove(key);
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+}
+
+type ActionFn<C> = Box<dyn FnMut(&mut C, &mut Blackboard) -> Status>;
+type ConditionFn<C> = Box<dyn FnMut(&C, &Blackboard) -> bool>;
+
+/// A node of a behavior tree that acts on a context of type `C`, such as the NPC being
+/// controlled.
+pub struct Node<C> {
+    kind: NodeKind<C>,
+}
+
+enum NodeKind<C> {
+    Action(ActionFn<C>),
+    Condition(ConditionFn<C>),
+    Sequence {
+        children: Vec<Node<C>>,
+        current: usize,
+    },
+    Selector {
+        children: Vec<Node<C>>,
+        current: usize,
+    },
+    Invert(Box<Node<C>>),
+    Succeed(Box<Node<C>>),
+    Repeat {
+        child: Box<Node<C>>,
+        count: u32,
+        done: u32,
+    },
+}
+
+impl<C> Node<C> {
+    /// Does some work, returning [`Status::Running`] until it has finished.
+    pub fn action<F: FnMut(&mut C, &mut Blackboard) -> Status + 'static>(f: F) -> Self {
+        Self {
+            kind: NodeKind::Action(Box::new(f)),
+        }
+    }
+
+    /// Succeeds if `f` returns true and fails otherwise.
+    pub fn condition<F: FnMut(&C, &Blackboard) -> bool + 'static>(f: F) -> Self {
+        Self {
+            kind: NodeKind::Condition(Box::new(f)),
+        }
+    }
+
+    /// Ticks its children in order until one fails, and succeeds if they all succeed. A running
+    /// child is resumed on the next tick rather than starting from the first child again.
+    pub fn sequence(children: Vec<Node<C>>) -> Self {
+        Self {
+            kind: NodeKind::Sequence {
+                children,
+                current: 0,
+            },
+        }
+    }
+
+    /// Ticks its children in order until one succeeds, and fails if they all fail. A running
+    /// child is resumed on the next tick rather than starting from the first child again.
+    pub fn selector(children: Vec<Node<C>>) -> Self {
+        Self {
+            kind: NodeKind::Selector {
+                children,
+                current: 0,
+            },
+        }
+    }
+
+    /// Swaps the success and failure of `child`.
+    pub fn invert(child: Node<C>) -> Self {
+        Self {
+            kind: NodeKind::Invert(Box::new(child)),
+        }
+    }
+
+    /// Succeeds whether `child` succeeds or fails.
+    pub fn succeed(child: Node<C>) -> Self {
+        Self {
+            kind: NodeKind::Succeed(Box::new(child)),
+        }
+    }
+
+    /// Runs `child` to completion `count` times, failing as soon as it fails.
+    pub fn repeat(count: u32, child: Node<C>) -> Self {
+        Self {
+            kind: NodeKind::Repeat {
+                child: Box::new(child),
+                count,
+                done: 0,
+            },
+        }
+    }
+
+    fn tick(&mut self, context: &mut C, blackboard: &mut Blackboard) -> Status {
+        match self.kind {
+            NodeKind::Action(ref mut action) => action(context, blackboard),
+            NodeKind::Condition(ref mut condition) => match condition(context, blackboard) {
+                true => Status::Success,
+                false => Status::Failure,
+            },
+            NodeKind::Sequence {
+                ref mut children,
+                ref mut current,
+            } => tick_composite(children, current, Status::Success, context, blackboard),
+            NodeKind::Selector {
+                ref mut children,
+                ref mut current,
+            } => tick_composite(children, current, Status::Failure, context, blackboard),
+            NodeKind::Invert(ref mut child) => match child.tick(context, blackboard) {
+                Status::Success => Status::Failure,
+                Status::Failure => Status::Success,
+                Status::Running => Status::Running,
+            },
+            NodeKind::Succeed(ref mut child) => match child.tick(context, blackboard) {
+                Status::Running => Status::Running,
+                _ => Status::Success,
+            },
+            NodeKind::Repeat {
+                ref mut child,
+                count,
+                ref mut done,
+            } => {
+                while *done < count {
+                    match child.tick(context, blackboard) {
+                        Status::Success => *done += 1,
+                        Status::Failure => {
+                            *done = 0;
+                            return Status::Failure;
+                        }
+                        Status::Running => return Status::Running,
+                    }
+                }
+                *done = 0;
+                Status::Success
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        match self.kind {
+            NodeKind::Action(_) | NodeKind::Condition(_) => {}
+            NodeKind::Sequence {
+                ref mut children,
+                ref mut current,
+            }
+            | NodeKind::Selector {
+                ref mut children,
+                ref mut current,
+            } => {
+                *current = 0;
+                children.iter_mut().for_each(Node::reset);
+            }
+            NodeKind::Invert(ref mut child) | NodeKind::Succeed(ref mut child) => child.reset(),
+            NodeKind::Repeat {
+                ref mut child,
+                ref mut done,
+                ..
+            } => {
+                *done = 0;
+                child.reset();
+            }
+        }
+    }
+}
+
+/// Ticks children from `current` while they return `keep_going`, which is what the composite
+/// returns if every child does.
+fn tick_composite<C>(
+    children: &mut [Node<C>],
+    current: &mut usize,
+    keep_going: Status,
+    context: &mut C,
+    blackboard: &mut Blackboard,
+) -> Status {
+    while let Some(child) = children.get_mut(*current) {
+        match child.tick(context, blackboard) {
+            Status::Running => return Status::Running,
+            status if status == keep_going => *current += 1,
+            status => {
+                *current = 0;
+                return status;
+            }
+        }
+    }
+
+    *current = 0;
+    keep_going
+}
+
+/// A behavior tree and the blackboard its nodes share, which the game ticks with the context it
+/// acts on, e.g. once per update.
+pub struct BehaviorTree<C> {
+    root: Node<C>,
+    blackboard: Blackboard,
+}
+
+impl<C> BehaviorTree<C> {
+    pub fn new(root: Node<C>) -> Self {
+        Self {
+            root,
+            blackboard: Blackboard::default(),
+        }
+    }
+
+    pub fn tick(&mut self, context: &mut C) -> Status {
+        self.root.tick(context, &mut self.blackboard)
+    }
+
+    /// Abandons any running nodes, so that the next tick starts from the root.
+    pub fn reset(&mut self) {
+        self.root.reset();
+    }
+
+    pub fn blackboard(&self) -> &Blackboard {
+        &self.blackboard
+    }
+
+    pub fn blackboard_mut(&mut self) -> &mut Blackboard {
+        &mut self.blackboard
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Default)]
+    struct Npc {
+        health: u32,
+        steps: u32,
+        log: Vec<&'static str>,
+    }
+
+    fn walk(steps: u32) -> Node<Npc> {
+        Node::action(move |npc: &mut Npc, _: &mut Blackboard| {
+            npc.steps += 1;
+            if npc.steps < steps {
+                Status::Running
+            } else {
+                npc.log.push("arrived");
+                Status::Success
+            }
+        })
+    }
+
+    fn log(message: &'static str) -> Node<Npc> {
+        Node::action(move |npc: &mut Npc, _: &mut Blackboard| {
+            npc.log.push(message);
+            Status::Success
+        })
+    }
+
+    #[test]
+    fn sequence_resumes_running_child() {
+        let mut tree = BehaviorTree::new(Node::sequence(vec![log("start"), walk(3), log("done")]));
+        let mut npc = Npc::default();
+
+        assert_eq!(tree.tick(&mut npc), Status::Running);
+        assert_eq!(tree.tick(&mut npc), Status::Running);
+        assert_eq!(tree.tick(&mut npc), Status::Success);
+        assert_eq!(npc.log, ["start", "arrived", "done"]);
+    }
+
+    #[test]
+    fn selector_falls_back_when_condition_fails() {
+        let low_health = Node::condition(|npc: &Npc, _: &Blackboard| npc.health < 10);
+        let mut tree = BehaviorTree::new(Node::selector(vec![
+            Node::sequence(vec![low_health, log("flee")]),
+            log("fight"),
+        ]));
+
+        let mut npc = Npc {
+            health: 50,
+            ..Default::default()
+        };
+        assert_eq!(tree.tick(&mut npc), Status::Success);
+        npc.health = 5;
+        assert_eq!(tree.tick(&mut npc), Status::Success);
+        assert_eq!(npc.log, ["fight", "flee"]);
+    }
+
+    #[test]
+    fn decorators_change_status() {
+        let fail = || Node::condition(|_: &Npc, _: &Blackboard| false);
+        let mut npc = Npc::default();
+
+        assert_eq!(
+            BehaviorTree::new(Node::invert(fail())).tick(&mut npc),
+            Status::Success
+        );
+        assert_eq!(
+            BehaviorTree::new(Node::succeed(fail())).tick(&mut npc),
+            Status::Success
+        );
+        assert_eq!(
+            BehaviorTree::new(Node::repeat(3, log("hit"))).tick(&mut npc),
+            Status::Success
+        );
+        assert_eq!(npc.log, ["hit", "hit", "hit"]);
+    }
+
+    #[test]
+    fn blackboard_is_shared_between_nodes() {
+        let mut tree = BehaviorTree::new(Node::sequence(vec![
+            Node::action(|_: &mut Npc, blackboard: &mut Blackboard| {
+                blackboard.set("target", 7_u32);
+                Status::Success
+            }),
+            Node::condition(|_: &Npc, blackboard: &Blackboard| {
+                blackboard.get::<u32>("target") == Some(&7)
+            }),
+        ]));
+
+        assert_eq!(tree.tick(&mut Npc::default()), Status::Success);
+        assert_eq!(tree.blackboard().get::<i32>("target"), None);
+    }
+}
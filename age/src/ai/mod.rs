@@ -0,0 +1,8 @@
+//! Scaffolding for NPC logic: finite state machines and behavior trees, both ticked by the game
+//! with the state they act on.
+
+pub use behavior_tree::*;
+pub use state_machine::*;
+
+mod behavior_tree;
+mod state_machine;
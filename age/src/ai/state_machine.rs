@@ -0,0 +1,193 @@
+use std::{collections::HashMap, hash::Hash};
+
+type EnterFn<C> = Box<dyn FnMut(&mut C)>;
+type UpdateFn<S, C> = Box<dyn FnMut(&mut C, f32) -> Option<S>>;
+
+struct StateHandlers<S, C> {
+    on_enter: Option<EnterFn<C>>,
+    on_update: Option<UpdateFn<S, C>>,
+    on_exit: Option<EnterFn<C>>,
+}
+
+impl<S, C> Default for StateHandlers<S, C> {
+    fn default() -> Self {
+        Self {
+            on_enter: None,
+            on_update: None,
+            on_exit: None,
+        }
+    }
+}
+
+/// Builds a [`StateMachine`] over states of type `S`, such as a fieldless enum, whose handlers act
+/// on a context of type `C`, such as the NPC being controlled.
+pub struct StateMachineBuilder<S, C> {
+    initial: S,
+    handlers: HashMap<S, StateHandlers<S, C>>,
+}
+
+impl<S: Copy + Eq + Hash, C> StateMachineBuilder<S, C> {
+    pub fn new(initial: S) -> Self {
+        Self {
+            initial,
+            handlers: HashMap::new(),
+        }
+    }
+
+    /// Called when the machine changes into `state`, including when it first starts in it.
+    pub fn on_enter<F: FnMut(&mut C) + 'static>(mut self, state: S, f: F) -> Self {
+        self.handlers.entry(state).or_default().on_enter = Some(Box::new(f));
+        self
+    }
+
+    /// Called on every update while the machine is in `state`, with the time since the last
+    /// update in seconds. Returning a state changes into it.
+    pub fn on_update<F: FnMut(&mut C, f32) -> Option<S> + 'static>(
+        mut self,
+        state: S,
+        f: F,
+    ) -> Self {
+        self.handlers.entry(state).or_default().on_update = Some(Box::new(f));
+        self
+    }
+
+    /// Called when the machine changes out of `state`.
+    pub fn on_exit<F: FnMut(&mut C) + 'static>(mut self, state: S, f: F) -> Self {
+        self.handlers.entry(state).or_default().on_exit = Some(Box::new(f));
+        self
+    }
+
+    pub fn build(self) -> StateMachine<S, C> {
+        StateMachine {
+            current: self.initial,
+            started: false,
+            time_in_state: 0.0,
+            handlers: self.handlers,
+        }
+    }
+}
+
+/// A finite state machine, which runs the handlers of its current state each time it is updated.
+pub struct StateMachine<S, C> {
+    current: S,
+    started: bool,
+    time_in_state: f32,
+    handlers: HashMap<S, StateHandlers<S, C>>,
+}
+
+impl<S: Copy + Eq + Hash, C> StateMachine<S, C> {
+    pub fn builder(initial: S) -> StateMachineBuilder<S, C> {
+        StateMachineBuilder::new(initial)
+    }
+
+    pub fn current(&self) -> S {
+        self.current
+    }
+
+    /// How long the machine has been in its current state, in seconds of update time.
+    pub fn time_in_state(&self) -> f32 {
+        self.time_in_state
+    }
+
+    /// Runs the current state's update handler, changing state if it returns one. The initial
+    /// state is entered on the first update.
+    pub fn update(&mut self, context: &mut C, dt: f32) {
+        if !self.started {
+            self.started = true;
+            self.enter(context);
+        }
+
+        self.time_in_state += dt;
+        let next = self
+            .handlers
+            .get_mut(&self.current)
+            .and_then(|handlers| handlers.on_update.as_mut())
+            .and_then(|on_update| on_update(context, dt));
+
+        if let Some(next) = next {
+            self.transition(context, next);
+        }
+    }
+
+    /// Changes into `state`, running the exit handler of the current state and the enter handler
+    /// of the new one, even if they are the same state.
+    pub fn transition(&mut self, context: &mut C, state: S) {
+        if self.started {
+            if let Some(on_exit) = self
+                .handlers
+                .get_mut(&self.current)
+                .and_then(|handlers| handlers.on_exit.as_mut())
+            {
+                on_exit(context);
+            }
+        }
+
+        self.current = state;
+        self.started = true;
+        self.time_in_state = 0.0;
+        self.enter(context);
+    }
+
+    fn enter(&mut self, context: &mut C) {
+        if let Some(on_enter) = self
+            .handlers
+            .get_mut(&self.current)
+            .and_then(|handlers| handlers.on_enter.as_mut())
+        {
+            on_enter(context);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum Guard {
+        Patrol,
+        Chase,
+    }
+
+    #[derive(Default)]
+    struct Npc {
+        sees_player: bool,
+        log: Vec<&'static str>,
+    }
+
+    fn guard() -> StateMachine<Guard, Npc> {
+        StateMachine::builder(Guard::Patrol)
+            .on_enter(Guard::Patrol, |npc: &mut Npc| npc.log.push("enter patrol"))
+            .on_update(Guard::Patrol, |npc: &mut Npc, _| {
+                npc.sees_player.then_some(Guard::Chase)
+            })
+            .on_exit(Guard::Patrol, |npc: &mut Npc| npc.log.push("exit patrol"))
+            .on_enter(Guard::Chase, |npc: &mut Npc| npc.log.push("enter chase"))
+            .build()
+    }
+
+    #[test]
+    fn initial_state_is_entered_on_first_update() {
+        let mut npc = Npc::default();
+        let mut fsm = guard();
+        assert!(npc.log.is_empty());
+
+        fsm.update(&mut npc, 0.5);
+        fsm.update(&mut npc, 0.5);
+        assert_eq!(npc.log, ["enter patrol"]);
+        assert_eq!(fsm.time_in_state(), 1.0);
+    }
+
+    #[test]
+    fn update_handler_changes_state() {
+        let mut npc = Npc::default();
+        let mut fsm = guard();
+        fsm.update(&mut npc, 0.5);
+
+        npc.sees_player = true;
+        fsm.update(&mut npc, 0.5);
+        assert_eq!(fsm.current(), Guard::Chase);
+        assert_eq!(fsm.time_in_state(), 0.0);
+        assert_eq!(npc.log, ["enter patrol", "exit patrol", "enter chase"]);
+    }
+}
@@ -1,47 +1,260 @@
+use std::{
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+
 use crate::{
+    crash, cvars,
     error::Error,
+    frame_dump::{FrameDump, DUMP_FRAME_SEED, DUMP_FRAME_TIMESTEP},
     graphics::{Graphics, View},
-    renderer::{Renderer, Surface},
-    sys::{Event, Sys},
-    Engine, Game,
+    pacing::FramePacer,
+    renderer::{CompositeAlphaMode, GpuCapabilities, GpuPreference, Renderer, Surface},
+    sys::{Event, GamepadInput, Sys},
+    AppMetadata, Color, Engine, Game,
 };
 
-pub(crate) fn run<G: Game>() -> Result<(), Error> {
+#[derive(Debug, Clone, Default)]
+pub struct AppBuilder {
+    metadata: AppMetadata,
+    crash_handler: bool,
+    gpu_preference: GpuPreference,
+    transparent: bool,
+    composite_alpha: CompositeAlphaMode,
+    frame_pacing: bool,
+    low_latency_input: bool,
+    parallel_encoding: bool,
+}
+
+impl AppBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Names the game, which is used for the window title and the directories that config,
+    /// saves and crash logs are kept in. `organization` and `version` may be empty.
+    pub fn with_app_metadata(mut self, name: &str, organization: &str, version: &str) -> Self {
+        self.metadata = AppMetadata::new(name, organization, version);
+        self
+    }
+
+    /// When enabled, panics are written to a crash log along with a backtrace, so that crashes
+    /// experienced by players can be reported.
+    pub fn with_crash_handler(mut self, enabled: bool) -> Self {
+        self.crash_handler = enabled;
+        self
+    }
+
+    pub fn with_gpu_preference(mut self, preference: GpuPreference) -> Self {
+        self.gpu_preference = preference;
+        self
+    }
+
+    /// When enabled, the window is cleared to transparent rather than blue, so that anything not
+    /// drawn to the backbuffer shows the desktop behind the window.
+    pub fn with_transparency(mut self, enabled: bool) -> Self {
+        self.transparent = enabled;
+        self
+    }
+
+    pub fn with_composite_alpha(mut self, mode: CompositeAlphaMode) -> Self {
+        self.composite_alpha = mode;
+        self
+    }
+
+    /// When enabled, frames are presented without waiting for the display and are started one
+    /// refresh of the window's monitor apart, which reduces input latency while avoiding judder.
+    pub fn with_frame_pacing(mut self, enabled: bool) -> Self {
+        self.frame_pacing = enabled;
+        self
+    }
+
+    /// When enabled along with frame pacing, input keeps being handled while waiting for the next
+    /// frame rather than the wait holding it back until after the frame, so each frame sees input
+    /// that arrived right up until it started.
+    pub fn with_low_latency_input(mut self, enabled: bool) -> Self {
+        self.low_latency_input = enabled;
+        self
+    }
+
+    /// When enabled, the draws for each draw target are encoded on their own thread, which can
+    /// shorten frames that draw to many targets, such as split-screen views with a minimap and
+    /// post-processing.
+    pub fn with_parallel_encoding(mut self, enabled: bool) -> Self {
+        self.parallel_encoding = enabled;
+        self
+    }
+
+    /// The graphics adapters that can be selected with [`GpuPreference::ByName`].
+    pub fn available_adapters() -> Vec<GpuCapabilities> {
+        Renderer::available_adapters()
+    }
+
+    pub fn run<G: Game>(self) -> ExitCode {
+        if self.crash_handler {
+            crash::install_handler(&self.metadata);
+        }
+
+        match run::<G>(&self) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("{err}");
+                ExitCode::FAILURE
+            }
+        }
+    }
+}
+
+fn run<G: Game>(builder: &AppBuilder) -> Result<(), Error> {
     let width = 1920;
     let height = 1080;
     let sys = Sys::init()?;
-    let window = sys.create_window(width, height)?;
-    let mut renderer = Renderer::new()?;
-    let mut surface = Surface::default();
+    let window = sys.create_window(
+        width,
+        height,
+        &builder.metadata.title(),
+        builder.transparent,
+    )?;
+    let mut renderer = Renderer::new(&builder.gpu_preference)?;
+    renderer.set_parallel_encoding(builder.parallel_encoding);
+    let clear_color = if builder.transparent {
+        Color::TRANSPARENT
+    } else {
+        Color::BLUE
+    };
+    let mut surface = Surface::new(builder.composite_alpha, clear_color, builder.frame_pacing);
+    let mut pacer = FramePacer::default();
     let backbuffer = renderer.create_backbuffer(width, height);
     let graphics = Graphics::new(&mut renderer, View::new(width, height));
+    renderer.mark_engine_resources();
 
-    let mut age = Engine::new(renderer, graphics);
+    let mut age = Engine::new(window.clone(), renderer, graphics, builder.metadata.clone());
+    let mut frame_dump = FrameDump::from_env();
+    if frame_dump.is_some() {
+        age.fixed_timestep = Some(DUMP_FRAME_TIMESTEP);
+        age.random_seed = DUMP_FRAME_SEED;
+    }
     let mut game = G::on_start(&mut age)?;
+    let mut last_frame_start: Option<Instant> = None;
+    let mut gamepad_input = GamepadInput::new();
 
     sys.run(|event, platform| {
         match event {
             Event::ExitRequested => game.on_exit_requested(&mut age),
 
+            Event::MouseInput { .. } | Event::MouseMoved { .. } | Event::MouseScrolled(_) => {
+                age.input_history.record(&event);
+                age.mouse.on_event(&event);
+            }
+
+            Event::ScaleFactorChanged(_) => {
+                // The window may have moved to a monitor with a different refresh rate.
+                pacer.set_refresh_rate(window.refresh_rate());
+                age.input_history.record(&event);
+                age.mouse.on_event(&event);
+                age.touches.on_event(&event);
+            }
+
+            Event::Touch { .. } => age.touches.on_event(&event),
+
+            Event::KeyboardInput(ref input) => {
+                age.input_history.record(&event);
+                age.keyboard.on_event(&event);
+                if let Some(key) = input.key.as_ref().filter(|_| input.pressed) {
+                    game.on_key_pressed(&mut age, key, input.repeat);
+                }
+                if !input.text.is_empty() {
+                    game.on_text_entered(&mut age, &input.text);
+                }
+            }
+
+            Event::Resized { width, height } => {
+                // Redraw straight away, as during a live resize the platform may not otherwise
+                // redraw until the resize has finished.
+                surface.resize(width, height);
+                pacer.set_refresh_rate(window.refresh_rate());
+                window.request_redraw();
+                game.on_size_changed(&mut age, width, height);
+            }
+
+            Event::FocusLost => age.keyboard.on_event(&event),
+
+            Event::WakeUp => window.request_redraw(),
+
             Event::PlatformReady => {
                 surface.init(&age.renderer, &window)?;
+                age.renderer.probe_surface(&surface);
+                pacer.set_refresh_rate(window.refresh_rate());
                 window.set_visible(true);
             }
 
+            Event::PlatformSuspended => surface.suspend(),
+
             Event::Update => {
+                if builder.frame_pacing {
+                    pacer.wait();
+                }
+                let frame_start = Instant::now();
+                age.advance_clock(
+                    last_frame_start.map_or(Duration::ZERO, |last| frame_start - last),
+                );
+                last_frame_start = Some(frame_start);
+                age.input_history.begin_frame(frame_start);
                 age.graphics.set_draw_target(&backbuffer);
                 age.graphics.set_view(age.graphics.get_default_view());
+                if let Some(view) = age.transitioned_view() {
+                    age.graphics.set_view(view);
+                }
+                age.network.poll();
+                age.keyboard.update();
+                age.mouse.update();
+                for (id, event) in gamepad_input.poll() {
+                    age.gamepads.on_event(id, &event);
+                    game.on_gamepad_event(&mut age, id, &event);
+                }
                 game.on_update(&mut age);
+                apply_cvars(&mut age, &mut surface);
+                let dump_readback = frame_dump.as_ref().map(|_| age.graphics.next_readback_id());
+                let mouse = age.mouse.position_physical();
+                age.renderer.set_zoom_focus(
+                    mouse.x / window.width().max(1) as f32,
+                    mouse.y / window.height().max(1) as f32,
+                );
                 age.renderer.submit(
                     age.graphics.data(),
                     age.graphics.draws().clone(),
                     &backbuffer,
+                    dump_readback,
                     &mut surface,
                 );
                 window.pre_present();
+                let presenting = Instant::now();
                 surface.present();
-                window.post_present();
-                age.graphics.reset();
+                let mut stats = age.renderer.frame_stats();
+                stats.present += presenting.elapsed();
+                stats.frame = frame_start.elapsed();
+                age.record_frame_stats(stats);
+                if let (Some(dump), Some(id)) = (frame_dump.as_mut(), dump_readback) {
+                    age.renderer.wait_for_texture_readbacks();
+                    let written = match age.renderer.take_texture_readback(id) {
+                        Some(image) => dump.write(&image),
+                        None => Err(Error::new("frame was not read back for dumping")),
+                    };
+                    if let Err(err) = written {
+                        eprintln!("{err}");
+                    }
+                }
+                match pacer.next_frame() {
+                    Some(next) if builder.frame_pacing && builder.low_latency_input => {
+                        platform.wake_at(next - pacer.wake_margin());
+                    }
+                    _ => window.post_present(),
+                }
+                age.graphics.reset(&mut age.renderer);
+                age.mouse.reset();
+                age.touches.reset();
+                age.keyboard.reset();
+                age.gamepads.reset();
             }
         };
 
@@ -52,5 +265,24 @@ pub(crate) fn run<G: Game>() -> Result<(), Error> {
         Ok(())
     })?;
 
+    if cfg!(debug_assertions) {
+        if let Some(report) = age.renderer.live_resources_report() {
+            eprintln!("{report}");
+        }
+    }
+
     Ok(())
 }
+
+/// Applies the engine's own cvars that have changed since the last frame.
+fn apply_cvars(age: &mut Engine, surface: &mut Surface) {
+    for name in age.cvars.take_changed() {
+        match name.as_str() {
+            cvars::RENDER_VSYNC => surface.set_vsync(age.cvars.get(&name).unwrap_or(true)),
+            cvars::RENDER_UI_ZOOM => age
+                .renderer
+                .set_ui_zoom(age.cvars.get(&name).unwrap_or(1.0)),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use crate::{
+    graphics::{MaterialId, TextureOptions},
+    renderer::Renderer,
+    Error, ErrorKind, Graphics, Image, Sprite, Texture,
+};
+
+/// Where a rectangle was placed by a [`RectPacker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PackedRect {
+    pub page: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Places rectangles on fixed size pages, starting a new page when one is full. Only positions are
+/// tracked, so that anything that needs packing, such as texture atlases, lightmaps or glyph caches,
+/// can keep the contents in its own storage.
+#[derive(Debug, Clone)]
+pub struct RectPacker {
+    page_width: u32,
+    page_height: u32,
+    padding: u32,
+    pages: Vec<Skyline>,
+}
+
+impl RectPacker {
+    pub fn new(page_width: u32, page_height: u32) -> Self {
+        Self {
+            page_width,
+            page_height,
+            padding: 0,
+            pages: Vec::new(),
+        }
+    }
+
+    /// Leaves `padding` pixels between packed rectangles, e.g. to stop texture filtering from
+    /// bleeding between neighbouring images.
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn page_width(&self) -> u32 {
+        self.page_width
+    }
+
+    pub fn page_height(&self) -> u32 {
+        self.page_height
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Places a single rectangle, or returns `None` if it is larger than a page.
+    pub fn pack(&mut self, width: u32, height: u32) -> Option<PackedRect> {
+        let (padded_width, padded_height) = (width + self.padding, height + self.padding);
+        if padded_width > self.page_width || padded_height > self.page_height {
+            return None;
+        }
+
+        let placed = self
+            .pages
+            .iter_mut()
+            .enumerate()
+            .find_map(|(page, skyline)| {
+                skyline
+                    .insert(padded_width, padded_height)
+                    .map(|(x, y)| (page, x, y))
+            });
+
+        let (page, x, y) = match placed {
+            Some(placed) => placed,
+            None => {
+                let mut skyline = Skyline::new(self.page_width, self.page_height);
+                let (x, y) = skyline.insert(padded_width, padded_height)?;
+                self.pages.push(skyline);
+                (self.pages.len() - 1, x, y)
+            }
+        };
+
+        Some(PackedRect {
+            page,
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+
+    /// Places every rectangle, tallest first as that packs more tightly, and returns where each
+    /// one was placed in the order they were given.
+    pub fn pack_all(&mut self, sizes: &[(u32, u32)]) -> Vec<Option<PackedRect>> {
+        let mut order = (0..sizes.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| std::cmp::Reverse((sizes[i].1, sizes[i].0)));
+
+        let mut packed = vec![None; sizes.len()];
+        for i in order {
+            packed[i] = self.pack(sizes[i].0, sizes[i].1);
+        }
+
+        packed
+    }
+}
+
+/// Packs images into atlas pages that are created directly on the GPU, without first copying the
+/// images into pages in memory.
+pub struct AtlasBuilder<'a> {
+    packer: RectPacker,
+    images: Vec<&'a Image>,
+}
+
+impl<'a> AtlasBuilder<'a> {
+    pub fn new(page_width: u32, page_height: u32) -> Self {
+        Self {
+            packer: RectPacker::new(page_width, page_height),
+            images: Vec::new(),
+        }
+    }
+
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.packer = self.packer.with_padding(padding);
+        self
+    }
+
+    /// Adds an image to the atlas, returning its index in [`Atlas::regions`].
+    pub fn add(&mut self, image: &'a Image) -> usize {
+        self.images.push(image);
+        self.images.len() - 1
+    }
+
+    /// Packs the images and uploads each one to its place in the atlas pages.
+    pub fn upload(
+        mut self,
+        renderer: &mut Renderer,
+        graphics: &mut Graphics,
+        options: &TextureOptions,
+    ) -> Result<Atlas, Error> {
+        let sizes = self
+            .images
+            .iter()
+            .map(|image| (image.width(), image.height()))
+            .collect::<Vec<_>>();
+
+        let regions = self
+            .packer
+            .pack_all(&sizes)
+            .into_iter()
+            .zip(sizes)
+            .map(|(region, (width, height))| {
+                region.ok_or_else(|| {
+                    Error::new(format!(
+                        "a {}x{} image does not fit on a {}x{} atlas page",
+                        width,
+                        height,
+                        self.packer.page_width(),
+                        self.packer.page_height()
+                    ))
+                    .with_kind(ErrorKind::Asset)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let pages = (0..self.packer.page_count())
+            .map(|_| {
+                graphics.create_texture(
+                    renderer,
+                    self.packer.page_width(),
+                    self.packer.page_height(),
+                    options,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        for (image, region) in self.images.iter().zip(regions.iter()) {
+            renderer.write_texture_region(
+                pages[region.page].texture(),
+                (region.x, region.y),
+                (region.width, region.height),
+                image.pixels(),
+            )?;
+        }
+
+        Ok(Atlas { pages, regions })
+    }
+}
+
+/// Atlas pages on the GPU and where each image was placed on them.
+#[derive(Debug, Clone)]
+pub struct Atlas {
+    pub pages: Vec<Texture>,
+    pub regions: Vec<PackedRect>,
+}
+
+impl Atlas {
+    /// Creates a sprite that draws the image at `index`.
+    pub fn sprite(&self, renderer: &mut Renderer, index: usize, material: MaterialId) -> Sprite {
+        let region = self.regions[index];
+        Sprite::from_texture_region(
+            renderer,
+            &self.pages[region.page],
+            (region.x, region.y),
+            (region.width, region.height),
+            material,
+        )
+    }
+}
+
+/// The top edge of the packed rectangles on a page, as segments from left to right.
+#[derive(Debug, Clone)]
+struct Skyline {
+    width: u32,
+    height: u32,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+impl Skyline {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            segments: vec![Segment { x: 0, y: 0, width }],
+        }
+    }
+
+    /// Places a rectangle as low as possible, then as far left as possible.
+    fn insert(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let (i, y) = (0..self.segments.len())
+            .filter_map(|i| self.fit(i, width, height).map(|y| (i, y)))
+            .min_by_key(|&(i, y)| (y + height, self.segments[i].x))?;
+
+        let x = self.segments[i].x;
+        self.segments.insert(
+            i,
+            Segment {
+                x,
+                y: y + height,
+                width,
+            },
+        );
+
+        // Trim the segments that are now underneath the new one.
+        let right = x + width;
+        while let Some(next) = self.segments.get_mut(i + 1) {
+            if next.x >= right {
+                break;
+            }
+
+            let overlap = right - next.x;
+            if next.width <= overlap {
+                self.segments.remove(i + 1);
+            } else {
+                next.x += overlap;
+                next.width -= overlap;
+                break;
+            }
+        }
+
+        // Merge neighbouring segments at the same height.
+        self.segments.dedup_by(|next, prev| {
+            let merge = next.y == prev.y;
+            if merge {
+                prev.width += next.width;
+            }
+            merge
+        });
+
+        Some((x, y))
+    }
+
+    /// The height a rectangle would be placed at if its left edge is at the start of segment `i`.
+    fn fit(&self, i: usize, width: u32, height: u32) -> Option<u32> {
+        let x = self.segments[i].x;
+        if x + width > self.width {
+            return None;
+        }
+
+        let mut y = 0;
+        let mut remaining = width;
+        for segment in self.segments[i..].iter() {
+            y = y.max(segment.y);
+            if y + height > self.height {
+                return None;
+            }
+            if segment.width >= remaining {
+                return Some(y);
+            }
+            remaining -= segment.width;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn overlaps(a: &PackedRect, b: &PackedRect) -> bool {
+        a.page == b.page
+            && a.x < b.x + b.width
+            && b.x < a.x + a.width
+            && a.y < b.y + b.height
+            && b.y < a.y + a.height
+    }
+
+    #[test]
+    fn packed_rects_do_not_overlap() {
+        let sizes = [
+            (10, 20),
+            (30, 10),
+            (16, 16),
+            (8, 8),
+            (20, 5),
+            (32, 32),
+            (7, 13),
+        ];
+        let mut packer = RectPacker::new(64, 64);
+        let packed = packer
+            .pack_all(&sizes)
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+
+        assert_eq!(packed.len(), sizes.len());
+        for (i, a) in packed.iter().enumerate() {
+            assert!(a.x + a.width <= 64 && a.y + a.height <= 64);
+            for b in packed[i + 1..].iter() {
+                assert!(!overlaps(a, b), "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn full_page_starts_new_page() {
+        let mut packer = RectPacker::new(16, 16);
+        assert_eq!(packer.pack(16, 16).unwrap().page, 0);
+        assert_eq!(packer.pack(8, 8).unwrap().page, 1);
+        assert_eq!(packer.page_count(), 2);
+    }
+
+    #[test]
+    fn rect_larger_than_page_is_not_packed() {
+        let mut packer = RectPacker::new(16, 16).with_padding(1);
+        assert!(packer.pack(16, 8).is_none());
+        assert_eq!(packer.pack(15, 8).map(|r| (r.x, r.y)), Some((0, 0)));
+        assert_eq!(packer.pack(15, 4).map(|r| (r.x, r.y)), Some((0, 9)));
+    }
+}
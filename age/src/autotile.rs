@@ -0,0 +1,163 @@
+use crate::math::{v2i, Vec2i};
+
+/// The tile variants that a [`TerrainSet`] chooses between.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TerrainLayout {
+    /// 16 variants, chosen by which of the four edge neighbors are the same terrain. Variant `i`
+    /// has an edge neighbor of the same terrain above if `i & 1`, to the right if `i & 2`, below
+    /// if `i & 4` and to the left if `i & 8`.
+    #[default]
+    Bitmask16,
+    /// 47 variants, which also take the corner neighbors into account where both edges next to
+    /// the corner are the same terrain. The variants are ordered by their neighbor mask, see
+    /// [`neighbor_mask`], with corners that make no difference cleared.
+    Blob47,
+}
+
+impl TerrainLayout {
+    pub fn variants(&self) -> usize {
+        match self {
+            TerrainLayout::Bitmask16 => 16,
+            TerrainLayout::Blob47 => 47,
+        }
+    }
+}
+
+/// The eight neighbors of a cell, in the order of their bits in a neighbor mask: above, above
+/// right, right, below right, below, below left, left and above left.
+const NEIGHBORS: [(i32, i32); 8] = [
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+];
+
+/// Which of the eight neighbors of `cell` are the same terrain, with above as bit 0 and the rest
+/// following clockwise.
+pub fn neighbor_mask<F: Fn(Vec2i) -> bool>(cell: Vec2i, is_terrain: F) -> u8 {
+    NEIGHBORS
+        .iter()
+        .enumerate()
+        .filter(|(_, &(x, y))| is_terrain(cell + v2i(x, y)))
+        .fold(0, |mask, (bit, _)| mask | 1 << bit)
+}
+
+/// Clears the corners of `mask` that do not have both of their edges set.
+fn blob_mask(mask: u8) -> u8 {
+    let edge = |bit: u8| mask & (1 << (bit % 8)) != 0;
+    (0..8u8).fold(0, |blob, bit| {
+        let set = match bit % 2 {
+            0 => edge(bit),
+            _ => edge(bit) && edge(bit - 1) && edge(bit + 1),
+        };
+        blob | (set as u8) << bit
+    })
+}
+
+/// The tiles of a terrain, such as grass or water, for each variant of a [`TerrainLayout`], so
+/// that the variant joining up with a cell's neighbors can be chosen automatically.
+///
+/// When a cell changes, the cell and its neighbors (see [`TerrainSet::affected_cells`]) should
+/// be given new tiles.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerrainSet {
+    layout: TerrainLayout,
+    tiles: Vec<u32>,
+    variants: [u8; 256], // The variant for each neighbor mask.
+}
+
+impl TerrainSet {
+    /// Creates a terrain set from the tile to use for each variant of `layout`, in order.
+    ///
+    /// Panics if the number of tiles does not match the layout.
+    pub fn new(layout: TerrainLayout, tiles: &[u32]) -> Self {
+        assert_eq!(
+            tiles.len(),
+            layout.variants(),
+            "terrain set needs a tile for each variant of {layout:?}"
+        );
+
+        let mut variants = [0; 256];
+        match layout {
+            TerrainLayout::Bitmask16 => {
+                for (mask, variant) in variants.iter_mut().enumerate() {
+                    let edges = [0, 2, 4, 6].map(|bit| (mask >> bit) & 1);
+                    *variant = (edges[0] | edges[1] << 1 | edges[2] << 2 | edges[3] << 3) as u8;
+                }
+            }
+            TerrainLayout::Blob47 => {
+                let mut blobs = (0..=255).map(blob_mask).collect::<Vec<_>>();
+                blobs.sort_unstable();
+                blobs.dedup();
+                for (mask, variant) in variants.iter_mut().enumerate() {
+                    let blob = blob_mask(mask as u8);
+                    *variant = blobs.binary_search(&blob).expect("blob mask is listed") as u8;
+                }
+            }
+        }
+
+        Self {
+            layout,
+            tiles: tiles.to_vec(),
+            variants,
+        }
+    }
+
+    pub fn layout(&self) -> TerrainLayout {
+        self.layout
+    }
+
+    /// The variant of the layout for a cell with neighbors `mask`, from [`neighbor_mask`].
+    pub fn variant(&self, mask: u8) -> usize {
+        self.variants[mask as usize] as usize
+    }
+
+    /// The tile for `cell`, chosen by which of its neighbors `is_terrain` says are the same
+    /// terrain.
+    pub fn tile<F: Fn(Vec2i) -> bool>(&self, cell: Vec2i, is_terrain: F) -> u32 {
+        self.tiles[self.variant(neighbor_mask(cell, is_terrain))]
+    }
+
+    /// The cells whose tiles may change when `cell` changes: the cell itself and its neighbors.
+    pub fn affected_cells(cell: Vec2i) -> impl Iterator<Item = Vec2i> {
+        std::iter::once(cell).chain(NEIGHBORS.iter().map(move |&(x, y)| cell + v2i(x, y)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn variants_follow_neighbors() {
+        // A plus shape, with one extra cell in the top-right corner.
+        let cells = [(0, 0), (0, -1), (1, 0), (0, 1), (-1, 0), (1, -1)];
+        let is_terrain = |cell: Vec2i| cells.contains(&(cell.x, cell.y));
+        let tiles = (0..47).collect::<Vec<_>>();
+
+        let bitmask = TerrainSet::new(TerrainLayout::Bitmask16, &tiles[..16]);
+        assert_eq!(bitmask.tile(v2i(0, 0), is_terrain), 15);
+        assert_eq!(bitmask.tile(v2i(0, -1), is_terrain), 4 | 2);
+        assert_eq!(bitmask.tile(v2i(5, 5), is_terrain), 0);
+
+        let blob = TerrainSet::new(TerrainLayout::Blob47, &tiles);
+        assert_eq!(blob.variant(0), 0);
+        assert_eq!(blob.variant(0xff), 46);
+        // Corners only count where both edges next to them are set.
+        assert_eq!(blob.variant(0b0000_0010), 0);
+        assert_eq!(
+            blob.tile(v2i(0, 0), is_terrain),
+            blob.variant(0b0101_0111) as u32
+        );
+        assert_ne!(
+            blob.tile(v2i(0, 0), is_terrain),
+            blob.variant(0b0101_0101) as u32
+        );
+
+        assert_eq!(TerrainSet::affected_cells(v2i(3, 3)).count(), 9);
+    }
+}
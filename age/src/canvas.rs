@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use crate::{
+    graphics::destroy_texture,
+    math::{v2, Rect, Vec2f},
+    BlendMode, ClearPolicy, Engine, Graphics, Image, RenderTexture, TextureOptions,
+    TextureReadbackId, View,
+};
+
+/// A drawing surface that covers the whole world, for strokes and terrain changes that should
+/// stay where they were drawn, such as in paint and sandbox games.
+///
+/// The canvas is split into square chunks, each with its own render texture of one texel per
+/// world unit. Only the chunks around the camera are kept on the GPU: chunks that have been drawn
+/// to are read back into memory when the camera moves away from them and uploaded again when it
+/// comes back, while chunks that have never been drawn to take up no memory at all.
+///
+/// Call [`Canvas::update`] each frame before drawing to the canvas or drawing the canvas itself.
+pub struct Canvas {
+    chunk_size: u32,
+    resident: HashMap<ChunkCoord, Chunk>,
+    paging_out: HashMap<ChunkCoord, (TextureReadbackId, RenderTexture)>,
+    stored: HashMap<ChunkCoord, Image>,
+    abandoned: Vec<TextureReadbackId>, // Readbacks of chunks that were needed again first.
+}
+
+type ChunkCoord = (i32, i32);
+
+struct Chunk {
+    target: RenderTexture,
+    dirty: bool, // Whether it has been drawn to since it was last stored.
+}
+
+impl Canvas {
+    /// Creates an empty canvas split into chunks of `chunk_size` world units square.
+    pub fn new(chunk_size: u32) -> Self {
+        assert!(chunk_size > 0, "canvas chunks must not be empty");
+        Self {
+            chunk_size,
+            resident: HashMap::new(),
+            paging_out: HashMap::new(),
+            stored: HashMap::new(),
+            abandoned: Vec::new(),
+        }
+    }
+
+    pub fn chunk_size(&self) -> u32 {
+        self.chunk_size
+    }
+
+    /// The number of chunks kept on the GPU.
+    pub fn resident_chunks(&self) -> usize {
+        self.resident.len() + self.paging_out.len()
+    }
+
+    /// The number of chunks that have been drawn to and read back into memory, so that they can
+    /// be paged in again.
+    pub fn stored_chunks(&self) -> usize {
+        self.stored.len()
+    }
+
+    /// Pages in the chunks that `view` can see, along with those next to them, and starts
+    /// paging out chunks that have fallen well outside it.
+    pub fn update(&mut self, age: &mut Engine, view: &View) {
+        for (coord, (id, target)) in std::mem::take(&mut self.paging_out) {
+            match age.renderer.take_texture_readback(id) {
+                Some(image) => {
+                    self.stored.insert(coord, image);
+                    destroy_texture(&mut age.renderer, target.texture());
+                }
+                None => {
+                    self.paging_out.insert(coord, (id, target));
+                }
+            }
+        }
+        self.abandoned
+            .retain(|&id| age.renderer.take_texture_readback(id).is_none());
+
+        let (min, max) = view.visible_bounds();
+        let visible = Rect::new(min, max - min);
+        let margin = v2(self.chunk_size as f32, self.chunk_size as f32);
+        let needed = Rect::new(visible.position - margin, visible.size + margin * 2.0);
+        for coord in self.chunks_overlapping(needed) {
+            self.page_in(age, coord);
+        }
+
+        let kept = Rect::new(needed.position - margin, needed.size + margin * 2.0);
+        let kept = self.chunks_overlapping(kept).collect::<Vec<_>>();
+        let leaving = self
+            .resident
+            .keys()
+            .filter(|coord| !kept.contains(coord))
+            .copied()
+            .collect::<Vec<_>>();
+        for coord in leaving {
+            let chunk = self.resident.remove(&coord).expect("chunk is resident");
+            match chunk.dirty {
+                true => {
+                    let id = age.graphics.read_texture(chunk.target.texture());
+                    self.paging_out.insert(coord, (id, chunk.target));
+                }
+                false => destroy_texture(&mut age.renderer, chunk.target.texture()),
+            }
+        }
+    }
+
+    /// Calls `draw` once for each chunk overlapping `area` in the world, with the chunk set as
+    /// the draw target and a view that places what is drawn at its world position. Strokes that
+    /// cross chunk borders are split between the chunks they cross. Chunks that are not resident
+    /// are paged in first.
+    pub fn draw<F>(&mut self, age: &mut Engine, area: Rect, mut draw: F)
+    where
+        F: FnMut(&mut Graphics),
+    {
+        for coord in self.chunks_overlapping(area).collect::<Vec<_>>() {
+            self.page_in(age, coord);
+            let view = View::fit_rect(self.chunk_rect(coord), self.chunk_size, self.chunk_size);
+            let chunk = self.resident.get_mut(&coord).expect("chunk was paged in");
+            chunk.dirty = true;
+
+            age.graphics
+                .draw_offscreen((&chunk.target).into(), view, |graphics| draw(graphics));
+        }
+    }
+
+    /// Draws the resident chunks at their place in the world, over what has been drawn already.
+    pub fn render(&self, graphics: &mut Graphics) {
+        for (&coord, chunk) in &self.resident {
+            let texture = chunk.target.texture();
+            graphics.draw_texture(texture, self.chunk_rect(coord), BlendMode::Alpha);
+        }
+        for (&coord, (_, target)) in &self.paging_out {
+            graphics.draw_texture(target.texture(), self.chunk_rect(coord), BlendMode::Alpha);
+        }
+    }
+
+    fn page_in(&mut self, age: &mut Engine, coord: ChunkCoord) {
+        if self.resident.contains_key(&coord) {
+            return;
+        }
+
+        if let Some((id, target)) = self.paging_out.remove(&coord) {
+            self.abandoned.push(id);
+            self.resident.insert(
+                coord,
+                Chunk {
+                    target,
+                    dirty: true,
+                },
+            );
+            return;
+        }
+
+        let options = TextureOptions {
+            label: Some("canvas chunk"),
+            ..Default::default()
+        };
+        let mut target = age.graphics.create_render_texture(
+            &mut age.renderer,
+            self.chunk_size,
+            self.chunk_size,
+            &options,
+        );
+        target.set_clear_policy(ClearPolicy::Never);
+        // The stored copy is kept, so the chunk only has to be read back again if it changes.
+        if let Some(image) = self.stored.get(&coord) {
+            age.renderer
+                .write_texture(target.texture().texture(), image.pixels())
+                .expect("stored chunks are the size of a chunk");
+        }
+        self.resident.insert(
+            coord,
+            Chunk {
+                target,
+                dirty: false,
+            },
+        );
+    }
+
+    fn chunk_rect(&self, (x, y): ChunkCoord) -> Rect {
+        let size = self.chunk_size as f32;
+        Rect::new(v2(x as f32 * size, y as f32 * size), v2(size, size))
+    }
+
+    fn chunk_at(&self, position: Vec2f) -> ChunkCoord {
+        let size = self.chunk_size as f32;
+        (
+            (position.x / size).floor() as i32,
+            (position.y / size).floor() as i32,
+        )
+    }
+
+    fn chunks_overlapping(&self, area: Rect) -> impl Iterator<Item = ChunkCoord> {
+        let (min_x, min_y) = self.chunk_at(area.min());
+        let (max_x, max_y) = self.chunk_at(area.max());
+        (min_y..=max_y).flat_map(move |y| (min_x..=max_x).map(move |x| (x, y)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chunks_cover_negative_positions() {
+        let canvas = Canvas::new(100);
+
+        assert_eq!(canvas.chunk_at(v2(-0.5, 99.9)), (-1, 0));
+        assert_eq!(
+            canvas
+                .chunks_overlapping(Rect::new(v2(-50.0, -50.0), v2(100.0, 60.0)))
+                .collect::<Vec<_>>(),
+            [(-1, -1), (0, -1), (-1, 0), (0, 0)]
+        );
+        assert_eq!(
+            canvas.chunk_rect((-1, 2)),
+            Rect::new(v2(-100.0, 200.0), v2(100.0, 100.0))
+        );
+    }
+}
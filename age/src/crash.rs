@@ -0,0 +1,48 @@
+use std::{
+    backtrace::Backtrace,
+    fmt::Write as _,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::AppMetadata;
+
+/// Installs a panic hook that writes the panic message and a backtrace to a crash log in the game's
+/// data directory, in addition to the default panic output.
+pub(crate) fn install_handler(metadata: &AppMetadata) {
+    let metadata = metadata.clone();
+    let default_hook = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+
+        let mut report = String::new();
+        let _ = writeln!(report, "{}", metadata.title());
+        let _ = writeln!(report, "{}", info);
+        let _ = writeln!(report);
+        let _ = writeln!(report, "{}", Backtrace::force_capture());
+
+        match write_crash_log(&metadata, &report) {
+            Some(path) => eprintln!("crash log written to '{}'", path.display()),
+            None => eprintln!("failed to write crash log"),
+        }
+    }));
+}
+
+fn write_crash_log(metadata: &AppMetadata, report: &str) -> Option<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    let dir = metadata.data_dir()?.join("crashes");
+    std::fs::create_dir_all(&dir).ok()?;
+    let name = match metadata.version().is_empty() {
+        true => format!("crash-{}.log", timestamp),
+        false => format!("crash-{}-{}.log", metadata.version(), timestamp),
+    };
+    let path = dir.join(name);
+    std::fs::write(&path, report).ok()?;
+
+    Some(path)
+}
@@ -0,0 +1,64 @@
+struct VsOut {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) id: u32) -> VsOut {
+    let uv = vec2(f32((id << 1) & 2), f32(id & 2));
+    let pos = vec4(uv * vec2(2.0, -2.0) + vec2(-1.0, 1.0), 0.0, 1.0);
+
+    return VsOut(pos, uv);
+}
+
+@group(0) @binding(0)
+var r_sampler: sampler;
+@group(0) @binding(1)
+var r_texture: texture_2d<f32>;
+
+struct PushConstant {
+    curvature: f32,
+    scanline_intensity: f32,
+    mask_intensity: f32,
+    bloom_intensity: f32,
+    // The offset and size of the region of the backbuffer shown in the window, in uv coordinates.
+    region: vec4<f32>,
+}
+
+var<push_constant> r_pc: PushConstant;
+
+const PI: f32 = 3.14159265;
+
+fn curve(uv: vec2<f32>) -> vec2<f32> {
+    let centered = uv * 2.0 - 1.0;
+    let offset = centered.yx * centered.yx * r_pc.curvature;
+
+    return (centered + centered * offset) * 0.5 + 0.5;
+}
+
+@fragment
+fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
+    let screen = curve(in.uv);
+    let uv = r_pc.region.xy + screen * r_pc.region.zw;
+    let size = vec2<f32>(textureDimensions(r_texture));
+    let texel = 1.0 / size;
+
+    // All samples are taken up front so that they stay in uniform control flow.
+    var color = textureSample(r_texture, r_sampler, uv).rgb;
+    let glow = (textureSample(r_texture, r_sampler, uv + vec2(texel.x, 0.0)).rgb
+        + textureSample(r_texture, r_sampler, uv - vec2(texel.x, 0.0)).rgb
+        + textureSample(r_texture, r_sampler, uv + vec2(0.0, texel.y)).rgb
+        + textureSample(r_texture, r_sampler, uv - vec2(0.0, texel.y)).rgb) * 0.25;
+    color += glow * glow * r_pc.bloom_intensity;
+
+    let scanline = 0.5 + 0.5 * cos(screen.y * size.y * 2.0 * PI);
+    color *= mix(1.0, scanline, r_pc.scanline_intensity);
+
+    var mask = vec3(1.0 - r_pc.mask_intensity);
+    mask[u32(in.pos.x) % 3u] = 1.0;
+    color *= mask;
+
+    let outside = any(screen < vec2(0.0)) || any(screen > vec2(1.0));
+
+    return select(vec4(color, 1.0), vec4(0.0, 0.0, 0.0, 1.0), outside);
+}
@@ -0,0 +1,435 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    path::Path,
+};
+
+use crate::{Error, ErrorKind};
+
+/// The value of a [`CVars`] entry.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CVarValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+impl CVarValue {
+    fn type_name(&self) -> &'static str {
+        match self {
+            CVarValue::Bool(_) => "bool",
+            CVarValue::Int(_) => "int",
+            CVarValue::Float(_) => "float",
+            CVarValue::String(_) => "string",
+        }
+    }
+
+    /// Parses `s` as a value of the same type as this one.
+    fn parse_as(&self, s: &str) -> Option<CVarValue> {
+        let s = s.trim();
+        match self {
+            CVarValue::Bool(_) => match s {
+                "true" | "1" | "on" => Some(CVarValue::Bool(true)),
+                "false" | "0" | "off" => Some(CVarValue::Bool(false)),
+                _ => None,
+            },
+            CVarValue::Int(_) => s.parse().ok().map(CVarValue::Int),
+            CVarValue::Float(_) => s.parse().ok().map(CVarValue::Float),
+            CVarValue::String(_) => Some(CVarValue::String(s.to_string())),
+        }
+    }
+}
+
+impl Display for CVarValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CVarValue::Bool(value) => write!(f, "{}", value),
+            CVarValue::Int(value) => write!(f, "{}", value),
+            CVarValue::Float(value) => write!(f, "{}", value),
+            CVarValue::String(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// A type that can be stored in [`CVars`].
+pub trait CVarType: Sized {
+    fn into_value(self) -> CVarValue;
+
+    fn from_value(value: &CVarValue) -> Option<Self>;
+}
+
+macro_rules! impl_cvar_type {
+    ($ty:ty, $variant:ident, $stored:ty) => {
+        impl CVarType for $ty {
+            fn into_value(self) -> CVarValue {
+                CVarValue::$variant(<$stored>::from(self))
+            }
+
+            // Values out of range for the type are rejected rather than wrapped.
+            fn from_value(value: &CVarValue) -> Option<Self> {
+                match *value {
+                    CVarValue::$variant(value) => <$ty>::try_from(value).ok(),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+impl_cvar_type!(bool, Bool, bool);
+impl_cvar_type!(i32, Int, i64);
+impl_cvar_type!(i64, Int, i64);
+impl_cvar_type!(u32, Int, i64);
+impl_cvar_type!(f64, Float, f64);
+
+impl CVarType for f32 {
+    fn into_value(self) -> CVarValue {
+        CVarValue::Float(f64::from(self))
+    }
+
+    // Values beyond the range of an f32 read back as infinity, as when parsing one.
+    fn from_value(value: &CVarValue) -> Option<Self> {
+        match *value {
+            CVarValue::Float(value) => Some(value as f32),
+            _ => None,
+        }
+    }
+}
+
+impl CVarType for String {
+    fn into_value(self) -> CVarValue {
+        CVarValue::String(self)
+    }
+
+    fn from_value(value: &CVarValue) -> Option<Self> {
+        match value {
+            CVarValue::String(value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Whether presenting waits for the display's vertical sync, which stops tearing.
+pub(crate) const RENDER_VSYNC: &str = "render.vsync";
+
+/// How far the window is magnified around the mouse cursor, see
+/// [`Engine::set_ui_zoom`](crate::Engine::set_ui_zoom).
+pub(crate) const RENDER_UI_ZOOM: &str = "render.ui_zoom";
+
+type ChangeFn = Box<dyn FnMut(&CVarValue)>;
+
+struct CVar {
+    value: CVarValue,
+    default: CVarValue,
+    valid: fn(&CVarValue) -> bool, // Whether a value fits the type the cvar was registered as.
+    description: String,
+    on_change: Vec<ChangeFn>,
+}
+
+/// A registry of named, typed settings that can be changed while the game is running, such as
+/// `render.vsync` or `debug.show_colliders`.
+///
+/// Names are conventionally grouped with a `.`, e.g. `audio.master_volume`. Values can be set
+/// from text, e.g. typed into a console, and saved to and loaded from a config file of one
+/// `name = value` per line.
+///
+/// The engine registers its own settings, `render.vsync` and `render.ui_zoom`, and applies them
+/// when they change.
+#[derive(Default)]
+pub struct CVars {
+    cvars: BTreeMap<String, CVar>,
+    changed: BTreeSet<String>, // Since the engine last applied its own settings.
+}
+
+impl CVars {
+    /// Adds a setting with its default value. Registering a name again replaces its default and
+    /// description, but keeps any value it has been set to if it is still the same type.
+    pub fn register<T: CVarType>(&mut self, name: &str, default: T, description: &str) {
+        let default = default.into_value();
+        let valid: fn(&CVarValue) -> bool = |value| T::from_value(value).is_some();
+        match self.cvars.get_mut(name) {
+            Some(cvar) => {
+                if !valid(&cvar.value) {
+                    cvar.value = default.clone();
+                }
+                cvar.default = default;
+                cvar.valid = valid;
+                cvar.description = description.to_string();
+            }
+            None => {
+                self.cvars.insert(
+                    name.to_string(),
+                    CVar {
+                        value: default.clone(),
+                        default,
+                        valid,
+                        description: description.to_string(),
+                        on_change: Vec::new(),
+                    },
+                );
+            }
+        }
+    }
+
+    /// Registers the settings the engine itself reads.
+    pub(crate) fn register_engine(&mut self) {
+        self.register(
+            RENDER_VSYNC,
+            true,
+            "Wait for vertical sync before presenting",
+        );
+        self.register(
+            RENDER_UI_ZOOM,
+            1.0_f32,
+            "Magnification around the mouse cursor",
+        );
+    }
+
+    /// The names of the settings that have changed since the last call.
+    pub(crate) fn take_changed(&mut self) -> BTreeSet<String> {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// The value of `name`, or `None` if it has not been registered or is not a `T`.
+    pub fn get<T: CVarType>(&self, name: &str) -> Option<T> {
+        T::from_value(&self.cvars.get(name)?.value)
+    }
+
+    pub fn get_value(&self, name: &str) -> Option<&CVarValue> {
+        self.cvars.get(name).map(|cvar| &cvar.value)
+    }
+
+    pub fn description(&self, name: &str) -> Option<&str> {
+        self.cvars.get(name).map(|cvar| cvar.description.as_str())
+    }
+
+    /// Changes the value of `name`, calling its change callbacks if the value is different.
+    pub fn set<T: CVarType>(&mut self, name: &str, value: T) -> Result<(), Error> {
+        self.set_value(name, value.into_value())
+    }
+
+    /// Changes the value of `name` from text, e.g. a console command, which is parsed as the
+    /// type the setting was registered with.
+    pub fn set_from_str(&mut self, name: &str, value: &str) -> Result<(), Error> {
+        let cvar = self.get_cvar(name)?;
+        let parsed = cvar.value.parse_as(value).ok_or_else(|| {
+            Error::new(format!(
+                "'{}' is not a valid {} for cvar '{}'",
+                value,
+                cvar.value.type_name(),
+                name
+            ))
+        })?;
+
+        self.set_value(name, parsed)
+    }
+
+    /// Changes `name` back to its default value.
+    pub fn reset(&mut self, name: &str) -> Result<(), Error> {
+        let default = self.get_cvar(name)?.default.clone();
+        self.set_value(name, default)
+    }
+
+    /// Calls `f` with the new value whenever `name` is changed.
+    pub fn on_change<F: FnMut(&CVarValue) + 'static>(
+        &mut self,
+        name: &str,
+        f: F,
+    ) -> Result<(), Error> {
+        self.get_cvar(name)?;
+        if let Some(cvar) = self.cvars.get_mut(name) {
+            cvar.on_change.push(Box::new(f));
+        }
+        Ok(())
+    }
+
+    /// The names of every setting in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.cvars.keys().map(String::as_str)
+    }
+
+    /// The settings whose values differ from their defaults, as one `name = value` per line.
+    pub fn to_config(&self) -> String {
+        self.cvars
+            .iter()
+            .filter(|(_, cvar)| cvar.value != cvar.default)
+            .map(|(name, cvar)| format!("{} = {}\n", name, cvar.value))
+            .collect()
+    }
+
+    /// Sets values from a config of one `name = value` per line, as written by
+    /// [`CVars::to_config`]. Blank lines and lines starting with `#` are ignored, as are settings
+    /// that have not been registered, so that old configs can still be loaded.
+    pub fn apply_config(&mut self, source: &str) -> Result<(), Error> {
+        for (n, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (name, value) = line.split_once('=').ok_or_else(|| {
+                Error::new(format!("invalid cvar on line {} of config", n + 1))
+                    .with_kind(ErrorKind::Asset)
+            })?;
+
+            let name = name.trim();
+            if self.cvars.contains_key(name) {
+                self.set_from_str(name, value).map_err(|err| {
+                    err.context(format!("invalid cvar on line {} of config", n + 1))
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_config()).map_err(|err| {
+            Error::new(format!("failed to write cvars '{}'", path.display()))
+                .with_kind(ErrorKind::Io)
+                .with_source(err)
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|err| {
+            Error::new(format!("failed to read cvars '{}'", path.display()))
+                .with_kind(ErrorKind::Io)
+                .with_source(err)
+        })?;
+
+        self.apply_config(&source)
+            .map_err(|err| err.context(format!("failed to load cvars '{}'", path.display())))
+    }
+
+    fn get_cvar(&self, name: &str) -> Result<&CVar, Error> {
+        self.cvars
+            .get(name)
+            .ok_or_else(|| Error::new(format!("unknown cvar '{}'", name)))
+    }
+
+    fn set_value(&mut self, name: &str, value: CVarValue) -> Result<(), Error> {
+        let cvar = self
+            .cvars
+            .get_mut(name)
+            .ok_or_else(|| Error::new(format!("unknown cvar '{}'", name)))?;
+
+        if std::mem::discriminant(&cvar.value) != std::mem::discriminant(&value) {
+            return Err(Error::new(format!(
+                "cvar '{}' is a {} but was set to a {}",
+                name,
+                cvar.value.type_name(),
+                value.type_name()
+            )));
+        }
+        if !(cvar.valid)(&value) {
+            return Err(Error::new(format!(
+                "{} is out of range for cvar '{}'",
+                value, name
+            )));
+        }
+
+        if cvar.value != value {
+            cvar.value = value;
+            self.changed.insert(name.to_string());
+            for on_change in cvar.on_change.iter_mut() {
+                on_change(&cvar.value);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+
+    fn cvars() -> CVars {
+        let mut cvars = CVars::default();
+        cvars.register("render.vsync", true, "Wait for vertical sync");
+        cvars.register("audio.master_volume", 0.8_f32, "Volume of all sound");
+        cvars.register("player.name", String::from("Ada"), "Name shown to others");
+        cvars.register("net.port", 7777_u32, "Port to host games on");
+        cvars.register("ai.budget", 8_i32, "Agents updated per frame");
+        cvars
+    }
+
+    #[test]
+    fn values_are_typed() {
+        let mut cvars = cvars();
+        assert_eq!(cvars.get::<bool>("render.vsync"), Some(true));
+        assert_eq!(cvars.get::<i32>("render.vsync"), None);
+
+        cvars.set("render.vsync", false).unwrap();
+        assert_eq!(cvars.get::<bool>("render.vsync"), Some(false));
+        assert!(cvars.set("render.vsync", 1).is_err());
+        assert!(cvars.set("render.missing", 1).is_err());
+    }
+
+    #[test]
+    fn set_from_str_parses_registered_type() {
+        let mut cvars = cvars();
+        cvars.set_from_str("audio.master_volume", "0.5").unwrap();
+        cvars.set_from_str("render.vsync", "off").unwrap();
+        assert_eq!(cvars.get::<f32>("audio.master_volume"), Some(0.5));
+        assert_eq!(cvars.get::<bool>("render.vsync"), Some(false));
+        assert!(cvars.set_from_str("audio.master_volume", "loud").is_err());
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        let mut cvars = cvars();
+        assert!(cvars.set_from_str("net.port", "-1").is_err());
+        assert!(cvars.set("ai.budget", i64::MAX).is_err());
+        assert_eq!(cvars.get::<u32>("net.port"), Some(7777));
+        assert_eq!(cvars.get::<i32>("ai.budget"), Some(8));
+
+        cvars.set_from_str("net.port", "4294967295").unwrap();
+        assert_eq!(cvars.get::<u32>("net.port"), Some(u32::MAX));
+        assert_eq!(
+            cvars.take_changed(),
+            BTreeSet::from(["net.port".to_string()])
+        );
+        assert!(cvars.take_changed().is_empty());
+    }
+
+    #[test]
+    fn change_callbacks_run_when_value_changes() {
+        let mut cvars = cvars();
+        let changes = Rc::new(Cell::new(0));
+        let counter = changes.clone();
+        cvars
+            .on_change("render.vsync", move |_| counter.set(counter.get() + 1))
+            .unwrap();
+
+        cvars.set("render.vsync", true).unwrap();
+        cvars.set("render.vsync", false).unwrap();
+        cvars.reset("render.vsync").unwrap();
+        assert_eq!(changes.get(), 2);
+    }
+
+    #[test]
+    fn config_round_trips_changed_values() {
+        let mut cvars = cvars();
+        cvars.set("audio.master_volume", 0.25_f32).unwrap();
+        cvars.set("player.name", String::from("Grace")).unwrap();
+        let config = cvars.to_config();
+        assert_eq!(config, "audio.master_volume = 0.25\nplayer.name = Grace\n");
+
+        let mut loaded = self::cvars();
+        loaded
+            .apply_config(&format!("# saved\n{}old.setting = 1\n", config))
+            .unwrap();
+        assert_eq!(loaded.get::<f32>("audio.master_volume"), Some(0.25));
+        assert_eq!(
+            loaded.get::<String>("player.name"),
+            Some(String::from("Grace"))
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use crate::{math::Rect, RenderTexture, View};
+
+/// A render texture covering an area of the world that decals, such as bullet holes, paint or
+/// blood, are permanently stamped into with [`Graphics::splat`](crate::Graphics::splat).
+///
+/// Drawing the map's texture over the world each frame shows every decal splatted so far for the
+/// cost of a single sprite, however many there are.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecalMap {
+    target: RenderTexture,
+    bounds: Rect,
+}
+
+impl DecalMap {
+    pub(crate) fn new(target: RenderTexture, bounds: Rect) -> Self {
+        Self { target, bounds }
+    }
+
+    pub fn target(&self) -> &RenderTexture {
+        &self.target
+    }
+
+    /// The area of the world that the map covers.
+    pub fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    /// The view that maps the world within the bounds onto the whole of the map's texture.
+    pub(crate) fn view(&self) -> View {
+        let (width, height) = self.target.texture().size();
+        View::fit_rect(self.bounds, width, height)
+    }
+
+    /// The size of texture needed to cover `bounds` at `texels_per_unit` texels per world unit.
+    pub(crate) fn texture_size(bounds: Rect, texels_per_unit: f32) -> (u32, u32) {
+        let size = |extent: f32| ((extent * texels_per_unit).ceil() as u32).max(1);
+        (size(bounds.size.x), size(bounds.size.y))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::math::v2;
+
+    #[test]
+    fn texture_covers_bounds() {
+        let bounds = Rect::new(v2(-100.0, 50.0), v2(300.5, 0.0));
+
+        assert_eq!(DecalMap::texture_size(bounds, 1.0), (301, 1));
+        assert_eq!(DecalMap::texture_size(bounds, 2.0), (601, 1));
+    }
+}
@@ -1,15 +1,23 @@
 struct GeometryVertex {
     @location(0) pos: vec2<f32>,
+    @location(1) uv: vec2<f32>,
 }
 
 struct VsOut {
     @builtin(position) pos: vec4<f32>,
     @location(0) color: vec4<f32>,
+    @location(1) uv: vec2<f32>,
 }
 
 @group(0) @binding(0)
 var<storage, read> r_globals: array<mat4x4<f32>>;
 
+@group(1) @binding(0)
+var r_sampler: sampler;
+
+@group(1) @binding(1)
+var r_texture: texture_2d<f32>;
+
 struct PushConstant {
     color: vec4<f32>,
     model: mat4x4<f32>,
@@ -28,11 +36,12 @@ fn vs_main(vertex: GeometryVertex) -> VsOut {
 
     return VsOut(
         pos,
-        color
+        color,
+        vertex.uv
     );
 }
 
 @fragment
 fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
-    return in.color;
+    return textureSample(r_texture, r_sampler, in.uv) * in.color;
 }
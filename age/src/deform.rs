@@ -0,0 +1,164 @@
+use crate::{
+    math::{v2, Vec2f},
+    renderer::GeometryVertex,
+};
+
+/// The number of control points along each side of a [`Deformation`]'s lattice.
+const POINTS: usize = 4;
+
+/// A warp of a sprite's quad, given by how far each point of a 4 by 4 lattice over the sprite is
+/// moved, as a fraction of the sprite's size. The lattice splits the sprite into a 3 by 3 grid of
+/// cells, so the middle of the sprite can bulge or pinch as well as its corners move.
+///
+/// Deformations are applied with [`Sprite::set_deformation`](crate::Sprite::set_deformation) and
+/// can be animated by interpolating between them with [`Deformation::lerp`], e.g. with the
+/// progress given by an [`Easing`](crate::math::Easing) for squash and stretch on landing.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Deformation {
+    offsets: [Vec2f; POINTS * POINTS], // Row by row, from the top-left.
+}
+
+impl Deformation {
+    pub const NONE: Self = Self {
+        offsets: [Vec2f::ZERO; POINTS * POINTS],
+    };
+
+    /// Moves the top-left, top-right, bottom-right and bottom-left corners by `corners`, with the
+    /// rest of the sprite following smoothly between them.
+    pub fn from_corners(corners: [Vec2f; 4]) -> Self {
+        let [top_left, top_right, bottom_right, bottom_left] = corners;
+        Self::from_fn(|u| {
+            let top = top_left + (top_right - top_left) * u.x;
+            let bottom = bottom_left + (bottom_right - bottom_left) * u.x;
+            top + (bottom - top) * u.y
+        })
+    }
+
+    /// Shifts the top edge sideways by `x` and the right edge down by `y`, keeping the
+    /// bottom-left corner in place, e.g. for grass swaying in the wind.
+    pub fn skew(x: f32, y: f32) -> Self {
+        Self::from_fn(|u| v2((1.0 - u.y) * x, u.x * y))
+    }
+
+    /// Stretches the sprite to `1.0 + amount` times its height and squashes it to keep its area,
+    /// or squashes it for a negative `amount`, keeping the middle of its bottom edge in place.
+    pub fn squash_stretch(amount: f32) -> Self {
+        let stretch = (1.0 + amount).max(f32::EPSILON);
+        Self::from_fn(|u| {
+            let deformed = v2(0.5 + (u.x - 0.5) / stretch, 1.0 - (1.0 - u.y) * stretch);
+            deformed - u
+        })
+    }
+
+    /// Sets how far the lattice point in `column` and `row`, each from 0 to 3, is moved.
+    pub fn with_offset(mut self, column: usize, row: usize, offset: Vec2f) -> Self {
+        assert!(
+            column < POINTS && row < POINTS,
+            "deformation lattice is {POINTS} by {POINTS} points"
+        );
+        self.offsets[row * POINTS + column] = offset;
+        self
+    }
+
+    pub fn offset(&self, column: usize, row: usize) -> Vec2f {
+        self.offsets[row * POINTS + column]
+    }
+
+    /// The deformation a fraction `t` of the way from this one to `to`.
+    pub fn lerp(&self, to: &Deformation, t: f32) -> Deformation {
+        let mut offsets = self.offsets;
+        for (offset, to) in offsets.iter_mut().zip(to.offsets) {
+            *offset += (to - *offset) * t;
+        }
+        Self { offsets }
+    }
+
+    fn from_fn(offset: impl Fn(Vec2f) -> Vec2f) -> Self {
+        let mut offsets = [Vec2f::ZERO; POINTS * POINTS];
+        for (i, point) in offsets.iter_mut().enumerate() {
+            *point = offset(lattice_point(i));
+        }
+        Self { offsets }
+    }
+
+    /// The vertices of a sprite of `size` pixels, showing the `uv_size` region of its texture at
+    /// `uv_min`, with this deformation applied.
+    pub(crate) fn vertices(
+        &self,
+        size: Vec2f,
+        uv_min: Vec2f,
+        uv_size: Vec2f,
+    ) -> [GeometryVertex; POINTS * POINTS] {
+        std::array::from_fn(|i| {
+            let u = lattice_point(i);
+            let pos = (u + self.offsets[i]) * size;
+            let uv = uv_min + u * uv_size;
+            GeometryVertex {
+                pos: [pos.x, pos.y],
+                uv: [uv.x, uv.y],
+            }
+        })
+    }
+}
+
+/// The indices of the triangles that make up the cells of a deformation lattice.
+pub(crate) const LATTICE_INDICES: [u16; (POINTS - 1) * (POINTS - 1) * 6] = {
+    let mut indices = [0; (POINTS - 1) * (POINTS - 1) * 6];
+    let mut cell = 0;
+    while cell < (POINTS - 1) * (POINTS - 1) {
+        let top_left = (cell / (POINTS - 1) * POINTS + cell % (POINTS - 1)) as u16;
+        let (top_right, bottom_left) = (top_left + 1, top_left + POINTS as u16);
+        let bottom_right = bottom_left + 1;
+        let quad = [
+            top_left,
+            top_right,
+            bottom_right,
+            top_left,
+            bottom_right,
+            bottom_left,
+        ];
+        let mut i = 0;
+        while i < 6 {
+            indices[cell * 6 + i] = quad[i];
+            i += 1;
+        }
+        cell += 1;
+    }
+    indices
+};
+
+/// Where lattice point `i` is on an undeformed sprite, from (0, 0) at the top-left to (1, 1) at
+/// the bottom-right.
+fn lattice_point(i: usize) -> Vec2f {
+    let last = (POINTS - 1) as f32;
+    v2((i % POINTS) as f32 / last, (i / POINTS) as f32 / last)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn deformations_move_lattice_points() {
+        let size = v2(30.0, 60.0);
+        let corner = |deformation: Deformation, i: usize| {
+            let vertex = deformation.vertices(size, Vec2f::ZERO, Vec2f::ONE)[i];
+            v2(vertex.pos[0], vertex.pos[1])
+        };
+
+        assert_eq!(corner(Deformation::NONE, 15), v2(30.0, 60.0));
+        assert_eq!(corner(Deformation::skew(0.5, 0.0), 0), v2(15.0, 0.0));
+        assert_eq!(corner(Deformation::skew(0.5, 0.0), 12), v2(0.0, 60.0));
+
+        // Stretching keeps the bottom middle in place and the area the same.
+        let stretched = Deformation::squash_stretch(1.0);
+        assert_eq!(corner(stretched, 0), v2(7.5, -60.0));
+        assert_eq!(corner(stretched, 15), v2(22.5, 60.0));
+
+        let halfway = Deformation::NONE.lerp(&stretched, 0.5);
+        assert_eq!(halfway.offset(0, 0), stretched.offset(0, 0) / 2.0);
+
+        assert_eq!(&LATTICE_INDICES[..6], &[0, 1, 5, 0, 5, 4]);
+        assert_eq!(&LATTICE_INDICES[48..], &[10, 11, 15, 10, 15, 14]);
+    }
+}
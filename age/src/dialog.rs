@@ -0,0 +1,80 @@
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, TryRecvError},
+};
+
+use rfd::AsyncFileDialog;
+
+use crate::sys::Window;
+
+/// The state of a file dialog opened with [`Engine::open_file_dialog`](crate::Engine::open_file_dialog)
+/// or [`Engine::save_file_dialog`](crate::Engine::save_file_dialog).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileDialogStatus {
+    /// The dialog is still open.
+    Pending,
+    /// The dialog was closed without choosing a file.
+    Cancelled,
+    Selected(PathBuf),
+}
+
+/// A native file dialog that runs alongside the game loop, which the game polls each frame until
+/// the user has chosen a file.
+pub struct FileDialog {
+    rx: Receiver<Option<PathBuf>>,
+    status: FileDialogStatus,
+}
+
+impl FileDialog {
+    pub(crate) fn open(window: &Window, filters: &[(&str, &[&str])]) -> Self {
+        let dialog = Self::builder(window, filters);
+        Self::spawn(async move { dialog.pick_file().await })
+    }
+
+    pub(crate) fn save(window: &Window, filters: &[(&str, &[&str])], file_name: &str) -> Self {
+        let mut dialog = Self::builder(window, filters);
+        if !file_name.is_empty() {
+            dialog = dialog.set_file_name(file_name);
+        }
+        Self::spawn(async move { dialog.save_file().await })
+    }
+
+    /// Whether the dialog is still open, and the chosen file once it has closed.
+    pub fn poll(&mut self) -> &FileDialogStatus {
+        if self.status == FileDialogStatus::Pending {
+            match self.rx.try_recv() {
+                Ok(Some(path)) => self.status = FileDialogStatus::Selected(path),
+                Ok(None) | Err(TryRecvError::Disconnected) => {
+                    self.status = FileDialogStatus::Cancelled
+                }
+                Err(TryRecvError::Empty) => {}
+            }
+        }
+
+        &self.status
+    }
+
+    // Dialogs must be created on the main thread on some platforms, but can be waited on from any.
+    fn builder(window: &Window, filters: &[(&str, &[&str])]) -> AsyncFileDialog {
+        filters.iter().fold(
+            AsyncFileDialog::new().set_parent(window),
+            |dialog, (name, extensions)| dialog.add_filter(*name, extensions),
+        )
+    }
+
+    fn spawn<F>(dialog: F) -> Self
+    where
+        F: std::future::Future<Output = Option<rfd::FileHandle>> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let path = pollster::block_on(dialog).map(|file| file.path().to_path_buf());
+            let _ = tx.send(path);
+        });
+
+        Self {
+            rx,
+            status: FileDialogStatus::Pending,
+        }
+    }
+}
@@ -1,7 +1,35 @@
 use std::fmt::Display;
 
+/// The part of the engine, or the system it depends upon, where an error originated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Loading or parsing game data, such as a language bundle.
+    Asset,
+    Audio,
+    /// The graphics adapter, device or window surface.
+    Gpu,
+    Io,
+    Network,
+    /// The operating system or windowing system.
+    Os,
+    Other,
+}
+
+impl ErrorKind {
+    /// Whether the engine can keep running after an error of this kind, e.g. by skipping a missing
+    /// asset or retrying a network operation.
+    pub fn is_recoverable(self) -> bool {
+        match self {
+            ErrorKind::Asset | ErrorKind::Audio | ErrorKind::Io | ErrorKind::Network => true,
+            ErrorKind::Gpu | ErrorKind::Os | ErrorKind::Other => false,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Error {
+    kind: ErrorKind,
     msg: String,
     src: Option<Box<dyn std::error::Error>>,
 }
@@ -9,17 +37,40 @@ pub struct Error {
 impl Error {
     pub fn new<S: Into<String>>(msg: S) -> Self {
         Self {
+            kind: ErrorKind::Other,
             msg: msg.into(),
             src: None,
         }
     }
 
+    pub fn with_kind(self, kind: ErrorKind) -> Self {
+        Self { kind, ..self }
+    }
+
     pub fn with_source<E: std::error::Error + 'static>(self, err: E) -> Self {
         Self {
             src: Some(Box::new(err)),
             ..self
         }
     }
+
+    /// Wraps this error with a message describing what was being done when it occurred. The new
+    /// error has the same kind and this error as its source.
+    pub fn context<S: Into<String>>(self, msg: S) -> Self {
+        Self {
+            kind: self.kind,
+            msg: msg.into(),
+            src: Some(Box::new(self)),
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn is_recoverable(&self) -> bool {
+        self.kind.is_recoverable()
+    }
 }
 
 impl Display for Error {
@@ -39,3 +90,37 @@ impl From<&str> for Error {
         Self::new(value)
     }
 }
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Error::new("an I/O operation failed")
+            .with_kind(ErrorKind::Io)
+            .with_source(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::error::Error as _;
+
+    use super::*;
+
+    #[test]
+    fn error_defaults_to_other() {
+        let err = Error::new("oops");
+        assert_eq!(err.kind(), ErrorKind::Other);
+        assert!(!err.is_recoverable());
+    }
+
+    #[test]
+    fn context_keeps_kind_and_chains_source() {
+        let err = Error::new("file not found")
+            .with_kind(ErrorKind::Io)
+            .context("failed to load level");
+
+        assert_eq!(err.to_string(), "failed to load level");
+        assert_eq!(err.kind(), ErrorKind::Io);
+        assert!(err.is_recoverable());
+        assert_eq!(err.source().unwrap().to_string(), "file not found");
+    }
+}
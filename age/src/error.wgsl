@@ -0,0 +1,23 @@
+// Drawn in place of a shader that failed to compile, so that the mistake is obvious on screen
+// rather than crashing the game.
+
+@group(0) @binding(0)
+var<storage, read> r_globals: array<mat4x4<f32>>;
+
+struct PushConstant {
+    color: vec4<f32>,
+    model: mat4x4<f32>,
+    globals_idx: u32,
+}
+
+var<push_constant> r_pc: PushConstant;
+
+@vertex
+fn vs_main(@location(0) pos: vec2<f32>) -> @builtin(position) vec4<f32> {
+    return r_globals[r_pc.globals_idx] * r_pc.model * vec4(pos, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4(1.0, 0.0, 1.0, 1.0);
+}
@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+
+use crate::{Error, ErrorKind, Image};
+
+/// The environment variable naming the directory that frames are dumped to.
+const DUMP_FRAMES_VAR: &str = "AGE_DUMP_FRAMES";
+
+/// The timestep, in seconds, that frames advance by while dumping frames.
+pub(crate) const DUMP_FRAME_TIMESTEP: f32 = 1.0 / 60.0;
+
+/// The seed given to games while dumping frames, so random choices are the same on every run.
+pub(crate) const DUMP_FRAME_SEED: u64 = 0;
+
+/// Writes each presented frame to a numbered PNG, so that runs can be compared against reference
+/// images in CI.
+pub(crate) struct FrameDump {
+    dir: PathBuf,
+    frame: u64,
+}
+
+impl FrameDump {
+    /// A frame dump into the directory named by `AGE_DUMP_FRAMES`, if it is set.
+    pub(crate) fn from_env() -> Option<Self> {
+        let dir = std::env::var_os(DUMP_FRAMES_VAR).filter(|dir| !dir.is_empty())?;
+        Some(Self::new(PathBuf::from(dir)))
+    }
+
+    fn new(dir: PathBuf) -> Self {
+        Self { dir, frame: 0 }
+    }
+
+    /// Writes `image` as the next frame, creating the directory if needed.
+    pub(crate) fn write(&mut self, image: &Image) -> Result<(), Error> {
+        std::fs::create_dir_all(&self.dir).map_err(|err| {
+            Error::new(format!(
+                "failed to create frame dump directory '{}'",
+                self.dir.display()
+            ))
+            .with_kind(ErrorKind::Io)
+            .with_source(err)
+        })?;
+
+        let path = self.frame_path(self.frame);
+        self.frame += 1;
+        image.save(path)
+    }
+
+    fn frame_path(&self, frame: u64) -> PathBuf {
+        self.dir.join(format!("frame-{:06}.png", frame))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frames_are_numbered_in_order() {
+        let dir = std::env::temp_dir().join(format!("age-frame-dump-{}", std::process::id()));
+        let mut dump = FrameDump::new(dir.clone());
+        let image = Image::new(1, 1, vec![10, 20, 30, 255]).unwrap();
+
+        dump.write(&image).unwrap();
+        dump.write(&image).unwrap();
+
+        assert_eq!(dump.frame_path(0), dir.join("frame-000000.png"));
+        let bytes = std::fs::read(dir.join("frame-000001.png")).unwrap();
+        assert_eq!(Image::from_png(&bytes).unwrap(), image);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,80 @@
+use std::{fmt::Write, time::Duration};
+
+/// How long the CPU spent on each part of a frame, from [`Engine::frame_stats`](crate::Engine::frame_stats).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStats {
+    /// From the start of the frame to it being presented, including the game's update.
+    pub frame: Duration,
+    /// Encoding the frame's render passes into command buffers.
+    pub encode: Duration,
+    /// Submitting the command buffers to the GPU queue.
+    pub submit: Duration,
+    /// Waiting for a surface texture to draw to and presenting it. Long waits usually mean the
+    /// GPU or the display is holding the frame back rather than the CPU.
+    pub present: Duration,
+    /// The render pass that took longest to encode.
+    pub slowest_pass: Option<PassStats>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PassStats {
+    /// The position of the pass among the frame's render passes.
+    pub index: usize,
+    pub draws: usize,
+    pub encode: Duration,
+}
+
+impl FrameStats {
+    /// A warning describing where the frame's time went, if it took longer than `budget`.
+    pub(crate) fn budget_warning(&self, budget: Duration) -> Option<String> {
+        if self.frame <= budget {
+            return None;
+        }
+
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        let mut warning = format!(
+            "frame over budget: frame={:.2}ms budget={:.2}ms encode={:.2}ms submit={:.2}ms present={:.2}ms",
+            ms(self.frame),
+            ms(budget),
+            ms(self.encode),
+            ms(self.submit),
+            ms(self.present),
+        );
+        if let Some(pass) = self.slowest_pass {
+            let _ = write!(
+                warning,
+                " slowest_pass={} ({} draws, {:.2}ms)",
+                pass.index,
+                pass.draws,
+                ms(pass.encode)
+            );
+        }
+        Some(warning)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn warns_only_over_budget() {
+        let stats = FrameStats {
+            frame: Duration::from_millis(20),
+            encode: Duration::from_millis(6),
+            submit: Duration::from_millis(1),
+            present: Duration::from_millis(2),
+            slowest_pass: Some(PassStats {
+                index: 3,
+                draws: 120,
+                encode: Duration::from_micros(4500),
+            }),
+        };
+
+        assert_eq!(stats.budget_warning(Duration::from_millis(25)), None);
+        assert_eq!(
+            stats.budget_warning(Duration::from_micros(16667)).as_deref(),
+            Some("frame over budget: frame=20.00ms budget=16.67ms encode=6.00ms submit=1.00ms present=2.00ms slowest_pass=3 (120 draws, 4.50ms)")
+        );
+    }
+}
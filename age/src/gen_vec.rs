@@ -1,4 +1,7 @@
-use std::{collections::VecDeque, ops::Index};
+use std::{
+    collections::VecDeque,
+    ops::{Index, IndexMut},
+};
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
 pub(crate) struct GenIdx(u32);
@@ -68,9 +71,33 @@ impl<T> GenVec<T> {
             self.resources[index].gen += 1;
         }
 
+        self.free.push_back(index);
         self.resources[index].item.take()
     }
 
+    /// The resource at `idx`, or `None` if it has been removed.
+    pub(crate) fn get(&self, idx: GenIdx) -> Option<&T> {
+        let (index, gen) = idx.split();
+        self.resources
+            .get(index)
+            .filter(|resource| resource.gen == gen)
+            .and_then(|resource| resource.item.as_ref())
+    }
+
+    pub(crate) fn get_mut(&mut self, idx: GenIdx) -> Option<&mut T> {
+        let (index, gen) = idx.split();
+        self.resources
+            .get_mut(index)
+            .filter(|resource| resource.gen == gen)
+            .and_then(|resource| resource.item.as_mut())
+    }
+
+    /// The indices of every resource that has not been removed.
+    pub(crate) fn indices(&self) -> impl Iterator<Item = GenIdx> + '_ {
+        self.iter().map(|(idx, _)| idx)
+    }
+
+    /// Every resource that has not been removed, along with its index.
     pub(crate) fn iter(&self) -> GenVecIter<'_, T> {
         GenVecIter {
             next: 0,
@@ -85,7 +112,7 @@ pub(crate) struct GenVecIter<'a, T> {
 }
 
 impl<'a, T> Iterator for GenVecIter<'a, T> {
-    type Item = &'a T;
+    type Item = (GenIdx, &'a T);
 
     fn next(&mut self) -> Option<Self::Item> {
         let resource = self.resources.get(self.next);
@@ -93,8 +120,9 @@ impl<'a, T> Iterator for GenVecIter<'a, T> {
         match resource {
             Some(resource) => {
                 let gen_idx = GenIdx::new(self.next, resource.gen);
+                self.next += 1;
                 match &resource.item {
-                    Some(item) => Some(item),
+                    Some(item) => Some((gen_idx, item)),
                     None => self.next(),
                 }
             }
@@ -116,3 +144,37 @@ impl<T> Index<GenIdx> for GenVec<T> {
         self.resources[index].item.as_ref().unwrap()
     }
 }
+
+impl<T> IndexMut<GenIdx> for GenVec<T> {
+    fn index_mut(&mut self, idx: GenIdx) -> &mut Self::Output {
+        let (index, gen) = idx.split();
+        assert_eq!(
+            gen, self.resources[index].gen,
+            "resource generation does not match"
+        );
+
+        self.resources[index].item.as_mut().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn iteration_skips_removed_resources() {
+        let mut resources = GenVec::default();
+        let a = resources.add("a");
+        let b = resources.add("b");
+        let c = resources.add("c");
+        resources.remove(b);
+
+        assert_eq!(resources.iter().collect::<Vec<_>>(), [(a, &"a"), (c, &"c")]);
+        assert_eq!(resources.indices().collect::<Vec<_>>(), [a, c]);
+
+        // The free slot is reused under a new generation.
+        let d = resources.add("d");
+        assert_ne!(b, d);
+        assert_eq!(resources.iter().map(|(_, r)| *r).collect::<String>(), "adc");
+    }
+}
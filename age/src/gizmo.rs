@@ -0,0 +1,394 @@
+use std::f32::consts::{PI, TAU};
+
+use crate::{
+    graphics::View,
+    math::{rect, v2, Vec2f},
+    Color, Graphics, Mouse, MouseButton,
+};
+
+/// Which part of an object's transform a [`Gizmo`] edits.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GizmoMode {
+    #[default]
+    Translate,
+    Rotate,
+    Scale,
+}
+
+/// The part of a [`Gizmo`] that is being dragged, which constrains the edit to an axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GizmoHandle {
+    X,
+    Y,
+    Both,
+}
+
+/// The change to apply to an object's transform since the last update of a [`Gizmo`]. The
+/// translation and rotation are added and the scale is multiplied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GizmoDelta {
+    pub translation: Vec2f,
+    pub rotation: f32,
+    pub scale: Vec2f,
+}
+
+impl GizmoDelta {
+    pub const IDENTITY: Self = Self {
+        translation: Vec2f::ZERO,
+        rotation: 0.0,
+        scale: Vec2f::ONE,
+    };
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Drag {
+    handle: GizmoHandle,
+    start: Vec2f,
+    angle: f32,
+    total: GizmoDelta,
+    applied: GizmoDelta,
+}
+
+/// Handles for moving, rotating and scaling an object with the mouse, as used by level editors
+/// and debug tools.
+///
+/// Call [`Gizmo::update`] each frame with the object's position and apply the returned delta to
+/// the object, then draw the gizmo over it with [`Gizmo::draw`].
+#[derive(Debug, Clone)]
+pub struct Gizmo {
+    mode: GizmoMode,
+    size: f32,
+    translate_snap: Option<f32>,
+    rotate_snap: Option<f32>,
+    scale_snap: Option<f32>,
+    hovered: Option<GizmoHandle>,
+    drag: Option<Drag>,
+}
+
+impl Default for Gizmo {
+    fn default() -> Self {
+        Self::new(GizmoMode::default())
+    }
+}
+
+impl Gizmo {
+    const HIT_DISTANCE: f32 = 6.0;
+    const LINE_WIDTH: f32 = 2.0;
+    const MIN_SCALE: f32 = 0.01;
+
+    pub fn new(mode: GizmoMode) -> Self {
+        Self {
+            mode,
+            size: 64.0,
+            translate_snap: None,
+            rotate_snap: None,
+            scale_snap: None,
+            hovered: None,
+            drag: None,
+        }
+    }
+
+    /// The length of the handles on screen in pixels, which stays the same at any zoom.
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Snaps the total translation of a drag to multiples of `step` world units.
+    pub fn with_translate_snap(mut self, step: f32) -> Self {
+        self.translate_snap = Some(step);
+        self
+    }
+
+    /// Snaps the total rotation of a drag to multiples of `step` radians.
+    pub fn with_rotate_snap(mut self, step: f32) -> Self {
+        self.rotate_snap = Some(step);
+        self
+    }
+
+    /// Snaps the total scale of a drag to multiples of `step`.
+    pub fn with_scale_snap(mut self, step: f32) -> Self {
+        self.scale_snap = Some(step);
+        self
+    }
+
+    pub fn get_mode(&self) -> GizmoMode {
+        self.mode
+    }
+
+    /// Changes what the gizmo edits, which ends any drag in progress.
+    pub fn set_mode(&mut self, mode: GizmoMode) {
+        self.mode = mode;
+        self.drag = None;
+    }
+
+    /// The handle under the mouse, or being dragged.
+    pub fn active_handle(&self) -> Option<GizmoHandle> {
+        self.drag.map(|drag| drag.handle).or(self.hovered)
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Drags the handles of a gizmo at `position` with the left mouse button, returning how the
+    /// object's transform should change, if at all.
+    pub fn update(&mut self, mouse: &Mouse, view: &View, position: Vec2f) -> Option<GizmoDelta> {
+        let cursor = view.screen_to_world(mouse.position_physical());
+        self.update_with(
+            position,
+            cursor,
+            mouse.pressed(MouseButton::Left),
+            mouse.held(MouseButton::Left),
+            view.get_zoom(),
+        )
+    }
+
+    fn update_with(
+        &mut self,
+        position: Vec2f,
+        cursor: Vec2f,
+        pressed: bool,
+        held: bool,
+        zoom: f32,
+    ) -> Option<GizmoDelta> {
+        let length = self.size / zoom;
+
+        if !held {
+            self.drag = None;
+        }
+
+        let mut drag = match self.drag {
+            Some(drag) => drag,
+            None => {
+                self.hovered = self.hit_test(position, cursor, length, Self::HIT_DISTANCE / zoom);
+                if let (Some(handle), true) = (self.hovered, pressed) {
+                    self.drag = Some(Drag {
+                        handle,
+                        start: cursor,
+                        angle: angle(cursor - position),
+                        total: GizmoDelta::IDENTITY,
+                        applied: GizmoDelta::IDENTITY,
+                    });
+                }
+                return None;
+            }
+        };
+
+        let offset = cursor - drag.start;
+        match self.mode {
+            GizmoMode::Translate => {
+                drag.total.translation = match drag.handle {
+                    GizmoHandle::X => v2(offset.x, 0.0),
+                    GizmoHandle::Y => v2(0.0, offset.y),
+                    GizmoHandle::Both => offset,
+                };
+            }
+            GizmoMode::Rotate => {
+                // Accumulate the change in angle so that the total can exceed a half turn.
+                let current = angle(cursor - position);
+                drag.total.rotation += wrap_angle(current - drag.angle);
+                drag.angle = current;
+            }
+            GizmoMode::Scale => {
+                let along = |d: f32| (1.0 + d / length).max(Self::MIN_SCALE);
+                drag.total.scale = match drag.handle {
+                    GizmoHandle::X => v2(along(offset.x), 1.0),
+                    GizmoHandle::Y => v2(1.0, along(offset.y)),
+                    GizmoHandle::Both => {
+                        let uniform = along((offset.x + offset.y) / 2.0);
+                        v2(uniform, uniform)
+                    }
+                };
+            }
+        }
+
+        let snapped = self.snap(drag.total);
+        let delta = GizmoDelta {
+            translation: snapped.translation - drag.applied.translation,
+            rotation: snapped.rotation - drag.applied.rotation,
+            scale: snapped.scale / drag.applied.scale,
+        };
+        drag.applied = snapped;
+        self.drag = Some(drag);
+
+        (delta != GizmoDelta::IDENTITY).then_some(delta)
+    }
+
+    fn hit_test(
+        &self,
+        position: Vec2f,
+        cursor: Vec2f,
+        length: f32,
+        distance: f32,
+    ) -> Option<GizmoHandle> {
+        let local = cursor - position;
+        match self.mode {
+            GizmoMode::Translate | GizmoMode::Scale => {
+                let center = length * 0.25;
+                if (0.0..=center).contains(&local.x) && (0.0..=center).contains(&local.y) {
+                    Some(GizmoHandle::Both)
+                } else if local.y.abs() <= distance && (0.0..=length).contains(&local.x) {
+                    Some(GizmoHandle::X)
+                } else if local.x.abs() <= distance && (0.0..=length).contains(&local.y) {
+                    Some(GizmoHandle::Y)
+                } else {
+                    None
+                }
+            }
+            GizmoMode::Rotate => {
+                ((local.len() - length).abs() <= distance).then_some(GizmoHandle::Both)
+            }
+        }
+    }
+
+    fn snap(&self, total: GizmoDelta) -> GizmoDelta {
+        let snap = |value: f32, step: Option<f32>| match step {
+            Some(step) if step > 0.0 => (value / step).round() * step,
+            _ => value,
+        };
+
+        GizmoDelta {
+            translation: v2(
+                snap(total.translation.x, self.translate_snap),
+                snap(total.translation.y, self.translate_snap),
+            ),
+            rotation: snap(total.rotation, self.rotate_snap),
+            scale: v2(
+                snap(total.scale.x, self.scale_snap).max(Self::MIN_SCALE),
+                snap(total.scale.y, self.scale_snap).max(Self::MIN_SCALE),
+            ),
+        }
+    }
+
+    /// Draws the handles of a gizmo at `position`, highlighting the active one.
+    pub fn draw(&self, graphics: &mut Graphics, view: &View, position: Vec2f) {
+        let length = self.size / view.get_zoom();
+        let width = Self::LINE_WIDTH / view.get_zoom();
+        let color = |handle: GizmoHandle, color: Color| {
+            if self.active_handle() == Some(handle) {
+                Color::YELLOW
+            } else {
+                color
+            }
+        };
+
+        match self.mode {
+            GizmoMode::Translate | GizmoMode::Scale => {
+                let x = color(GizmoHandle::X, Color::RED);
+                let y = color(GizmoHandle::Y, Color::GREEN);
+                graphics.draw_line(position, position + v2(length, 0.0), width, x);
+                graphics.draw_line(position, position + v2(0.0, length), width, y);
+
+                // Arrow heads for translating and boxes for scaling.
+                let tip = length * 0.1;
+                let (x_end, y_end) = (position + v2(length, 0.0), position + v2(0.0, length));
+                match self.mode {
+                    GizmoMode::Translate => {
+                        graphics.draw_line(x_end, x_end + v2(-tip, -tip), width, x);
+                        graphics.draw_line(x_end, x_end + v2(-tip, tip), width, x);
+                        graphics.draw_line(y_end, y_end + v2(-tip, -tip), width, y);
+                        graphics.draw_line(y_end, y_end + v2(tip, -tip), width, y);
+                    }
+                    _ => {
+                        let half = v2(tip, tip) / 2.0;
+                        graphics.fill_rect(rect(x_end - half, half * 2.0), x);
+                        graphics.fill_rect(rect(y_end - half, half * 2.0), y);
+                    }
+                }
+
+                let center = length * 0.25;
+                graphics.fill_rect(
+                    rect(position, v2(center, center)),
+                    color(GizmoHandle::Both, Color::BLUE),
+                );
+            }
+            GizmoMode::Rotate => {
+                const SEGMENTS: usize = 48;
+                let ring = color(GizmoHandle::Both, Color::BLUE);
+                let point = |i: usize| {
+                    let a = i as f32 / SEGMENTS as f32 * TAU;
+                    position + v2(a.cos(), a.sin()) * length
+                };
+                for i in 0..SEGMENTS {
+                    graphics.draw_line(point(i), point(i + 1), width, ring);
+                }
+            }
+        }
+    }
+}
+
+fn angle(v: Vec2f) -> f32 {
+    v.y.atan2(v.x)
+}
+
+/// Wraps an angle into the range `-PI..=PI`.
+fn wrap_angle(a: f32) -> f32 {
+    (a + PI).rem_euclid(TAU) - PI
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn drag(gizmo: &mut Gizmo, from: Vec2f, to: Vec2f) -> Option<GizmoDelta> {
+        gizmo.update_with(Vec2f::ZERO, from, false, false, 1.0);
+        gizmo.update_with(Vec2f::ZERO, from, true, true, 1.0);
+        gizmo.update_with(Vec2f::ZERO, to, false, true, 1.0)
+    }
+
+    #[test]
+    fn translate_is_constrained_to_axis() {
+        let mut gizmo = Gizmo::new(GizmoMode::Translate);
+        let delta = drag(&mut gizmo, v2(50.0, 0.0), v2(60.0, 7.0)).unwrap();
+        assert_eq!(gizmo.active_handle(), Some(GizmoHandle::X));
+        assert_eq!(delta.translation, v2(10.0, 0.0));
+    }
+
+    #[test]
+    fn translate_snaps_total_and_returns_increments() {
+        let mut gizmo = Gizmo::new(GizmoMode::Translate).with_translate_snap(8.0);
+        assert_eq!(drag(&mut gizmo, v2(5.0, 5.0), v2(8.0, 6.0)), None);
+
+        let delta = gizmo
+            .update_with(Vec2f::ZERO, v2(15.0, 18.0), false, true, 1.0)
+            .unwrap();
+        assert_eq!(delta.translation, v2(8.0, 16.0));
+
+        let delta = gizmo
+            .update_with(Vec2f::ZERO, v2(21.0, 18.0), false, true, 1.0)
+            .unwrap();
+        assert_eq!(delta.translation, v2(8.0, 0.0));
+    }
+
+    #[test]
+    fn rotate_follows_cursor_around_ring() {
+        let mut gizmo = Gizmo::new(GizmoMode::Rotate);
+        let delta = drag(&mut gizmo, v2(64.0, 0.0), v2(0.0, 64.0)).unwrap();
+        assert!((delta.rotation - PI / 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn scale_is_relative_to_handle_length() {
+        let mut gizmo = Gizmo::new(GizmoMode::Scale);
+        let delta = drag(&mut gizmo, v2(0.0, 64.0), v2(0.0, 128.0)).unwrap();
+        assert_eq!(delta.scale, v2(1.0, 2.0));
+
+        let delta = gizmo
+            .update_with(Vec2f::ZERO, v2(0.0, 96.0), false, true, 1.0)
+            .unwrap();
+        assert_eq!(delta.scale, v2(1.0, 0.75));
+    }
+
+    #[test]
+    fn releasing_ends_drag() {
+        let mut gizmo = Gizmo::new(GizmoMode::Translate);
+        drag(&mut gizmo, v2(50.0, 0.0), v2(60.0, 0.0));
+        assert!(gizmo.is_dragging());
+        assert_eq!(
+            gizmo.update_with(Vec2f::ZERO, v2(70.0, 0.0), false, false, 1.0),
+            None
+        );
+        assert!(!gizmo.is_dragging());
+    }
+}
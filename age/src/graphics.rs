@@ -1,13 +1,24 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    path::Path,
+    sync::{Mutex, PoisonError},
+};
+
 use crate::{
+    deform::{Deformation, LATTICE_INDICES},
     gen_vec::{GenIdx, GenVec},
-    math::{v2, Mat4, Vec2f},
+    math::{v2, Dimensions, Easing, Mat4, Rect, Vec2f},
     renderer::{
-        cast_slice, BindGroupDesc, BindGroupId, BindGroupLayoutDesc, BindGroupLayoutId,
-        BindingResource, BindingType, BufferDesc, BufferId, BufferUsages, CommandBuffer,
-        DrawCommand, DrawTarget, GeometryVertex, PipelineLayoutDesc, PipelineLayoutId, RenderData,
-        RenderPipelineDesc, RenderPipelineId, Renderer, ShaderDesc, ShaderId, TextureFormat,
+        cast_slice, region_fits, BindGroupDesc, BindGroupId, BindGroupLayoutDesc,
+        BindGroupLayoutId, BindingResource, BindingType, BufferDesc, BufferId, BufferUsages,
+        ClearPolicy, ColorTarget, ColorTargetDesc, CommandBuffer, DrawCommand, DrawTarget,
+        GeometryVertex, OutlinePass, PickPass, PipelineLayoutDesc, PipelineLayoutId, RenderData,
+        RenderPipelineDesc, RenderPipelineId, Renderer, SamplerDesc, SamplerId, ShaderDesc,
+        ShaderId, TextureDesc, TextureFormat, TextureId, TextureOp, TextureReadbackId,
+        TextureViewDesc, TextureViewId, Vertex, VertexBufferType,
     },
-    Color,
+    texture_pool::{TexturePool, TransientTextureDesc, TransientTextureStats},
+    AddressMode, BlendMode, Color, DecalMap, Engine, Error, ErrorKind, FilterMode, Image, LightMap,
 };
 
 pub struct Graphics {
@@ -20,14 +31,45 @@ pub struct Graphics {
     globals_bgl: BindGroupLayoutId,
     globals_bg: BindGroupId,
     globals_sbo: BufferId,
+    texture_bgl: BindGroupLayoutId,
+    white_texture: Texture,
+    light_pipeline: RenderPipelineId,
+    pick_pipeline: RenderPipelineId,
+    outline_pipeline: RenderPipelineId,
+    blend_pipelines: [RenderPipelineId; BlendMode::ALL.len()],
+    unit_quad: Mesh,
+    deformed: Mesh, // Lattice indices over the deformed vertices of every sprite drawn this frame.
+    deformed_vertices: Vec<GeometryVertex>,
 
     materials: GenVec<Material>,
 
     draw_target: DrawTarget,
     clear_color: Option<Color>,
+    drawn_targets: HashSet<TextureViewId>, // Drawn to this frame, for ClearPolicy::OnFirstUse.
     needs_render_pass: bool,
     draws: CommandBuffer,
     views: Vec<View>,
+    view_idx: usize, // Index in `views` of the view being drawn with.
+    lights_return_target: Option<DrawTarget>,
+    transient_textures: TexturePool<RenderTexture>,
+    draw_order: DrawOrder,
+    outline: Option<(Color, f32)>, // Outline of the draw being submitted by DrawParams.
+    next_readback: u64,
+}
+
+/// The thickest outline, in pixels, that can be drawn with [`DrawParams::outline`].
+pub const MAX_OUTLINE_THICKNESS: f32 = 8.0;
+
+/// The order draws are made in within a render pass.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DrawOrder {
+    /// The order they were submitted in, so later draws are drawn over earlier ones.
+    #[default]
+    Submission,
+    /// Grouped by pipeline, texture and mesh to reduce state changes, for draws that do not
+    /// overlap or whose order does not matter, such as opaque tiles. Draws submitted in this
+    /// order are still drawn after earlier draws submitted in order and before later ones.
+    ByState,
 }
 
 impl Graphics {
@@ -42,9 +84,19 @@ impl Graphics {
             }],
         });
 
+        let texture_bgl = renderer.create_bind_group_layout(&BindGroupLayoutDesc {
+            label,
+            entries: &[
+                BindingType::Sampler,
+                BindingType::Texture {
+                    multisampled: false,
+                },
+            ],
+        });
+
         let default_pl = renderer.create_pipeline_layout(&PipelineLayoutDesc {
             label,
-            bind_group_layouts: &[globals_bgl],
+            bind_group_layouts: &[globals_bgl, texture_bgl],
         });
 
         let default_shader = renderer.create_shader(ShaderDesc {
@@ -59,7 +111,72 @@ impl Graphics {
             vs_main: "vs_main",
             fs_main: "fs_main",
             buffers: &[renderer.geometry_vertex_buffer_layout()],
-            color_target_format: TextureFormat::Rgba8Unorm,
+            color_targets: &[ColorTargetDesc::new(
+                TextureFormat::Rgba8Unorm,
+                BlendMode::Replace,
+            )],
+        });
+
+        let light_pipeline = renderer.create_render_pipeline(&RenderPipelineDesc {
+            label: Some("light"),
+            layout: default_pl,
+            shader: default_shader,
+            vs_main: "vs_main",
+            fs_main: "fs_main",
+            buffers: &[renderer.geometry_vertex_buffer_layout()],
+            color_targets: &[ColorTargetDesc::new(
+                TextureFormat::Rgba8Unorm,
+                BlendMode::Additive,
+            )],
+        });
+
+        let pick_shader = renderer.create_shader(ShaderDesc {
+            label: Some("pick"),
+            source: include_str!("pick.wgsl"),
+        });
+
+        let pick_pipeline = renderer.create_render_pipeline(&RenderPipelineDesc {
+            label: Some("pick"),
+            layout: default_pl,
+            shader: pick_shader,
+            vs_main: "vs_main",
+            fs_main: "fs_main",
+            buffers: &[renderer.geometry_vertex_buffer_layout()],
+            color_targets: &[ColorTargetDesc::new(
+                TextureFormat::R32Uint,
+                BlendMode::Replace,
+            )],
+        });
+
+        let outline_shader = renderer.create_shader(ShaderDesc {
+            label: Some("outline mask"),
+            source: include_str!("outline_mask.wgsl"),
+        });
+
+        let outline_pipeline = renderer.create_render_pipeline(&RenderPipelineDesc {
+            label: Some("outline mask"),
+            layout: default_pl,
+            shader: outline_shader,
+            vs_main: "vs_main",
+            fs_main: "fs_main",
+            buffers: &[renderer.geometry_vertex_buffer_layout()],
+            color_targets: &[ColorTargetDesc::new(
+                TextureFormat::Rgba8Unorm,
+                BlendMode::Replace,
+            )],
+        });
+
+        // Decals and whole textures are drawn with the default shader, in any blend mode.
+        let blend_pipelines = BlendMode::ALL.map(|blend| {
+            renderer.create_render_pipeline(&RenderPipelineDesc {
+                label: Some("splat"),
+                layout: default_pl,
+                shader: default_shader,
+                vs_main: "vs_main",
+                fs_main: "fs_main",
+                buffers: &[renderer.geometry_vertex_buffer_layout()],
+                color_targets: &[ColorTargetDesc::new(TextureFormat::Rgba8Unorm, blend)],
+            })
         });
 
         let globals_sbo = renderer.create_buffer(&BufferDesc {
@@ -84,14 +201,42 @@ impl Graphics {
             globals_bgl,
             globals_bg,
             globals_sbo,
+            texture_bgl,
+            white_texture: Texture::INVALID,
+            light_pipeline,
+            pick_pipeline,
+            outline_pipeline,
+            blend_pipelines,
+            // Lines and rects are drawn with the default pipeline rather than a material.
+            unit_quad: Mesh::new(
+                renderer,
+                &Sprite::VERTICES,
+                &Sprite::INDICES,
+                MaterialId::INVALID,
+            ),
+            // The vertex buffer grows to fit the deformed sprites drawn in a frame.
+            deformed: Mesh::new(
+                renderer,
+                &Deformation::NONE.vertices(Vec2f::ONE, Vec2f::ZERO, Vec2f::ONE),
+                &LATTICE_INDICES,
+                MaterialId::INVALID,
+            ),
+            deformed_vertices: Vec::new(),
 
             materials: GenVec::default(),
 
             draw_target: DrawTarget::INVALID,
             clear_color: None,
+            drawn_targets: HashSet::new(),
             needs_render_pass: true,
             draws: CommandBuffer::default(),
             views: Vec::new(),
+            view_idx: 0,
+            lights_return_target: None,
+            transient_textures: TexturePool::default(),
+            draw_order: DrawOrder::default(),
+            outline: None,
+            next_readback: 0,
         };
 
         graphics.default_material = graphics.create_material(&MaterialDesc {
@@ -99,9 +244,235 @@ impl Graphics {
             pipeline: graphics.default_pipeline(),
         });
 
+        // Untextured sprites sample a single white texel, so that they are drawn in their color.
+        graphics.white_texture = graphics
+            .create_texture_from_image(
+                renderer,
+                &Image::from_color(1, 1, Color::WHITE),
+                &TextureOptions::default(),
+            )
+            .expect("a 1x1 texture is always supported");
+
         graphics
     }
 
+    /// Creates a texture whose pixels are all transparent black.
+    pub fn create_texture(
+        &mut self,
+        renderer: &mut Renderer,
+        width: u32,
+        height: u32,
+        options: &TextureOptions,
+    ) -> Texture {
+        // todo: srgb once the backbuffer is srgb.
+        self.create_texture_with_format(renderer, width, height, TextureFormat::Rgba8Unorm, options)
+    }
+
+    fn create_texture_with_format(
+        &mut self,
+        renderer: &mut Renderer,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        options: &TextureOptions,
+    ) -> Texture {
+        let label = options.label;
+
+        let texture = renderer.create_texture(&TextureDesc {
+            label,
+            width,
+            height,
+            format,
+        });
+
+        let view = renderer.create_texture_view(&TextureViewDesc {
+            label,
+            texture,
+            format,
+        });
+
+        let sampler = renderer.create_sampler(&SamplerDesc {
+            label,
+            address_mode_u: options.address_mode,
+            address_mode_v: options.address_mode,
+            mag_filter: options.filter,
+            min_filter: options.filter,
+        });
+
+        let bg = renderer.create_bind_group(&BindGroupDesc {
+            label,
+            layout: self.texture_bgl,
+            resources: &[
+                BindingResource::Sampler(sampler),
+                BindingResource::TextureView(view),
+            ],
+        });
+
+        Texture {
+            texture,
+            view,
+            sampler,
+            bg,
+            width,
+            height,
+        }
+    }
+
+    /// A render texture matching `desc` for drawing to within this frame, such as a scratch target
+    /// for post-processing, reusing one from an earlier frame where possible. It is returned at the
+    /// end of the frame, so must not be kept, and its contents are undefined until it has been
+    /// cleared or drawn to.
+    ///
+    /// Panics if `desc` has a format that cannot be sampled as a color, such as `R32Uint`.
+    pub fn acquire_transient_texture(
+        &mut self,
+        renderer: &mut Renderer,
+        desc: &TransientTextureDesc,
+    ) -> RenderTexture {
+        assert_ne!(
+            desc.format,
+            TextureFormat::R32Uint,
+            "transient textures must have a color format"
+        );
+
+        if let Some(target) = self.transient_textures.acquire(desc) {
+            return target;
+        }
+
+        let options = TextureOptions {
+            label: Some("transient texture"),
+            ..Default::default()
+        };
+        let target = RenderTexture {
+            texture: self.create_texture_with_format(
+                renderer,
+                desc.width,
+                desc.height,
+                desc.format,
+                &options,
+            ),
+            options: RenderTextureOptions::from(&options),
+            clear_policy: ClearPolicy::default(),
+        };
+        self.transient_textures.insert(desc, target);
+        target
+    }
+
+    /// How transient textures were used over the last frame.
+    pub fn transient_texture_stats(&self) -> TransientTextureStats {
+        self.transient_textures.stats()
+    }
+
+    /// Uploads `image` to a new texture that sprites can be drawn with. Fails if the image is
+    /// empty or larger than the GPU's maximum texture size.
+    pub fn create_texture_from_image(
+        &mut self,
+        renderer: &mut Renderer,
+        image: &Image,
+        options: &TextureOptions,
+    ) -> Result<Texture, Error> {
+        check_texture_size(image.width(), image.height(), renderer.max_texture_size())?;
+        let texture = self.create_texture(renderer, image.width(), image.height(), options);
+        renderer.write_texture(texture.texture, image.pixels())?;
+        Ok(texture)
+    }
+
+    /// Creates a texture that can be set as the draw target and then drawn like any other texture.
+    pub fn create_render_texture(
+        &mut self,
+        renderer: &mut Renderer,
+        width: u32,
+        height: u32,
+        options: &TextureOptions,
+    ) -> RenderTexture {
+        RenderTexture {
+            texture: self.create_texture(renderer, width, height, options),
+            options: RenderTextureOptions::from(options),
+            clear_policy: ClearPolicy::default(),
+        }
+    }
+
+    /// Recreates `target` at a new size, e.g. when the window has been resized. Its contents are
+    /// cleared. Sprites created from it keep drawing it, with the size and texture coordinates
+    /// they were created with.
+    pub fn resize_render_texture(
+        &mut self,
+        renderer: &mut Renderer,
+        target: &mut RenderTexture,
+        width: u32,
+        height: u32,
+    ) {
+        let options = TextureOptions {
+            label: target.options.label,
+            address_mode: target.options.address_mode,
+            filter: target.options.filter,
+        };
+        let mut resized = self.create_texture(renderer, width, height, &options);
+
+        // Sprites hold the bind group, so the new texture is bound in its place.
+        renderer.replace_bind_group(target.texture.bg, resized.bg);
+        resized.bg = target.texture.bg;
+        let old = std::mem::replace(&mut target.texture, resized);
+        renderer.destroy_sampler(old.sampler);
+        renderer.destroy_texture_view(old.view);
+        renderer.destroy_texture(old.texture);
+    }
+
+    /// Creates a light map that darkens the scene to `ambient` wherever it is not lit.
+    pub fn create_light_map(
+        &mut self,
+        renderer: &mut Renderer,
+        width: u32,
+        height: u32,
+        ambient: Color,
+    ) -> LightMap {
+        let options = TextureOptions {
+            label: Some("light map"),
+            ..Default::default()
+        };
+        let target = self.create_render_texture(renderer, width, height, &options);
+        LightMap::new(target, ambient)
+    }
+
+    /// Recreates `light_map` at a new size, e.g. to match the window after it has been resized.
+    pub fn resize_light_map(
+        &mut self,
+        renderer: &mut Renderer,
+        light_map: &mut LightMap,
+        width: u32,
+        height: u32,
+    ) {
+        self.resize_render_texture(renderer, light_map.target_mut(), width, height);
+    }
+
+    /// Creates a decal map covering `bounds` in the world, with `texels_per_unit` texels for each
+    /// world unit. It starts out transparent.
+    pub fn create_decal_map(
+        &mut self,
+        renderer: &mut Renderer,
+        bounds: Rect,
+        texels_per_unit: f32,
+    ) -> DecalMap {
+        let (width, height) = DecalMap::texture_size(bounds, texels_per_unit);
+        let options = TextureOptions {
+            label: Some("decal map"),
+            ..Default::default()
+        };
+        let mut target = self.create_render_texture(renderer, width, height, &options);
+        target.set_clear_policy(ClearPolicy::Never);
+        DecalMap::new(target, bounds)
+    }
+
+    /// Loads a PNG image from a file and uploads it to a new texture with the default options.
+    pub fn load_texture<P: AsRef<Path>>(
+        &mut self,
+        renderer: &mut Renderer,
+        path: P,
+    ) -> Result<Texture, Error> {
+        let image = Image::load(path)?;
+        self.create_texture_from_image(renderer, &image, &TextureOptions::default())
+    }
+
     pub fn create_material(&mut self, desc: &MaterialDesc) -> MaterialId {
         let material = Material {
             label: desc.label.map(|s| s.to_string()),
@@ -115,6 +486,14 @@ impl Graphics {
         self.default_material
     }
 
+    /// The label `material` was created with, if any, e.g. to tell materials apart when
+    /// debugging.
+    pub fn material_label(&self, material: MaterialId) -> Option<&str> {
+        self.materials
+            .get(material.0)
+            .and_then(|material| material.label.as_deref())
+    }
+
     pub fn default_pipeline(&self) -> RenderPipelineId {
         self.default_pipeline
     }
@@ -131,8 +510,16 @@ impl Graphics {
         self.globals_bgl
     }
 
+    pub fn texture_bind_group_layout(&self) -> BindGroupLayoutId {
+        self.texture_bgl
+    }
+
+    /// A 1x1 white texture, used by sprites that are not created from a texture.
+    pub fn white_texture(&self) -> &Texture {
+        &self.white_texture
+    }
+
     pub(crate) fn data(&self) -> RenderData {
-        // todo: where does the buffer get resized if the data is larger?
         let mut data = Vec::with_capacity(std::mem::size_of::<[f32; 16]>() * self.views.len());
         for v in self.views.iter() {
             data.extend(cast_slice(&v.view_projection().to_cols_array()));
@@ -140,8 +527,12 @@ impl Graphics {
 
         RenderData {
             dest: self.globals_sbo,
+            globals_bgl: self.globals_bgl,
+            globals_bg: self.globals_bg,
             size: std::mem::size_of::<Mat4>() * self.views.len(),
             data,
+            vertices_dest: self.deformed.buffers.vbo,
+            vertices: cast_slice(&self.deformed_vertices).to_vec(),
         }
     }
 
@@ -149,9 +540,25 @@ impl Graphics {
         &self.draws
     }
 
-    pub(crate) fn reset(&mut self) {
+    /// Submits everything drawn so far this frame to the GPU without waiting for the end of the
+    /// frame, e.g. so a texture that has just been drawn can be read back straight away, or to
+    /// keep the recorded commands small in frames with a great many draws. Drawing carries on
+    /// into a new render pass with the same draw target and view.
+    pub fn flush(&mut self, renderer: &mut Renderer) {
+        renderer.flush(self.data(), self.draws.clone());
+        self.draws.clear_passes();
+        self.needs_render_pass = true;
+    }
+
+    pub(crate) fn reset(&mut self, renderer: &mut Renderer) {
         self.draws.clear();
         self.views.clear();
+        self.view_idx = 0;
+        self.deformed_vertices.clear();
+        self.drawn_targets.clear();
+        for target in self.transient_textures.end_frame() {
+            destroy_texture(renderer, &target.texture);
+        }
     }
 }
 
@@ -163,20 +570,442 @@ impl Graphics {
     }
 
     pub fn draw_sprite(&mut self, sprite: &Sprite) {
+        self.draw_sprite_with(sprite, sprite.color, sprite.get_transform(), 0);
+    }
+
+    /// Starts drawing `sprite` with parameters that override its own, e.g.
+    /// `graphics.draw(&sprite).at(position).origin_center().tint(Color::RED).submit()`.
+    pub fn draw<'a>(&'a mut self, sprite: &'a Sprite) -> DrawParams<'a> {
+        DrawParams {
+            graphics: self,
+            sprite,
+            color: sprite.color,
+            origin: sprite.origin,
+            position: sprite.position,
+            rotation: sprite.rotation,
+            scale: sprite.scale,
+            pick_id: 0,
+            outline: None,
+        }
+    }
+
+    fn draw_sprite_with(&mut self, sprite: &Sprite, color: Color, model: Mat4, pick_id: u32) {
+        let mesh = self.sprite_mesh(sprite);
+        self.draw_mesh_with(&mesh, sprite.texture_bg, color, model, pick_id);
+    }
+
+    /// The mesh to draw `sprite` with, which for a deformed sprite is a lattice over vertices
+    /// deformed for this draw alone.
+    fn sprite_mesh(&mut self, sprite: &Sprite) -> Mesh {
+        if sprite.deformation == Deformation::NONE {
+            return sprite.mesh.clone();
+        }
+
+        let base_vertex = self.deformed_vertices.len() as i32;
+        self.deformed_vertices.extend(sprite.deformation.vertices(
+            sprite.size_vec2(),
+            sprite.uv_min,
+            sprite.uv_size,
+        ));
+        Mesh {
+            material: sprite.mesh.material,
+            base_vertex,
+            ..self.deformed.clone()
+        }
+    }
+
+    /// Draws `mesh` in a single color, transformed by `model`.
+    pub fn draw_mesh(&mut self, mesh: &Mesh, color: Color, model: Mat4) {
+        self.draw_mesh_with(mesh, None, color, model, 0);
+    }
+
+    fn draw_mesh_with(
+        &mut self,
+        mesh: &Mesh,
+        texture_bg: Option<BindGroupId>,
+        color: Color,
+        model: Mat4,
+        pick_id: u32,
+    ) {
+        let pipeline = self.materials[mesh.material.0].pipeline;
+        self.draw_mesh_with_pipeline(mesh, pipeline, texture_bg, color, model, pick_id);
+    }
+
+    fn draw_mesh_with_pipeline(
+        &mut self,
+        mesh: &Mesh,
+        pipeline: RenderPipelineId,
+        texture_bg: Option<BindGroupId>,
+        color: Color,
+        model: Mat4,
+        pick_id: u32,
+    ) {
         self.push_draw_command(DrawCommand {
-            pipeline: self.materials[sprite.mesh.material.0].pipeline,
-            vbo: sprite.mesh.buffers.vbo,
-            ibo: sprite.mesh.buffers.ibo,
-            index_count: 6,
+            pipeline,
+            vbo: mesh.buffers.vbo,
+            ibo: mesh.buffers.ibo,
+            index_count: mesh.index_count,
+            base_vertex: mesh.base_vertex,
+            instances: mesh.instances,
 
             // todo: these need to move to a per-scene ubo.
             globals_bg: self.globals_bg,
+            texture_bg: texture_bg.unwrap_or(self.white_texture.bg),
 
             // todo: these need to move to a per-object ubo.
-            color: sprite.color,
-            model: sprite.get_transform(),
-            globals_idx: self.views.len() - 1,
+            color,
+            model,
+            globals_idx: self.view_idx,
+            pick_id,
+            occlusion_query: None,
+            sortable: self.draw_order == DrawOrder::ByState,
+        });
+    }
+
+    /// Draws the whole of `texture` stretched over `rect`, blended with what has been drawn
+    /// already.
+    pub fn draw_texture(&mut self, texture: &Texture, rect: Rect, blend: BlendMode) {
+        let model = Mat4::translation(rect.position) * Mat4::scale(rect.size);
+        let quad = self.unit_quad.clone();
+        self.draw_mesh_with_pipeline(
+            &quad,
+            self.blend_pipelines[blend as usize],
+            Some(texture.bg),
+            Color::WHITE,
+            model,
+            0,
+        );
+    }
+
+    /// Draws a line `width` pixels wide from `from` to `to`.
+    pub fn draw_line(&mut self, from: Vec2f, to: Vec2f, width: f32, color: Color) {
+        let delta = to - from;
+        let model = Mat4::translation(from)
+            * Mat4::rotation(delta.y.atan2(delta.x))
+            * Mat4::scale(v2(delta.len(), width))
+            * Mat4::translation(v2(0.0, -0.5));
+        self.draw_quad(color, model);
+    }
+
+    /// Draws `rect` filled with `color`.
+    pub fn fill_rect(&mut self, rect: Rect, color: Color) {
+        let model = Mat4::translation(rect.position) * Mat4::scale(rect.size);
+        self.draw_quad(color, model);
+    }
+
+    /// Draws a grid over everything that `view` can see, with a major line every five lines of
+    /// `spacing` world units, plus the x and y axes and a marker at the origin. Lines are merged
+    /// as the view zooms out so that the grid does not become too dense to read.
+    pub fn draw_grid(&mut self, spacing: f32, color: Color, view: &View) {
+        let (min, max) = view.visible_bounds();
+        let zoom = view.get_zoom();
+        let step = grid_step(spacing, zoom);
+        let width = 1.0 / zoom;
+        let minor = Color::rgba(color.r * 0.5, color.g * 0.5, color.b * 0.5, color.a);
+
+        let lines = |min: f32, max: f32| {
+            let first = (min / step).floor() as i64;
+            let last = (max / step).ceil() as i64;
+            (first..=last).map(move |i| (i as f32 * step, i % GRID_MAJOR_EVERY as i64 == 0))
+        };
+
+        for (x, major) in lines(min.x, max.x) {
+            let color = if major { color } else { minor };
+            self.draw_line(v2(x, min.y), v2(x, max.y), width, color);
+        }
+        for (y, major) in lines(min.y, max.y) {
+            let color = if major { color } else { minor };
+            self.draw_line(v2(min.x, y), v2(max.x, y), width, color);
+        }
+
+        self.draw_line(v2(min.x, 0.0), v2(max.x, 0.0), width * 2.0, Color::RED);
+        self.draw_line(v2(0.0, min.y), v2(0.0, max.y), width * 2.0, Color::GREEN);
+        let marker = v2(6.0, 6.0) / zoom;
+        self.fill_rect(Rect::new(-marker / 2.0, marker), Color::BLUE);
+    }
+
+    fn draw_quad(&mut self, color: Color, model: Mat4) {
+        let quad = self.unit_quad.clone();
+        self.draw_mesh_with_pipeline(&quad, self.default_pipeline, None, color, model, 0);
+    }
+
+    /// Copies the `size` pixels at `src_origin` in `src` to `dst_origin` in `dst`, after anything
+    /// that has already been drawn this frame. Fails if the region does not fit in either texture.
+    pub fn copy_texture(
+        &mut self,
+        src: &Texture,
+        src_origin: (u32, u32),
+        dst: &Texture,
+        dst_origin: (u32, u32),
+        size: (u32, u32),
+    ) -> Result<(), Error> {
+        if !region_fits(src_origin, size, (src.width, src.height)) {
+            return Err(Error::new("copy region is outside of the source texture")
+                .with_kind(ErrorKind::Gpu));
+        }
+        if !region_fits(dst_origin, size, (dst.width, dst.height)) {
+            return Err(
+                Error::new("copy region is outside of the destination texture")
+                    .with_kind(ErrorKind::Gpu),
+            );
+        }
+
+        self.record_op(TextureOp::Copy {
+            src: src.texture,
+            src_origin,
+            dst: dst.texture,
+            dst_origin,
+            size,
+        });
+
+        Ok(())
+    }
+
+    /// Draws the whole of `src` stretched over the whole of `target`, after anything that has
+    /// already been drawn this frame.
+    pub fn blit<T: Into<DrawTarget>>(&mut self, src: &Texture, target: T, filter: FilterMode) {
+        self.record_op(TextureOp::Blit {
+            src: src.view,
+            dst: target.into().texture_view(),
+            filter,
+            blend: BlendMode::Replace,
+        });
+    }
+
+    /// Reads the pixels of `texture` back from the GPU once everything drawn to it so far this
+    /// frame has been drawn. The pixels are available from [`Renderer::take_texture_readback`] a
+    /// frame or more later.
+    pub fn read_texture(&mut self, texture: &Texture) -> TextureReadbackId {
+        let id = self.next_readback_id();
+        self.record_op(TextureOp::Read {
+            src: texture.texture,
+            id,
+        });
+        id
+    }
+
+    pub(crate) fn next_readback_id(&mut self) -> TextureReadbackId {
+        let id = TextureReadbackId(self.next_readback);
+        self.next_readback += 1;
+        id
+    }
+
+    /// Starts drawing lights into `light_map`, which is first cleared to its ambient color.
+    pub fn begin_lights(&mut self, light_map: &LightMap) {
+        assert!(
+            self.lights_return_target.is_none(),
+            "lights are already being drawn"
+        );
+
+        self.lights_return_target = Some(self.draw_target);
+        self.set_draw_target(light_map.target());
+        self.clear(light_map.get_ambient());
+    }
+
+    /// Adds a light of `color` to the light map, by drawing `sprite` stretched to a circle of
+    /// `radius` around `position`. Sprites created from [`LightMap::falloff_image`] give soft
+    /// round lights.
+    pub fn draw_light(&mut self, sprite: &Sprite, position: Vec2f, radius: f32, color: Color) {
+        assert!(
+            self.lights_return_target.is_some(),
+            "lights must be drawn between `begin_lights` and `end_lights`"
+        );
+
+        let model = Mat4::translation(position - v2(radius, radius))
+            * Mat4::scale(v2(radius * 2.0, radius * 2.0) / sprite.size_vec2());
+        let mesh = self.sprite_mesh(sprite);
+        self.draw_mesh_with_pipeline(
+            &mesh,
+            self.light_pipeline,
+            sprite.texture_bg,
+            color,
+            model,
+            0,
+        );
+    }
+
+    /// Returns to the draw target from before [`Graphics::begin_lights`] and multiplies it by
+    /// `light_map`, after anything that has already been drawn to it this frame.
+    pub fn end_lights(&mut self, light_map: &LightMap) {
+        let target = self
+            .lights_return_target
+            .take()
+            .expect("`end_lights` called without `begin_lights`");
+
+        self.set_draw_target(target);
+        self.record_op(TextureOp::Blit {
+            src: light_map.target().texture().view,
+            dst: target.texture_view(),
+            filter: FilterMode::Linear,
+            blend: BlendMode::Multiply,
+        });
+    }
+
+    /// Permanently stamps `decal` into `decal_map`, centered on `position` in the world and rotated
+    /// by `rotation` radians, blending it with the decals already there. The sprite's color and
+    /// scale are used, while its position, origin and rotation are not. The draw target and view
+    /// are left as they were.
+    pub fn splat(
+        &mut self,
+        decal: &Sprite,
+        position: Vec2f,
+        rotation: f32,
+        decal_map: &DecalMap,
+        blend: BlendMode,
+    ) {
+        let origin = decal.center();
+        let model = transform(position - origin, origin, rotation, decal.scale);
+        self.draw_offscreen(decal_map.target().into(), decal_map.view(), |graphics| {
+            let mesh = graphics.sprite_mesh(decal);
+            graphics.draw_mesh_with_pipeline(
+                &mesh,
+                graphics.blend_pipelines[blend as usize],
+                decal.texture_bg,
+                decal.color,
+                model,
+                0,
+            );
+        });
+    }
+
+    /// Calls `draw` with `target` and `view` set, then puts back the draw target and view that
+    /// were set before, along with any clear that was still to be made.
+    pub(crate) fn draw_offscreen(
+        &mut self,
+        target: DrawTarget,
+        view: View,
+        draw: impl FnOnce(&mut Self),
+    ) {
+        let (draw_target, clear_color) = (self.draw_target, self.clear_color);
+        // There is no view yet when called before the first frame, e.g. while loading.
+        let previous_view = (!self.views.is_empty()).then_some(self.view_idx);
+
+        self.set_draw_target(target);
+        self.set_view(view);
+        draw(self);
+
+        self.set_draw_target(draw_target);
+        self.clear_color = clear_color;
+        // The view is already in the globals, so draw with it again rather than adding a copy.
+        if let Some(previous_view) = previous_view {
+            self.view_idx = previous_view;
+        }
+    }
+
+    /// Starts drawing sprites that have a pick id into an id buffer of `width` by `height` pixels,
+    /// which should match the size of the window. Call again to resize it. Only sprites that are
+    /// drawn to the window should be given a pick id.
+    pub fn enable_picking(&mut self, renderer: &mut Renderer, width: u32, height: u32) {
+        self.disable_picking(renderer);
+
+        let label = Some("pick");
+        let texture = renderer.create_texture(&TextureDesc {
+            label,
+            width,
+            height,
+            format: TextureFormat::R32Uint,
+        });
+        let view = renderer.create_texture_view(&TextureViewDesc {
+            label,
+            texture,
+            format: TextureFormat::R32Uint,
+        });
+
+        self.draws.pick = Some(PickPass {
+            texture,
+            view,
+            size: (width, height),
+            draws: Vec::new(),
+            read_at: None,
+        });
+    }
+
+    pub fn disable_picking(&mut self, renderer: &mut Renderer) {
+        if let Some(pick) = self.draws.pick.take() {
+            renderer.destroy_texture_view(pick.view);
+            renderer.destroy_texture(pick.texture);
+        }
+    }
+
+    pub fn is_picking_enabled(&self) -> bool {
+        self.draws.pick.is_some()
+    }
+
+    /// Reads back the pick id at `position` in physical pixels once this frame has been drawn.
+    /// The result is available from [`Renderer::last_pick`] a frame or more later.
+    pub fn request_pick(&mut self, position: (u32, u32)) {
+        if let Some(ref mut pick) = self.draws.pick {
+            pick.read_at = Some(position);
+        }
+    }
+
+    /// Starts drawing outlines around sprites submitted with [`DrawParams::outline`], using a mask
+    /// the size of the backbuffer. Outlines are drawn over everything else drawn to the window,
+    /// so only sprites that are drawn to the window should be outlined.
+    pub fn enable_outlines(&mut self, renderer: &mut Renderer) {
+        self.disable_outlines(renderer);
+
+        let (width, height) = renderer.backbuffer_size();
+        let label = Some("outline mask");
+        let texture = renderer.create_texture(&TextureDesc {
+            label,
+            width,
+            height,
+            format: TextureFormat::Rgba8Unorm,
         });
+        let view = renderer.create_texture_view(&TextureViewDesc {
+            label,
+            texture,
+            format: TextureFormat::Rgba8Unorm,
+        });
+
+        self.draws.outline = Some(OutlinePass {
+            texture,
+            view,
+            bg: renderer.create_outline_bind_group(view),
+            draws: Vec::new(),
+        });
+    }
+
+    pub fn disable_outlines(&mut self, renderer: &mut Renderer) {
+        if let Some(outline) = self.draws.outline.take() {
+            renderer.destroy_bind_group(outline.bg);
+            renderer.destroy_texture_view(outline.view);
+            renderer.destroy_texture(outline.texture);
+        }
+    }
+
+    pub fn is_outlining_enabled(&self) -> bool {
+        self.draws.outline.is_some()
+    }
+
+    /// Counts the samples that the draws that follow put inside the draw target, until
+    /// [`Graphics::end_occlusion_query`], under `id`. The count is read back from the GPU
+    /// asynchronously and is available from [`Renderer::occlusion_samples`] a frame or more
+    /// later. A count of zero means the draws were wholly off the target, e.g. a layer that has
+    /// scrolled out of view, so the layer can be skipped until it might come back into view.
+    ///
+    /// Samples are counted even where something is drawn over them, so a query cannot tell that
+    /// its draws are hidden behind opaque foreground.
+    pub fn begin_occlusion_query(&mut self, id: u32) {
+        self.draws.occlusion_query = Some(id);
+    }
+
+    pub fn end_occlusion_query(&mut self) {
+        self.draws.occlusion_query = None;
+    }
+
+    pub fn get_draw_order(&self) -> DrawOrder {
+        self.draw_order
+    }
+
+    /// Sets the order that the draws which follow are made in, until it is next set.
+    pub fn set_draw_order(&mut self, order: DrawOrder) {
+        self.draw_order = order;
+    }
+
+    pub fn get_draw_target(&self) -> DrawTarget {
+        self.draw_target
     }
 
     pub fn set_draw_target<T: Into<DrawTarget>>(&mut self, target: T) {
@@ -190,6 +1019,7 @@ impl Graphics {
     }
 
     pub fn set_view(&mut self, view: View) {
+        self.view_idx = self.views.len();
         self.views.push(view);
     }
 
@@ -198,16 +1028,133 @@ impl Graphics {
             self.push_render_pass();
         }
 
+        if let Some(ref mut pick) = self.draws.pick {
+            if draw.pick_id != 0 {
+                pick.draws.push(DrawCommand {
+                    pipeline: self.pick_pipeline,
+                    ..draw.clone()
+                });
+            }
+        }
+
+        if let (Some(ref mut pass), Some((color, thickness))) =
+            (&mut self.draws.outline, self.outline)
+        {
+            // The mask holds the outline color, with the thickness in alpha.
+            pass.draws.push(DrawCommand {
+                pipeline: self.outline_pipeline,
+                color: Color {
+                    a: thickness / MAX_OUTLINE_THICKNESS,
+                    ..color
+                },
+                ..draw.clone()
+            });
+        }
+
         self.draws.record(draw);
     }
 
+    fn record_op(&mut self, op: TextureOp) {
+        self.draws.record_op(op);
+
+        // Draws after the op must be in a new render pass, which loads the result of the op.
+        self.clear_color = None;
+        self.needs_render_pass = true;
+    }
+
     fn push_render_pass(&mut self) {
         self.needs_render_pass = false;
-        self.draws
-            .set_render_pass(self.draw_target.texture_view(), self.clear_color);
+        let target = self
+            .draw_target
+            .with_clear_color(self.clear_color)
+            .with_clear_policies(&mut self.drawn_targets);
+        self.draws.set_render_pass(target);
+
+        // The clear colors of the draw target only apply to the first pass that draws to it.
+        self.draw_target = self.draw_target.without_clear_colors();
+    }
+}
+
+/// Parameters for a single draw of a sprite, created by [`Graphics::draw`]. Nothing is drawn until
+/// [`DrawParams::submit`] is called.
+#[must_use = "the sprite is not drawn until `submit` is called"]
+pub struct DrawParams<'a> {
+    graphics: &'a mut Graphics,
+    sprite: &'a Sprite,
+    color: Color,
+    origin: Vec2f,
+    position: Vec2f,
+    rotation: f32,
+    scale: Vec2f,
+    pick_id: u32,
+    outline: Option<(Color, f32)>,
+}
+
+impl<'a> DrawParams<'a> {
+    pub fn at(mut self, position: Vec2f) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// The point, relative to the top-left of the sprite, that it is rotated around.
+    pub fn origin(mut self, origin: Vec2f) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    pub fn origin_center(self) -> Self {
+        let center = self.sprite.center();
+        self.origin(center)
+    }
+
+    pub fn rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn scale(mut self, scale: Vec2f) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn tint(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Makes the visible parts of the sprite pickable as `id`, which must not be zero, when
+    /// picking is enabled. See [`Graphics::enable_picking`].
+    pub fn pick_id(mut self, id: u32) -> Self {
+        assert_ne!(id, 0, "pick id 0 is reserved for where nothing was drawn");
+        self.pick_id = id;
+        self
+    }
+
+    /// Draws an outline `thickness` pixels wide around the visible parts of the sprite, e.g. to
+    /// show that it is selected, when outlines are enabled. See [`Graphics::enable_outlines`].
+    /// The thickness is clamped to [`MAX_OUTLINE_THICKNESS`].
+    pub fn outline(mut self, color: Color, thickness: f32) -> Self {
+        self.outline = Some((color, thickness.clamp(0.0, MAX_OUTLINE_THICKNESS)));
+        self
+    }
+
+    pub fn submit(self) {
+        let model = transform(self.position, self.origin, self.rotation, self.scale);
+        self.graphics.outline = self.outline;
+        self.graphics
+            .draw_sprite_with(self.sprite, self.color, model, self.pick_id);
+        self.graphics.outline = None;
     }
 }
 
+fn transform(position: Vec2f, origin: Vec2f, rotation: f32, scale: Vec2f) -> Mat4 {
+    Mat4::translation(position)
+        * Mat4::translation(origin)
+        * Mat4::rotation(rotation)
+        * Mat4::translation(-origin)
+        * Mat4::scale(scale)
+}
+
 #[derive(Clone)]
 pub struct Sprite {
     color: Color,
@@ -217,24 +1164,75 @@ pub struct Sprite {
     position: Vec2f,
     rotation: f32,
     scale: Vec2f,
+    texture_bg: Option<BindGroupId>,
+    uv_min: Vec2f,
+    uv_size: Vec2f,
+    deformation: Deformation,
 
     mesh: Mesh,
 }
 
 impl Sprite {
-    const INDICES: [u16; 8] = [0, 1, 2, 0, 2, 3, 0, 0]; // todo: Index alignment.
+    const INDICES: [u16; 6] = [0, 1, 2, 0, 2, 3];
     const VERTICES: [GeometryVertex; 4] = [
-        GeometryVertex { pos: [0.0, 0.0] },
-        GeometryVertex { pos: [1.0, 0.0] },
-        GeometryVertex { pos: [1.0, 1.0] },
-        GeometryVertex { pos: [0.0, 1.0] },
+        GeometryVertex {
+            pos: [0.0, 0.0],
+            uv: [0.0, 0.0],
+        },
+        GeometryVertex {
+            pos: [1.0, 0.0],
+            uv: [1.0, 0.0],
+        },
+        GeometryVertex {
+            pos: [1.0, 1.0],
+            uv: [1.0, 1.0],
+        },
+        GeometryVertex {
+            pos: [0.0, 1.0],
+            uv: [0.0, 1.0],
+        },
     ];
 
+    /// Creates a sprite that draws the whole of `texture`, tinted white.
+    pub fn from_texture(renderer: &mut Renderer, texture: &Texture, material: MaterialId) -> Self {
+        Self::from_texture_region(renderer, texture, (0, 0), texture.size(), material)
+    }
+
+    /// Creates a sprite that draws the `size` pixels at `origin` in `texture`, tinted white, e.g.
+    /// a single image in an atlas.
+    pub fn from_texture_region(
+        renderer: &mut Renderer,
+        texture: &Texture,
+        origin: (u32, u32),
+        size: (u32, u32),
+        material: MaterialId,
+    ) -> Self {
+        let uv_min = v2(origin.0 as f32, origin.1 as f32) / texture.size_vec2();
+        let uv_size = v2(size.0 as f32, size.1 as f32) / texture.size_vec2();
+
+        Self {
+            color: Color::WHITE,
+            texture_bg: Some(texture.bg),
+            ..Self::new(renderer, size.0, size.1, uv_min, uv_size, material)
+        }
+    }
+
     pub fn from_image(
         renderer: &mut Renderer,
         width: u32,
         height: u32,
         material: MaterialId,
+    ) -> Self {
+        Self::new(renderer, width, height, Vec2f::ZERO, Vec2f::ONE, material)
+    }
+
+    fn new(
+        renderer: &mut Renderer,
+        width: u32,
+        height: u32,
+        uv_min: Vec2f,
+        uv_size: Vec2f,
+        material: MaterialId,
     ) -> Self {
         let vertices = Self::VERTICES
             .iter()
@@ -242,28 +1240,13 @@ impl Sprite {
                 let mut v = *v;
                 v.pos[0] *= width as f32;
                 v.pos[1] *= height as f32;
+                v.uv[0] = uv_min.x + v.uv[0] * uv_size.x;
+                v.uv[1] = uv_min.y + v.uv[1] * uv_size.y;
                 v
             })
             .collect::<Vec<_>>();
 
-        let vbo = renderer.create_buffer(&BufferDesc {
-            label: Some("sprite"),
-            size: std::mem::size_of::<[GeometryVertex; 4]>(),
-            usage: BufferUsages::VERTEX,
-        });
-        renderer.write_buffer(vbo, &vertices);
-
-        let ibo = renderer.create_buffer(&BufferDesc {
-            label: Some("sprite"),
-            size: std::mem::size_of::<[u16; 8]>(),
-            usage: BufferUsages::INDEX,
-        });
-        renderer.write_buffer(ibo, &Self::INDICES);
-
-        let buffers = MeshBuffers { vbo, ibo };
-
-        // let material = Material { pipeline };
-        let mesh = Mesh { buffers, material };
+        let mesh = Mesh::new(renderer, &vertices, &Self::INDICES, material);
         Self {
             color: Color::GREEN,
             width,
@@ -272,10 +1255,25 @@ impl Sprite {
             position: Vec2f::ZERO,
             rotation: 0.0,
             scale: Vec2f::ONE,
+            texture_bg: None,
+            uv_min,
+            uv_size,
+            deformation: Deformation::NONE,
             mesh,
         }
     }
 
+    pub fn get_deformation(&self) -> Deformation {
+        self.deformation
+    }
+
+    /// Warps the sprite's quad by `deformation` when it is drawn, e.g. for squash and stretch.
+    /// The deformed vertices are made for each draw, so clones of the sprite deform independently
+    /// and a sprite can be drawn with different deformations in the same frame.
+    pub fn set_deformation(&mut self, deformation: Deformation) {
+        self.deformation = deformation;
+    }
+
     pub fn height(&self) -> u32 {
         self.height
     }
@@ -285,11 +1283,192 @@ impl Sprite {
     }
 
     pub fn get_transform(&self) -> Mat4 {
-        Mat4::translation(self.position)
-            * Mat4::translation(self.origin)
-            * Mat4::rotation(self.rotation)
-            * Mat4::translation(-self.origin)
-            * Mat4::scale(self.scale)
+        transform(self.position, self.origin, self.rotation, self.scale)
+    }
+}
+
+impl Dimensions for Sprite {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+/// A texture uploaded to the GPU along with the sampler and bind group used to draw it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Texture {
+    texture: TextureId,
+    view: TextureViewId,
+    sampler: SamplerId,
+    bg: BindGroupId,
+    width: u32,
+    height: u32,
+}
+
+impl Texture {
+    const INVALID: Self = Self {
+        texture: TextureId::INVALID,
+        view: TextureViewId::INVALID,
+        sampler: SamplerId::INVALID,
+        bg: BindGroupId::INVALID,
+        width: 0,
+        height: 0,
+    };
+
+    pub fn texture(&self) -> TextureId {
+        self.texture
+    }
+
+    pub fn view(&self) -> TextureViewId {
+        self.view
+    }
+
+    pub fn sampler(&self) -> SamplerId {
+        self.sampler
+    }
+
+    pub fn bind_group(&self) -> BindGroupId {
+        self.bg
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+impl Dimensions for Texture {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+/// Fails if a texture of `width` by `height` pixels would be empty or larger than `max_size` on
+/// either side.
+fn check_texture_size(width: u32, height: u32, max_size: u32) -> Result<(), Error> {
+    if width == 0 || height == 0 {
+        return Err(
+            Error::new(format!("image of {width}x{height} pixels is empty"))
+                .with_kind(ErrorKind::Asset),
+        );
+    }
+    if width > max_size || height > max_size {
+        return Err(Error::new(format!(
+            "image of {width}x{height} pixels is larger than the maximum texture size of {max_size}"
+        ))
+        .with_kind(ErrorKind::Asset));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn destroy_texture(renderer: &mut Renderer, texture: &Texture) {
+    renderer.destroy_bind_group(texture.bg);
+    renderer.destroy_sampler(texture.sampler);
+    renderer.destroy_texture_view(texture.view);
+    renderer.destroy_texture(texture.texture);
+}
+
+/// A texture that sprites can be drawn to, by passing it to [`Graphics::set_draw_target`].
+///
+/// By default it is cleared to transparent the first time it is drawn to each frame, so nothing
+/// drawn in the last frame is left over; see [`RenderTexture::set_clear_policy`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderTexture {
+    texture: Texture,
+    options: RenderTextureOptions,
+    clear_policy: ClearPolicy,
+}
+
+impl RenderTexture {
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn get_clear_policy(&self) -> ClearPolicy {
+        self.clear_policy
+    }
+
+    /// Sets when the texture is cleared without [`Graphics::clear`] being called. A clear made
+    /// with [`Graphics::clear`] always takes priority.
+    pub fn set_clear_policy(&mut self, policy: ClearPolicy) {
+        self.clear_policy = policy;
+    }
+}
+
+impl From<&RenderTexture> for DrawTarget {
+    fn from(target: &RenderTexture) -> Self {
+        DrawTarget::with_color_targets(&[target.into()])
+    }
+}
+
+impl From<&RenderTexture> for ColorTarget {
+    fn from(target: &RenderTexture) -> Self {
+        ColorTarget::new(target.texture.view).with_clear_policy(target.clear_policy)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct RenderTextureOptions {
+    label: Option<&'static str>,
+    address_mode: AddressMode,
+    filter: FilterMode,
+}
+
+impl From<&TextureOptions<'_>> for RenderTextureOptions {
+    fn from(options: &TextureOptions) -> Self {
+        Self {
+            label: options.label.map(intern_label),
+            address_mode: options.address_mode,
+            filter: options.filter,
+        }
+    }
+}
+
+/// A `'static` copy of `label`, so that render textures can keep their label and still be
+/// `Copy`. Each distinct label is only ever allocated once.
+fn intern_label(label: &str) -> &'static str {
+    static LABELS: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+    let mut labels = LABELS.lock().unwrap_or_else(PoisonError::into_inner);
+    match labels.get(label) {
+        Some(interned) => interned,
+        None => {
+            let interned = Box::leak(label.to_string().into_boxed_str());
+            labels.insert(interned);
+            interned
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureOptions<'desc> {
+    pub label: Option<&'desc str>,
+    pub address_mode: AddressMode,
+    pub filter: FilterMode,
+}
+
+impl Default for TextureOptions<'_> {
+    fn default() -> Self {
+        Self {
+            label: Some("texture"),
+            address_mode: AddressMode::ClampToEdge,
+            filter: FilterMode::Nearest,
+        }
     }
 }
 
@@ -317,10 +1496,98 @@ struct MeshBuffers {
     ibo: BufferId,
 }
 
+/// Triangles that are drawn with a material, optionally many times over with per-instance data
+/// from [`Mesh::set_instances`].
 #[derive(Clone)]
 pub struct Mesh {
     buffers: MeshBuffers,
+    index_count: usize,
     material: MaterialId,
+    instances: Option<(BufferId, u32)>,
+    base_vertex: i32, // Added to each index, for meshes sharing a vertex buffer.
+}
+
+impl Mesh {
+    /// A mesh of `vertices`, which can be any [`Vertex`] type with a
+    /// [`Geometry`](VertexBufferType::Geometry) layout that the material's pipeline reads from
+    /// its first vertex buffer.
+    pub fn new<V: Vertex + Copy>(
+        renderer: &mut Renderer,
+        vertices: &[V],
+        indices: &[u16],
+        material: MaterialId,
+    ) -> Self {
+        assert_eq!(
+            V::layout().buffer_type(),
+            VertexBufferType::Geometry,
+            "mesh vertices must have a geometry layout"
+        );
+
+        let vbo = renderer.create_buffer(&BufferDesc {
+            label: Some("mesh"),
+            size: std::mem::size_of_val(vertices),
+            usage: BufferUsages::VERTEX,
+        });
+        renderer.write_buffer(vbo, vertices);
+
+        // Buffer writes must be a multiple of 4 bytes, so pad an odd number of indices.
+        let mut padded = indices.to_vec();
+        padded.resize(indices.len().next_multiple_of(2), 0);
+        let ibo = renderer.create_buffer(&BufferDesc {
+            label: Some("mesh"),
+            size: std::mem::size_of_val(padded.as_slice()),
+            usage: BufferUsages::INDEX,
+        });
+        renderer.write_buffer(ibo, &padded);
+
+        Self {
+            buffers: MeshBuffers { vbo, ibo },
+            index_count: indices.len(),
+            material,
+            instances: None,
+            base_vertex: 0,
+        }
+    }
+
+    /// Draws the mesh once for each of `instances`, which the material's pipeline reads from its
+    /// second vertex buffer. The instance buffer is reused while the instances fit in it, so it
+    /// can be updated every frame, and is replaced when they outgrow it, which leaves clones of
+    /// the mesh made before then with a destroyed buffer.
+    pub fn set_instances<I: Vertex + Copy>(&mut self, renderer: &mut Renderer, instances: &[I]) {
+        assert_eq!(
+            I::layout().buffer_type(),
+            VertexBufferType::Instance,
+            "mesh instances must have an instance layout"
+        );
+
+        let size = std::mem::size_of_val(instances);
+        let buffer = match self.instances {
+            Some((buffer, _)) if renderer.buffer_size(buffer) >= size => buffer,
+            existing => {
+                if let Some((buffer, _)) = existing {
+                    renderer.destroy_buffer(buffer);
+                }
+                renderer.create_buffer(&BufferDesc {
+                    label: Some("mesh instances"),
+                    size: size.next_power_of_two().max(4),
+                    usage: BufferUsages::VERTEX,
+                })
+            }
+        };
+        if size > 0 {
+            renderer.write_buffer(buffer, instances);
+        }
+        self.instances = Some((buffer, instances.len() as u32));
+    }
+
+    /// The number of times the mesh is drawn, which is one unless instances have been set.
+    pub fn instance_count(&self) -> u32 {
+        self.instances.map_or(1, |(_, count)| count)
+    }
+
+    pub fn index_count(&self) -> usize {
+        self.index_count
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -343,6 +1610,29 @@ impl View {
         }
     }
 
+    /// A view the size of the window in physical pixels, e.g. for drawing to a render texture
+    /// that tracks the window size.
+    pub fn from_window(age: &Engine) -> Self {
+        let (width, height) = age.window_size();
+        Self::new(width, height)
+    }
+
+    /// A view of `width` by `height` pixels that is zoomed and positioned to show all of
+    /// `world_rect` as large as it fits, centered.
+    pub fn fit_rect(world_rect: Rect, width: u32, height: u32) -> Self {
+        let viewport = v2(width as f32, height as f32);
+        let size = v2(
+            world_rect.size.x.max(f32::EPSILON),
+            world_rect.size.y.max(f32::EPSILON),
+        );
+        let zoom = (viewport.x / size.x).min(viewport.y / size.y);
+
+        let mut view = Self::new(width, height);
+        view.zoom = zoom;
+        view.position = world_rect.center() - viewport / (2.0 * zoom);
+        view
+    }
+
     pub fn get_position(&self) -> Vec2f {
         self.position
     }
@@ -367,6 +1657,75 @@ impl View {
         self.zoom = zoom;
     }
 
+    /// Multiplies the zoom by `factor` while keeping the world position under `screen` in place,
+    /// which is how zooming with the mouse wheel is expected to behave.
+    pub fn zoom_at(&mut self, screen: Vec2f, factor: f32) {
+        let world = self.screen_to_world(screen);
+        self.zoom *= factor;
+
+        // screen_to_world is (2I - R) * position + rotate(screen / zoom - half, rotation) + half
+        // for the rotation matrix R, so solve that for the position that gives `world` back.
+        let half = v2(self.width as f32, self.height as f32) / 2.0;
+        let rhs = world - rotate(screen / self.zoom - half, self.rotation) - half;
+        let (sin, cos) = self.rotation.sin_cos();
+        let (a, b) = (2.0 - cos, sin);
+        let det = a * a + b * b;
+        self.position = v2(a * rhs.x - b * rhs.y, b * rhs.x + a * rhs.y) / det;
+    }
+
+    /// The smallest world space rectangle that contains everything the view can see, as its
+    /// minimum and maximum corners.
+    pub fn visible_bounds(&self) -> (Vec2f, Vec2f) {
+        let (width, height) = (self.width as f32, self.height as f32);
+        let corners = [
+            v2(0.0, 0.0),
+            v2(width, 0.0),
+            v2(0.0, height),
+            v2(width, height),
+        ]
+        .map(|corner| self.screen_to_world(corner));
+
+        corners
+            .iter()
+            .fold((corners[0], corners[0]), |(min, max), c| {
+                (
+                    v2(min.x.min(c.x), min.y.min(c.y)),
+                    v2(max.x.max(c.x), max.y.max(c.y)),
+                )
+            })
+    }
+
+    /// Converts a position in pixels relative to the top-left of the draw target into world space.
+    pub fn screen_to_world(&self, screen: Vec2f) -> Vec2f {
+        let origin = self.position + v2(self.width as f32, self.height as f32) / 2.0;
+        rotate(screen / self.zoom - origin, self.rotation) + origin + self.position
+    }
+
+    /// Converts a position in world space into pixels relative to the top-left of the draw target.
+    pub fn world_to_screen(&self, world: Vec2f) -> Vec2f {
+        let origin = self.position + v2(self.width as f32, self.height as f32) / 2.0;
+        (rotate(world - self.position - origin, -self.rotation) + origin) * self.zoom
+    }
+
+    /// The view a fraction `t` of the way from this view to `to`, interpolating position,
+    /// rotation and zoom. The size is taken from `to`.
+    pub fn lerp(&self, to: &View, t: f32) -> View {
+        // Zoom is interpolated geometrically so that zooming in and out feel equally fast, which
+        // is only possible between positive zooms.
+        let zoom = match self.zoom > 0.0 && to.zoom > 0.0 {
+            true => self.zoom * (to.zoom / self.zoom).powf(t),
+            false => self.zoom + (to.zoom - self.zoom) * t,
+        };
+
+        View {
+            width: to.width,
+            height: to.height,
+            position: self.position + (to.position - self.position) * t,
+            rotation: self.rotation + (to.rotation - self.rotation) * t,
+            zoom,
+        }
+    }
+
     pub fn view_projection(&self) -> Mat4 {
         let width = self.width as f32 / self.zoom;
         let height = self.height as f32 / self.zoom;
@@ -383,3 +1742,279 @@ impl View {
         proj * view
     }
 }
+
+/// A move from one view to another over time, such as a cutscene camera pan.
+#[derive(Debug, Clone, Copy)]
+pub struct ViewTransition {
+    from: View,
+    to: View,
+    duration: f32,
+    easing: Easing,
+}
+
+impl ViewTransition {
+    /// Moves from `from` to `to` over `duration` seconds.
+    pub fn new(from: View, to: View, duration: f32, easing: Easing) -> Self {
+        Self {
+            from,
+            to,
+            duration,
+            easing,
+        }
+    }
+
+    /// The view `elapsed` seconds into the transition, which is `to` once it has finished.
+    pub fn view_at(&self, elapsed: f32) -> View {
+        let t = match self.duration > 0.0 {
+            true => elapsed / self.duration,
+            false => 1.0,
+        };
+        self.from.lerp(&self.to, self.easing.apply(t))
+    }
+
+    pub fn is_finished(&self, elapsed: f32) -> bool {
+        elapsed >= self.duration
+    }
+}
+
+/// How many grid lines there are between each major line drawn by [`Graphics::draw_grid`].
+const GRID_MAJOR_EVERY: u32 = 5;
+
+/// The closest grid lines can be on screen, in pixels, before they are merged.
+const GRID_MIN_SPACING: f32 = 8.0;
+
+/// The spacing between grid lines at `zoom`, which grows by [`GRID_MAJOR_EVERY`] whenever the
+/// lines would be closer together than [`GRID_MIN_SPACING`], so that major lines stay major.
+fn grid_step(spacing: f32, zoom: f32) -> f32 {
+    let mut step = spacing.abs().max(f32::EPSILON);
+    while step * zoom < GRID_MIN_SPACING {
+        step *= GRID_MAJOR_EVERY as f32;
+    }
+    step
+}
+
+fn rotate(v: Vec2f, rotation: f32) -> Vec2f {
+    let (sin, cos) = rotation.sin_cos();
+    v2(v.x * cos - v.y * sin, v.x * sin + v.y * cos)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn every_view_drawn_with_fits_in_the_globals() {
+        // Drawing needs a graphics adapter, so there is nothing to check without one.
+        let Ok(mut renderer) = Renderer::new(&crate::GpuPreference::LowPower) else {
+            return;
+        };
+        let mut graphics = Graphics::new(&mut renderer, View::new(64, 64));
+        let options = TextureOptions::default();
+        let target = graphics.create_render_texture(&mut renderer, 64, 64, &options);
+
+        graphics.set_draw_target(&target);
+        for zoom in [1.0, 2.0, 4.0] {
+            let mut view = View::new(64, 64);
+            view.set_zoom(zoom);
+            graphics.set_view(view);
+            graphics.fill_rect(Rect::new(v2(0.0, 0.0), v2(8.0, 8.0)), Color::WHITE);
+        }
+        // Writing past the end of the globals would fail validation, which panics.
+        graphics.flush(&mut renderer);
+        renderer.wait_for_texture_readbacks();
+    }
+
+    #[test]
+    fn copies_must_fit_in_both_textures() {
+        // Drawing needs a graphics adapter, so there is nothing to check without one.
+        let Ok(mut renderer) = Renderer::new(&crate::GpuPreference::LowPower) else {
+            return;
+        };
+        let mut graphics = Graphics::new(&mut renderer, View::new(64, 64));
+        let src = Texture {
+            width: 16,
+            height: 16,
+            ..Texture::INVALID
+        };
+        let dst = Texture {
+            width: 8,
+            height: 8,
+            ..Texture::INVALID
+        };
+
+        assert!(graphics
+            .copy_texture(&src, (8, 8), &dst, (0, 0), (8, 8))
+            .is_ok());
+        let err = graphics
+            .copy_texture(&src, (u32::MAX, 0), &dst, (0, 0), (8, 8))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Gpu);
+        assert!(graphics
+            .copy_texture(&src, (0, 0), &dst, (4, 0), (8, 8))
+            .is_err());
+    }
+
+    #[test]
+    fn offscreen_draws_restore_the_view_without_copying_it() {
+        // Drawing needs a graphics adapter, so there is nothing to check without one.
+        let Ok(mut renderer) = Renderer::new(&crate::GpuPreference::LowPower) else {
+            return;
+        };
+        let mut graphics = Graphics::new(&mut renderer, View::new(64, 64));
+        let options = TextureOptions::default();
+        let window = graphics.create_render_texture(&mut renderer, 64, 64, &options);
+        let target = graphics.create_render_texture(&mut renderer, 16, 16, &options);
+        let rect = Rect::new(v2(0.0, 0.0), v2(8.0, 8.0));
+
+        graphics.set_draw_target(&window);
+        graphics.set_view(View::new(64, 64));
+        for _ in 0..2 {
+            graphics.draw_offscreen((&target).into(), View::new(16, 16), |graphics| {
+                graphics.fill_rect(rect, Color::WHITE);
+            });
+        }
+        assert_eq!(graphics.views.len(), 3);
+        assert_eq!(graphics.view_idx, 0);
+
+        graphics.fill_rect(rect, Color::WHITE);
+        graphics.flush(&mut renderer);
+        renderer.wait_for_texture_readbacks();
+    }
+
+    #[test]
+    fn screen_to_world_inverts_world_to_screen() {
+        let mut view = View::new(320, 180);
+        view.set_position(v2(40.0, -25.0));
+        view.set_rotation(0.7);
+        view.set_zoom(2.5);
+
+        let screen = v2(100.0, 60.0);
+        let round_trip = view.world_to_screen(view.screen_to_world(screen));
+        assert!((round_trip - screen).len() < 1e-3, "{}", round_trip);
+    }
+
+    #[test]
+    fn grid_step_grows_when_zoomed_out() {
+        assert_eq!(grid_step(10.0, 1.0), 10.0);
+        assert_eq!(grid_step(10.0, 0.5), 50.0);
+        assert_eq!(grid_step(10.0, 0.01), 1250.0);
+        assert_eq!(grid_step(10.0, 4.0), 10.0);
+    }
+
+    #[test]
+    fn visible_bounds_contain_rotated_view() {
+        let mut view = View::new(100, 100);
+        view.set_rotation(std::f32::consts::FRAC_PI_4);
+
+        let (min, max) = view.visible_bounds();
+        let diagonal = 100.0 * std::f32::consts::SQRT_2;
+        assert!((max.x - min.x - diagonal).abs() < 1e-3);
+        assert!((max.y - min.y - diagonal).abs() < 1e-3);
+    }
+
+    #[test]
+    fn world_to_screen_matches_view_projection() {
+        let mut view = View::new(320, 180);
+        view.set_position(v2(40.0, -25.0));
+        view.set_rotation(0.7);
+        view.set_zoom(2.5);
+
+        let world = v2(75.0, 20.0);
+        let m = view.view_projection().to_cols_array();
+        let ndc_x = m[0] * world.x + m[4] * world.y + m[12];
+        let ndc_y = m[1] * world.x + m[5] * world.y + m[13];
+        let expected = v2((ndc_x + 1.0) / 2.0 * 320.0, (1.0 - ndc_y) / 2.0 * 180.0);
+
+        let screen = view.world_to_screen(world);
+        assert!(
+            (screen - expected).len() < 1e-3,
+            "{} != {}",
+            screen,
+            expected
+        );
+    }
+
+    #[test]
+    fn transition_interpolates_between_views() {
+        let from = View::new(320, 180);
+        let mut to = View::new(320, 180);
+        to.set_position(v2(100.0, 50.0));
+        to.set_rotation(1.0);
+        to.set_zoom(4.0);
+        let transition = ViewTransition::new(from, to, 2.0, Easing::Linear);
+
+        let halfway = transition.view_at(1.0);
+        assert_eq!(halfway.get_position(), v2(50.0, 25.0));
+        assert_eq!(halfway.get_rotation(), 0.5);
+        assert_eq!(halfway.get_zoom(), 2.0);
+        assert!(!transition.is_finished(1.0));
+
+        assert_eq!(transition.view_at(3.0).get_position(), v2(100.0, 50.0));
+        assert!(transition.is_finished(3.0));
+
+        // A zero zoom cannot be interpolated geometrically.
+        let mut collapsed = from;
+        collapsed.set_zoom(0.0);
+        assert_eq!(collapsed.lerp(&to, 0.5).get_zoom(), 2.0);
+    }
+
+    #[test]
+    fn fit_rect_shows_whole_rect_centered() {
+        let world = Rect::new(v2(100.0, 100.0), v2(400.0, 100.0));
+        let view = View::fit_rect(world, 200, 200);
+
+        assert_eq!(view.get_zoom(), 0.5);
+        assert_eq!(view.world_to_screen(world.center()), v2(100.0, 100.0));
+        assert_eq!(view.world_to_screen(world.min()), v2(0.0, 75.0));
+        assert_eq!(view.world_to_screen(world.max()), v2(200.0, 125.0));
+    }
+
+    #[test]
+    fn zoom_at_keeps_point_under_cursor() {
+        let mut view = View::new(320, 180);
+        view.set_position(v2(40.0, -25.0));
+        view.set_rotation(0.7);
+
+        let cursor = v2(250.0, 30.0);
+        let before = view.screen_to_world(cursor);
+        view.zoom_at(cursor, 1.5);
+        view.zoom_at(cursor, 1.5);
+
+        let after = view.screen_to_world(cursor);
+        assert!((after - before).len() < 1e-3, "{} != {}", after, before);
+        assert_eq!(view.get_zoom(), 2.25);
+    }
+
+    #[test]
+    fn screen_to_world_offsets_by_position_and_zoom() {
+        let mut view = View::new(320, 180);
+        view.set_position(v2(40.0, -25.0));
+        view.set_zoom(2.0);
+
+        assert_eq!(view.screen_to_world(v2(100.0, 60.0)), v2(90.0, 5.0));
+    }
+
+    #[test]
+    fn textures_must_be_within_size_limits() {
+        assert!(check_texture_size(1, 1, 2048).is_ok());
+        assert!(check_texture_size(2048, 16, 2048).is_ok());
+
+        let empty = check_texture_size(0, 16, 2048).unwrap_err();
+        assert_eq!(empty.kind(), ErrorKind::Asset);
+        assert!(check_texture_size(16, 2049, 2048).is_err());
+    }
+
+    #[test]
+    fn render_texture_labels_are_kept() {
+        let label = String::from("minimap");
+        let options = RenderTextureOptions::from(&TextureOptions {
+            label: Some(&label),
+            ..Default::default()
+        });
+        assert_eq!(options.label, Some("minimap"));
+        assert!(std::ptr::eq(
+            intern_label("minimap"),
+            options.label.unwrap()
+        ));
+    }
+}
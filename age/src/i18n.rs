@@ -0,0 +1,244 @@
+use std::{collections::HashMap, path::Path};
+
+use crate::{Error, ErrorKind};
+
+/// The translated messages for a single language.
+///
+/// Bundles are written as one `key = value` message per line. Blank lines and lines starting with
+/// `#` are ignored. Values may contain `{name}` placeholders which are replaced with arguments when
+/// the message is translated, and `{{` or `}}` for literal braces.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bundle {
+    language: String,
+    messages: HashMap<String, String>,
+}
+
+impl Bundle {
+    pub fn new<S: Into<String>>(language: S) -> Self {
+        Self {
+            language: language.into(),
+            messages: HashMap::new(),
+        }
+    }
+
+    pub fn parse<S: Into<String>>(language: S, source: &str) -> Result<Self, Error> {
+        let mut bundle = Self::new(language);
+        for (n, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match line.split_once('=') {
+                Some((key, value)) if !key.trim().is_empty() => {
+                    bundle.insert(key.trim(), value.trim());
+                }
+                _ => {
+                    return Err(Error::new(format!(
+                        "invalid message on line {} of '{}' language bundle",
+                        n + 1,
+                        bundle.language
+                    ))
+                    .with_kind(ErrorKind::Asset))
+                }
+            }
+        }
+
+        Ok(bundle)
+    }
+
+    pub fn load<S: Into<String>, P: AsRef<Path>>(language: S, path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|err| {
+            Error::new(format!(
+                "failed to read language bundle '{}'",
+                path.display()
+            ))
+            .with_kind(ErrorKind::Io)
+            .with_source(err)
+        })?;
+
+        Self::parse(language, &source)
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    pub fn insert<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) {
+        self.messages.insert(key.into(), value.into());
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.messages.get(key).map(|s| s.as_str())
+    }
+}
+
+/// A set of language bundles and the language currently used for translation.
+#[derive(Debug, Clone, Default)]
+pub struct Localization {
+    bundles: HashMap<String, Bundle>,
+    language: Option<String>,
+    fallback: Option<String>,
+}
+
+impl Localization {
+    /// Adds a bundle, replacing any bundle for the same language. The first bundle added becomes
+    /// the active language.
+    pub fn add_bundle(&mut self, bundle: Bundle) {
+        if self.language.is_none() {
+            self.language = Some(bundle.language.clone());
+        }
+        self.bundles.insert(bundle.language.clone(), bundle);
+    }
+
+    pub fn get_language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    pub fn set_language(&mut self, language: &str) -> Result<(), Error> {
+        if !self.bundles.contains_key(language) {
+            return Err(Error::new(format!(
+                "no bundle has been added for language '{}'",
+                language
+            )));
+        }
+
+        self.language = Some(language.to_string());
+        Ok(())
+    }
+
+    pub fn get_fallback_language(&self) -> Option<&str> {
+        self.fallback.as_deref()
+    }
+
+    /// Sets the language used for messages that are missing from the active language.
+    pub fn set_fallback_language(&mut self, language: Option<&str>) {
+        self.fallback = language.map(|s| s.to_string());
+    }
+
+    pub fn languages(&self) -> impl Iterator<Item = &str> {
+        self.bundles.keys().map(|s| s.as_str())
+    }
+
+    /// Translates `key` into the active language. If there is no message for `key` in either the
+    /// active or fallback language then `key` is returned.
+    pub fn tr(&self, key: &str) -> String {
+        self.tr_args(key, &[])
+    }
+
+    /// Translates `key` into the active language, replacing each `{name}` placeholder with the
+    /// matching argument.
+    pub fn tr_args(&self, key: &str, args: &[(&str, &str)]) -> String {
+        match self.message(key) {
+            Some(message) => interpolate(message, args),
+            None => key.to_string(),
+        }
+    }
+
+    /// Translates `key` into the active language, or returns `default` if there is no message
+    /// for it.
+    pub(crate) fn tr_or(&self, key: &str, default: String) -> String {
+        match self.message(key) {
+            Some(message) => interpolate(message, &[]),
+            None => default,
+        }
+    }
+
+    fn message(&self, key: &str) -> Option<&str> {
+        [self.language.as_deref(), self.fallback.as_deref()]
+            .into_iter()
+            .flatten()
+            .filter_map(|language| self.bundles.get(language))
+            .find_map(|bundle| bundle.get(key))
+    }
+}
+
+fn interpolate(message: &str, args: &[(&str, &str)]) -> String {
+    let mut result = String::with_capacity(message.len());
+    let mut chars = message.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                result.push('}');
+            }
+            '{' => {
+                let name = chars.by_ref().take_while(|&c| c != '}').collect::<String>();
+                match args.iter().find(|(arg, _)| *arg == name.trim()) {
+                    Some((_, value)) => result.push_str(value),
+                    None => {
+                        result.push('{');
+                        result.push_str(&name);
+                        result.push('}');
+                    }
+                }
+            }
+            c => result.push(c),
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn localization() -> Localization {
+        let mut i18n = Localization::default();
+        i18n.add_bundle(
+            Bundle::parse(
+                "en",
+                "# Menu\nmenu.start = Start\nmenu.greeting = Hello, {name}!\nmenu.quit = Quit",
+            )
+            .unwrap(),
+        );
+        i18n.add_bundle(Bundle::parse("fr", "menu.start = Commencer").unwrap());
+        i18n
+    }
+
+    #[test]
+    fn first_bundle_is_active_language() {
+        let i18n = localization();
+        assert_eq!(i18n.get_language(), Some("en"));
+        assert_eq!(i18n.tr("menu.start"), "Start");
+    }
+
+    #[test]
+    fn language_can_be_switched() {
+        let mut i18n = localization();
+        i18n.set_language("fr").unwrap();
+        assert_eq!(i18n.tr("menu.start"), "Commencer");
+        assert!(i18n.set_language("de").is_err());
+    }
+
+    #[test]
+    fn missing_message_uses_fallback_then_key() {
+        let mut i18n = localization();
+        i18n.set_language("fr").unwrap();
+        assert_eq!(i18n.tr("menu.quit"), "menu.quit");
+        i18n.set_fallback_language(Some("en"));
+        assert_eq!(i18n.tr("menu.quit"), "Quit");
+    }
+
+    #[test]
+    fn arguments_are_interpolated() {
+        let i18n = localization();
+        assert_eq!(
+            i18n.tr_args("menu.greeting", &[("name", "Ashley")]),
+            "Hello, Ashley!"
+        );
+        assert_eq!(i18n.tr("menu.greeting"), "Hello, {name}!");
+        assert_eq!(interpolate("{{literal}}", &[]), "{literal}");
+    }
+
+    #[test]
+    fn invalid_line_is_an_error() {
+        assert!(Bundle::parse("en", "menu.start").is_err());
+    }
+}
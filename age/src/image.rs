@@ -0,0 +1,246 @@
+use std::path::Path;
+
+use crate::{math::Dimensions, Color, Error, ErrorKind};
+
+/// An image held in memory as tightly packed 8-bit RGBA pixels, row by row from the top-left.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Result<Self, Error> {
+        let expected = width as usize * height as usize * 4;
+        if pixels.len() != expected {
+            return Err(Error::new(format!(
+                "a {}x{} image needs {} bytes of pixel data but {} were given",
+                width,
+                height,
+                expected,
+                pixels.len()
+            ))
+            .with_kind(ErrorKind::Asset));
+        }
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn from_color(width: u32, height: u32, color: Color) -> Self {
+        let rgba = color
+            .to_array_f32()
+            .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+        let pixels = rgba.repeat(width as usize * height as usize);
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Decodes a PNG image.
+    pub fn from_png(bytes: &[u8]) -> Result<Self, Error> {
+        let err = |err: png::DecodingError| {
+            Error::new("failed to decode png image")
+                .with_kind(ErrorKind::Asset)
+                .with_source(err)
+        };
+
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(err)?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).map_err(err)?;
+        buf.truncate(info.buffer_size());
+
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => buf,
+            png::ColorType::Rgb => buf
+                .chunks_exact(3)
+                .flat_map(|p| [p[0], p[1], p[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => buf
+                .chunks_exact(2)
+                .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                .collect(),
+            png::ColorType::Grayscale => buf.iter().flat_map(|&p| [p, p, p, 255]).collect(),
+            png::ColorType::Indexed => {
+                return Err(
+                    Error::new("indexed png images are not supported").with_kind(ErrorKind::Asset)
+                )
+            }
+        };
+
+        Self::new(info.width, info.height, pixels)
+    }
+
+    /// Rasterizes an SVG image, scaling its size by `scale`. Text is not drawn, so it should be
+    /// converted to paths when the image is exported.
+    #[cfg(feature = "svg")]
+    pub fn from_svg(bytes: &[u8], scale: f32) -> Result<Self, Error> {
+        use resvg::{
+            tiny_skia, usvg,
+            usvg::{fontdb, TreeParsing, TreePostProc},
+        };
+
+        let mut tree = usvg::Tree::from_data(bytes, &usvg::Options::default()).map_err(|err| {
+            Error::new("failed to parse svg image")
+                .with_kind(ErrorKind::Asset)
+                .with_source(err)
+        })?;
+        let steps = usvg::PostProcessingSteps {
+            convert_text_into_paths: false,
+        };
+        tree.postprocess(steps, &fontdb::Database::new());
+
+        let width = (tree.size.width() * scale).ceil() as u32;
+        let height = (tree.size.height() * scale).ceil() as u32;
+        let mut pixmap = tiny_skia::Pixmap::new(width, height).ok_or_else(|| {
+            Error::new(format!(
+                "cannot rasterize svg image at {}x{} pixels",
+                width, height
+            ))
+            .with_kind(ErrorKind::Asset)
+        })?;
+        resvg::render(
+            &tree,
+            tiny_skia::Transform::from_scale(scale, scale),
+            &mut pixmap.as_mut(),
+        );
+
+        let pixels = pixmap
+            .pixels()
+            .iter()
+            .flat_map(|p| {
+                let p = p.demultiply();
+                [p.red(), p.green(), p.blue(), p.alpha()]
+            })
+            .collect();
+
+        Self::new(width, height, pixels)
+    }
+
+    /// Loads an SVG image from a file and rasterizes it, scaling its size by `scale`.
+    #[cfg(feature = "svg")]
+    pub fn load_svg<P: AsRef<Path>>(path: P, scale: f32) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|err| {
+            Error::new(format!("failed to read image '{}'", path.display()))
+                .with_kind(ErrorKind::Io)
+                .with_source(err)
+        })?;
+
+        Self::from_svg(&bytes, scale)
+            .map_err(|err| err.context(format!("failed to load image '{}'", path.display())))
+    }
+
+    /// Loads a PNG image from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|err| {
+            Error::new(format!("failed to read image '{}'", path.display()))
+                .with_kind(ErrorKind::Io)
+                .with_source(err)
+        })?;
+
+        Self::from_png(&bytes)
+            .map_err(|err| err.context(format!("failed to load image '{}'", path.display())))
+    }
+
+    /// Encodes the image as a PNG.
+    pub fn to_png(&self) -> Result<Vec<u8>, Error> {
+        let err = |err: png::EncodingError| {
+            Error::new("failed to encode png image")
+                .with_kind(ErrorKind::Asset)
+                .with_source(err)
+        };
+
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(err)?;
+        writer.write_image_data(&self.pixels).map_err(err)?;
+        writer.finish().map_err(err)?;
+        Ok(bytes)
+    }
+
+    /// Saves the image to a file as a PNG.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let bytes = self
+            .to_png()
+            .map_err(|err| err.context(format!("failed to save image '{}'", path.display())))?;
+        std::fs::write(path, bytes).map_err(|err| {
+            Error::new(format!("failed to write image '{}'", path.display()))
+                .with_kind(ErrorKind::Io)
+                .with_source(err)
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+}
+
+impl Dimensions for Image {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pixel_data_must_match_size() {
+        assert!(Image::new(2, 2, vec![0; 16]).is_ok());
+        assert!(Image::new(2, 2, vec![0; 12]).is_err());
+    }
+
+    #[test]
+    fn png_is_decoded_to_rgba() {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[255, 0, 0, 0, 0, 255]).unwrap();
+        writer.finish().unwrap();
+
+        let image = Image::from_png(&bytes).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 1));
+        assert_eq!(image.pixels(), &[255, 0, 0, 255, 0, 0, 255, 255]);
+    }
+
+    #[cfg(feature = "svg")]
+    #[test]
+    fn svg_is_rasterized_at_scale() {
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="2">
+            <rect width="4" height="2" fill="red"/>
+        </svg>"#;
+
+        let image = Image::from_svg(svg, 2.0).unwrap();
+        assert_eq!((image.width(), image.height()), (8, 4));
+        assert_eq!(&image.pixels()[0..4], &[255, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,166 @@
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    math::{v2, Vec2f},
+    sys::{Event, KeyCode, Keyboard, Mouse, MouseButton, ScanCode, ScrollDelta},
+};
+
+#[derive(Default)]
+pub(crate) struct InputHistory {
+    events: VecDeque<InputEvent>,
+    oldest_pending: Option<Instant>,
+    latency: Duration,
+}
+
+impl InputHistory {
+    const CAPACITY: usize = 32;
+
+    pub(crate) fn record(&mut self, event: &Event) {
+        self.oldest_pending.get_or_insert_with(Instant::now);
+
+        let event = match event {
+            Event::KeyboardInput(input) => InputEvent::Key {
+                key: input.key.clone(),
+                scancode: input.scancode,
+                pressed: input.pressed,
+                repeat: input.repeat,
+                text: input.text.clone(),
+            },
+            Event::MouseInput { button, pressed } => InputEvent::MouseButton {
+                button: *button,
+                pressed: *pressed,
+            },
+            Event::MouseScrolled(ScrollDelta::Lines { x, y }) => {
+                InputEvent::MouseScrolledLines(v2(*x, *y))
+            }
+            Event::MouseScrolled(ScrollDelta::Pixels { x, y }) => {
+                InputEvent::MouseScrolledPixels(v2(*x as f32, *y as f32))
+            }
+            // Cursor movement is too frequent to be useful in the history and the current position
+            // is part of the snapshot anyway.
+            _ => return,
+        };
+
+        if self.events.len() == Self::CAPACITY {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    /// Marks the input recorded so far as seen by the game at `now`, measuring how long the
+    /// oldest of it waited to be seen.
+    pub(crate) fn begin_frame(&mut self, now: Instant) {
+        self.latency = self.oldest_pending.take().map_or(Duration::ZERO, |oldest| {
+            now.saturating_duration_since(oldest)
+        });
+    }
+
+    /// How long the oldest input seen by this frame waited between arriving and the frame
+    /// starting, or zero if there was no new input.
+    pub(crate) fn latency(&self) -> Duration {
+        self.latency
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputEvent {
+    Key {
+        key: Option<KeyCode>,
+        scancode: ScanCode,
+        pressed: bool,
+        repeat: bool,
+        text: String,
+    },
+    MouseButton {
+        button: MouseButton,
+        pressed: bool,
+    },
+    MouseScrolledLines(Vec2f),
+    MouseScrolledPixels(Vec2f),
+}
+
+impl Display for InputEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputEvent::Key {
+                key,
+                scancode,
+                pressed,
+                repeat,
+                text,
+            } => write!(
+                f,
+                "key {:?} {:?} pressed={} repeat={} text={:?}",
+                key, scancode, pressed, repeat, text
+            ),
+            InputEvent::MouseButton { button, pressed } => {
+                write!(f, "mouse button {:?} pressed={}", button, pressed)
+            }
+            InputEvent::MouseScrolledLines(delta) => write!(f, "mouse scrolled {} lines", delta),
+            InputEvent::MouseScrolledPixels(delta) => write!(f, "mouse scrolled {} pixels", delta),
+        }
+    }
+}
+
+/// The state of all input devices at a point in time, along with the most recent input events,
+/// oldest first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputSnapshot {
+    pub keys_held: Vec<KeyCode>,
+    pub scancodes_held: Vec<ScanCode>,
+    pub mouse_buttons_held: Vec<MouseButton>,
+    pub mouse_position: Vec2f,
+    pub scale_factor: f64,
+    pub scroll_delta: Vec2f,
+    pub recent_events: Vec<InputEvent>,
+    pub input_latency: Duration,
+}
+
+impl InputSnapshot {
+    pub(crate) fn new(keyboard: &Keyboard, mouse: &Mouse, history: &InputHistory) -> Self {
+        // Sort so that snapshots of the same state always compare and print the same.
+        let mut keys_held = keyboard.held_keys().cloned().collect::<Vec<_>>();
+        keys_held.sort_by_key(|key| format!("{:?}", key));
+        let mut scancodes_held = keyboard.held_scancodes().collect::<Vec<_>>();
+        scancodes_held.sort_by_key(|scancode| format!("{:?}", scancode));
+        let mut mouse_buttons_held = mouse.held_buttons().collect::<Vec<_>>();
+        mouse_buttons_held.sort_by_key(|button| format!("{:?}", button));
+
+        Self {
+            keys_held,
+            scancodes_held,
+            mouse_buttons_held,
+            mouse_position: mouse.position_physical(),
+            scale_factor: mouse.scale_factor(),
+            scroll_delta: mouse.scroll_delta(),
+            recent_events: history.events.iter().cloned().collect(),
+            input_latency: history.latency(),
+        }
+    }
+}
+
+impl Display for InputSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "keys held: {:?}", self.keys_held)?;
+        writeln!(f, "scancodes held: {:?}", self.scancodes_held)?;
+        writeln!(f, "mouse buttons held: {:?}", self.mouse_buttons_held)?;
+        writeln!(f, "mouse position: {}", self.mouse_position)?;
+        writeln!(f, "scale factor: {}", self.scale_factor)?;
+        writeln!(f, "scroll delta: {}", self.scroll_delta)?;
+        writeln!(
+            f,
+            "input latency: {:.1} ms",
+            self.input_latency.as_secs_f64() * 1000.0
+        )?;
+        writeln!(f, "recent events:")?;
+        for event in self.recent_events.iter() {
+            writeln!(f, "  {}", event)?;
+        }
+
+        Ok(())
+    }
+}
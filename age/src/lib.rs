@@ -1,27 +1,104 @@
-use std::process::ExitCode;
+use std::{
+    process::ExitCode,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
+pub use app::AppBuilder;
+pub use atlas::{Atlas, AtlasBuilder, PackedRect, RectPacker};
+pub use autotile::{neighbor_mask, TerrainLayout, TerrainSet};
+pub use canvas::Canvas;
 pub use color::*;
-pub use error::Error;
-pub use graphics::{Graphics, Sprite};
+pub use cvars::{CVarType, CVarValue, CVars};
+pub use decal::DecalMap;
+pub use deform::Deformation;
+#[cfg(feature = "dialog")]
+pub use dialog::{FileDialog, FileDialogStatus};
+pub use error::{Error, ErrorKind};
+pub use frame_stats::{FrameStats, PassStats};
+pub use gizmo::{Gizmo, GizmoDelta, GizmoHandle, GizmoMode};
+pub use graphics::{
+    DrawOrder, DrawParams, Graphics, Mesh, RenderTexture, Sprite, Texture, TextureOptions, View,
+    ViewTransition, MAX_OUTLINE_THICKNESS,
+};
+use i18n::Localization;
+pub use image::Image;
+use input::InputHistory;
+pub use input::{InputEvent, InputSnapshot};
+pub use light::LightMap;
+use math::{v2, Easing, Rect, Vec2f};
+pub use metadata::AppMetadata;
+use net::Network;
+pub use os::{Attention, Os};
+pub use path::Path;
+#[cfg(feature = "discord")]
+pub use presence::{Presence, PresenceActivity};
+pub use raw_window_handle;
+use raw_window_handle::{DisplayHandle, HasDisplayHandle, HasWindowHandle, WindowHandle};
+pub use render_graph::{GraphTextures, RenderGraph};
 use renderer::Renderer;
+pub use renderer::{
+    AddressMode, BlendMode, BufferDesc, BufferUsages, ClearPolicy, ColorTarget, ColorTargetDesc,
+    CompositeAlphaMode, CrtEffect, DeviceType, DrawBudget, DrawStats, DrawTarget, DynamicBuffer,
+    FilterMode, GeometryVertex, GpuCapabilities, GpuPreference, PresentMode, QualityPreset,
+    ShaderError, TextureReadbackId, Vertex, VertexAttribute, VertexBufferLayoutDesc,
+    VertexBufferType, VertexFormat, MAX_COLOR_TARGETS,
+};
+#[cfg(feature = "scripting")]
+pub use script::Script;
+pub use sprite_sheet::{Animation, SpriteSheet};
+use sys::Window;
+pub use sys::{
+    Gamepad, GamepadAxis, GamepadButton, GamepadEvent, GamepadId, Gamepads, KeyCode, Keyboard,
+    Mouse, MouseButton, ScanCode, TouchId, Touches,
+};
+pub use text_input::TextInput;
+pub use texture_pool::{TransientTextureDesc, TransientTextureStats};
+use ui::Insets;
 
+pub mod ai;
 mod app;
+mod atlas;
+mod autotile;
+mod canvas;
 mod color;
+mod crash;
+mod cvars;
+mod decal;
+mod deform;
+#[cfg(feature = "dialog")]
+mod dialog;
 mod error;
+mod frame_dump;
+mod frame_stats;
 mod gen_vec;
+mod gizmo;
 mod graphics;
+pub mod i18n;
+mod image;
+mod input;
+mod light;
 pub mod math;
+mod metadata;
+pub mod net;
+mod os;
+mod pacing;
+mod path;
+pub mod physics;
+#[cfg(feature = "discord")]
+mod presence;
+mod render_graph;
 mod renderer;
+mod resources;
+#[cfg(feature = "scripting")]
+mod script;
+mod sprite_sheet;
 mod sys;
+mod text_input;
+mod texture_pool;
+pub mod ui;
 
 pub fn run<G: Game>() -> ExitCode {
-    match app::run::<G>() {
-        Ok(()) => ExitCode::SUCCESS,
-        Err(err) => {
-            eprintln!("{err}");
-            ExitCode::FAILURE
-        }
-    }
+    AppBuilder::new().run::<G>()
 }
 
 pub trait Game<T = Self> {
@@ -32,24 +109,452 @@ pub trait Game<T = Self> {
     fn on_exit_requested(&mut self, age: &mut Engine) {
         age.exit();
     }
+
+    /// Called with text typed by the user, after any dead key or compose sequences have been
+    /// resolved by the platform, e.g. `´` followed by `e` is entered as `é`.
+    fn on_text_entered(&mut self, _age: &mut Engine, _text: &str) {}
+
+    /// Called each time a key is pressed, including repeated presses while it is held, which
+    /// suits text editing better than polling the keyboard.
+    fn on_key_pressed(&mut self, _age: &mut Engine, _key: &KeyCode, _repeat: bool) {}
+
+    /// Called for each change to a gamepad at the start of the frame, after [`Engine::gamepads`]
+    /// has been updated, e.g. to show which pad a player is using as soon as it is connected.
+    fn on_gamepad_event(&mut self, _age: &mut Engine, _id: GamepadId, _event: &GamepadEvent) {}
+
+    /// Called when the window has been resized, with its new size in physical pixels. Render
+    /// textures that track the window size should be resized here.
+    fn on_size_changed(&mut self, _age: &mut Engine, _width: u32, _height: u32) {}
 }
 
 pub struct Engine {
     exit: bool,
+    window: Window,
+    metadata: AppMetadata,
+    input_history: InputHistory,
     pub renderer: Renderer,
     pub graphics: Graphics,
+    pub i18n: Localization,
+    pub cvars: CVars,
+    pub keyboard: Keyboard,
+    pub mouse: Mouse,
+    pub touches: Touches,
+    pub gamepads: Gamepads,
+    network: Network,
+    #[cfg(feature = "discord")]
+    presence: Presence,
+    safe_area_insets: Insets,
+    view_transition: Option<(ViewTransition, f32)>, // Started at this engine time.
+    time: f32,
+    delta_time: f32,
+    fixed_timestep: Option<f32>,
+    random_seed: u64,
+    frame_stats: FrameStats,
+    frame_budget: Option<Duration>,
+    budget_warned_at: Option<Instant>,
+    draw_budget: Option<DrawBudget>,
+    draw_budget_warned_at: Option<Instant>,
 }
 
 impl Engine {
-    fn new(renderer: Renderer, graphics: Graphics) -> Self {
+    fn new(window: Window, renderer: Renderer, graphics: Graphics, metadata: AppMetadata) -> Self {
+        let mouse = Mouse::new(window.scale_factor());
+        let touches = Touches::new(window.scale_factor());
+        let mut cvars = CVars::default();
+        cvars.register_engine();
+        let random_seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+
         Self {
             exit: false,
+            window,
+            metadata,
+            input_history: InputHistory::default(),
             renderer,
             graphics,
+            i18n: Localization::default(),
+            cvars,
+            keyboard: Keyboard::default(),
+            mouse,
+            touches,
+            gamepads: Gamepads::default(),
+            network: Network::default(),
+            #[cfg(feature = "discord")]
+            presence: Presence::default(),
+            safe_area_insets: Insets::default(),
+            view_transition: None,
+            time: 0.0,
+            delta_time: 0.0,
+            fixed_timestep: None,
+            random_seed,
+            frame_stats: FrameStats::default(),
+            frame_budget: None,
+            budget_warned_at: None,
+            draw_budget: None,
+            draw_budget_warned_at: None,
         }
     }
 
     pub fn exit(&mut self) {
         self.exit = true;
     }
+
+    /// Captures the current state of all input devices along with the most recent input events,
+    /// which is useful when diagnosing input issues.
+    pub fn input_snapshot(&self) -> InputSnapshot {
+        InputSnapshot::new(&self.keyboard, &self.mouse, &self.input_history)
+    }
+
+    /// How long the oldest input seen by this frame waited between arriving and the frame
+    /// starting, or zero if there was no new input.
+    pub fn input_latency(&self) -> std::time::Duration {
+        self.input_history.latency()
+    }
+
+    /// Whether `key` was pressed within `seconds` of the start of this frame.
+    pub fn key_pressed_within(&self, key: &KeyCode, seconds: f32) -> bool {
+        self.keyboard.pressed_within(key, seconds)
+    }
+
+    /// Translates `key` into the active language.
+    pub fn tr(&self, key: &str) -> String {
+        self.i18n.tr(key)
+    }
+
+    /// Translates `key` into the active language, replacing each `{name}` placeholder with the
+    /// matching argument.
+    pub fn tr_args(&self, key: &str, args: &[(&str, &str)]) -> String {
+        self.i18n.tr_args(key, args)
+    }
+
+    /// Starts drawing `sprite` with parameters that override its own.
+    pub fn draw<'a>(&'a mut self, sprite: &'a Sprite) -> DrawParams<'a> {
+        self.graphics.draw(sprite)
+    }
+
+    /// Draws a grid of lines `spacing` world units apart over everything that `view` can see.
+    pub fn draw_grid(&mut self, spacing: f32, color: Color, view: &View) {
+        self.graphics.draw_grid(spacing, color, view);
+    }
+
+    /// Magnifies everything drawn to the window by `zoom` around the mouse cursor, for players
+    /// who need a closer look. A zoom of `1.0` turns magnification off. This sets the
+    /// `render.ui_zoom` cvar, so it can also be changed from a console or config.
+    pub fn set_ui_zoom(&mut self, zoom: f32) {
+        let _ = self.cvars.set(cvars::RENDER_UI_ZOOM, zoom);
+    }
+
+    /// Moves the view from `from` to `to` over `duration` seconds. The engine sets the view at
+    /// the start of each frame until the transition is cancelled or another one starts, holding
+    /// `to` once it has finished.
+    pub fn transition_view(&mut self, from: View, to: View, duration: f32, easing: Easing) {
+        let transition = ViewTransition::new(from, to, duration, easing);
+        self.view_transition = Some((transition, self.time));
+    }
+
+    /// Stops the engine from setting the view, leaving it to the game.
+    pub fn cancel_view_transition(&mut self) {
+        self.view_transition = None;
+    }
+
+    /// Whether a view transition has started and not yet finished.
+    pub fn is_view_transitioning(&self) -> bool {
+        self.view_transition
+            .as_ref()
+            .is_some_and(|(transition, start)| !transition.is_finished(self.time - start))
+    }
+
+    /// The view for this frame of the current view transition, if there is one.
+    fn transitioned_view(&self) -> Option<View> {
+        self.view_transition
+            .as_ref()
+            .map(|(transition, start)| transition.view_at(self.time - start))
+    }
+
+    /// The number of seconds the game has been running for, as of the start of this frame.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// The number of seconds between the start of the last frame and the start of this one.
+    pub fn delta_time(&self) -> f32 {
+        self.delta_time
+    }
+
+    /// A seed for the game's random number generators, which differs between runs except when
+    /// dumping frames, so that dumped runs make the same random choices.
+    pub fn random_seed(&self) -> u64 {
+        self.random_seed
+    }
+
+    /// Whether every presented frame is being written to a PNG in the directory named by the
+    /// `AGE_DUMP_FRAMES` environment variable. While dumping, each frame advances
+    /// [`Engine::time`] by a fixed 1/60th of a second, however long it really took.
+    pub fn is_dumping_frames(&self) -> bool {
+        self.fixed_timestep.is_some()
+    }
+
+    /// Advances the engine's clock at the start of a frame, `elapsed` after the last one started.
+    fn advance_clock(&mut self, elapsed: Duration) {
+        self.delta_time = self.fixed_timestep.unwrap_or(elapsed.as_secs_f32());
+        self.time += self.delta_time;
+    }
+
+    /// Uploads `image` to a new texture that sprites can be drawn with. Fails if the image is
+    /// empty or larger than the GPU's maximum texture size.
+    pub fn create_texture_from_image(
+        &mut self,
+        image: &Image,
+        options: &TextureOptions,
+    ) -> Result<Texture, Error> {
+        self.graphics
+            .create_texture_from_image(&mut self.renderer, image, options)
+    }
+
+    /// Creates a light map that darkens the scene to `ambient` wherever it is not lit.
+    pub fn create_light_map(&mut self, width: u32, height: u32, ambient: Color) -> LightMap {
+        self.graphics
+            .create_light_map(&mut self.renderer, width, height, ambient)
+    }
+
+    /// Creates a decal map covering `bounds` in the world, that decals can be splatted into with
+    /// [`Graphics::splat`].
+    pub fn create_decal_map(&mut self, bounds: Rect, texels_per_unit: f32) -> DecalMap {
+        self.graphics
+            .create_decal_map(&mut self.renderer, bounds, texels_per_unit)
+    }
+
+    /// Adds a light of `color` around `position` to the light map that lights are being drawn to.
+    pub fn draw_light(&mut self, sprite: &Sprite, position: Vec2f, radius: f32, color: Color) {
+        self.graphics.draw_light(sprite, position, radius, color);
+    }
+
+    /// The pick id of the sprite under `screen_position`, given in physical pixels, or `None` if
+    /// there is no pickable sprite there or picking is not enabled. Ids are read back from the
+    /// GPU asynchronously, so the result is for a position requested a frame or more earlier.
+    pub fn pick(&mut self, screen_position: Vec2f) -> Option<u32> {
+        if !self.graphics.is_picking_enabled() {
+            return None;
+        }
+
+        if screen_position.x >= 0.0 && screen_position.y >= 0.0 {
+            self.graphics
+                .request_pick((screen_position.x as u32, screen_position.y as u32));
+        }
+        self.renderer.last_pick()
+    }
+
+    /// The number of samples drawn inside the draw target for occlusion query `id`, begun with
+    /// [`Graphics::begin_occlusion_query`], in the most recently read back frame. `None` if that
+    /// frame had no such query, or before any frame has been read back.
+    pub fn occlusion_samples(&self, id: u32) -> Option<u64> {
+        self.renderer.occlusion_samples(id)
+    }
+
+    /// Submits everything drawn so far this frame to the GPU, see [`Graphics::flush`].
+    pub fn flush(&mut self) {
+        self.graphics.flush(&mut self.renderer);
+    }
+
+    /// Where the time went in the last frame, e.g. to tell whether stutters come from the game,
+    /// from encoding draws or from waiting on the GPU.
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_stats
+    }
+
+    pub fn get_frame_budget(&self) -> Option<Duration> {
+        self.frame_budget
+    }
+
+    /// Logs a warning with the [`FrameStats`] of any frame that takes longer than `budget`, at
+    /// most once a second.
+    pub fn set_frame_budget(&mut self, budget: Option<Duration>) {
+        self.frame_budget = budget;
+    }
+
+    pub fn get_draw_budget(&self) -> Option<DrawBudget> {
+        self.draw_budget
+    }
+
+    /// In debug builds, logs a warning with the [`DrawStats`] of any frame that goes over a limit
+    /// of `budget`, along with the labels of the textures bound most often, at most once a
+    /// second.
+    pub fn set_draw_budget(&mut self, budget: Option<DrawBudget>) {
+        self.draw_budget = budget;
+    }
+
+    fn record_frame_stats(&mut self, stats: FrameStats) {
+        self.frame_stats = stats;
+
+        if let Some(warning) = self
+            .frame_budget
+            .and_then(|budget| stats.budget_warning(budget))
+        {
+            warn_throttled(&mut self.budget_warned_at, &warning);
+        }
+
+        let draw_budget = self.draw_budget.filter(|_| cfg!(debug_assertions));
+        if let Some(warning) = draw_budget.and_then(|budget| {
+            let most_bound = self.renderer.most_bound_textures(5);
+            self.renderer
+                .draw_stats()
+                .budget_warning(&budget, &most_bound)
+        }) {
+            warn_throttled(&mut self.draw_budget_warned_at, &warning);
+        }
+    }
+
+    /// The draw calls made and state changed to draw the last frame, e.g. to see the effect of
+    /// [`DrawOrder::ByState`].
+    pub fn draw_stats(&self) -> DrawStats {
+        self.renderer.draw_stats()
+    }
+
+    /// Loads a PNG image from a file and uploads it to a new texture with the default options.
+    pub fn load_texture<P: AsRef<std::path::Path>>(&mut self, path: P) -> Result<Texture, Error> {
+        self.graphics.load_texture(&mut self.renderer, path)
+    }
+
+    pub fn gpu_capabilities(&self) -> &GpuCapabilities {
+        self.renderer.gpu_capabilities()
+    }
+
+    pub fn network(&mut self) -> &mut Network {
+        &mut self.network
+    }
+
+    /// The player's activity shown on their Discord profile, e.g.
+    /// `age.presence().set(PresenceActivity::new().with_details("Exploring the caves"))`.
+    #[cfg(feature = "discord")]
+    pub fn presence(&mut self) -> &mut Presence {
+        &mut self.presence
+    }
+
+    /// Opens a native dialog for choosing an existing file, limited to the `(name, extensions)`
+    /// filters given, if any. The game keeps running while the dialog is open.
+    #[cfg(feature = "dialog")]
+    pub fn open_file_dialog(&self, filters: &[(&str, &[&str])]) -> FileDialog {
+        FileDialog::open(&self.window, filters)
+    }
+
+    /// Opens a native dialog for choosing where to save a file, suggesting `file_name` unless it
+    /// is empty. The game keeps running while the dialog is open.
+    #[cfg(feature = "dialog")]
+    pub fn save_file_dialog(&self, filters: &[(&str, &[&str])], file_name: &str) -> FileDialog {
+        FileDialog::save(&self.window, filters, file_name)
+    }
+
+    /// Integrations with the desktop, such as asking for the user's attention from the taskbar.
+    pub fn os(&self) -> Os<'_> {
+        Os::new(&self.window)
+    }
+
+    /// The OS handle of the game window, for integrations such as overlays, native dialogs or
+    /// video playback that need to draw into or parent themselves to it.
+    pub fn window_handle(&self) -> Result<WindowHandle<'_>, Error> {
+        Ok(self.window.window_handle()?)
+    }
+
+    /// The OS handle of the display connection that the game window belongs to.
+    pub fn display_handle(&self) -> Result<DisplayHandle<'_>, Error> {
+        Ok(self.window.display_handle()?)
+    }
+
+    /// The size of the inside of the game window in physical pixels.
+    pub fn window_size(&self) -> (u32, u32) {
+        (self.window.width(), self.window.height())
+    }
+
+    /// The ratio of physical pixels to logical pixels for the monitor the window is on.
+    pub fn scale_factor(&self) -> f64 {
+        self.window.scale_factor()
+    }
+
+    /// The part of the window, in physical pixels, that is not covered by notches or lost to TV
+    /// overscan, which UI should be kept within. Desktop platforms don't report any unsafe area,
+    /// so this is the whole window less any insets set with [`Engine::set_safe_area_insets`].
+    pub fn safe_area(&self) -> Rect {
+        let (width, height) = self.window_size();
+        self.safe_area_insets
+            .shrink(Rect::new(Vec2f::ZERO, v2(width as f32, height as f32)))
+    }
+
+    /// Pretends that the edges of the window are unsafe by `insets` physical pixels, to check that
+    /// UI stays clear of them on displays with notches or overscan.
+    pub fn set_safe_area_insets(&mut self, insets: Insets) {
+        self.safe_area_insets = insets;
+    }
+
+    /// The game's name, organization and version, set with [`AppBuilder::with_app_metadata`].
+    pub fn app_metadata(&self) -> &AppMetadata {
+        &self.metadata
+    }
+
+    /// Shows `suffix` in the window title after the game's title, e.g.
+    /// `age.set_title_suffix(&format!("{:.0} fps", age.fps()))`. An empty suffix shows the
+    /// game's title alone.
+    pub fn set_title_suffix(&mut self, suffix: &str) {
+        let title = match suffix.is_empty() {
+            true => self.metadata.title(),
+            false => format!("{} - {}", self.metadata.title(), suffix),
+        };
+        self.window.set_title(&title);
+    }
+
+    /// Shows or hides the window's title bar and borders. Without them the window is borderless,
+    /// and can be moved with [`Engine::begin_window_drag`] from the game's own title bar.
+    pub fn set_decorations(&mut self, decorations: bool) {
+        self.window.set_decorations(decorations);
+    }
+
+    pub fn set_resizable(&mut self, resizable: bool) {
+        self.window.set_resizable(resizable);
+    }
+
+    /// Moves the window with the mouse until the left button is released, for a custom title
+    /// bar. Call this when the left button is pressed over the title bar.
+    pub fn begin_window_drag(&mut self) -> Result<(), Error> {
+        self.window.drag()
+    }
+
+    /// The number of frames per second at the current frame's [`Engine::delta_time`].
+    pub fn fps(&self) -> f32 {
+        match self.delta_time > 0.0 {
+            true => 1.0 / self.delta_time,
+            false => 0.0,
+        }
+    }
+
+    /// The refresh rate in hertz of the monitor the window is on, if the platform reports it, for
+    /// adapting animation smoothing to the display.
+    pub fn display_refresh_rate(&self) -> Option<f32> {
+        self.window.refresh_rate()
+    }
+
+    pub fn mouse_position_logical(&self) -> Vec2f {
+        self.mouse.position_logical()
+    }
+
+    pub fn mouse_position_physical(&self) -> Vec2f {
+        self.mouse.position_physical()
+    }
+
+    /// Warps the mouse cursor to `position`, given in logical pixels relative to the top-left of
+    /// the window.
+    pub fn set_mouse_position(&mut self, position: Vec2f) -> Result<(), Error> {
+        self.window.set_cursor_position(position.x, position.y)?;
+        self.mouse.set_position_logical(position);
+        Ok(())
+    }
+}
+
+/// Logs `warning` unless a warning was logged through `warned_at` less than a second ago.
+fn warn_throttled(warned_at: &mut Option<Instant>, warning: &str) {
+    let now = Instant::now();
+    if warned_at.is_none_or(|at| now - at >= Duration::from_secs(1)) {
+        eprintln!("{warning}");
+        *warned_at = Some(now);
+    }
 }
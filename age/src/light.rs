@@ -0,0 +1,71 @@
+use crate::{math::v2, Color, Image, RenderTexture};
+
+/// A render texture that lights are added to, which is multiplied over the scene so that it is
+/// darkened to the ambient color wherever it is not lit.
+///
+/// Lights are drawn between [`Graphics::begin_lights`](crate::Graphics::begin_lights) and
+/// [`Graphics::end_lights`](crate::Graphics::end_lights), and the light map should be the same
+/// size as the target it is composited over.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightMap {
+    target: RenderTexture,
+    ambient: Color,
+}
+
+impl LightMap {
+    pub(crate) fn new(target: RenderTexture, ambient: Color) -> Self {
+        Self { target, ambient }
+    }
+
+    pub fn target(&self) -> &RenderTexture {
+        &self.target
+    }
+
+    pub(crate) fn target_mut(&mut self) -> &mut RenderTexture {
+        &mut self.target
+    }
+
+    /// The light in places that are not lit, e.g. [`Color::BLACK`] for complete darkness.
+    pub fn get_ambient(&self) -> Color {
+        self.ambient
+    }
+
+    pub fn set_ambient(&mut self, ambient: Color) {
+        self.ambient = ambient;
+    }
+
+    /// A white circle `size` pixels across that fades to transparent at its edge, which light
+    /// sprites can be created from.
+    pub fn falloff_image(size: u32) -> Image {
+        let radius = size as f32 / 2.0;
+        let center = v2(radius, radius);
+
+        let mut pixels = Vec::with_capacity(size as usize * size as usize * 4);
+        for y in 0..size {
+            for x in 0..size {
+                let distance = (v2(x as f32 + 0.5, y as f32 + 0.5) - center).len() / radius;
+                let intensity = (1.0 - distance).clamp(0.0, 1.0);
+                let alpha = (intensity * intensity * 255.0).round() as u8;
+                pixels.extend([255, 255, 255, alpha]);
+            }
+        }
+
+        Image::new(size, size, pixels).expect("falloff pixels match its size")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn falloff_is_brightest_at_center() {
+        let image = LightMap::falloff_image(9);
+        let alpha = |x: usize, y: usize| image.pixels()[(y * 9 + x) * 4 + 3];
+
+        assert!(alpha(4, 4) > 200);
+        assert!(alpha(4, 4) > alpha(2, 4));
+        assert_eq!(alpha(0, 0), 0);
+        assert_eq!(alpha(8, 4), alpha(0, 4));
+    }
+}
@@ -0,0 +1,56 @@
+use super::{v2, Rect, Vec2f};
+
+/// Something with a size in whole pixels, such as an image, texture or sprite. Its size as a
+/// vector, in floats and as a rectangle all follow from its width and height.
+pub trait Dimensions {
+    fn width(&self) -> u32;
+
+    fn height(&self) -> u32;
+
+    fn size_vec2(&self) -> Vec2f {
+        v2(self.width_f32(), self.height_f32())
+    }
+
+    fn width_f32(&self) -> f32 {
+        self.width() as f32
+    }
+
+    fn height_f32(&self) -> f32 {
+        self.height() as f32
+    }
+
+    /// The center, relative to the top-left corner.
+    fn center(&self) -> Vec2f {
+        self.size_vec2() / 2.0
+    }
+
+    /// The bounds, with the top-left corner at the origin.
+    fn rect(&self) -> Rect {
+        Rect::new(Vec2f::ZERO, self.size_vec2())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Size(u32, u32);
+
+    impl Dimensions for Size {
+        fn width(&self) -> u32 {
+            self.0
+        }
+
+        fn height(&self) -> u32 {
+            self.1
+        }
+    }
+
+    #[test]
+    fn sizes_follow_from_width_and_height() {
+        let size = Size(30, 20);
+        assert_eq!(size.size_vec2(), v2(30.0, 20.0));
+        assert_eq!(size.center(), v2(15.0, 10.0));
+        assert_eq!(size.rect(), Rect::new(Vec2f::ZERO, v2(30.0, 20.0)));
+    }
+}
@@ -0,0 +1,178 @@
+use super::{v2, v2i, Vec2f, Vec2i};
+
+/// How the cells of a [`Grid`] are shaped and laid out in the world.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GridKind {
+    #[default]
+    Square,
+    /// Diamond-shaped cells, with x running down and to the right and y running down and to the
+    /// left. The cell size is the width and height of a diamond.
+    Isometric,
+    /// Hexagons with a pointed top, in rows where odd rows are shifted right by half a cell. The
+    /// cell size is the width and height of a hexagon, and rows overlap by a quarter of it.
+    HexPointy,
+    /// Hexagons with a flat top, in columns where odd columns are shifted down by half a cell.
+    /// The cell size is the width and height of a hexagon, and columns overlap by a quarter of it.
+    HexFlat,
+}
+
+/// Converts between cells of a grid and positions in the world, for square, isometric and
+/// hexagonal grids.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Grid {
+    kind: GridKind,
+    cell_size: Vec2f,
+    origin: Vec2f,
+}
+
+impl Grid {
+    pub fn new(kind: GridKind, cell_size: Vec2f) -> Self {
+        Self {
+            kind,
+            cell_size,
+            origin: Vec2f::ZERO,
+        }
+    }
+
+    /// Places the center of cell (0, 0) at `origin` in the world.
+    pub fn with_origin(mut self, origin: Vec2f) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    pub fn kind(&self) -> GridKind {
+        self.kind
+    }
+
+    pub fn cell_size(&self) -> Vec2f {
+        self.cell_size
+    }
+
+    /// The position of the center of `cell` in the world.
+    pub fn grid_to_world(&self, cell: Vec2i) -> Vec2f {
+        let (x, y) = (cell.x as f32, cell.y as f32);
+        let size = self.cell_size;
+        let offset = match self.kind {
+            GridKind::Square => v2(x * size.x, y * size.y),
+            GridKind::Isometric => v2((x - y) * size.x / 2.0, (x + y) * size.y / 2.0),
+            GridKind::HexPointy => {
+                let shift = (cell.y & 1) as f32 / 2.0;
+                v2((x + shift) * size.x, y * size.y * 0.75)
+            }
+            GridKind::HexFlat => {
+                let shift = (cell.x & 1) as f32 / 2.0;
+                v2(x * size.x * 0.75, (y + shift) * size.y)
+            }
+        };
+        self.origin + offset
+    }
+
+    /// The cell that contains `world`.
+    pub fn world_to_grid(&self, world: Vec2f) -> Vec2i {
+        let local = world - self.origin;
+        let size = self.cell_size;
+        match self.kind {
+            GridKind::Square => v2i(
+                (local.x / size.x).round() as i32,
+                (local.y / size.y).round() as i32,
+            ),
+            GridKind::Isometric => {
+                let (u, v) = (local.x / (size.x / 2.0), local.y / (size.y / 2.0));
+                v2i(
+                    ((u + v) / 2.0).round() as i32,
+                    ((v - u) / 2.0).round() as i32,
+                )
+            }
+            GridKind::HexPointy => {
+                // Scaled so the hexagons are regular with a radius of one.
+                let (x, y) = (local.x / size.x * SQRT_3, local.y / size.y * 2.0);
+                let (q, r) = hex_round(x * SQRT_3 / 3.0 - y / 3.0, y * 2.0 / 3.0);
+                v2i(q + (r - (r & 1)) / 2, r)
+            }
+            GridKind::HexFlat => {
+                let (x, y) = (local.x / size.x * 2.0, local.y / size.y * SQRT_3);
+                let (q, r) = hex_round(x * 2.0 / 3.0, -x / 3.0 + y * SQRT_3 / 3.0);
+                v2i(q, r + (q - (q & 1)) / 2)
+            }
+        }
+    }
+
+    /// The cells that share an edge with `cell`: four for square and isometric grids and six
+    /// for hexagonal grids.
+    pub fn neighbors(&self, cell: Vec2i) -> impl Iterator<Item = Vec2i> {
+        const SQUARE: &[(i32, i32)] = &[(1, 0), (0, 1), (-1, 0), (0, -1)];
+        const EVEN_ROW: &[(i32, i32)] = &[(1, 0), (0, -1), (-1, -1), (-1, 0), (-1, 1), (0, 1)];
+        const ODD_ROW: &[(i32, i32)] = &[(1, 0), (1, -1), (0, -1), (-1, 0), (0, 1), (1, 1)];
+        const EVEN_COLUMN: &[(i32, i32)] = &[(1, 0), (1, -1), (0, -1), (-1, -1), (-1, 0), (0, 1)];
+        const ODD_COLUMN: &[(i32, i32)] = &[(1, 1), (1, 0), (0, -1), (-1, 0), (-1, 1), (0, 1)];
+
+        let offsets = match self.kind {
+            GridKind::Square | GridKind::Isometric => SQUARE,
+            GridKind::HexPointy if cell.y & 1 == 0 => EVEN_ROW,
+            GridKind::HexPointy => ODD_ROW,
+            GridKind::HexFlat if cell.x & 1 == 0 => EVEN_COLUMN,
+            GridKind::HexFlat => ODD_COLUMN,
+        };
+        offsets.iter().map(move |&(x, y)| cell + v2i(x, y))
+    }
+
+    /// A key to sort cells by so that drawing them in order draws cells further back first,
+    /// letting tiles taller than their cell overlap the cells behind them.
+    pub fn draw_order(&self, cell: Vec2i) -> (i32, i32) {
+        match self.kind {
+            GridKind::Square | GridKind::HexPointy => (cell.y, cell.x),
+            GridKind::Isometric => (cell.x + cell.y, cell.x),
+            GridKind::HexFlat => (cell.y * 2 + (cell.x & 1), cell.x),
+        }
+    }
+}
+
+const SQRT_3: f32 = 1.732_050_8;
+
+/// Rounds fractional axial hex coordinates to the hex that contains them.
+fn hex_round(q: f32, r: f32) -> (i32, i32) {
+    let s = -q - r;
+    let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    } else if dr > ds {
+        rr = -rq - rs;
+    }
+    (rq as i32, rr as i32)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cells_round_trip_through_world() {
+        let kinds = [
+            GridKind::Square,
+            GridKind::Isometric,
+            GridKind::HexPointy,
+            GridKind::HexFlat,
+        ];
+        for kind in kinds {
+            let grid = Grid::new(kind, v2(64.0, 32.0)).with_origin(v2(10.0, -20.0));
+            for y in -3..=3 {
+                for x in -3..=3 {
+                    let cell = v2i(x, y);
+                    let center = grid.grid_to_world(cell);
+                    assert_eq!(grid.world_to_grid(center), cell, "{kind:?}");
+                    assert_eq!(grid.world_to_grid(center + v2(5.0, 3.0)), cell, "{kind:?}");
+
+                    // Each cell is a neighbor of its neighbors.
+                    for neighbor in grid.neighbors(cell) {
+                        assert!(grid.neighbors(neighbor).any(|n| n == cell), "{kind:?}");
+                    }
+                }
+            }
+        }
+
+        let iso = Grid::new(GridKind::Isometric, v2(64.0, 32.0));
+        assert_eq!(iso.grid_to_world(v2i(1, 1)), v2(0.0, 32.0));
+        assert!(iso.draw_order(v2i(1, 1)) > iso.draw_order(v2i(2, -1)));
+    }
+}
@@ -1,7 +1,15 @@
+pub use dimensions::*;
+pub use easing::*;
+pub use grid::*;
 pub use mat4::*;
+pub use rect::*;
 pub use vec2f::*;
 pub use vec2i::*;
 
+mod dimensions;
+mod easing;
+mod grid;
 mod mat4;
+mod rect;
 mod vec2f;
 mod vec2i;
@@ -0,0 +1,75 @@
+use std::fmt::Display;
+
+use super::vec2f::Vec2f;
+
+pub const fn rect(position: Vec2f, size: Vec2f) -> Rect {
+    Rect::new(position, size)
+}
+
+/// An axis-aligned rectangle, given by the position of its top-left corner and its size.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub position: Vec2f,
+    pub size: Vec2f,
+}
+
+impl Rect {
+    pub const fn new(position: Vec2f, size: Vec2f) -> Self {
+        Self { position, size }
+    }
+
+    pub fn min(&self) -> Vec2f {
+        self.position
+    }
+
+    pub fn max(&self) -> Vec2f {
+        self.position + self.size
+    }
+
+    pub fn center(&self) -> Vec2f {
+        self.position + self.size / 2.0
+    }
+
+    pub fn contains(&self, point: Vec2f) -> bool {
+        let (min, max) = (self.min(), self.max());
+        point.x >= min.x && point.x < max.x && point.y >= min.y && point.y < max.y
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        let (min, max) = (self.min(), self.max());
+        let (other_min, other_max) = (other.min(), other.max());
+        min.x < other_max.x && other_min.x < max.x && min.y < other_max.y && other_min.y < max.y
+    }
+}
+
+impl Display for Rect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}, {}]", self.position, self.size)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::math::v2;
+
+    use super::*;
+
+    #[test]
+    fn rect_center() {
+        assert_eq!(rect(v2(2.0, 4.0), v2(4.0, 2.0)).center(), v2(4.0, 5.0));
+    }
+
+    #[test]
+    fn rect_contains_point() {
+        let r = rect(Vec2f::ZERO, v2(2.0, 2.0));
+        assert!(r.contains(v2(0.0, 1.0)));
+        assert!(!r.contains(v2(2.0, 1.0)));
+    }
+
+    #[test]
+    fn rects_intersect() {
+        let r = rect(Vec2f::ZERO, v2(2.0, 2.0));
+        assert!(r.intersects(&rect(v2(1.0, 1.0), v2(2.0, 2.0))));
+        assert!(!r.intersects(&rect(v2(2.0, 0.0), v2(2.0, 2.0))));
+    }
+}
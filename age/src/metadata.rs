@@ -0,0 +1,122 @@
+use std::path::PathBuf;
+
+/// The name, organization and version of the game, set with
+/// [`AppBuilder::with_app_metadata`](crate::AppBuilder::with_app_metadata). They give the default
+/// window title and the directories that config, saves and crash logs are kept in.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AppMetadata {
+    name: String,
+    organization: String,
+    version: String,
+}
+
+impl Default for AppMetadata {
+    fn default() -> Self {
+        Self::new("age", "", "")
+    }
+}
+
+impl AppMetadata {
+    /// `organization` and `version` may be empty if the game does not have them.
+    pub fn new(name: &str, organization: &str, version: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            organization: organization.to_string(),
+            version: version.to_string(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn organization(&self) -> &str {
+        &self.organization
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// The game's name followed by its version, e.g. `Space Miner 1.2.0`.
+    pub fn title(&self) -> String {
+        match self.version.is_empty() {
+            true => self.name.clone(),
+            false => format!("{} {}", self.name, self.version),
+        }
+    }
+
+    /// The directory for the game's settings, such as saved [`CVars`](crate::CVars), within the
+    /// platform's per-user config directory.
+    pub fn config_dir(&self) -> Option<PathBuf> {
+        Some(self.app_dir(config_dir()?))
+    }
+
+    /// The directory for the game's per-user data, such as crash logs, within the platform's
+    /// per-user data directory.
+    pub fn data_dir(&self) -> Option<PathBuf> {
+        Some(self.app_dir(data_dir()?))
+    }
+
+    /// The directory for the player's saved games.
+    pub fn save_dir(&self) -> Option<PathBuf> {
+        Some(self.data_dir()?.join("saves"))
+    }
+
+    fn app_dir(&self, base: PathBuf) -> PathBuf {
+        match self.organization.is_empty() {
+            true => base.join(&self.name),
+            false => base.join(&self.organization).join(&self.name),
+        }
+    }
+}
+
+fn env(key: &str) -> Option<PathBuf> {
+    std::env::var_os(key)
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+/// The platform's directory for per-user application data.
+fn data_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        env("LOCALAPPDATA").or_else(|| env("APPDATA"))
+    } else if cfg!(target_os = "macos") {
+        env("HOME").map(|home| home.join("Library/Application Support"))
+    } else {
+        env("XDG_DATA_HOME").or_else(|| env("HOME").map(|home| home.join(".local/share")))
+    }
+}
+
+/// The platform's directory for per-user application settings.
+fn config_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        env("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env("HOME").map(|home| home.join("Library/Application Support"))
+    } else {
+        env("XDG_CONFIG_HOME").or_else(|| env("HOME").map(|home| home.join(".config")))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn directories_are_named_after_the_game() {
+        let game = AppMetadata::new("Space Miner", "Rock Co", "1.2.0");
+        let base = PathBuf::from("base");
+
+        assert_eq!(game.title(), "Space Miner 1.2.0");
+        assert_eq!(
+            game.app_dir(base.clone()),
+            base.join("Rock Co").join("Space Miner")
+        );
+        assert_eq!(AppMetadata::default().title(), "age");
+        assert_eq!(
+            AppMetadata::default().app_dir(base.clone()),
+            base.join("age")
+        );
+    }
+}
@@ -0,0 +1,428 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+};
+
+use crate::{Error, ErrorKind};
+
+use super::{Channel, Network, MAX_MESSAGE_SIZE};
+
+const MAGIC: [u8; 4] = *b"LSTP";
+const INPUT: u8 = 0;
+const CHECKSUM: u8 = 1;
+const HEADER_SIZE: usize = 9; // Magic (4) + message kind (1) + tick (4).
+
+/// The most ticks [`Lockstep::update`] will simulate at once, so a long frame is not made longer
+/// by catching up.
+const MAX_TICKS: u32 = 8;
+
+/// Exchanges per-tick inputs between peers for games that run a deterministic simulation in
+/// lockstep.
+///
+/// Each tick, the game submits its local input, which is scheduled `delay` ticks in the future to
+/// hide network latency, and then advances the simulation only once the inputs from every peer are
+/// available for the current tick. Checksums of the simulation state can be compared between
+/// peers to detect when they have diverged. [`Lockstep::update`] does this at a fixed timestep
+/// however long each frame is.
+pub struct Lockstep {
+    delay: u32,
+    tick: u32,
+    timestep: f32,
+    accumulator: f32,
+    peers: Vec<SocketAddr>,
+    local_inputs: BTreeMap<u32, Vec<u8>>,
+    remote_inputs: HashMap<SocketAddr, BTreeMap<u32, Vec<u8>>>,
+    local_checksums: BTreeMap<u32, u64>,
+    remote_checksums: HashMap<SocketAddr, BTreeMap<u32, u64>>,
+    desync: Option<u32>,
+}
+
+/// The inputs from all peers for a single tick.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TickInputs {
+    pub tick: u32,
+    pub local: Vec<u8>,
+    /// The inputs of remote peers, ordered by address.
+    pub remote: Vec<(SocketAddr, Vec<u8>)>,
+}
+
+impl Lockstep {
+    /// Creates a lockstep session with the given peers. The first `delay` ticks have empty inputs
+    /// for every peer.
+    pub fn new<I: IntoIterator<Item = SocketAddr>>(delay: u32, peers: I) -> Self {
+        let mut peers = peers.into_iter().collect::<Vec<_>>();
+        peers.sort();
+
+        let empty = (0..delay)
+            .map(|tick| (tick, Vec::new()))
+            .collect::<BTreeMap<_, _>>();
+        let remote_inputs = peers.iter().map(|peer| (*peer, empty.clone())).collect();
+        let remote_checksums = peers.iter().map(|peer| (*peer, BTreeMap::new())).collect();
+
+        Self {
+            delay,
+            tick: 0,
+            timestep: 1.0 / 60.0,
+            accumulator: 0.0,
+            peers,
+            local_inputs: empty,
+            remote_inputs,
+            local_checksums: BTreeMap::new(),
+            remote_checksums,
+            desync: None,
+        }
+    }
+
+    /// The length of each tick in seconds when run by [`Lockstep::update`], which defaults to
+    /// 1/60th of a second.
+    pub fn with_timestep(mut self, timestep: f32) -> Self {
+        self.timestep = timestep;
+        self
+    }
+
+    pub fn timestep(&self) -> f32 {
+        self.timestep
+    }
+
+    /// The tick that will be returned by the next successful call to [`Lockstep::advance`].
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    pub fn delay(&self) -> u32 {
+        self.delay
+    }
+
+    /// Schedules `input` for `delay` ticks after the current tick and sends it to every peer. This
+    /// should be called once per tick, before [`Lockstep::advance`].
+    pub fn submit_input(&mut self, network: &mut Network, input: &[u8]) -> Result<(), Error> {
+        let tick = self.tick + self.delay;
+        if self.local_inputs.contains_key(&tick) {
+            return Err(Error::new(format!(
+                "input has already been submitted for tick {}",
+                tick
+            ))
+            .with_kind(ErrorKind::Network));
+        }
+        if input.len() > MAX_MESSAGE_SIZE - HEADER_SIZE {
+            return Err(Error::new(format!(
+                "input of {} bytes is larger than the maximum of {} bytes",
+                input.len(),
+                MAX_MESSAGE_SIZE - HEADER_SIZE
+            ))
+            .with_kind(ErrorKind::Network));
+        }
+
+        self.check_connected(network)?;
+        let message = encode(INPUT, tick, input);
+        for peer in self.peers.iter() {
+            network.send(*peer, Channel::Reliable, &message)?;
+        }
+        self.local_inputs.insert(tick, input.to_vec());
+
+        Ok(())
+    }
+
+    /// Sends a checksum of the simulation state after `tick` so that peers can check they agree.
+    pub fn submit_checksum(
+        &mut self,
+        network: &mut Network,
+        tick: u32,
+        checksum: u64,
+    ) -> Result<(), Error> {
+        self.check_connected(network)?;
+        self.local_checksums.insert(tick, checksum);
+        self.compare_checksums(tick);
+
+        let message = encode(CHECKSUM, tick, &checksum.to_be_bytes());
+        for peer in self.peers.iter() {
+            network.send(*peer, Channel::Reliable, &message)?;
+        }
+
+        Ok(())
+    }
+
+    /// Fails if any peer is not connected, so that a message is sent to every peer or to none.
+    fn check_connected(&self, network: &Network) -> Result<(), Error> {
+        match self.peers.iter().find(|peer| !network.is_connected(**peer)) {
+            Some(peer) => Err(
+                Error::new(format!("not connected to lockstep peer {}", peer))
+                    .with_kind(ErrorKind::Network),
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Handles a message received from the network, returning `false` if it is not a lockstep
+    /// message so that the game can handle it instead.
+    pub fn receive(&mut self, from: SocketAddr, data: &[u8]) -> bool {
+        let Some((kind, tick, payload)) = decode(data) else {
+            return false;
+        };
+
+        match kind {
+            INPUT => {
+                // Inputs for ticks that have already been simulated are stale.
+                if let Some(inputs) = self.remote_inputs.get_mut(&from) {
+                    if tick >= self.tick {
+                        inputs.insert(tick, payload.to_vec());
+                    }
+                }
+            }
+
+            CHECKSUM => {
+                if let (Some(checksums), Ok(checksum)) = (
+                    self.remote_checksums.get_mut(&from),
+                    <[u8; 8]>::try_from(payload),
+                ) {
+                    checksums.insert(tick, u64::from_be_bytes(checksum));
+                    self.compare_checksums(tick);
+                }
+            }
+
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Whether the inputs from every peer are available for the current tick.
+    pub fn is_ready(&self) -> bool {
+        self.local_inputs.contains_key(&self.tick)
+            && self
+                .remote_inputs
+                .values()
+                .all(|inputs| inputs.contains_key(&self.tick))
+    }
+
+    /// Takes the inputs for the current tick and moves on to the next one, or returns `None` if
+    /// any peer's input has not arrived yet.
+    pub fn advance(&mut self) -> Option<TickInputs> {
+        if !self.is_ready() {
+            return None;
+        }
+
+        let tick = self.tick;
+        let local = self.local_inputs.remove(&tick).unwrap();
+        let remote = self
+            .peers
+            .iter()
+            .map(|peer| {
+                let input = self.remote_inputs.get_mut(peer).unwrap().remove(&tick);
+                (*peer, input.unwrap())
+            })
+            .collect();
+        self.tick += 1;
+
+        Some(TickInputs {
+            tick,
+            local,
+            remote,
+        })
+    }
+
+    /// Runs the session for `elapsed` seconds in fixed ticks, carrying any time left over into
+    /// the next update. Each tick, `input` is called for the local input to submit for the tick it
+    /// is scheduled for, then `simulate` is called with the inputs for the current tick. While a
+    /// peer's input has not arrived the session waits, and the time spent waiting is dropped
+    /// rather than caught up on. Messages must still be passed to [`Lockstep::receive`]. Returns
+    /// the number of ticks simulated.
+    pub fn update<I, S>(
+        &mut self,
+        network: &mut Network,
+        elapsed: f32,
+        mut input: I,
+        mut simulate: S,
+    ) -> Result<u32, Error>
+    where
+        I: FnMut(u32) -> Vec<u8>,
+        S: FnMut(&TickInputs),
+    {
+        self.accumulator += elapsed;
+
+        let mut ticks = 0;
+        let mut waiting = false;
+        while self.accumulator >= self.timestep && ticks < MAX_TICKS {
+            let scheduled = self.tick + self.delay;
+            if !self.local_inputs.contains_key(&scheduled) {
+                self.submit_input(network, &input(scheduled))?;
+            }
+            let Some(inputs) = self.advance() else {
+                waiting = true;
+                break;
+            };
+            simulate(&inputs);
+            self.accumulator -= self.timestep;
+            ticks += 1;
+        }
+
+        if waiting || ticks == MAX_TICKS {
+            self.accumulator = self.accumulator.min(self.timestep);
+        }
+
+        Ok(ticks)
+    }
+
+    /// The earliest tick at which a peer's checksum differed from ours, if any.
+    pub fn desync(&self) -> Option<u32> {
+        self.desync
+    }
+
+    fn compare_checksums(&mut self, tick: u32) {
+        let Some(local) = self.local_checksums.get(&tick) else {
+            return;
+        };
+
+        let mut agreed = true;
+        for checksums in self.remote_checksums.values_mut() {
+            match checksums.get(&tick) {
+                Some(remote) if remote != local => {
+                    self.desync = Some(self.desync.map_or(tick, |desync| desync.min(tick)));
+                }
+                Some(_) => {}
+                None => agreed = false,
+            }
+        }
+
+        // Once every peer's checksum has been compared there is no need to keep them.
+        if agreed {
+            self.local_checksums.remove(&tick);
+            for checksums in self.remote_checksums.values_mut() {
+                checksums.remove(&tick);
+            }
+        }
+    }
+}
+
+/// A 64-bit FNV-1a hash, suitable for checksums of simulation state.
+pub fn checksum(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn encode(kind: u8, tick: u32, payload: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(HEADER_SIZE + payload.len());
+    buf.extend(MAGIC);
+    buf.push(kind);
+    buf.extend(tick.to_be_bytes());
+    buf.extend(payload);
+    buf
+}
+
+fn decode(buf: &[u8]) -> Option<(u8, u32, &[u8])> {
+    if buf.len() < HEADER_SIZE || buf[0..4] != MAGIC {
+        return None;
+    }
+
+    let tick = u32::from_be_bytes(buf[5..9].try_into().unwrap());
+    Some((buf[4], tick, &buf[HEADER_SIZE..]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn peer() -> SocketAddr {
+        "127.0.0.1:4000".parse().unwrap()
+    }
+
+    #[test]
+    fn first_ticks_are_empty() {
+        let mut lockstep = Lockstep::new(2, [peer()]);
+        assert_eq!(
+            lockstep.advance(),
+            Some(TickInputs {
+                tick: 0,
+                local: Vec::new(),
+                remote: vec![(peer(), Vec::new())],
+            })
+        );
+        assert!(lockstep.advance().is_some());
+        assert!(lockstep.advance().is_none());
+    }
+
+    #[test]
+    fn waits_for_remote_input() {
+        let mut network = Network::default();
+        let mut lockstep = Lockstep::new(0, [peer()]);
+        lockstep.local_inputs.insert(0, vec![1]);
+        assert!(!lockstep.is_ready());
+
+        assert!(lockstep.receive(peer(), &encode(INPUT, 0, &[2])));
+        assert_eq!(lockstep.advance().unwrap().remote, vec![(peer(), vec![2])]);
+        assert_eq!(lockstep.tick(), 1);
+
+        // Not connected, so the input cannot be sent.
+        assert!(lockstep.submit_input(&mut network, &[3]).is_err());
+    }
+
+    #[test]
+    fn input_is_not_sent_unless_every_peer_is_connected() {
+        let (_server, mut client, server_addr) = crate::net::test::connected_pair();
+        let mut lockstep = Lockstep::new(0, [server_addr, peer()]);
+
+        let err = lockstep.submit_input(&mut client, &[1]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Network);
+        assert!(client.connections[&server_addr].unacked.is_empty());
+
+        // Nothing was scheduled, so the input can be submitted again once connected.
+        assert!(lockstep.local_inputs.is_empty());
+    }
+
+    #[test]
+    fn update_runs_fixed_ticks_and_waits_for_peers() {
+        let (_server, mut client, server_addr) = crate::net::test::connected_pair();
+        let mut lockstep = Lockstep::new(1, [server_addr]).with_timestep(0.25);
+
+        let mut simulated = Vec::new();
+        let ticks = lockstep.update(
+            &mut client,
+            0.6,
+            |tick| vec![tick as u8],
+            |inputs| simulated.push(inputs.tick),
+        );
+        // Tick 0 has empty inputs, then tick 1 waits for the peer.
+        assert_eq!(ticks.unwrap(), 1);
+        assert_eq!(simulated, vec![0]);
+        assert_eq!(lockstep.local_inputs.get(&1), Some(&vec![1]));
+
+        assert!(lockstep.receive(server_addr, &encode(INPUT, 1, &[9])));
+        let ticks = lockstep.update(
+            &mut client,
+            0.0,
+            |tick| vec![tick as u8],
+            |inputs| {
+                assert_eq!(inputs.remote, vec![(server_addr, vec![9])]);
+                simulated.push(inputs.tick)
+            },
+        );
+        // The wait was dropped, leaving a single tick's worth of time.
+        assert_eq!(ticks.unwrap(), 1);
+        assert_eq!(simulated, vec![0, 1]);
+    }
+
+    #[test]
+    fn other_messages_are_not_handled() {
+        let mut lockstep = Lockstep::new(0, [peer()]);
+        assert!(!lockstep.receive(peer(), b"hello world"));
+    }
+
+    #[test]
+    fn mismatched_checksum_is_a_desync() {
+        let mut lockstep = Lockstep::new(0, [peer()]);
+        lockstep.local_checksums.insert(5, checksum(b"state"));
+        lockstep.receive(
+            peer(),
+            &encode(CHECKSUM, 5, &checksum(b"state").to_be_bytes()),
+        );
+        assert_eq!(lockstep.desync(), None);
+
+        lockstep.local_checksums.insert(6, checksum(b"state"));
+        lockstep.receive(
+            peer(),
+            &encode(CHECKSUM, 6, &checksum(b"other").to_be_bytes()),
+        );
+        assert_eq!(lockstep.desync(), Some(6));
+    }
+}
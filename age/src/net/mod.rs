@@ -0,0 +1,808 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    io,
+    net::{Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use crate::{Error, ErrorKind};
+
+pub use lockstep::*;
+
+mod lockstep;
+
+const PROTOCOL_ID: u32 = 0x4147_4530; // "AGE0"
+const HEADER_SIZE: usize = 9; // Protocol id (4) + packet kind (1) + sequence (4).
+const MAX_PACKET_SIZE: usize = 1200;
+const CONNECT_RESEND_INTERVAL: Duration = Duration::from_millis(250);
+const RELIABLE_RESEND_INTERVAL: Duration = Duration::from_millis(100);
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+const DISCOVERY_QUERY_INTERVAL: Duration = Duration::from_secs(1);
+const DISCOVERY_EXPIRY: Duration = Duration::from_secs(3);
+
+/// The port that hosts listen on for discovery queries unless another is chosen.
+pub const DEFAULT_DISCOVERY_PORT: u16 = 47470;
+
+/// The largest message that can be sent in a single call to [`Network::send`].
+pub const MAX_MESSAGE_SIZE: usize = MAX_PACKET_SIZE - HEADER_SIZE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Channel {
+    /// Messages are delivered exactly once and in the order they were sent.
+    Reliable,
+    /// Messages may be dropped, duplicated or arrive out of order.
+    Unreliable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetEvent {
+    Connected(SocketAddr),
+    /// A peer started with [`Network::connect`] did not accept before the timeout.
+    ConnectFailed(SocketAddr),
+    Disconnected(SocketAddr),
+    Message {
+        from: SocketAddr,
+        channel: Channel,
+        data: Vec<u8>,
+    },
+}
+
+/// A lightweight UDP transport that manages connections to peers and delivers messages on
+/// reliable and unreliable channels.
+///
+/// The engine polls the network at the start of every frame, after which any received messages
+/// and connection changes are available from [`Network::next_event`].
+pub struct Network {
+    socket: Option<UdpSocket>,
+    accept_connections: bool,
+    connections: HashMap<SocketAddr, Connection>,
+    events: VecDeque<NetEvent>,
+    timeout: Duration,
+    advertiser: Option<Advertiser>,
+    discoverer: Option<Discoverer>,
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        Self {
+            socket: None,
+            accept_connections: false,
+            connections: HashMap::new(),
+            events: VecDeque::new(),
+            timeout: Duration::from_secs(5),
+            advertiser: None,
+            discoverer: None,
+        }
+    }
+}
+
+impl Network {
+    /// Binds to `addr` and accepts connections from any peer that connects to it.
+    pub fn listen<A: ToSocketAddrs>(&mut self, addr: A) -> Result<(), Error> {
+        self.bind(addr)?;
+        self.accept_connections = true;
+        Ok(())
+    }
+
+    /// Starts connecting to the peer at `addr`, binding to an ephemeral port first if the network
+    /// is not already bound. [`NetEvent::Connected`] is raised once the peer accepts, or
+    /// [`NetEvent::ConnectFailed`] if it does not accept within the timeout.
+    pub fn connect<A: ToSocketAddrs>(&mut self, addr: A) -> Result<SocketAddr, Error> {
+        let peer = resolve(addr)?;
+        if self.socket.is_none() {
+            let any: SocketAddr = if peer.is_ipv4() {
+                ([0, 0, 0, 0], 0).into()
+            } else {
+                ([0u16; 8], 0).into()
+            };
+            self.bind(any)?;
+        }
+
+        let now = Instant::now();
+        let mut connection = Connection::new(ConnectionState::Connecting, now);
+        connection.last_sent = now;
+        self.connections.insert(peer, connection);
+        self.send_packet(peer, PacketKind::Connect, 0, &[]);
+
+        Ok(peer)
+    }
+
+    /// Closes the connection to `peer`, notifying it that we are leaving.
+    pub fn disconnect(&mut self, peer: SocketAddr) {
+        if self.connections.remove(&peer).is_some() {
+            self.send_packet(peer, PacketKind::Disconnect, 0, &[]);
+            self.events.push_back(NetEvent::Disconnected(peer));
+        }
+    }
+
+    /// Disconnects from all peers and releases the socket.
+    pub fn close(&mut self) {
+        let peers = self.connections.keys().copied().collect::<Vec<_>>();
+        for peer in peers {
+            self.disconnect(peer);
+        }
+        self.socket = None;
+        self.accept_connections = false;
+    }
+
+    pub fn send(&mut self, peer: SocketAddr, channel: Channel, data: &[u8]) -> Result<(), Error> {
+        if data.len() > MAX_MESSAGE_SIZE {
+            return Err(Error::new(format!(
+                "message of {} bytes is larger than the maximum of {} bytes",
+                data.len(),
+                MAX_MESSAGE_SIZE
+            ))
+            .with_kind(ErrorKind::Network));
+        }
+
+        let now = Instant::now();
+        let connection = match self.connections.get_mut(&peer) {
+            Some(connection) if connection.state == ConnectionState::Connected => connection,
+            _ => {
+                return Err(
+                    Error::new(format!("not connected to {}", peer)).with_kind(ErrorKind::Network)
+                )
+            }
+        };
+        connection.last_sent = now;
+
+        let sequence = match channel {
+            Channel::Reliable => {
+                let sequence = connection.next_sequence;
+                connection.next_sequence += 1;
+                connection.unacked.insert(
+                    sequence,
+                    PendingMessage {
+                        data: data.to_vec(),
+                        last_sent: now,
+                    },
+                );
+                sequence
+            }
+            Channel::Unreliable => 0,
+        };
+
+        let kind = match channel {
+            Channel::Reliable => PacketKind::Reliable,
+            Channel::Unreliable => PacketKind::Unreliable,
+        };
+        self.send_packet(peer, kind, sequence, data);
+
+        Ok(())
+    }
+
+    /// Sends `data` to every connected peer.
+    pub fn broadcast(&mut self, channel: Channel, data: &[u8]) -> Result<(), Error> {
+        for peer in self.peers().collect::<Vec<_>>() {
+            self.send(peer, channel, data)?;
+        }
+
+        Ok(())
+    }
+
+    /// The peers that are currently connected.
+    pub fn peers(&self) -> impl Iterator<Item = SocketAddr> + '_ {
+        self.connections
+            .iter()
+            .filter(|(_, c)| c.state == ConnectionState::Connected)
+            .map(|(peer, _)| *peer)
+    }
+
+    pub fn is_connected(&self, peer: SocketAddr) -> bool {
+        self.connections
+            .get(&peer)
+            .is_some_and(|c| c.state == ConnectionState::Connected)
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.socket.as_ref().and_then(|s| s.local_addr().ok())
+    }
+
+    pub fn get_timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Sets how long a peer may be silent before it is considered disconnected.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Takes the next event received since the network was last polled.
+    pub fn next_event(&mut self) -> Option<NetEvent> {
+        self.events.pop_front()
+    }
+
+    /// Answers discovery queries on `port` of the local network so that other games can find
+    /// this one. The network must already be listening for connections. `info` is passed to the
+    /// peers that discover this host, e.g. to describe the game.
+    pub fn advertise(&mut self, port: u16, info: &[u8]) -> Result<(), Error> {
+        let game_port = match (self.accept_connections, self.local_addr()) {
+            (true, Some(addr)) => addr.port(),
+            _ => {
+                return Err(
+                    Error::new("the network must be listening before it can be advertised")
+                        .with_kind(ErrorKind::Network),
+                )
+            }
+        };
+        if info.len() > MAX_MESSAGE_SIZE {
+            return Err(Error::new(format!(
+                "advertised info of {} bytes is larger than the maximum of {} bytes",
+                info.len(),
+                MAX_MESSAGE_SIZE
+            ))
+            .with_kind(ErrorKind::Network));
+        }
+
+        self.advertiser = Some(Advertiser {
+            socket: bind_socket((Ipv4Addr::UNSPECIFIED, port))?,
+            game_port,
+            info: info.to_vec(),
+        });
+
+        Ok(())
+    }
+
+    pub fn stop_advertising(&mut self) {
+        self.advertiser = None;
+    }
+
+    /// Periodically sends discovery queries to `addr`, which is usually the broadcast address and
+    /// discovery port, e.g. `(Ipv4Addr::BROADCAST, DEFAULT_DISCOVERY_PORT)`. Hosts that answer
+    /// are available from [`Network::discovered_hosts`].
+    pub fn discover<A: ToSocketAddrs>(&mut self, addr: A) -> Result<(), Error> {
+        let target = resolve(addr)?;
+        let socket = bind_socket((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_broadcast(true).map_err(|err| {
+            Error::new("failed to configure network socket")
+                .with_kind(ErrorKind::Network)
+                .with_source(err)
+        })?;
+
+        let discoverer = Discoverer {
+            socket,
+            target,
+            last_query: Instant::now(),
+            hosts: HashMap::new(),
+        };
+        discoverer.query();
+        self.discoverer = Some(discoverer);
+
+        Ok(())
+    }
+
+    pub fn stop_discovering(&mut self) {
+        self.discoverer = None;
+    }
+
+    /// The hosts that have answered discovery queries recently.
+    pub fn discovered_hosts(&self) -> impl Iterator<Item = &DiscoveredHost> {
+        self.discoverer.iter().flat_map(|d| d.hosts.values())
+    }
+
+    /// Receives any pending packets, resends unacknowledged reliable messages and times out
+    /// silent peers. This is called by the engine every frame.
+    pub fn poll(&mut self) {
+        let now = Instant::now();
+        if let Some(advertiser) = &self.advertiser {
+            advertiser.poll();
+        }
+        if let Some(discoverer) = &mut self.discoverer {
+            discoverer.poll(now);
+        }
+
+        let Some(socket) = &self.socket else {
+            return;
+        };
+
+        let mut buf = [0; MAX_PACKET_SIZE];
+        let mut packets = Vec::new();
+        while let Some((len, from)) = receive_from(socket, &mut buf) {
+            packets.push((from, buf[..len].to_vec()));
+        }
+
+        for (from, buf) in packets {
+            if let Some(packet) = Packet::decode(&buf) {
+                self.receive(from, packet.kind, packet.sequence, packet.payload);
+            }
+        }
+
+        self.maintain(now);
+    }
+
+    fn bind<A: ToSocketAddrs>(&mut self, addr: A) -> Result<(), Error> {
+        self.socket = Some(bind_socket(addr)?);
+        Ok(())
+    }
+
+    fn receive(&mut self, from: SocketAddr, kind: PacketKind, sequence: u32, payload: &[u8]) {
+        let now = Instant::now();
+
+        if kind == PacketKind::Connect {
+            if !self.connections.contains_key(&from) {
+                if !self.accept_connections {
+                    return;
+                }
+                self.connections
+                    .insert(from, Connection::new(ConnectionState::Connected, now));
+                self.events.push_back(NetEvent::Connected(from));
+            }
+            // Always answer, as our previous accept may have been lost.
+            self.send_packet(from, PacketKind::Accept, 0, &[]);
+            return;
+        }
+
+        let Some(connection) = self.connections.get_mut(&from) else {
+            return;
+        };
+        connection.last_received = now;
+
+        match kind {
+            PacketKind::Accept => {
+                if connection.state == ConnectionState::Connecting {
+                    connection.state = ConnectionState::Connected;
+                    self.events.push_back(NetEvent::Connected(from));
+                }
+            }
+
+            PacketKind::Disconnect => {
+                self.connections.remove(&from);
+                self.events.push_back(NetEvent::Disconnected(from));
+            }
+
+            PacketKind::Unreliable => self.events.push_back(NetEvent::Message {
+                from,
+                channel: Channel::Unreliable,
+                data: payload.to_vec(),
+            }),
+
+            PacketKind::Reliable => {
+                if sequence >= connection.next_expected {
+                    connection.received.insert(sequence, payload.to_vec());
+                }
+                while let Some(data) = connection.received.remove(&connection.next_expected) {
+                    connection.next_expected += 1;
+                    self.events.push_back(NetEvent::Message {
+                        from,
+                        channel: Channel::Reliable,
+                        data,
+                    });
+                }
+                self.send_packet(from, PacketKind::Ack, sequence, &[]);
+            }
+
+            PacketKind::Ack => {
+                connection.unacked.remove(&sequence);
+            }
+
+            PacketKind::Connect
+            | PacketKind::Heartbeat
+            | PacketKind::DiscoveryQuery
+            | PacketKind::DiscoveryResponse => {}
+        }
+    }
+
+    fn maintain(&mut self, now: Instant) {
+        let mut timed_out = Vec::new();
+        let mut outgoing = Vec::new();
+
+        for (peer, connection) in self.connections.iter_mut() {
+            if now.duration_since(connection.last_received) > self.timeout {
+                timed_out.push((*peer, connection.state));
+                continue;
+            }
+
+            match connection.state {
+                ConnectionState::Connecting => {
+                    if now.duration_since(connection.last_sent) >= CONNECT_RESEND_INTERVAL {
+                        connection.last_sent = now;
+                        outgoing.push((*peer, PacketKind::Connect, 0, Vec::new()));
+                    }
+                }
+
+                ConnectionState::Connected => {
+                    for (sequence, message) in connection.unacked.iter_mut() {
+                        if now.duration_since(message.last_sent) >= RELIABLE_RESEND_INTERVAL {
+                            message.last_sent = now;
+                            connection.last_sent = now;
+                            outgoing.push((
+                                *peer,
+                                PacketKind::Reliable,
+                                *sequence,
+                                message.data.clone(),
+                            ));
+                        }
+                    }
+
+                    if now.duration_since(connection.last_sent) >= HEARTBEAT_INTERVAL {
+                        connection.last_sent = now;
+                        outgoing.push((*peer, PacketKind::Heartbeat, 0, Vec::new()));
+                    }
+                }
+            }
+        }
+
+        for (peer, kind, sequence, data) in outgoing {
+            self.send_packet(peer, kind, sequence, &data);
+        }
+
+        for (peer, state) in timed_out {
+            self.connections.remove(&peer);
+            self.events.push_back(match state {
+                ConnectionState::Connecting => NetEvent::ConnectFailed(peer),
+                ConnectionState::Connected => NetEvent::Disconnected(peer),
+            });
+        }
+    }
+
+    fn send_packet(&self, peer: SocketAddr, kind: PacketKind, sequence: u32, payload: &[u8]) {
+        if let Some(socket) = &self.socket {
+            // Sending is best effort; lost reliable messages are resent and lost connections
+            // eventually time out.
+            let _ = socket.send_to(&Packet::encode(kind, sequence, payload), peer);
+        }
+    }
+}
+
+fn bind_socket<A: ToSocketAddrs>(addr: A) -> Result<UdpSocket, Error> {
+    let socket = UdpSocket::bind(addr).map_err(|err| {
+        Error::new("failed to bind network socket")
+            .with_kind(ErrorKind::Network)
+            .with_source(err)
+    })?;
+    socket.set_nonblocking(true).map_err(|err| {
+        Error::new("failed to configure network socket")
+            .with_kind(ErrorKind::Network)
+            .with_source(err)
+    })?;
+    Ok(socket)
+}
+
+fn receive_from(socket: &UdpSocket, buf: &mut [u8]) -> Option<(usize, SocketAddr)> {
+    loop {
+        match socket.recv_from(buf) {
+            Ok(received) => return Some(received),
+            // Some platforms report a peer's port being unreachable on the next receive, which
+            // only affects that peer.
+            Err(err) if err.kind() == io::ErrorKind::ConnectionReset => continue,
+            Err(_) => return None,
+        }
+    }
+}
+
+fn resolve<A: ToSocketAddrs>(addr: A) -> Result<SocketAddr, Error> {
+    addr.to_socket_addrs()
+        .map_err(|err| {
+            Error::new("failed to resolve network address")
+                .with_kind(ErrorKind::Network)
+                .with_source(err)
+        })?
+        .next()
+        .ok_or_else(|| {
+            Error::new("network address did not resolve to any addresses")
+                .with_kind(ErrorKind::Network)
+        })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConnectionState {
+    Connecting,
+    Connected,
+}
+
+struct Connection {
+    state: ConnectionState,
+    last_received: Instant,
+    last_sent: Instant,
+    next_sequence: u32,
+    unacked: BTreeMap<u32, PendingMessage>,
+    next_expected: u32,
+    received: BTreeMap<u32, Vec<u8>>,
+}
+
+impl Connection {
+    fn new(state: ConnectionState, now: Instant) -> Self {
+        Self {
+            state,
+            last_received: now,
+            last_sent: now,
+            next_sequence: 0,
+            unacked: BTreeMap::new(),
+            next_expected: 0,
+            received: BTreeMap::new(),
+        }
+    }
+}
+
+/// A host found on the local network by [`Network::discover`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredHost {
+    /// The address to pass to [`Network::connect`] to join the host's game.
+    pub addr: SocketAddr,
+    /// The info the host is advertising.
+    pub info: Vec<u8>,
+    last_seen: Instant,
+}
+
+struct Advertiser {
+    socket: UdpSocket,
+    game_port: u16,
+    info: Vec<u8>,
+}
+
+impl Advertiser {
+    fn poll(&self) {
+        let mut buf = [0; MAX_PACKET_SIZE];
+        while let Some((len, from)) = receive_from(&self.socket, &mut buf) {
+            if let Some(Packet {
+                kind: PacketKind::DiscoveryQuery,
+                ..
+            }) = Packet::decode(&buf[..len])
+            {
+                let response = Packet::encode(
+                    PacketKind::DiscoveryResponse,
+                    self.game_port as u32,
+                    &self.info,
+                );
+                let _ = self.socket.send_to(&response, from);
+            }
+        }
+    }
+}
+
+struct Discoverer {
+    socket: UdpSocket,
+    target: SocketAddr,
+    last_query: Instant,
+    hosts: HashMap<SocketAddr, DiscoveredHost>,
+}
+
+impl Discoverer {
+    fn query(&self) {
+        let query = Packet::encode(PacketKind::DiscoveryQuery, 0, &[]);
+        let _ = self.socket.send_to(&query, self.target);
+    }
+
+    fn poll(&mut self, now: Instant) {
+        let mut buf = [0; MAX_PACKET_SIZE];
+        while let Some((len, from)) = receive_from(&self.socket, &mut buf) {
+            if let Some(Packet {
+                kind: PacketKind::DiscoveryResponse,
+                sequence: port,
+                payload,
+            }) = Packet::decode(&buf[..len])
+            {
+                let addr = SocketAddr::new(from.ip(), port as u16);
+                self.hosts.insert(
+                    addr,
+                    DiscoveredHost {
+                        addr,
+                        info: payload.to_vec(),
+                        last_seen: now,
+                    },
+                );
+            }
+        }
+
+        self.hosts
+            .retain(|_, host| now.duration_since(host.last_seen) < DISCOVERY_EXPIRY);
+
+        if now.duration_since(self.last_query) >= DISCOVERY_QUERY_INTERVAL {
+            self.last_query = now;
+            self.query();
+        }
+    }
+}
+
+struct PendingMessage {
+    data: Vec<u8>,
+    last_sent: Instant,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+enum PacketKind {
+    Connect = 0,
+    Accept = 1,
+    Disconnect = 2,
+    Heartbeat = 3,
+    Unreliable = 4,
+    Reliable = 5,
+    Ack = 6,
+    DiscoveryQuery = 7,
+    DiscoveryResponse = 8,
+}
+
+impl TryFrom<u8> for PacketKind {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(PacketKind::Connect),
+            1 => Ok(PacketKind::Accept),
+            2 => Ok(PacketKind::Disconnect),
+            3 => Ok(PacketKind::Heartbeat),
+            4 => Ok(PacketKind::Unreliable),
+            5 => Ok(PacketKind::Reliable),
+            6 => Ok(PacketKind::Ack),
+            7 => Ok(PacketKind::DiscoveryQuery),
+            8 => Ok(PacketKind::DiscoveryResponse),
+            _ => Err(Error::new(format!(
+                "packet kind {} is not supported",
+                value
+            ))),
+        }
+    }
+}
+
+struct Packet<'a> {
+    kind: PacketKind,
+    sequence: u32,
+    payload: &'a [u8],
+}
+
+impl<'a> Packet<'a> {
+    fn encode(kind: PacketKind, sequence: u32, payload: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(HEADER_SIZE + payload.len());
+        buf.extend(PROTOCOL_ID.to_be_bytes());
+        buf.push(kind as u8);
+        buf.extend(sequence.to_be_bytes());
+        buf.extend(payload);
+        buf
+    }
+
+    fn decode(buf: &'a [u8]) -> Option<Self> {
+        if buf.len() < HEADER_SIZE || buf[0..4] != PROTOCOL_ID.to_be_bytes() {
+            return None;
+        }
+
+        Some(Self {
+            kind: PacketKind::try_from(buf[4]).ok()?,
+            sequence: u32::from_be_bytes(buf[5..9].try_into().unwrap()),
+            payload: &buf[HEADER_SIZE..],
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn poll_until<F: FnMut(&mut Network, &mut Network) -> bool>(
+        a: &mut Network,
+        b: &mut Network,
+        mut done: F,
+    ) {
+        let start = Instant::now();
+        while !done(a, b) {
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            a.poll();
+            b.poll();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    pub(super) fn connected_pair() -> (Network, Network, SocketAddr) {
+        let mut server = Network::default();
+        server.listen("127.0.0.1:0").unwrap();
+        let mut client = Network::default();
+        let server_addr = client.connect(server.local_addr().unwrap()).unwrap();
+
+        poll_until(&mut server, &mut client, |_, client| {
+            client.is_connected(server_addr)
+        });
+
+        (server, client, server_addr)
+    }
+
+    #[test]
+    fn packet_round_trips() {
+        let buf = Packet::encode(PacketKind::Reliable, 42, &[1, 2, 3]);
+        let packet = Packet::decode(&buf).unwrap();
+        assert_eq!(packet.kind, PacketKind::Reliable);
+        assert_eq!(packet.sequence, 42);
+        assert_eq!(packet.payload, &[1, 2, 3]);
+        assert!(Packet::decode(&[0; HEADER_SIZE]).is_none());
+    }
+
+    #[test]
+    fn client_connects_to_server() {
+        let (mut server, mut client, server_addr) = connected_pair();
+
+        assert_eq!(client.next_event(), Some(NetEvent::Connected(server_addr)));
+        assert!(matches!(server.next_event(), Some(NetEvent::Connected(_))));
+    }
+
+    #[test]
+    fn reliable_messages_arrive_in_order() {
+        let (mut server, mut client, server_addr) = connected_pair();
+        while server.next_event().is_some() {}
+
+        for i in 0..10u8 {
+            client.send(server_addr, Channel::Reliable, &[i]).unwrap();
+        }
+
+        let mut received = Vec::new();
+        poll_until(&mut server, &mut client, |server, _| {
+            while let Some(event) = server.next_event() {
+                if let NetEvent::Message { data, .. } = event {
+                    received.extend(data);
+                }
+            }
+            received.len() == 10
+        });
+
+        assert_eq!(received, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn disconnect_is_reported_to_peer() {
+        let (mut server, mut client, server_addr) = connected_pair();
+        while server.next_event().is_some() {}
+
+        client.disconnect(server_addr);
+        let mut disconnected = false;
+        poll_until(&mut server, &mut client, |server, _| {
+            disconnected |= matches!(server.next_event(), Some(NetEvent::Disconnected(_)));
+            disconnected
+        });
+        assert_eq!(server.peers().count(), 0);
+    }
+
+    #[test]
+    fn advertised_host_is_discovered() {
+        let mut host = Network::default();
+        host.listen("127.0.0.1:0").unwrap();
+        let discovery_port = UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        host.advertise(discovery_port, b"my game").unwrap();
+
+        let mut client = Network::default();
+        client.discover(("127.0.0.1", discovery_port)).unwrap();
+        poll_until(&mut host, &mut client, |_, client| {
+            client.discovered_hosts().count() > 0
+        });
+
+        let found = client.discovered_hosts().next().unwrap();
+        assert_eq!(found.addr, host.local_addr().unwrap());
+        assert_eq!(found.info, b"my game");
+    }
+
+    #[test]
+    fn advertising_requires_listening() {
+        let mut network = Network::default();
+        let err = network.advertise(0, &[]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Network);
+    }
+
+    #[test]
+    fn oversized_message_is_an_error() {
+        let (_server, mut client, server_addr) = connected_pair();
+        let data = vec![0; MAX_MESSAGE_SIZE + 1];
+        let err = client
+            .send(server_addr, Channel::Unreliable, &data)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Network);
+    }
+
+    #[test]
+    fn unanswered_connect_fails() {
+        // Nothing listens on the socket, so the connect is never accepted.
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut client = Network::default();
+        client.set_timeout(Duration::from_millis(50));
+        let peer = client.connect(silent.local_addr().unwrap()).unwrap();
+
+        let mut idle = Network::default();
+        let mut event = None;
+        poll_until(&mut client, &mut idle, |client, _| {
+            event = client.next_event();
+            event.is_some()
+        });
+        assert_eq!(event, Some(NetEvent::ConnectFailed(peer)));
+        assert!(!client.is_connected(peer));
+    }
+}
@@ -0,0 +1,59 @@
+use crate::sys::Window;
+#[cfg(feature = "notifications")]
+use crate::{Error, ErrorKind};
+
+/// How urgently the window asks for the user's attention.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Attention {
+    /// Flashes the taskbar entry once, or bounces the dock icon once on macOS.
+    #[default]
+    Informational,
+    /// Flashes the taskbar entry, or bounces the dock icon on macOS, until the window is focused.
+    Critical,
+}
+
+impl From<Attention> for winit::window::UserAttentionType {
+    fn from(attention: Attention) -> Self {
+        match attention {
+            Attention::Informational => winit::window::UserAttentionType::Informational,
+            Attention::Critical => winit::window::UserAttentionType::Critical,
+        }
+    }
+}
+
+/// Integrations with the desktop the game is running on, such as the taskbar.
+pub struct Os<'a> {
+    window: &'a Window,
+}
+
+impl<'a> Os<'a> {
+    pub(crate) fn new(window: &'a Window) -> Self {
+        Self { window }
+    }
+
+    /// Asks for the user's attention when the window is not focused, e.g. when a long task has
+    /// finished. Has no effect if the window is already focused.
+    pub fn request_attention(&self, attention: Attention) {
+        self.window.request_user_attention(Some(attention.into()));
+    }
+
+    pub fn cancel_attention(&self) {
+        self.window.request_user_attention(None);
+    }
+
+    /// Shows a desktop notification from the game, named after the window title.
+    #[cfg(feature = "notifications")]
+    pub fn notify(&self, summary: &str, body: &str) -> Result<(), Error> {
+        notify_rust::Notification::new()
+            .appname(&self.window.title())
+            .summary(summary)
+            .body(body)
+            .show()
+            .map(|_| ())
+            .map_err(|err| {
+                Error::new("failed to show notification")
+                    .with_kind(ErrorKind::Os)
+                    .with_source(err)
+            })
+    }
+}
@@ -0,0 +1,51 @@
+struct VsOut {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) id: u32) -> VsOut {
+    let uv = vec2(f32((id << 1) & 2), f32(id & 2));
+	let pos = vec4(uv * vec2(2.0, -2.0) + vec2(-1.0, 1.0), 0.0, 1.0);
+
+    return VsOut(pos, uv);
+}
+
+@group(0) @binding(0)
+var r_sampler: sampler;
+@group(0) @binding(1)
+var r_mask: texture_2d<f32>;
+
+// Must match MAX_OUTLINE_THICKNESS.
+const MAX_THICKNESS: i32 = 8;
+
+@fragment
+fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(r_mask));
+    let pixel = vec2<i32>(in.uv * vec2<f32>(size));
+
+    // Outlines are drawn around the outlined sprites, never over them.
+    if textureLoad(r_mask, pixel, 0).a > 0.0 {
+        discard;
+    }
+
+    // The mask holds each sprite's outline color, with the outline thickness as a fraction of
+    // MAX_THICKNESS in alpha. Use the nearest sprite whose outline reaches this pixel.
+    var color = vec4(0.0);
+    var nearest = f32(MAX_THICKNESS) + 1.0;
+    for (var y = -MAX_THICKNESS; y <= MAX_THICKNESS; y++) {
+        for (var x = -MAX_THICKNESS; x <= MAX_THICKNESS; x++) {
+            let mask = textureLoad(r_mask, clamp(pixel + vec2(x, y), vec2(0), size - 1), 0);
+            let distance = length(vec2(f32(x), f32(y)));
+            if mask.a > 0.0 && distance <= mask.a * f32(MAX_THICKNESS) + 0.5 && distance < nearest {
+                color = vec4(mask.rgb, 1.0);
+                nearest = distance;
+            }
+        }
+    }
+
+    if color.a == 0.0 {
+        discard;
+    }
+    return color;
+}
@@ -0,0 +1,47 @@
+struct GeometryVertex {
+    @location(0) pos: vec2<f32>,
+    @location(1) uv: vec2<f32>,
+}
+
+struct VsOut {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var<storage, read> r_globals: array<mat4x4<f32>>;
+
+@group(1) @binding(0)
+var r_sampler: sampler;
+
+@group(1) @binding(1)
+var r_texture: texture_2d<f32>;
+
+struct PushConstant {
+    color: vec4<f32>,
+    model: mat4x4<f32>,
+    globals_idx: u32,
+    pick_id: u32,
+}
+
+var<push_constant> r_pc: PushConstant;
+
+@vertex
+fn vs_main(vertex: GeometryVertex) -> VsOut {
+    let view_proj = r_globals[r_pc.globals_idx];
+    let pos = view_proj * r_pc.model * vec4(vertex.pos, 0.0, 1.0);
+
+    return VsOut(pos, vertex.uv);
+}
+
+@fragment
+fn fs_main(in: VsOut) -> @location(0) vec4<f32> {
+    // Only the visible parts of a sprite are outlined. The color holds the outline color, with
+    // its thickness in alpha, so the sprite's own alpha is used alone.
+    let alpha = textureSample(r_texture, r_sampler, in.uv).a;
+    if alpha < 0.5 {
+        discard;
+    }
+
+    return r_pc.color;
+}
@@ -0,0 +1,95 @@
+use std::time::{Duration, Instant};
+
+// Sleeps can overshoot by around a millisecond, so the end of a wait is spun instead.
+const SPIN_MARGIN: Duration = Duration::from_millis(1);
+
+/// Starts frames one display refresh apart, so that with a low latency present mode each frame
+/// samples input as late as it can while still being ready for the next refresh.
+#[derive(Debug, Default)]
+pub(crate) struct FramePacer {
+    interval: Option<Duration>,
+    next: Option<Instant>,
+}
+
+impl FramePacer {
+    /// Sets the refresh rate of the display in hertz, or disables pacing if it is not known.
+    pub(crate) fn set_refresh_rate(&mut self, refresh_rate: Option<f32>) {
+        self.interval = refresh_rate
+            .filter(|hz| *hz > 0.0)
+            .map(|hz| Duration::from_secs_f32(1.0 / hz));
+    }
+
+    /// When the next frame is due to start, if frames are being paced.
+    pub(crate) fn next_frame(&self) -> Option<Instant> {
+        self.next.filter(|_| self.interval.is_some())
+    }
+
+    /// How early to stop handling events before the next frame, leaving [`FramePacer::wait`] to
+    /// wait out the rest precisely.
+    pub(crate) fn wake_margin(&self) -> Duration {
+        SPIN_MARGIN
+    }
+
+    /// Waits until the next frame is due to start.
+    pub(crate) fn wait(&mut self) {
+        let now = Instant::now();
+        let deadline = self.schedule(now);
+        if let Some(sleep) = deadline
+            .saturating_duration_since(now)
+            .checked_sub(SPIN_MARGIN)
+        {
+            std::thread::sleep(sleep);
+        }
+        while Instant::now() < deadline {
+            std::hint::spin_loop();
+        }
+    }
+
+    /// When the frame starting at `now` should start, keeping frames on the refresh interval
+    /// unless a slow frame has put them more than an interval behind, when they start afresh.
+    fn schedule(&mut self, now: Instant) -> Instant {
+        let Some(interval) = self.interval else {
+            return now;
+        };
+
+        let deadline = match self.next {
+            Some(next) if now < next + interval => next,
+            _ => now,
+        };
+        self.next = Some(deadline + interval);
+        deadline
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frames_are_scheduled_one_interval_apart() {
+        let mut pacer = FramePacer::default();
+        pacer.set_refresh_rate(Some(100.0));
+        let start = Instant::now();
+        let interval = Duration::from_millis(10);
+
+        assert_eq!(pacer.schedule(start), start);
+        // An early frame waits for the next refresh, and a slightly late one keeps the cadence.
+        assert_eq!(pacer.schedule(start + interval / 2), start + interval);
+        assert_eq!(
+            pacer.schedule(start + interval * 2 + interval / 2),
+            start + interval * 2
+        );
+        // A frame more than an interval late starts afresh.
+        let late = start + interval * 10;
+        assert_eq!(pacer.schedule(late), late);
+    }
+
+    #[test]
+    fn unknown_refresh_rate_does_not_wait() {
+        let mut pacer = FramePacer::default();
+        pacer.set_refresh_rate(None);
+        let now = Instant::now();
+        assert_eq!(pacer.schedule(now), now);
+        assert_eq!(pacer.schedule(now), now);
+    }
+}
@@ -0,0 +1,221 @@
+use lyon_tessellation::{
+    math::point, path::Path as LyonPath, BuffersBuilder, FillOptions, FillTessellator, FillVertex,
+    StrokeOptions, StrokeTessellator, StrokeVertex, VertexBuffers,
+};
+
+use crate::{
+    graphics::{MaterialId, Mesh},
+    math::Vec2f,
+    renderer::{GeometryVertex, Renderer},
+    Error,
+};
+
+/// The maximum distance between a curve and the line segments it is approximated with.
+const TOLERANCE: f32 = 0.1;
+
+/// A vector shape made of lines and curves, which can be filled or stroked into a [`Mesh`].
+///
+/// Each `move_to` starts a new sub-path, which can be closed back to its start with `close`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Path {
+    commands: Vec<PathCommand>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PathCommand {
+    MoveTo(Vec2f),
+    LineTo(Vec2f),
+    QuadTo(Vec2f, Vec2f),
+    CubicTo(Vec2f, Vec2f, Vec2f),
+    Close,
+}
+
+impl Path {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn move_to(mut self, to: Vec2f) -> Self {
+        self.commands.push(PathCommand::MoveTo(to));
+        self
+    }
+
+    pub fn line_to(mut self, to: Vec2f) -> Self {
+        self.commands.push(PathCommand::LineTo(to));
+        self
+    }
+
+    pub fn quad_to(mut self, ctrl: Vec2f, to: Vec2f) -> Self {
+        self.commands.push(PathCommand::QuadTo(ctrl, to));
+        self
+    }
+
+    pub fn cubic_to(mut self, ctrl1: Vec2f, ctrl2: Vec2f, to: Vec2f) -> Self {
+        self.commands.push(PathCommand::CubicTo(ctrl1, ctrl2, to));
+        self
+    }
+
+    pub fn close(mut self) -> Self {
+        self.commands.push(PathCommand::Close);
+        self
+    }
+
+    /// Tessellates the inside of the path.
+    pub fn fill(&self, renderer: &mut Renderer, material: MaterialId) -> Result<Mesh, Error> {
+        let geometry = self.tessellate_fill()?;
+        Ok(Mesh::new(
+            renderer,
+            &geometry.vertices,
+            &geometry.indices,
+            material,
+        ))
+    }
+
+    /// Tessellates a line `width` pixels wide along the path.
+    pub fn stroke(
+        &self,
+        renderer: &mut Renderer,
+        width: f32,
+        material: MaterialId,
+    ) -> Result<Mesh, Error> {
+        let geometry = self.tessellate_stroke(width)?;
+        Ok(Mesh::new(
+            renderer,
+            &geometry.vertices,
+            &geometry.indices,
+            material,
+        ))
+    }
+
+    fn tessellate_fill(&self) -> Result<VertexBuffers<GeometryVertex, u16>, Error> {
+        let mut geometry = VertexBuffers::new();
+        FillTessellator::new()
+            .tessellate_path(
+                &self.to_lyon(),
+                &FillOptions::tolerance(TOLERANCE),
+                &mut BuffersBuilder::new(&mut geometry, |v: FillVertex| vertex(v.position())),
+            )
+            .map_err(|err| Error::new("failed to fill path").with_source(err))?;
+
+        Ok(geometry)
+    }
+
+    fn tessellate_stroke(&self, width: f32) -> Result<VertexBuffers<GeometryVertex, u16>, Error> {
+        let mut geometry = VertexBuffers::new();
+        StrokeTessellator::new()
+            .tessellate_path(
+                &self.to_lyon(),
+                &StrokeOptions::tolerance(TOLERANCE).with_line_width(width),
+                &mut BuffersBuilder::new(&mut geometry, |v: StrokeVertex| vertex(v.position())),
+            )
+            .map_err(|err| Error::new("failed to stroke path").with_source(err))?;
+
+        Ok(geometry)
+    }
+
+    fn to_lyon(&self) -> LyonPath {
+        let p = |v: Vec2f| point(v.x, v.y);
+
+        let mut builder = LyonPath::builder();
+        let mut start = Vec2f::ZERO;
+        let mut current = Vec2f::ZERO;
+        let mut open = false;
+        for command in self.commands.iter() {
+            // Drawing without a preceding move starts a sub-path from where the last one ended.
+            if !open && !matches!(command, PathCommand::MoveTo(_) | PathCommand::Close) {
+                builder.begin(p(current));
+                start = current;
+                open = true;
+            }
+
+            match *command {
+                PathCommand::MoveTo(to) => {
+                    if open {
+                        builder.end(false);
+                    }
+                    builder.begin(p(to));
+                    start = to;
+                    current = to;
+                    open = true;
+                }
+                PathCommand::LineTo(to) => {
+                    builder.line_to(p(to));
+                    current = to;
+                }
+                PathCommand::QuadTo(ctrl, to) => {
+                    builder.quadratic_bezier_to(p(ctrl), p(to));
+                    current = to;
+                }
+                PathCommand::CubicTo(ctrl1, ctrl2, to) => {
+                    builder.cubic_bezier_to(p(ctrl1), p(ctrl2), p(to));
+                    current = to;
+                }
+                PathCommand::Close => {
+                    if open {
+                        builder.end(true);
+                        current = start;
+                        open = false;
+                    }
+                }
+            }
+        }
+
+        if open {
+            builder.end(false);
+        }
+
+        builder.build()
+    }
+}
+
+fn vertex(position: lyon_tessellation::math::Point) -> GeometryVertex {
+    GeometryVertex {
+        pos: position.to_array(),
+        uv: [0.0, 0.0],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::math::v2;
+
+    use super::*;
+
+    fn square() -> Path {
+        Path::new()
+            .move_to(v2(0.0, 0.0))
+            .line_to(v2(10.0, 0.0))
+            .line_to(v2(10.0, 10.0))
+            .line_to(v2(0.0, 10.0))
+            .close()
+    }
+
+    #[test]
+    fn square_is_filled_with_two_triangles() {
+        let geometry = square().tessellate_fill().unwrap();
+        assert_eq!(geometry.vertices.len(), 4);
+        assert_eq!(geometry.indices.len(), 6);
+    }
+
+    #[test]
+    fn stroke_is_offset_by_half_width() {
+        let geometry = square().tessellate_stroke(2.0).unwrap();
+        let min_x = geometry
+            .vertices
+            .iter()
+            .map(|v| v.pos[0])
+            .fold(f32::MAX, f32::min);
+        assert_eq!(min_x, -1.0);
+    }
+
+    #[test]
+    fn curves_are_flattened() {
+        let geometry = Path::new()
+            .move_to(v2(0.0, 0.0))
+            .quad_to(v2(50.0, 100.0), v2(100.0, 0.0))
+            .close()
+            .tessellate_fill()
+            .unwrap();
+        assert!(geometry.vertices.len() > 3);
+    }
+}
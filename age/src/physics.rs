@@ -0,0 +1,653 @@
+//! Simple rigid body physics for boxes and circles, which is enough for platformers and top-down
+//! games. Bodies do not rotate, and positions are in pixels with y pointing down.
+
+use crate::{
+    gen_vec::{GenIdx, GenVec},
+    math::{v2, Rect, Vec2f},
+    Color, Graphics,
+};
+
+/// The most steps [`PhysicsWorld::update`] takes in one call, so that a long frame does not make
+/// the next one even longer.
+const MAX_STEPS: u32 = 8;
+
+/// How far bodies may overlap before they are pushed apart, which stops resting bodies jittering.
+const PENETRATION_SLOP: f32 = 0.01;
+
+/// How much of the overlap between bodies is corrected each step.
+const PENETRATION_CORRECTION: f32 = 0.8;
+
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BodyId(GenIdx);
+
+impl BodyId {
+    pub const INVALID: Self = Self(GenIdx::INVALID);
+}
+
+impl std::fmt::Debug for BodyId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("BodyId").field(&self.0.idx()).finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BodyType {
+    /// Never moves, e.g. level geometry.
+    Static,
+    /// Moves at its velocity but is not affected by gravity or collisions, e.g. moving platforms.
+    Kinematic,
+    /// Moved by gravity and by collisions with other bodies.
+    Dynamic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    /// An axis-aligned box centered on the body's position.
+    Aabb { half_size: Vec2f },
+    /// A circle centered on the body's position.
+    Circle { radius: f32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Body {
+    pub body_type: BodyType,
+    pub shape: Shape,
+    pub position: Vec2f,
+    pub velocity: Vec2f,
+    pub mass: f32,
+    /// How much speed is kept when bouncing off another body, from `0.0` for none to `1.0` for
+    /// all of it. The lower of the two bodies' restitutions is used.
+    pub restitution: f32,
+    pub gravity_scale: f32,
+}
+
+impl Body {
+    pub fn new(body_type: BodyType, shape: Shape) -> Self {
+        Self {
+            body_type,
+            shape,
+            position: Vec2f::ZERO,
+            velocity: Vec2f::ZERO,
+            mass: 1.0,
+            restitution: 0.0,
+            gravity_scale: 1.0,
+        }
+    }
+
+    pub fn dynamic(shape: Shape) -> Self {
+        Self::new(BodyType::Dynamic, shape)
+    }
+
+    pub fn kinematic(shape: Shape) -> Self {
+        Self::new(BodyType::Kinematic, shape)
+    }
+
+    /// A [`BodyType::Static`] body.
+    pub fn fixed(shape: Shape) -> Self {
+        Self::new(BodyType::Static, shape)
+    }
+
+    pub fn with_position(mut self, position: Vec2f) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn with_velocity(mut self, velocity: Vec2f) -> Self {
+        self.velocity = velocity;
+        self
+    }
+
+    pub fn with_mass(mut self, mass: f32) -> Self {
+        self.mass = mass;
+        self
+    }
+
+    pub fn with_restitution(mut self, restitution: f32) -> Self {
+        self.restitution = restitution;
+        self
+    }
+
+    pub fn with_gravity_scale(mut self, gravity_scale: f32) -> Self {
+        self.gravity_scale = gravity_scale;
+        self
+    }
+
+    /// Changes the velocity of a dynamic body by `impulse` divided by its mass.
+    pub fn apply_impulse(&mut self, impulse: Vec2f) {
+        self.velocity += impulse * self.inverse_mass();
+    }
+
+    /// The minimum and maximum corners of the smallest box that contains the body.
+    pub fn bounds(&self) -> (Vec2f, Vec2f) {
+        let half_size = match self.shape {
+            Shape::Aabb { half_size } => half_size,
+            Shape::Circle { radius } => v2(radius, radius),
+        };
+
+        (self.position - half_size, self.position + half_size)
+    }
+
+    fn inverse_mass(&self) -> f32 {
+        match self.body_type {
+            BodyType::Dynamic if self.mass > 0.0 => 1.0 / self.mass,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Two bodies that were found to be overlapping during a step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact {
+    pub a: BodyId,
+    pub b: BodyId,
+    /// The direction from `a` to `b` that separates them.
+    pub normal: Vec2f,
+    pub depth: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    pub body: BodyId,
+    pub point: Vec2f,
+    pub normal: Vec2f,
+    pub distance: f32,
+}
+
+/// The bodies being simulated, which are stepped at a fixed timestep however long each frame is.
+pub struct PhysicsWorld {
+    bodies: GenVec<Body>,
+    gravity: Vec2f,
+    timestep: f32,
+    accumulator: f32,
+}
+
+impl Default for PhysicsWorld {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PhysicsWorld {
+    pub fn new() -> Self {
+        Self {
+            bodies: GenVec::default(),
+            gravity: v2(0.0, 980.0),
+            timestep: 1.0 / 60.0,
+            accumulator: 0.0,
+        }
+    }
+
+    /// The acceleration of dynamic bodies in pixels per second squared, which defaults to
+    /// 980 pixels per second squared downwards.
+    pub fn with_gravity(mut self, gravity: Vec2f) -> Self {
+        self.gravity = gravity;
+        self
+    }
+
+    /// The length of each step in seconds, which defaults to 1/60th of a second.
+    pub fn with_timestep(mut self, timestep: f32) -> Self {
+        self.timestep = timestep;
+        self
+    }
+
+    pub fn get_gravity(&self) -> Vec2f {
+        self.gravity
+    }
+
+    pub fn set_gravity(&mut self, gravity: Vec2f) {
+        self.gravity = gravity;
+    }
+
+    pub fn timestep(&self) -> f32 {
+        self.timestep
+    }
+
+    pub fn add(&mut self, body: Body) -> BodyId {
+        BodyId(self.bodies.add(body))
+    }
+
+    /// Adds a static box body for each of `rects`, such as those from [`merge_solid_cells`].
+    pub fn add_static_rects(&mut self, rects: &[Rect]) -> Vec<BodyId> {
+        rects
+            .iter()
+            .map(|rect| {
+                let shape = Shape::Aabb {
+                    half_size: rect.size / 2.0,
+                };
+                self.add(Body::fixed(shape).with_position(rect.center()))
+            })
+            .collect()
+    }
+
+    pub fn remove(&mut self, body: BodyId) -> Option<Body> {
+        self.bodies.get(body.0)?;
+        self.bodies.remove(body.0)
+    }
+
+    pub fn get(&self, body: BodyId) -> Option<&Body> {
+        self.bodies.get(body.0)
+    }
+
+    pub fn get_mut(&mut self, body: BodyId) -> Option<&mut Body> {
+        self.bodies.get_mut(body.0)
+    }
+
+    pub fn bodies(&self) -> impl Iterator<Item = (BodyId, &Body)> + '_ {
+        self.bodies.iter().map(|(idx, body)| (BodyId(idx), body))
+    }
+
+    /// Outlines the bounds of every body in `color`, to check them against what is drawn.
+    pub fn draw_debug(&self, graphics: &mut Graphics, color: Color) {
+        for (_, body) in self.bodies() {
+            let (min, max) = body.bounds();
+            let corners = [min, v2(max.x, min.y), max, v2(min.x, max.y)];
+            for i in 0..corners.len() {
+                graphics.draw_line(corners[i], corners[(i + 1) % corners.len()], 1.0, color);
+            }
+        }
+    }
+
+    /// Advances the simulation by `elapsed` seconds in fixed steps, carrying any time left over
+    /// into the next update, and calls `on_contact` for every contact in every step. Returns the
+    /// number of steps taken.
+    pub fn update<F: FnMut(&Contact)>(&mut self, elapsed: f32, mut on_contact: F) -> u32 {
+        self.accumulator += elapsed;
+
+        let mut steps = 0;
+        while self.accumulator >= self.timestep && steps < MAX_STEPS {
+            self.step(self.timestep, &mut on_contact);
+            self.accumulator -= self.timestep;
+            steps += 1;
+        }
+
+        // Drop the time that could not be caught up on rather than carrying it forever.
+        if steps == MAX_STEPS {
+            self.accumulator = self.accumulator.min(self.timestep);
+        }
+
+        steps
+    }
+
+    /// Advances the simulation by a single step of `dt` seconds.
+    pub fn step<F: FnMut(&Contact)>(&mut self, dt: f32, mut on_contact: F) {
+        let ids = self.bodies.indices().collect::<Vec<_>>();
+
+        for &idx in ids.iter() {
+            let body = &mut self.bodies[idx];
+            match body.body_type {
+                BodyType::Static => continue,
+                BodyType::Kinematic => {}
+                BodyType::Dynamic => body.velocity += self.gravity * body.gravity_scale * dt,
+            }
+            body.position += body.velocity * dt;
+        }
+
+        for (i, &a) in ids.iter().enumerate() {
+            for &b in ids[i + 1..].iter() {
+                let (mut body_a, mut body_b) = (self.bodies[a], self.bodies[b]);
+                if body_a.body_type != BodyType::Dynamic && body_b.body_type != BodyType::Dynamic {
+                    continue;
+                }
+
+                let (normal, depth) = match collide(&body_a, &body_b) {
+                    Some(collision) => collision,
+                    None => continue,
+                };
+
+                resolve(&mut body_a, &mut body_b, normal, depth);
+                self.bodies[a] = body_a;
+                self.bodies[b] = body_b;
+
+                on_contact(&Contact {
+                    a: BodyId(a),
+                    b: BodyId(b),
+                    normal,
+                    depth,
+                });
+            }
+        }
+    }
+
+    /// Finds the nearest body hit by a ray from `origin` in `direction`, up to `max_distance`
+    /// away. Bodies that contain the origin are ignored, so rays can be cast from inside a body.
+    pub fn raycast(&self, origin: Vec2f, direction: Vec2f, max_distance: f32) -> Option<RayHit> {
+        let direction = direction.normalize();
+
+        self.bodies()
+            .filter_map(|(id, body)| {
+                let (distance, normal) = match body.shape {
+                    Shape::Aabb { half_size } => {
+                        ray_aabb(origin, direction, body.position, half_size)?
+                    }
+                    Shape::Circle { radius } => {
+                        ray_circle(origin, direction, body.position, radius)?
+                    }
+                };
+
+                (distance <= max_distance).then_some(RayHit {
+                    body: id,
+                    point: origin + direction * distance,
+                    normal,
+                    distance,
+                })
+            })
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+}
+
+/// Pushes two overlapping bodies apart and bounces them off each other.
+fn resolve(a: &mut Body, b: &mut Body, normal: Vec2f, depth: f32) {
+    let (inv_a, inv_b) = (a.inverse_mass(), b.inverse_mass());
+    let inv_sum = inv_a + inv_b;
+    if inv_sum == 0.0 {
+        return;
+    }
+
+    let closing = (b.velocity - a.velocity).dot(normal);
+    if closing < 0.0 {
+        let restitution = a.restitution.min(b.restitution);
+        let impulse = normal * (-(1.0 + restitution) * closing / inv_sum);
+        a.velocity -= impulse * inv_a;
+        b.velocity += impulse * inv_b;
+    }
+
+    let correction =
+        normal * ((depth - PENETRATION_SLOP).max(0.0) / inv_sum * PENETRATION_CORRECTION);
+    a.position -= correction * inv_a;
+    b.position += correction * inv_b;
+}
+
+/// The normal from `a` to `b` and the depth of their overlap, if they overlap.
+fn collide(a: &Body, b: &Body) -> Option<(Vec2f, f32)> {
+    match (a.shape, b.shape) {
+        (Shape::Aabb { half_size: ha }, Shape::Aabb { half_size: hb }) => {
+            aabb_aabb(a.position, ha, b.position, hb)
+        }
+        (Shape::Circle { radius: ra }, Shape::Circle { radius: rb }) => {
+            circle_circle(a.position, ra, b.position, rb)
+        }
+        (Shape::Aabb { half_size }, Shape::Circle { radius }) => {
+            aabb_circle(a.position, half_size, b.position, radius)
+        }
+        (Shape::Circle { radius }, Shape::Aabb { half_size }) => {
+            aabb_circle(b.position, half_size, a.position, radius)
+                .map(|(normal, depth)| (-normal, depth))
+        }
+    }
+}
+
+fn sign(x: f32) -> f32 {
+    if x < 0.0 {
+        -1.0
+    } else {
+        1.0
+    }
+}
+
+fn aabb_aabb(pa: Vec2f, ha: Vec2f, pb: Vec2f, hb: Vec2f) -> Option<(Vec2f, f32)> {
+    let d = pb - pa;
+    let overlap_x = ha.x + hb.x - d.x.abs();
+    let overlap_y = ha.y + hb.y - d.y.abs();
+    if overlap_x <= 0.0 || overlap_y <= 0.0 {
+        return None;
+    }
+
+    if overlap_x < overlap_y {
+        Some((v2(sign(d.x), 0.0), overlap_x))
+    } else {
+        Some((v2(0.0, sign(d.y)), overlap_y))
+    }
+}
+
+fn circle_circle(pa: Vec2f, ra: f32, pb: Vec2f, rb: f32) -> Option<(Vec2f, f32)> {
+    let d = pb - pa;
+    let radii = ra + rb;
+    if d.len_sq() >= radii * radii {
+        return None;
+    }
+
+    let distance = d.len();
+    let normal = if distance > 0.0 {
+        d / distance
+    } else {
+        v2(0.0, 1.0)
+    };
+    Some((normal, radii - distance))
+}
+
+fn aabb_circle(pa: Vec2f, half_size: Vec2f, pc: Vec2f, radius: f32) -> Option<(Vec2f, f32)> {
+    let local = pc - pa;
+    let closest = v2(
+        local.x.clamp(-half_size.x, half_size.x),
+        local.y.clamp(-half_size.y, half_size.y),
+    );
+
+    if closest == local {
+        // The circle's center is inside the box, so push it out of the nearest side.
+        let overlap_x = half_size.x - local.x.abs();
+        let overlap_y = half_size.y - local.y.abs();
+        return if overlap_x < overlap_y {
+            Some((v2(sign(local.x), 0.0), overlap_x + radius))
+        } else {
+            Some((v2(0.0, sign(local.y)), overlap_y + radius))
+        };
+    }
+
+    let d = local - closest;
+    if d.len_sq() >= radius * radius {
+        return None;
+    }
+
+    let distance = d.len();
+    Some((d / distance, radius - distance))
+}
+
+fn ray_aabb(
+    origin: Vec2f,
+    direction: Vec2f,
+    center: Vec2f,
+    half_size: Vec2f,
+) -> Option<(f32, Vec2f)> {
+    let (min, max) = (center - half_size, center + half_size);
+
+    let mut near = f32::NEG_INFINITY;
+    let mut far = f32::INFINITY;
+    let mut normal = Vec2f::ZERO;
+    for (o, d, min, max, axis) in [
+        (origin.x, direction.x, min.x, max.x, v2(1.0, 0.0)),
+        (origin.y, direction.y, min.y, max.y, v2(0.0, 1.0)),
+    ] {
+        if d == 0.0 {
+            if o < min || o > max {
+                return None;
+            }
+            continue;
+        }
+
+        let (t1, t2) = ((min - o) / d, (max - o) / d);
+        let (t_near, t_far) = (t1.min(t2), t1.max(t2));
+        if t_near > near {
+            near = t_near;
+            normal = axis * -sign(d);
+        }
+        far = far.min(t_far);
+    }
+
+    (near >= 0.0 && near <= far).then_some((near, normal))
+}
+
+/// Merges the solid cells of a `width` by `height` grid of square cells into rectangles, so that
+/// a level can collide with a few boxes rather than one for every cell. Each rectangle is grown
+/// as far right and then as far down as it can go, which is not always the fewest rectangles but
+/// is close for typical levels. Cell (0, 0) has its top-left corner at the world origin.
+pub fn merge_solid_cells<F>(width: u32, height: u32, cell_size: Vec2f, is_solid: F) -> Vec<Rect>
+where
+    F: Fn(u32, u32) -> bool,
+{
+    let (w, h) = (width as usize, height as usize);
+    let mut merged = vec![false; w * h];
+    let free =
+        |x: usize, y: usize, merged: &[bool]| !merged[y * w + x] && is_solid(x as u32, y as u32);
+
+    let mut rects = Vec::new();
+    for y in 0..h {
+        for x in 0..w {
+            if !free(x, y, &merged) {
+                continue;
+            }
+
+            let right = (x..w).take_while(|&x| free(x, y, &merged)).count();
+            let down = (y..h)
+                .take_while(|&y| (x..x + right).all(|x| free(x, y, &merged)))
+                .count();
+            for row in merged[y * w..].chunks_mut(w).take(down) {
+                row[x..x + right].fill(true);
+            }
+
+            rects.push(Rect::new(
+                v2(x as f32 * cell_size.x, y as f32 * cell_size.y),
+                v2(right as f32 * cell_size.x, down as f32 * cell_size.y),
+            ));
+        }
+    }
+
+    rects
+}
+
+fn ray_circle(origin: Vec2f, direction: Vec2f, center: Vec2f, radius: f32) -> Option<(f32, Vec2f)> {
+    let m = origin - center;
+    let b = m.dot(direction);
+    let c = m.len_sq() - radius * radius;
+    if c <= 0.0 || b > 0.0 {
+        return None;
+    }
+
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let distance = -b - discriminant.sqrt();
+    let normal = (origin + direction * distance - center) / radius;
+    Some((distance, normal))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ground() -> Body {
+        Body::fixed(Shape::Aabb {
+            half_size: v2(100.0, 10.0),
+        })
+        .with_position(v2(0.0, 110.0))
+    }
+
+    #[test]
+    fn solid_cells_merge_into_rects() {
+        let level = ["##..", "##.#", "...#", "####"];
+        let is_solid = |x: u32, y: u32| level[y as usize].as_bytes()[x as usize] == b'#';
+
+        let rects = merge_solid_cells(4, 4, v2(16.0, 16.0), is_solid);
+        assert_eq!(
+            rects,
+            [
+                Rect::new(v2(0.0, 0.0), v2(32.0, 32.0)),
+                Rect::new(v2(48.0, 16.0), v2(16.0, 48.0)),
+                Rect::new(v2(0.0, 48.0), v2(48.0, 16.0)),
+            ]
+        );
+
+        let mut world = PhysicsWorld::new();
+        let bodies = world.add_static_rects(&rects);
+        assert_eq!(
+            world.get(bodies[1]).unwrap().bounds(),
+            (v2(48.0, 16.0), v2(64.0, 64.0))
+        );
+    }
+
+    #[test]
+    fn dynamic_bodies_fall_and_static_bodies_do_not() {
+        let mut world = PhysicsWorld::new().with_gravity(v2(0.0, 10.0));
+        let ball = world.add(Body::dynamic(Shape::Circle { radius: 1.0 }));
+        let wall =
+            world.add(Body::fixed(Shape::Circle { radius: 1.0 }).with_position(v2(50.0, 0.0)));
+
+        world.step(1.0, |_| {});
+        assert_eq!(world.get(ball).unwrap().velocity, v2(0.0, 10.0));
+        assert_eq!(world.get(ball).unwrap().position, v2(0.0, 10.0));
+        assert_eq!(world.get(wall).unwrap().position, v2(50.0, 0.0));
+    }
+
+    #[test]
+    fn box_comes_to_rest_on_ground() {
+        let mut world = PhysicsWorld::new();
+        world.add(ground());
+        let crate_ = world.add(Body::dynamic(Shape::Aabb {
+            half_size: v2(5.0, 5.0),
+        }));
+
+        let mut contacts = 0;
+        for _ in 0..300 {
+            world.update(1.0 / 60.0, |_| contacts += 1);
+        }
+
+        let body = world.get(crate_).unwrap();
+        assert!(contacts > 0);
+        assert!((body.position.y - 95.0).abs() < 0.5, "{}", body.position);
+        assert!(body.velocity.y.abs() < 20.0, "{}", body.velocity);
+    }
+
+    #[test]
+    fn restitution_bounces_body_back() {
+        let mut world = PhysicsWorld::new().with_gravity(Vec2f::ZERO);
+        world.add(ground().with_restitution(1.0));
+        let ball = world.add(
+            Body::dynamic(Shape::Circle { radius: 5.0 })
+                .with_position(v2(0.0, 95.5))
+                .with_velocity(v2(0.0, 60.0))
+                .with_restitution(1.0),
+        );
+
+        let mut contact = None;
+        world.step(1.0 / 60.0, |c| contact = Some(*c));
+
+        assert_eq!(contact.unwrap().normal, v2(0.0, -1.0));
+        assert_eq!(world.get(ball).unwrap().velocity, v2(0.0, -60.0));
+    }
+
+    #[test]
+    fn update_steps_at_fixed_timestep() {
+        let mut world = PhysicsWorld::new().with_timestep(0.1);
+        assert_eq!(world.update(0.25, |_| {}), 2);
+        assert_eq!(world.update(0.06, |_| {}), 1);
+        assert_eq!(world.update(100.0, |_| {}), MAX_STEPS);
+    }
+
+    #[test]
+    fn raycast_finds_nearest_body() {
+        let mut world = PhysicsWorld::new();
+        let near =
+            world.add(Body::fixed(Shape::Circle { radius: 5.0 }).with_position(v2(20.0, 0.0)));
+        world.add(
+            Body::fixed(Shape::Aabb {
+                half_size: v2(5.0, 5.0),
+            })
+            .with_position(v2(50.0, 0.0)),
+        );
+
+        let hit = world.raycast(Vec2f::ZERO, v2(2.0, 0.0), 100.0).unwrap();
+        assert_eq!(hit.body, near);
+        assert_eq!(hit.point, v2(15.0, 0.0));
+        assert_eq!(hit.normal, v2(-1.0, 0.0));
+
+        assert!(world.raycast(Vec2f::ZERO, v2(1.0, 0.0), 10.0).is_none());
+        assert!(world.raycast(Vec2f::ZERO, v2(0.0, 1.0), 100.0).is_none());
+    }
+
+    #[test]
+    fn raycast_hits_box_side() {
+        let hit = ray_aabb(v2(0.0, -20.0), v2(0.0, 1.0), Vec2f::ZERO, v2(5.0, 5.0));
+        assert_eq!(hit, Some((15.0, v2(0.0, -1.0))));
+    }
+}
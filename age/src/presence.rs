@@ -0,0 +1,224 @@
+use std::{
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use discord_rich_presence::{activity, DiscordIpc, DiscordIpcClient};
+
+/// How long to wait before trying to connect to Discord again, e.g. if it was not running.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// What the player is doing, shown on their Discord profile by [`Presence::set`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PresenceActivity {
+    state: Option<String>,
+    details: Option<String>,
+    start: Option<i64>, // Seconds since the Unix epoch.
+    end: Option<i64>,
+    party_size: Option<(u32, u32)>,
+}
+
+impl PresenceActivity {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The second line shown, e.g. `In a party`.
+    pub fn with_state(mut self, state: &str) -> Self {
+        self.state = Some(state.to_string());
+        self
+    }
+
+    /// The first line shown, e.g. `Exploring the caves`.
+    pub fn with_details(mut self, details: &str) -> Self {
+        self.details = Some(details.to_string());
+        self
+    }
+
+    /// Shows the time elapsed since `start`.
+    pub fn with_start(mut self, start: SystemTime) -> Self {
+        self.start = Some(unix_seconds(start));
+        self
+    }
+
+    /// Shows the time remaining until `end`, e.g. for a timed round.
+    pub fn with_end(mut self, end: SystemTime) -> Self {
+        self.end = Some(unix_seconds(end));
+        self
+    }
+
+    /// Shows `current` of `max` players after the state, e.g. `(2 of 4)`.
+    pub fn with_party_size(mut self, current: u32, max: u32) -> Self {
+        self.party_size = Some((current, max));
+        self
+    }
+
+    pub fn state(&self) -> Option<&str> {
+        self.state.as_deref()
+    }
+
+    pub fn details(&self) -> Option<&str> {
+        self.details.as_deref()
+    }
+
+    pub fn party_size(&self) -> Option<(u32, u32)> {
+        self.party_size
+    }
+
+    fn to_activity(&self) -> activity::Activity<'_> {
+        let mut activity = activity::Activity::new();
+        if let Some(state) = &self.state {
+            activity = activity.state(state.as_str());
+        }
+        if let Some(details) = &self.details {
+            activity = activity.details(details.as_str());
+        }
+        if self.start.is_some() || self.end.is_some() {
+            let mut timestamps = activity::Timestamps::new();
+            if let Some(start) = self.start {
+                timestamps = timestamps.start(start);
+            }
+            if let Some(end) = self.end {
+                timestamps = timestamps.end(end);
+            }
+            activity = activity.timestamps(timestamps);
+        }
+        if let Some((current, max)) = self.party_size {
+            let size = [current, max].map(|n| n.min(i32::MAX as u32) as i32);
+            activity = activity.party(activity::Party::new().size(size));
+        }
+        activity
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as i64,
+        Err(err) => -(err.duration().as_secs() as i64),
+    }
+}
+
+/// The player's activity shown on their Discord profile, reached with
+/// [`Engine::presence`](crate::Engine::presence). Discord is talked to on a background thread,
+/// which connects once [`Presence::connect`] is called, and keeps trying to reconnect if Discord
+/// is not running or is restarted, so the game never waits on it.
+#[derive(Default)]
+pub struct Presence {
+    tx: Option<Sender<Option<PresenceActivity>>>,
+    activity: Option<PresenceActivity>,
+}
+
+impl Presence {
+    /// Starts connecting to Discord as the application with `client_id`, found in the Discord
+    /// developer portal. Any activity already set is shown once connected.
+    pub fn connect(&mut self, client_id: &str) {
+        let (tx, rx) = mpsc::channel();
+        let client = DiscordIpcClient::new(client_id);
+        std::thread::spawn(move || run(client, rx));
+
+        if let Some(activity) = &self.activity {
+            let _ = tx.send(Some(activity.clone()));
+        }
+        self.tx = Some(tx);
+    }
+
+    /// Stops showing the activity and closes the connection to Discord.
+    pub fn disconnect(&mut self) {
+        self.tx = None;
+    }
+
+    /// Shows `activity` on the player's profile, replacing any activity set before.
+    pub fn set(&mut self, activity: PresenceActivity) {
+        if self.activity.as_ref() != Some(&activity) {
+            self.send(Some(activity.clone()));
+            self.activity = Some(activity);
+        }
+    }
+
+    /// Stops showing an activity on the player's profile.
+    pub fn clear(&mut self) {
+        if self.activity.take().is_some() {
+            self.send(None);
+        }
+    }
+
+    pub fn get(&self) -> Option<&PresenceActivity> {
+        self.activity.as_ref()
+    }
+
+    fn send(&mut self, activity: Option<PresenceActivity>) {
+        if let Some(tx) = &self.tx {
+            if tx.send(activity).is_err() {
+                self.tx = None;
+            }
+        }
+    }
+}
+
+/// Keeps `client` connected and showing the latest activity received, until the [`Presence`]
+/// is dropped or disconnected.
+fn run(mut client: DiscordIpcClient, rx: Receiver<Option<PresenceActivity>>) {
+    let mut activity: Option<PresenceActivity> = None;
+    let mut connected = false;
+    let mut shown = false; // Whether `activity` has been sent since connecting.
+
+    loop {
+        if !connected {
+            connected = client.connect().is_ok();
+            shown = false;
+        }
+        if connected && !shown {
+            let result = match &activity {
+                Some(activity) => client.set_activity(activity.to_activity()),
+                None => client.clear_activity(),
+            };
+            if result.is_err() {
+                let _ = client.close();
+            }
+            connected = result.is_ok();
+            shown = result.is_ok();
+        }
+
+        let timeout = match connected {
+            true => Duration::MAX,
+            false => RECONNECT_INTERVAL,
+        };
+        match rx.recv_timeout(timeout) {
+            Ok(latest) => {
+                activity = latest;
+                shown = false;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    if connected {
+        let _ = client.clear_activity();
+        let _ = client.close();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn activities_are_kept_until_cleared() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let activity = PresenceActivity::new()
+            .with_state("In a party")
+            .with_details("Exploring the caves")
+            .with_start(start)
+            .with_party_size(2, 4);
+        assert_eq!(activity.start, Some(1_700_000_000));
+        assert_eq!(activity.party_size(), Some((2, 4)));
+
+        let mut presence = Presence::default();
+        presence.set(activity.clone());
+        assert_eq!(presence.get(), Some(&activity));
+        presence.clear();
+        assert_eq!(presence.get(), None);
+        assert_eq!(unix_seconds(UNIX_EPOCH - Duration::from_secs(5)), -5);
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+
+use crate::{
+    Color, ColorTarget, DrawTarget, Engine, Error, RenderTexture, Texture, TransientTextureDesc,
+};
+
+/// A frame's render passes, each declaring the named targets it reads and writes, so that the
+/// passes can be run in an order that satisfies them rather than the order they were added in.
+///
+/// A pass runs after every pass that writes a target it reads, and passes that write the same
+/// target run in the order they were added. Targets with a clear color are cleared by the first
+/// pass that writes them. Targets created with [`RenderGraph::create_target`] are only needed
+/// within the frame, so their textures are taken from a pool and reused in later frames.
+///
+/// ```ignore
+/// let mut graph = RenderGraph::new();
+/// graph.import("screen", age.graphics.get_draw_target());
+/// graph.create_target("scene", width, height, Some(Color::BLACK));
+/// graph.add_pass("post", &["scene"], &["screen"], |age, textures| {
+///     age.graphics.blit(textures.get("scene").unwrap(), ...);
+/// });
+/// graph.add_pass("scene", &[], &["scene"], |age, _| { /* draw the world */ });
+/// graph.execute(age)?;
+/// ```
+#[derive(Default)]
+pub struct RenderGraph<'a> {
+    targets: Vec<GraphTarget>,
+    passes: Vec<GraphPass<'a>>,
+}
+
+struct GraphTarget {
+    name: String,
+    kind: GraphTargetKind,
+    clear_color: Option<Color>,
+}
+
+enum GraphTargetKind {
+    Imported(DrawTarget),
+    Transient { width: u32, height: u32 },
+}
+
+type PassFn<'a> = Box<dyn FnMut(&mut Engine, &GraphTextures) + 'a>;
+
+struct GraphPass<'a> {
+    name: String,
+    reads: Vec<usize>,
+    writes: Vec<usize>,
+    run: PassFn<'a>,
+}
+
+/// The textures of the targets a render graph pass reads, by name.
+#[derive(Default)]
+pub struct GraphTextures {
+    textures: HashMap<String, Texture>,
+}
+
+impl GraphTextures {
+    pub fn get(&self, name: &str) -> Option<&Texture> {
+        self.textures.get(name)
+    }
+}
+
+impl<'a> RenderGraph<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a target that is drawn to elsewhere, such as the current draw target or a render
+    /// texture kept between frames. Only the first color target of `target` is used, and it is
+    /// never cleared by the graph. Passes can read it to run after whatever draws to it, but its
+    /// texture is not passed to them.
+    pub fn import<T: Into<DrawTarget>>(&mut self, name: &str, target: T) {
+        self.add_target(name, GraphTargetKind::Imported(target.into()), None);
+    }
+
+    /// Adds a target that only lives for this frame, cleared to `clear_color` if given.
+    pub fn create_target(
+        &mut self,
+        name: &str,
+        width: u32,
+        height: u32,
+        clear_color: Option<Color>,
+    ) {
+        self.add_target(
+            name,
+            GraphTargetKind::Transient { width, height },
+            clear_color,
+        );
+    }
+
+    /// Adds a pass that draws to the targets in `writes`, which must have been added already, as
+    /// the color targets of a single draw target in that order. `run` is called with the draw
+    /// target set and the textures of the targets in `reads`.
+    ///
+    /// Panics if a target has not been added.
+    pub fn add_pass<F>(&mut self, name: &str, reads: &[&str], writes: &[&str], run: F)
+    where
+        F: FnMut(&mut Engine, &GraphTextures) + 'a,
+    {
+        let reads = reads.iter().map(|read| self.target(read)).collect();
+        let writes = writes.iter().map(|write| self.target(write)).collect();
+        self.passes.push(GraphPass {
+            name: name.to_string(),
+            reads,
+            writes,
+            run: Box::new(run),
+        });
+    }
+
+    /// Runs every pass in order, then restores the draw target that was set beforehand.
+    pub fn execute(mut self, age: &mut Engine) -> Result<(), Error> {
+        let order = self.schedule()?;
+        let textures = self.acquire_textures(age);
+        let previous_target = age.graphics.get_draw_target();
+
+        let mut cleared = vec![false; self.targets.len()];
+        for i in order {
+            let pass = &mut self.passes[i];
+            if !pass.writes.is_empty() {
+                let color_targets = pass
+                    .writes
+                    .iter()
+                    .map(|&write| {
+                        let target = match &self.targets[write].kind {
+                            GraphTargetKind::Imported(target) => target.color_targets()[0],
+                            GraphTargetKind::Transient { .. } => {
+                                ColorTarget::from(&textures[&write])
+                            }
+                        };
+                        match self.targets[write].clear_color {
+                            Some(color) if !cleared[write] => {
+                                cleared[write] = true;
+                                target.with_clear_color(color)
+                            }
+                            _ => target,
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                age.graphics
+                    .set_draw_target(DrawTarget::with_color_targets(&color_targets));
+            }
+
+            let reads = GraphTextures {
+                textures: pass
+                    .reads
+                    .iter()
+                    .filter_map(|&read| {
+                        let texture = match &self.targets[read].kind {
+                            GraphTargetKind::Imported(_) => return None,
+                            GraphTargetKind::Transient { .. } => *textures[&read].texture(),
+                        };
+                        Some((self.targets[read].name.clone(), texture))
+                    })
+                    .collect(),
+            };
+            (pass.run)(age, &reads);
+        }
+
+        age.graphics.set_draw_target(previous_target);
+        Ok(())
+    }
+
+    fn add_target(&mut self, name: &str, kind: GraphTargetKind, clear_color: Option<Color>) {
+        assert!(
+            self.targets.iter().all(|target| target.name != name),
+            "render graph target `{name}` has already been added"
+        );
+        self.targets.push(GraphTarget {
+            name: name.to_string(),
+            kind,
+            clear_color,
+        });
+    }
+
+    fn target(&self, name: &str) -> usize {
+        self.targets
+            .iter()
+            .position(|target| target.name == name)
+            .unwrap_or_else(|| panic!("render graph target `{name}` has not been added"))
+    }
+
+    fn acquire_textures(&self, age: &mut Engine) -> HashMap<usize, RenderTexture> {
+        let used = |i: &usize| {
+            self.passes
+                .iter()
+                .any(|pass| pass.reads.contains(i) || pass.writes.contains(i))
+        };
+
+        (0..self.targets.len())
+            .filter(used)
+            .filter_map(|i| match self.targets[i].kind {
+                GraphTargetKind::Transient { width, height } => Some((
+                    i,
+                    age.graphics.acquire_transient_texture(
+                        &mut age.renderer,
+                        &TransientTextureDesc::new(width, height),
+                    ),
+                )),
+                GraphTargetKind::Imported(_) => None,
+            })
+            .collect()
+    }
+
+    /// The order to run the passes in, keeping the order they were added in where possible.
+    fn schedule(&self) -> Result<Vec<usize>, Error> {
+        // after[i] holds the passes that must run before pass i.
+        let mut after = vec![Vec::new(); self.passes.len()];
+        for (i, pass) in self.passes.iter().enumerate() {
+            if let Some(&target) = pass.reads.iter().find(|read| pass.writes.contains(read)) {
+                return Err(Error::new(format!(
+                    "render graph pass `{}` both reads and writes `{}`",
+                    pass.name, self.targets[target].name
+                )));
+            }
+
+            for (j, other) in self.passes.iter().enumerate() {
+                let reads_output = pass.reads.iter().any(|read| other.writes.contains(read));
+                let writes_after = j < i && pass.writes.iter().any(|w| other.writes.contains(w));
+                if reads_output || writes_after {
+                    after[i].push(j);
+                }
+            }
+        }
+
+        let mut order = Vec::with_capacity(self.passes.len());
+        let mut done = vec![false; self.passes.len()];
+        while order.len() < self.passes.len() {
+            let next = (0..self.passes.len())
+                .find(|&i| !done[i] && after[i].iter().all(|&j| done[j]))
+                .ok_or_else(|| {
+                    let waiting = (0..self.passes.len())
+                        .filter(|&i| !done[i])
+                        .map(|i| self.passes[i].name.as_str())
+                        .collect::<Vec<_>>();
+                    Error::new(format!(
+                        "render graph passes depend on each other: {}",
+                        waiting.join(", ")
+                    ))
+                })?;
+            done[next] = true;
+            order.push(next);
+        }
+
+        Ok(order)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn graph() -> RenderGraph<'static> {
+        let mut graph = RenderGraph::new();
+        graph.import("screen", DrawTarget::INVALID);
+        graph.create_target("scene", 64, 64, Some(Color::BLACK));
+        graph.create_target("bloom", 32, 32, None);
+        graph
+    }
+
+    fn names(graph: &RenderGraph) -> Vec<String> {
+        let order = graph.schedule().unwrap();
+        order
+            .into_iter()
+            .map(|i| graph.passes[i].name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn passes_run_after_what_they_read() {
+        let mut graph = graph();
+        graph.add_pass("composite", &["scene", "bloom"], &["screen"], |_, _| {});
+        graph.add_pass("bloom", &["scene"], &["bloom"], |_, _| {});
+        graph.add_pass("world", &[], &["scene"], |_, _| {});
+        graph.add_pass("particles", &[], &["scene"], |_, _| {});
+        graph.add_pass("ui", &[], &["screen"], |_, _| {});
+
+        assert_eq!(
+            names(&graph),
+            ["world", "particles", "bloom", "composite", "ui"]
+        );
+    }
+
+    #[test]
+    fn cycles_and_feedback_are_errors() {
+        let mut graph = graph();
+        graph.add_pass("a", &["bloom"], &["scene"], |_, _| {});
+        graph.add_pass("b", &["scene"], &["bloom"], |_, _| {});
+        assert!(graph.schedule().is_err());
+
+        let mut graph = self::graph();
+        graph.add_pass("feedback", &["scene"], &["scene"], |_, _| {});
+        assert!(graph.schedule().is_err());
+    }
+}
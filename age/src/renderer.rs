@@ -1,28 +1,95 @@
-use std::{borrow::Cow, num::NonZeroU64};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt::{Display, Write as _},
+    num::NonZeroU64,
+    ops::{AddAssign, Range},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 
 use crate::{
+    error::ErrorKind,
+    frame_stats::{FrameStats, PassStats},
     gen_vec::{GenIdx, GenVec},
     math::Mat4,
+    resources::{ResourceKind, ResourceTracker},
     sys::Window,
-    Color, Error,
+    Color, Error, Image,
 };
 
-#[derive(Default)]
 pub(crate) struct Surface<'window> {
     s: Option<wgpu::Surface<'window>>,
     config: Option<wgpu::SurfaceConfiguration>,
     frame: Option<wgpu::SurfaceTexture>,
+    alpha_mode: CompositeAlphaMode,
+    clear_color: Color,
+    size: (u32, u32),
+    low_latency: bool,
+    suspended: bool,
+    vsync: bool,
+    vsync_present_mode: wgpu::PresentMode, // Chosen when the surface is created.
+    needs_configure: bool,
 }
 
 impl<'window> Surface<'window> {
-    pub(crate) fn acquire(&mut self) -> wgpu::TextureView {
+    /// When `low_latency` is set, the surface presents in mailbox mode where it is supported, so
+    /// that presenting never waits for the display and the newest frame is always shown.
+    pub(crate) fn new(
+        alpha_mode: CompositeAlphaMode,
+        clear_color: Color,
+        low_latency: bool,
+    ) -> Self {
+        Self {
+            s: None,
+            config: None,
+            frame: None,
+            alpha_mode,
+            clear_color,
+            size: (0, 0),
+            low_latency,
+            suspended: false,
+            vsync: true,
+            vsync_present_mode: wgpu::PresentMode::Fifo,
+            needs_configure: false,
+        }
+    }
+
+    /// Acquires the next surface texture to render to, or `None` if there is nothing to render to,
+    /// e.g. because the window is minimised or the app is suspended. The surface is reconfigured
+    /// first if the window has been resized since the last frame.
+    pub(crate) fn acquire(&mut self, device: &wgpu::Device) -> Option<wgpu::TextureView> {
+        if self.suspended {
+            return None;
+        }
         assert!(self.s.is_some(), "surface has not been initialised");
 
-        let frame = match self.s.as_ref().unwrap().get_current_texture() {
+        let (width, height) = self.size;
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let config = self.config.as_mut().unwrap();
+        if self.needs_configure || (config.width, config.height) != self.size {
+            config.width = width;
+            config.height = height;
+            self.s.as_ref().unwrap().configure(device, config);
+            self.needs_configure = false;
+        }
+
+        let s = self.s.as_ref().unwrap();
+        let frame = match s.get_current_texture() {
             Ok(frame) => frame,
-            Err(_) => {
-                // todo: try to recreate
-                panic!("failed to obtain next surface texture");
+            Err(wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Lost) => {
+                s.configure(device, config);
+                s.get_current_texture().ok()?
+            }
+            Err(wgpu::SurfaceError::Timeout) => return None,
+            Err(wgpu::SurfaceError::OutOfMemory) => {
+                panic!("out of memory obtaining next surface texture");
             }
         };
 
@@ -34,7 +101,32 @@ impl<'window> Surface<'window> {
 
         self.frame = Some(frame);
 
-        view
+        Some(view)
+    }
+
+    /// Sets whether presenting waits for the display's vertical sync. Without it, frames are
+    /// presented as soon as they are ready, which can tear. The surface is reconfigured when the
+    /// next frame is acquired.
+    pub(crate) fn set_vsync(&mut self, vsync: bool) {
+        self.vsync = vsync;
+        let present_mode = self.present_mode();
+        if let Some(config) = self.config.as_mut() {
+            config.present_mode = present_mode;
+            self.needs_configure = true;
+        }
+    }
+
+    fn present_mode(&self) -> wgpu::PresentMode {
+        match self.vsync {
+            true => self.vsync_present_mode,
+            false => wgpu::PresentMode::AutoNoVsync,
+        }
+    }
+
+    /// Records the new size of the window. The surface is reconfigured when the next frame is
+    /// acquired, so that a burst of resize events only reconfigures it once.
+    pub(crate) fn resize(&mut self, width: u32, height: u32) {
+        self.size = (width, height);
     }
 
     pub(crate) fn init(
@@ -46,19 +138,64 @@ impl<'window> Surface<'window> {
         let s = renderer.instance.create_surface(window)?;
         let mut config = match s.get_default_config(&renderer.adapter, width, height) {
             Some(config) => config,
-            None => return Err("window surface is not supported by the graphics adapter".into()),
+            None => {
+                return Err(
+                    Error::new("window surface is not supported by the graphics adapter")
+                        .with_kind(ErrorKind::Gpu),
+                )
+            }
         };
 
         config.format = wgpu::TextureFormat::Bgra8Unorm; // todo: deal with srgb.
 
+        let capabilities = s.get_capabilities(&renderer.adapter);
+        if self.low_latency {
+            if capabilities
+                .present_modes
+                .contains(&wgpu::PresentMode::Mailbox)
+            {
+                config.present_mode = wgpu::PresentMode::Mailbox;
+            } else {
+                eprintln!(
+                    "present mode {:?} is not supported by the window surface, using {:?}",
+                    PresentMode::Mailbox,
+                    PresentMode::from(config.present_mode)
+                );
+            }
+        }
+        self.vsync_present_mode = config.present_mode;
+        config.present_mode = self.present_mode();
+
+        let alpha_mode = self.alpha_mode.into();
+        if capabilities.alpha_modes.contains(&alpha_mode) {
+            config.alpha_mode = alpha_mode;
+        } else {
+            eprintln!(
+                "composite alpha mode {:?} is not supported by the window surface, using {:?}",
+                self.alpha_mode,
+                CompositeAlphaMode::from(config.alpha_mode)
+            );
+        }
+
         s.configure(&renderer.device, &config);
 
         self.s = Some(s);
         self.config = Some(config);
+        self.size = (width, height);
+        self.suspended = false;
 
         Ok(())
     }
 
+    /// Releases the surface while the app is suspended. On Android the window's native surface
+    /// is destroyed on suspend, so a new one is created by [`Surface::init`] when it resumes.
+    pub(crate) fn suspend(&mut self) {
+        self.frame = None;
+        self.s = None;
+        self.config = None;
+        self.suspended = true;
+    }
+
     pub(crate) fn present(&mut self) {
         if let Some(frame) = self.frame.take() {
             frame.present();
@@ -151,6 +288,88 @@ impl std::fmt::Debug for BufferId {
     }
 }
 
+/// A buffer that is written to from the start each frame and grows when it runs out of space,
+/// for geometry whose size changes from frame to frame.
+pub struct DynamicBuffer {
+    buffer: BufferId,
+    usage: BufferUsages,
+    cursor: usize,
+    retired: Vec<BufferId>,
+}
+
+impl DynamicBuffer {
+    pub fn new(renderer: &mut Renderer, desc: &BufferDesc) -> Self {
+        Self {
+            buffer: renderer.create_buffer(desc),
+            usage: desc.usage,
+            cursor: 0,
+            retired: Vec::new(),
+        }
+    }
+
+    /// The current buffer, which changes when the buffer grows.
+    pub fn buffer(&self) -> BufferId {
+        self.buffer
+    }
+
+    /// The number of bytes written since the last reset.
+    pub fn len(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cursor == 0
+    }
+
+    /// Appends `data` after anything written since the last reset, growing the buffer if needed,
+    /// and returns the offset in bytes that it was written at.
+    pub fn push<T: Copy>(&mut self, renderer: &mut Renderer, data: &[T]) -> usize {
+        let bytes = cast_slice(data);
+        let len = align_to(bytes.len(), wgpu::COPY_BUFFER_ALIGNMENT as usize);
+
+        let offset = self.cursor;
+        let capacity = renderer.buffer_size(self.buffer);
+        if offset + len > capacity {
+            let grown = renderer.create_buffer(&BufferDesc {
+                label: Some("dynamic buffer"),
+                size: (capacity * 2).max(offset + len),
+                usage: self.usage,
+            });
+            if offset > 0 {
+                renderer.copy_buffer(self.buffer, grown, offset);
+            }
+
+            // Draws recorded this frame may still use the old buffer.
+            self.retired.push(self.buffer);
+            self.buffer = grown;
+        }
+
+        if len == bytes.len() {
+            renderer.write_buffer_at(self.buffer, offset, bytes);
+        } else {
+            let mut padded = bytes.to_vec();
+            padded.resize(len, 0);
+            renderer.write_buffer_at(self.buffer, offset, &padded);
+        }
+        self.cursor += len;
+
+        offset
+    }
+
+    /// Starts writing from the beginning of the buffer again. This should be called once per
+    /// frame, after the previous frame has been submitted.
+    pub fn reset(&mut self, renderer: &mut Renderer) {
+        self.cursor = 0;
+        for buffer in self.retired.drain(..) {
+            renderer.destroy_buffer(buffer);
+        }
+    }
+}
+
+fn align_to(size: usize, alignment: usize) -> usize {
+    size.div_ceil(alignment) * alignment
+}
+
 pub struct BufferDesc<'desc> {
     pub label: Option<&'desc str>,
     pub size: usize,
@@ -168,12 +387,15 @@ bitflags::bitflags! {
 
 impl From<BufferUsages> for wgpu::BufferUsages {
     fn from(value: BufferUsages) -> Self {
-        match value {
-            BufferUsages::INDEX => wgpu::BufferUsages::INDEX,
-            BufferUsages::VERTEX => wgpu::BufferUsages::VERTEX,
-            BufferUsages::STORAGE => wgpu::BufferUsages::STORAGE,
-            _ => unreachable!(),
-        }
+        value
+            .iter()
+            .map(|usage| match usage {
+                BufferUsages::INDEX => wgpu::BufferUsages::INDEX,
+                BufferUsages::VERTEX => wgpu::BufferUsages::VERTEX,
+                BufferUsages::STORAGE => wgpu::BufferUsages::STORAGE,
+                _ => unreachable!(),
+            })
+            .fold(wgpu::BufferUsages::empty(), |usages, usage| usages | usage)
     }
 }
 
@@ -219,7 +441,67 @@ pub struct RenderPipelineDesc<'desc> {
     pub vs_main: &'desc str,
     pub fs_main: &'desc str,
     pub buffers: &'desc [VertexBufferLayoutId],
-    pub color_target_format: TextureFormat,
+    /// The format and blending of each target drawn to, where fragment output `@location(i)` is
+    /// written to the `i`th color target of the [`DrawTarget`].
+    pub color_targets: &'desc [ColorTargetDesc],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ColorTargetDesc {
+    pub format: TextureFormat,
+    pub blend: BlendMode,
+}
+
+impl ColorTargetDesc {
+    pub const fn new(format: TextureFormat, blend: BlendMode) -> Self {
+        Self { format, blend }
+    }
+}
+
+/// How the output of a pipeline is combined with what is already in the draw target.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Overwrites the target.
+    #[default]
+    Replace,
+    /// Draws over the target, letting it show through transparent parts.
+    Alpha,
+    /// Adds to the target, weighted by alpha, e.g. for lights and glows.
+    Additive,
+    /// Multiplies the target, keeping its alpha, e.g. to darken a scene with a light map.
+    Multiply,
+}
+
+impl BlendMode {
+    pub(crate) const ALL: [BlendMode; 4] = [
+        BlendMode::Replace,
+        BlendMode::Alpha,
+        BlendMode::Additive,
+        BlendMode::Multiply,
+    ];
+}
+
+impl From<BlendMode> for Option<wgpu::BlendState> {
+    fn from(value: BlendMode) -> Self {
+        let component = |src_factor, dst_factor| wgpu::BlendComponent {
+            src_factor,
+            dst_factor,
+            operation: wgpu::BlendOperation::Add,
+        };
+
+        match value {
+            BlendMode::Replace => None,
+            BlendMode::Alpha => Some(wgpu::BlendState::ALPHA_BLENDING),
+            BlendMode::Additive => Some(wgpu::BlendState {
+                color: component(wgpu::BlendFactor::SrcAlpha, wgpu::BlendFactor::One),
+                alpha: component(wgpu::BlendFactor::One, wgpu::BlendFactor::One),
+            }),
+            BlendMode::Multiply => Some(wgpu::BlendState {
+                color: component(wgpu::BlendFactor::Dst, wgpu::BlendFactor::Zero),
+                alpha: component(wgpu::BlendFactor::Zero, wgpu::BlendFactor::One),
+            }),
+        }
+    }
 }
 
 #[derive(Default, Clone, Copy, PartialEq, Eq, Hash)]
@@ -236,11 +518,11 @@ impl std::fmt::Debug for SamplerId {
 }
 
 pub struct SamplerDesc<'desc> {
-    label: Option<&'desc str>,
-    address_mode_u: AddressMode,
-    address_mode_v: AddressMode,
-    mag_filter: FilterMode,
-    min_filter: FilterMode,
+    pub label: Option<&'desc str>,
+    pub address_mode_u: AddressMode,
+    pub address_mode_v: AddressMode,
+    pub mag_filter: FilterMode,
+    pub min_filter: FilterMode,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -273,6 +555,55 @@ impl From<FilterMode> for wgpu::FilterMode {
     }
 }
 
+/// Why a shader failed to compile, with the position of the problem in its source, 1-based, and
+/// the source around it annotated with the problem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShaderError {
+    pub line: u32,
+    pub column: u32,
+    pub message: String,
+    pub snippet: String,
+}
+
+impl ShaderError {
+    fn check(source: &str, label: &str) -> Result<(), ShaderError> {
+        let module = naga::front::wgsl::parse_str(source).map_err(|err| {
+            let location = err.location(source);
+            ShaderError {
+                line: location.map_or(0, |l| l.line_number),
+                column: location.map_or(0, |l| l.line_position),
+                message: err.message().to_string(),
+                snippet: err.emit_to_string_with_path(source, label),
+            }
+        })?;
+
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::all(),
+        )
+        .validate(&module)
+        .map_err(|err| {
+            let location = err.location(source);
+            ShaderError {
+                line: location.map_or(0, |l| l.line_number),
+                column: location.map_or(0, |l| l.line_position),
+                message: err.as_inner().to_string(),
+                snippet: err.emit_to_string_with_path(source, label),
+            }
+        })?;
+
+        Ok(())
+    }
+}
+
+impl Display for ShaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.snippet)
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
 #[derive(Default, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ShaderId(GenIdx);
 
@@ -305,10 +636,10 @@ impl std::fmt::Debug for TextureId {
 }
 
 pub struct TextureDesc<'desc> {
-    label: Option<&'desc str>,
-    width: u32,
-    height: u32,
-    format: TextureFormat,
+    pub label: Option<&'desc str>,
+    pub width: u32,
+    pub height: u32,
+    pub format: TextureFormat,
 }
 
 #[derive(Default, Clone, Copy, PartialEq, Eq, Hash)]
@@ -325,15 +656,16 @@ impl std::fmt::Debug for TextureViewId {
 }
 
 pub struct TextureViewDesc<'desc> {
-    label: Option<&'desc str>,
-    texture: TextureId,
-    format: TextureFormat,
+    pub label: Option<&'desc str>,
+    pub texture: TextureId,
+    pub format: TextureFormat,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TextureFormat {
     Bgra8Unorm,
     Rgba8Unorm,
+    R32Uint,
 }
 
 impl From<TextureFormat> for wgpu::TextureFormat {
@@ -341,6 +673,7 @@ impl From<TextureFormat> for wgpu::TextureFormat {
         match value {
             TextureFormat::Bgra8Unorm => wgpu::TextureFormat::Bgra8Unorm,
             TextureFormat::Rgba8Unorm => wgpu::TextureFormat::Rgba8Unorm,
+            TextureFormat::R32Uint => wgpu::TextureFormat::R32Uint,
         }
     }
 }
@@ -352,10 +685,11 @@ impl TryFrom<wgpu::TextureFormat> for TextureFormat {
         match value {
             wgpu::TextureFormat::Bgra8Unorm => Ok(TextureFormat::Bgra8Unorm),
             wgpu::TextureFormat::Rgba8Unorm => Ok(TextureFormat::Rgba8Unorm),
-            _ => Err(Error::new(format!(
-                "texture format {:?} is not supported",
-                value
-            ))),
+            wgpu::TextureFormat::R32Uint => Ok(TextureFormat::R32Uint),
+            _ => Err(
+                Error::new(format!("texture format {:?} is not supported", value))
+                    .with_kind(ErrorKind::Gpu),
+            ),
         }
     }
 }
@@ -375,46 +709,219 @@ impl std::fmt::Debug for VertexBufferLayoutId {
     }
 }
 
+/// Describes how the vertices in a buffer are laid out, usually obtained from [`Vertex::layout`].
 pub struct VertexBufferLayoutDesc<'desc> {
     stride: usize,
     buffer_type: VertexBufferType,
     attributes: &'desc [VertexAttribute],
 }
 
-#[derive(Debug, Clone, Copy)]
+impl<'desc> VertexBufferLayoutDesc<'desc> {
+    /// A layout where each element is `stride` bytes apart.
+    pub const fn new(
+        stride: usize,
+        buffer_type: VertexBufferType,
+        attributes: &'desc [VertexAttribute],
+    ) -> Self {
+        Self {
+            stride,
+            buffer_type,
+            attributes,
+        }
+    }
+
+    pub fn buffer_type(&self) -> VertexBufferType {
+        self.buffer_type
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum VertexBufferType {
+    /// One element per vertex.
     Geometry,
+    /// One element per instance, shared by all of its vertices.
+    Instance,
 }
 
 impl From<VertexBufferType> for wgpu::VertexStepMode {
     fn from(value: VertexBufferType) -> Self {
         match value {
             VertexBufferType::Geometry => wgpu::VertexStepMode::Vertex,
+            VertexBufferType::Instance => wgpu::VertexStepMode::Instance,
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct VertexAttribute {
     format: VertexFormat,
     offset: usize,
     location: usize,
 }
 
-#[derive(Debug, Clone, Copy)]
+impl VertexAttribute {
+    /// An attribute read as `format` from `offset` bytes into each element, and passed to the
+    /// shader at `@location(location)`.
+    pub const fn new(format: VertexFormat, offset: usize, location: usize) -> Self {
+        Self {
+            format,
+            offset,
+            location,
+        }
+    }
+}
+
+/// The format of a vertex attribute as stored in a buffer. Normalized formats (`Unorm`, `Snorm`)
+/// are read by the shader as floats in the range `0.0..=1.0` or `-1.0..=1.0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum VertexFormat {
+    Uint8x2,
+    Uint8x4,
+    Sint8x2,
+    Sint8x4,
+    Unorm8x2,
+    Unorm8x4,
+    Snorm8x2,
+    Snorm8x4,
+    Uint16x2,
+    Uint16x4,
+    Sint16x2,
+    Sint16x4,
+    Unorm16x2,
+    Unorm16x4,
+    Snorm16x2,
+    Snorm16x4,
+    Float16x2,
+    Float16x4,
+    Float32,
     Float32x2,
+    Float32x3,
     Float32x4,
+    Uint32,
+    Uint32x2,
+    Uint32x3,
+    Uint32x4,
+    Sint32,
+    Sint32x2,
+    Sint32x3,
+    Sint32x4,
+}
+
+impl VertexFormat {
+    /// The size of the attribute in bytes.
+    pub const fn size(self) -> usize {
+        match self {
+            VertexFormat::Uint8x2
+            | VertexFormat::Sint8x2
+            | VertexFormat::Unorm8x2
+            | VertexFormat::Snorm8x2 => 2,
+            VertexFormat::Uint8x4
+            | VertexFormat::Sint8x4
+            | VertexFormat::Unorm8x4
+            | VertexFormat::Snorm8x4
+            | VertexFormat::Uint16x2
+            | VertexFormat::Sint16x2
+            | VertexFormat::Unorm16x2
+            | VertexFormat::Snorm16x2
+            | VertexFormat::Float16x2
+            | VertexFormat::Float32
+            | VertexFormat::Uint32
+            | VertexFormat::Sint32 => 4,
+            VertexFormat::Uint16x4
+            | VertexFormat::Sint16x4
+            | VertexFormat::Unorm16x4
+            | VertexFormat::Snorm16x4
+            | VertexFormat::Float16x4
+            | VertexFormat::Float32x2
+            | VertexFormat::Uint32x2
+            | VertexFormat::Sint32x2 => 8,
+            VertexFormat::Float32x3 | VertexFormat::Uint32x3 | VertexFormat::Sint32x3 => 12,
+            VertexFormat::Float32x4 | VertexFormat::Uint32x4 | VertexFormat::Sint32x4 => 16,
+        }
+    }
 }
 
 impl From<VertexFormat> for wgpu::VertexFormat {
     fn from(value: VertexFormat) -> Self {
         match value {
+            VertexFormat::Uint8x2 => wgpu::VertexFormat::Uint8x2,
+            VertexFormat::Uint8x4 => wgpu::VertexFormat::Uint8x4,
+            VertexFormat::Sint8x2 => wgpu::VertexFormat::Sint8x2,
+            VertexFormat::Sint8x4 => wgpu::VertexFormat::Sint8x4,
+            VertexFormat::Unorm8x2 => wgpu::VertexFormat::Unorm8x2,
+            VertexFormat::Unorm8x4 => wgpu::VertexFormat::Unorm8x4,
+            VertexFormat::Snorm8x2 => wgpu::VertexFormat::Snorm8x2,
+            VertexFormat::Snorm8x4 => wgpu::VertexFormat::Snorm8x4,
+            VertexFormat::Uint16x2 => wgpu::VertexFormat::Uint16x2,
+            VertexFormat::Uint16x4 => wgpu::VertexFormat::Uint16x4,
+            VertexFormat::Sint16x2 => wgpu::VertexFormat::Sint16x2,
+            VertexFormat::Sint16x4 => wgpu::VertexFormat::Sint16x4,
+            VertexFormat::Unorm16x2 => wgpu::VertexFormat::Unorm16x2,
+            VertexFormat::Unorm16x4 => wgpu::VertexFormat::Unorm16x4,
+            VertexFormat::Snorm16x2 => wgpu::VertexFormat::Snorm16x2,
+            VertexFormat::Snorm16x4 => wgpu::VertexFormat::Snorm16x4,
+            VertexFormat::Float16x2 => wgpu::VertexFormat::Float16x2,
+            VertexFormat::Float16x4 => wgpu::VertexFormat::Float16x4,
+            VertexFormat::Float32 => wgpu::VertexFormat::Float32,
             VertexFormat::Float32x2 => wgpu::VertexFormat::Float32x2,
+            VertexFormat::Float32x3 => wgpu::VertexFormat::Float32x3,
             VertexFormat::Float32x4 => wgpu::VertexFormat::Float32x4,
+            VertexFormat::Uint32 => wgpu::VertexFormat::Uint32,
+            VertexFormat::Uint32x2 => wgpu::VertexFormat::Uint32x2,
+            VertexFormat::Uint32x3 => wgpu::VertexFormat::Uint32x3,
+            VertexFormat::Uint32x4 => wgpu::VertexFormat::Uint32x4,
+            VertexFormat::Sint32 => wgpu::VertexFormat::Sint32,
+            VertexFormat::Sint32x2 => wgpu::VertexFormat::Sint32x2,
+            VertexFormat::Sint32x3 => wgpu::VertexFormat::Sint32x3,
+            VertexFormat::Sint32x4 => wgpu::VertexFormat::Sint32x4,
         }
     }
 }
 
+/// A type that can be stored in a vertex buffer. Usually implemented with [`impl_vertex!`].
+pub trait Vertex {
+    fn layout() -> VertexBufferLayoutDesc<'static>;
+}
+
+/// Implements [`Vertex`] for a `#[repr(C)]` struct, giving the format and shader location of each
+/// field. Offsets and the stride are taken from the struct itself. Fields that are not listed are
+/// not passed to the shader.
+///
+/// ```ignore
+/// #[repr(C)]
+/// struct Particle {
+///     position: [f32; 2],
+///     color: [u8; 4],
+/// }
+///
+/// impl_vertex!(Particle, Instance, {
+///     position: Float32x2 => 2,
+///     color: Unorm8x4 => 3,
+/// });
+/// ```
+#[macro_export]
+macro_rules! impl_vertex {
+    ($ty:ty, $buffer_type:ident, { $($field:ident: $format:ident => $location:expr),* $(,)? }) => {
+        impl $crate::Vertex for $ty {
+            fn layout() -> $crate::VertexBufferLayoutDesc<'static> {
+                const ATTRIBUTES: &[$crate::VertexAttribute] = &[$(
+                    $crate::VertexAttribute::new(
+                        $crate::VertexFormat::$format,
+                        ::std::mem::offset_of!($ty, $field),
+                        $location,
+                    )
+                ),*];
+
+                $crate::VertexBufferLayoutDesc::new(
+                    ::std::mem::size_of::<$ty>(),
+                    $crate::VertexBufferType::$buffer_type,
+                    ATTRIBUTES,
+                )
+            }
+        }
+    };
+}
+
 struct VertexBufferLayout {
     stride: u64,
     step_mode: wgpu::VertexStepMode,
@@ -442,12 +949,40 @@ pub struct Renderer {
     backbuffer_bgl: BindGroupLayoutId,
     #[allow(dead_code)]
     backbuffer_pl: PipelineLayoutId,
-    #[allow(dead_code)]
     backbuffer_shader: ShaderId,
     backbuffer_pipeline: RenderPipelineId,
+    #[allow(dead_code)]
+    present_shader: ShaderId,
+    error_shader: ShaderId,
+    #[allow(dead_code)]
+    crt_shader: ShaderId,
+    crt_pipeline: RenderPipelineId,
+    #[allow(dead_code)]
+    outline_shader: ShaderId,
+    outline_pipeline: RenderPipelineId,
+    crt_effect: Option<CrtEffect>,
+    ui_zoom: f32,
+    zoom_focus: [f32; 2],
+    blit_pipelines: [RenderPipelineId; BlendMode::ALL.len()],
+    blit_linear_sampler: SamplerId,
+    blit_nearest_sampler: SamplerId,
+    blit_bgs: HashMap<(TextureViewId, FilterMode), wgpu::BindGroup>, // Dropped with the view.
+    backbuffer_size: (u32, u32),
+    capabilities: GpuCapabilities,
+    quality: QualityPreset,
     geometry_vertex_buffer_layout: VertexBufferLayoutId,
+    pick_readback: PickReadback,
+    occlusion_readback: OcclusionReadback,
+    texture_readbacks: TextureReadbacks,
+    draw_stats: DrawStats,
+    flushed: (DrawStats, Option<PassStats>), // Of draws flushed since the last frame.
+    texture_binds: HashMap<BindGroupId, usize>, // Counted in debug builds only.
+    last_texture_binds: HashMap<BindGroupId, usize>,
+    frame_stats: FrameStats,
+    parallel_encoding: bool,
 
     bgs: GenVec<wgpu::BindGroup>,
+    resources: ResourceTracker,
     bgls: GenVec<wgpu::BindGroupLayout>,
     buffer_layouts: GenVec<VertexBufferLayout>,
     buffer_layout_attribs: GenVec<Vec<wgpu::VertexAttribute>>,
@@ -461,33 +996,51 @@ pub struct Renderer {
 }
 
 impl Renderer {
-    pub(crate) fn new() -> Result<Self, Error> {
-        let flags = if cfg!(debug_assertions) {
-            wgpu::InstanceFlags::DEBUG | wgpu::InstanceFlags::VALIDATION
-        } else {
-            wgpu::InstanceFlags::empty()
-        };
+    pub(crate) fn new(preference: &GpuPreference) -> Result<Self, Error> {
+        let instance = create_instance();
+        let required_features = wgpu::Features::PUSH_CONSTANTS;
 
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::VULKAN, //DX12,
-            flags,
-            ..Default::default()
-        });
+        let adapter = match preference {
+            GpuPreference::HighPerformance | GpuPreference::LowPower => {
+                let power_preference = match preference {
+                    GpuPreference::LowPower => wgpu::PowerPreference::LowPower,
+                    _ => wgpu::PowerPreference::HighPerformance,
+                };
+                pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference,
+                    force_fallback_adapter: false,
+                    compatible_surface: None,
+                }))
+            }
+            GpuPreference::ByName(name) => {
+                let name = name.to_lowercase();
+                instance
+                    .enumerate_adapters(BACKENDS)
+                    .into_iter()
+                    .filter(|adapter| adapter.features().contains(required_features))
+                    .find(|adapter| adapter.get_info().name.to_lowercase().contains(&name))
+            }
+        };
 
-        let adapter =
-            match pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                force_fallback_adapter: false,
-                compatible_surface: None,
-            })) {
-                Some(adapter) => adapter,
-                None => {
-                    return Err("failed to get graphics adapter".into());
-                }
-            };
+        let adapter = match adapter {
+            Some(adapter) => adapter,
+            None => {
+                return Err(Error::new(format!(
+                    "failed to get graphics adapter matching preference {:?}",
+                    preference
+                ))
+                .with_kind(ErrorKind::Gpu));
+            }
+        };
 
-        let required_features = wgpu::Features::PUSH_CONSTANTS;
-        assert!(adapter.features().contains(required_features));
+        if !adapter.features().contains(required_features) {
+            return Err(Error::new(format!(
+                "graphics adapter '{}' does not support the required features {:?}",
+                adapter.get_info().name,
+                required_features
+            ))
+            .with_kind(ErrorKind::Gpu));
+        }
 
         let required_limits = wgpu::Limits {
             max_push_constant_size: 128,
@@ -517,11 +1070,15 @@ impl Renderer {
         )) {
             Ok((device, queue)) => (device, queue),
             Err(_) => {
-                return Err("failed to get graphics queue".into());
+                return Err(Error::new("failed to get graphics queue").with_kind(ErrorKind::Gpu));
             }
         };
 
+        let capabilities = GpuCapabilities::probe(&adapter);
+        let quality = QualityPreset::recommended(&capabilities);
         let belt = wgpu::util::StagingBelt::new(1024);
+        let pick_readback = PickReadback::new(&device);
+        let occlusion_readback = OcclusionReadback::new(&device);
 
         let mut renderer = Self {
             instance,
@@ -534,9 +1091,35 @@ impl Renderer {
             backbuffer_pl: PipelineLayoutId::INVALID,
             backbuffer_shader: ShaderId::INVALID,
             backbuffer_pipeline: RenderPipelineId::INVALID,
+            present_shader: ShaderId::INVALID,
+            error_shader: ShaderId::INVALID,
+            crt_shader: ShaderId::INVALID,
+            crt_pipeline: RenderPipelineId::INVALID,
+            outline_shader: ShaderId::INVALID,
+            outline_pipeline: RenderPipelineId::INVALID,
+            crt_effect: None,
+            ui_zoom: 1.0,
+            zoom_focus: [0.5, 0.5],
+            blit_pipelines: [RenderPipelineId::INVALID; BlendMode::ALL.len()],
+            blit_linear_sampler: SamplerId::INVALID,
+            blit_nearest_sampler: SamplerId::INVALID,
+            blit_bgs: HashMap::new(),
+            backbuffer_size: (0, 0),
+            capabilities,
+            quality,
             geometry_vertex_buffer_layout: VertexBufferLayoutId::INVALID,
+            pick_readback,
+            occlusion_readback,
+            texture_readbacks: TextureReadbacks::default(),
+            draw_stats: DrawStats::default(),
+            flushed: Default::default(),
+            texture_binds: HashMap::new(),
+            last_texture_binds: HashMap::new(),
+            frame_stats: FrameStats::default(),
+            parallel_encoding: false,
 
             bgs: GenVec::default(),
+            resources: ResourceTracker::default(),
             bgls: GenVec::default(),
             buffer_layouts: GenVec::default(),
             buffer_layout_attribs: GenVec::default(),
@@ -549,6 +1132,11 @@ impl Renderer {
             texture_views: GenVec::default(),
         };
 
+        renderer.error_shader = renderer.create_shader(ShaderDesc {
+            label: Some("error"),
+            source: include_str!("error.wgsl"),
+        });
+
         renderer.backbuffer_bgl = renderer.create_bind_group_layout(&BindGroupLayoutDesc {
             label: Some("backbuffer"),
             entries: &[
@@ -569,23 +1157,107 @@ impl Renderer {
             source: include_str!("backbuffer.wgsl"),
         });
 
+        renderer.present_shader = renderer.create_shader(ShaderDesc {
+            label: Some("present"),
+            source: include_str!("present.wgsl"),
+        });
+
         renderer.backbuffer_pipeline = renderer.create_render_pipeline(&RenderPipelineDesc {
             label: Some("backbuffer"),
             layout: renderer.backbuffer_pl,
-            shader: renderer.backbuffer_shader,
+            shader: renderer.present_shader,
+            vs_main: "vs_main",
+            fs_main: "fs_main",
+            buffers: &[],
+            // todo: How do we get this from the surface, which is created later when resume is called?
+            color_targets: &[ColorTargetDesc::new(
+                TextureFormat::Bgra8Unorm,
+                BlendMode::Replace,
+            )],
+        });
+
+        renderer.crt_shader = renderer.create_shader(ShaderDesc {
+            label: Some("crt"),
+            source: include_str!("crt.wgsl"),
+        });
+
+        renderer.crt_pipeline = renderer.create_render_pipeline(&RenderPipelineDesc {
+            label: Some("crt"),
+            layout: renderer.backbuffer_pl,
+            shader: renderer.crt_shader,
+            vs_main: "vs_main",
+            fs_main: "fs_main",
+            buffers: &[],
+            color_targets: &[ColorTargetDesc::new(
+                TextureFormat::Bgra8Unorm,
+                BlendMode::Replace,
+            )],
+        });
+
+        renderer.outline_shader = renderer.create_shader(ShaderDesc {
+            label: Some("outline"),
+            source: include_str!("outline.wgsl"),
+        });
+
+        renderer.outline_pipeline = renderer.create_render_pipeline(&RenderPipelineDesc {
+            label: Some("outline"),
+            layout: renderer.backbuffer_pl,
+            shader: renderer.outline_shader,
             vs_main: "vs_main",
             fs_main: "fs_main",
             buffers: &[],
-            color_target_format: TextureFormat::Bgra8Unorm, // todo: How do we get this from the surface, which is created later when resume is called?
+            color_targets: &[ColorTargetDesc::new(
+                TextureFormat::Rgba8Unorm,
+                BlendMode::Replace,
+            )],
         });
 
+        // Blits sample the source across the whole of the destination, converting between formats.
+        for blend in BlendMode::ALL {
+            renderer.blit_pipelines[blend as usize] =
+                renderer.create_render_pipeline(&RenderPipelineDesc {
+                    label: Some("blit"),
+                    layout: renderer.backbuffer_pl,
+                    shader: renderer.backbuffer_shader,
+                    vs_main: "vs_main",
+                    fs_main: "fs_main",
+                    buffers: &[],
+                    color_targets: &[ColorTargetDesc::new(TextureFormat::Rgba8Unorm, blend)],
+                });
+        }
+
+        for filter in [FilterMode::Linear, FilterMode::Nearest] {
+            let sampler = renderer.create_sampler(&SamplerDesc {
+                label: Some("blit"),
+                address_mode_u: AddressMode::ClampToEdge,
+                address_mode_v: AddressMode::ClampToEdge,
+                mag_filter: filter,
+                min_filter: filter,
+            });
+            match filter {
+                FilterMode::Linear => renderer.blit_linear_sampler = sampler,
+                FilterMode::Nearest => renderer.blit_nearest_sampler = sampler,
+            }
+        }
+
         renderer.geometry_vertex_buffer_layout =
             renderer.create_vertex_buffer_layout(&GeometryVertex::layout());
 
         Ok(renderer)
     }
 
+    /// The graphics adapters available on this system, which can be chosen between with
+    /// [`GpuPreference::ByName`].
+    pub fn available_adapters() -> Vec<GpuCapabilities> {
+        create_instance()
+            .enumerate_adapters(BACKENDS)
+            .iter()
+            .map(GpuCapabilities::probe)
+            .collect()
+    }
+
     pub(crate) fn create_backbuffer(&mut self, width: u32, height: u32) -> Backbuffer {
+        self.backbuffer_size = (width, height);
         Backbuffer::new(
             width,
             height,
@@ -595,6 +1267,23 @@ impl Renderer {
         )
     }
 
+    /// The size of the texture that is drawn to the window, in pixels.
+    pub(crate) fn backbuffer_size(&self) -> (u32, u32) {
+        self.backbuffer_size
+    }
+
+    /// A bind group for drawing the outline mask `view` over the backbuffer.
+    pub(crate) fn create_outline_bind_group(&mut self, view: TextureViewId) -> BindGroupId {
+        self.create_bind_group(&BindGroupDesc {
+            label: Some("outline"),
+            layout: self.backbuffer_bgl,
+            resources: &[
+                BindingResource::Sampler(self.blit_nearest_sampler),
+                BindingResource::TextureView(view),
+            ],
+        })
+    }
+
     pub fn create_bind_group(&mut self, desc: &BindGroupDesc) -> BindGroupId {
         let layout = &self.bgls[desc.layout.0];
         let entries = desc
@@ -623,7 +1312,10 @@ impl Renderer {
             entries: &entries,
         });
 
-        BindGroupId(self.bgs.add(bg))
+        let id = BindGroupId(self.bgs.add(bg));
+        self.resources
+            .created(ResourceKind::BindGroup, id.0, desc.label);
+        id
     }
 
     pub fn create_bind_group_layout(&mut self, desc: &BindGroupLayoutDesc) -> BindGroupLayoutId {
@@ -653,11 +1345,14 @@ impl Renderer {
         let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
             label: desc.label,
             size: desc.size as u64,
-            usage: wgpu::BufferUsages::COPY_DST | desc.usage.into(),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC | desc.usage.into(),
             mapped_at_creation: false,
         });
 
-        BufferId(self.buffers.add(buffer))
+        let id = BufferId(self.buffers.add(buffer));
+        self.resources
+            .created(ResourceKind::Buffer, id.0, desc.label);
+        id
     }
 
     pub fn create_pipeline_layout(&mut self, desc: &PipelineLayoutDesc) -> PipelineLayoutId {
@@ -695,6 +1390,18 @@ impl Renderer {
             })
             .collect::<Vec<_>>();
 
+        let targets = desc
+            .color_targets
+            .iter()
+            .map(|target| {
+                Some(wgpu::ColorTargetState {
+                    format: target.format.into(),
+                    blend: target.blend.into(),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })
+            })
+            .collect::<Vec<_>>();
+
         let pipeline = self
             .device
             .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
@@ -721,11 +1428,7 @@ impl Renderer {
                 fragment: Some(wgpu::FragmentState {
                     module: &self.shaders[desc.shader.0],
                     entry_point: desc.fs_main,
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: desc.color_target_format.into(),
-                        blend: None,
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
+                    targets: &targets,
                 }),
                 multiview: None,
             });
@@ -746,15 +1449,56 @@ impl Renderer {
         SamplerId(self.samplers.add(sampler))
     }
 
+    /// Compiles a shader, panicking if it has errors. In debug builds the errors are printed
+    /// instead and a shader that draws geometry in magenta is returned in its place, so that
+    /// mistakes made while iterating on a shader don't crash the game. The replacement has
+    /// `vs_main` and `fs_main` entry points, with vertex positions at location 0.
     pub fn create_shader(&mut self, desc: ShaderDesc) -> ShaderId {
+        match self.try_create_shader(desc) {
+            Ok(shader) => shader,
+            Err(err) if cfg!(debug_assertions) && self.error_shader != ShaderId::INVALID => {
+                match std::error::Error::source(&err) {
+                    Some(src) => eprintln!("{err}\n{src}"),
+                    None => eprintln!("{err}"),
+                }
+                self.error_shader
+            }
+            Err(err) => match std::error::Error::source(&err) {
+                Some(src) => panic!("{err}\n{src}"),
+                None => panic!("{err}"),
+            },
+        }
+    }
+
+    /// Compiles a shader, returning an error with the location of the first problem in the
+    /// source if it does not compile.
+    pub fn try_create_shader(&mut self, desc: ShaderDesc) -> Result<ShaderId, Error> {
+        let label = desc.label.unwrap_or("shader");
+        ShaderError::check(desc.source, label).map_err(|err| {
+            Error::new(format!(
+                "failed to compile {} at {}:{}",
+                label, err.line, err.column
+            ))
+            .with_kind(ErrorKind::Asset)
+            .with_source(err)
+        })?;
+
+        // Anything naga accepts that the device does not, e.g. an unsupported feature, is caught
+        // here rather than by the device's uncaptured error handler.
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
         let shader = self
             .device
             .create_shader_module(wgpu::ShaderModuleDescriptor {
                 label: desc.label,
                 source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(desc.source)),
             });
+        if let Some(err) = pollster::block_on(self.device.pop_error_scope()) {
+            return Err(Error::new(format!("failed to compile {}", label))
+                .with_kind(ErrorKind::Gpu)
+                .with_source(err));
+        }
 
-        ShaderId(self.shaders.add(shader))
+        Ok(ShaderId(self.shaders.add(shader)))
     }
 
     pub fn create_texture(&mut self, desc: &TextureDesc) -> TextureId {
@@ -770,12 +1514,16 @@ impl Renderer {
             dimension: wgpu::TextureDimension::D2,
             format: desc.format.into(), // todo: can we use srgb?
             usage: wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::COPY_SRC
                 | wgpu::TextureUsages::RENDER_ATTACHMENT
                 | wgpu::TextureUsages::TEXTURE_BINDING,
             view_formats: &[], // todo: srgb?
         });
 
-        TextureId(self.textures.add(texture))
+        let id = TextureId(self.textures.add(texture));
+        self.resources
+            .created(ResourceKind::Texture, id.0, desc.label);
+        id
     }
 
     pub fn create_texture_view(&mut self, desc: &TextureViewDesc) -> TextureViewId {
@@ -816,88 +1564,165 @@ impl Renderer {
         }))
     }
 
+    pub fn destroy_bind_group(&mut self, bind_group: BindGroupId) {
+        self.bgs.remove(bind_group.0);
+        self.resources
+            .destroyed(ResourceKind::BindGroup, bind_group.0);
+    }
+
+    /// Moves the bind group `with` into the place of `bind_group`, destroying the bind group that
+    /// was there, so that everything holding `bind_group` binds the new one.
+    pub(crate) fn replace_bind_group(&mut self, bind_group: BindGroupId, with: BindGroupId) {
+        if let Some(replacement) = self.bgs.remove(with.0) {
+            self.bgs[bind_group.0] = replacement;
+        }
+        self.resources.destroyed(ResourceKind::BindGroup, with.0);
+    }
+
+    pub fn destroy_buffer(&mut self, buffer: BufferId) {
+        if let Some(wgpu_buffer) = self.buffers.remove(buffer.0) {
+            wgpu_buffer.destroy();
+        }
+        self.resources.destroyed(ResourceKind::Buffer, buffer.0);
+    }
+
+    pub fn destroy_sampler(&mut self, sampler: SamplerId) {
+        self.samplers.remove(sampler.0);
+    }
+
+    pub fn destroy_texture(&mut self, texture: TextureId) {
+        if let Some(wgpu_texture) = self.textures.remove(texture.0) {
+            wgpu_texture.destroy();
+        }
+        self.resources.destroyed(ResourceKind::Texture, texture.0);
+    }
+
+    pub fn destroy_texture_view(&mut self, texture_view: TextureViewId) {
+        self.texture_views.remove(texture_view.0);
+        self.blit_bgs.retain(|(view, _), _| *view != texture_view);
+    }
+
     pub fn geometry_vertex_buffer_layout(&self) -> VertexBufferLayoutId {
         self.geometry_vertex_buffer_layout
     }
 
+    pub fn get_crt_effect(&self) -> Option<CrtEffect> {
+        self.crt_effect
+    }
+
+    /// Applies a CRT effect when the backbuffer is presented to the window, or disables it when
+    /// `None` is given.
+    pub fn set_crt_effect(&mut self, effect: Option<CrtEffect>) {
+        self.crt_effect = effect;
+    }
+
+    pub fn get_ui_zoom(&self) -> f32 {
+        self.ui_zoom
+    }
+
+    /// Magnifies everything drawn to the window by `zoom`, which is at least `1.0`, around the
+    /// mouse cursor. The content under the cursor stays under it, so mouse positions need no
+    /// adjustment and games don't have to change their views.
+    pub fn set_ui_zoom(&mut self, zoom: f32) {
+        self.ui_zoom = zoom.max(1.0);
+    }
+
+    /// Sets the point that the UI zoom magnifies around, as a fraction of the window size.
+    pub(crate) fn set_zoom_focus(&mut self, x: f32, y: f32) {
+        self.zoom_focus = [x.clamp(0.0, 1.0), y.clamp(0.0, 1.0)];
+    }
+
+    /// What the graphics adapter is capable of. Present modes are only known once the window
+    /// surface has been created.
+    pub fn gpu_capabilities(&self) -> &GpuCapabilities {
+        &self.capabilities
+    }
+
+    /// The largest width or height, in pixels, that a texture can have on the graphics device.
+    pub fn max_texture_size(&self) -> u32 {
+        self.device.limits().max_texture_dimension_2d
+    }
+
+    pub(crate) fn probe_surface(&mut self, surface: &Surface) {
+        if let Some(s) = surface.s.as_ref() {
+            self.capabilities.present_modes = s
+                .get_capabilities(&self.adapter)
+                .present_modes
+                .into_iter()
+                .map(PresentMode::from)
+                .collect();
+        }
+    }
+
+    pub fn get_quality(&self) -> QualityPreset {
+        self.quality
+    }
+
+    /// Sets the quality preset, which defaults to the one recommended for the graphics adapter.
+    pub fn set_quality(&mut self, quality: QualityPreset) {
+        self.quality = quality;
+    }
+
+    /// Submits the frame and presents the backbuffer. The backbuffer is read back under
+    /// `backbuffer_readback` once everything has been drawn to it, outlines included.
     pub(crate) fn submit(
         &mut self,
         data: RenderData,
-        buf: CommandBuffer,
+        mut buf: CommandBuffer,
         backbuffer: &Backbuffer,
+        backbuffer_readback: Option<TextureReadbackId>,
         surface: &mut Surface,
     ) {
         // This could all be done on a background thread.
 
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("submit"),
-            });
-
-        // todo: can we pass staging belt to graphics rather than clone twice?
-        self.belt
-            .write_buffer(
-                &mut encoder,
-                &self.buffers[data.dest.0],
-                0,
-                NonZeroU64::new(data.size as u64).unwrap(),
-                &self.device,
-            )
-            .clone_from_slice(&data.data);
+        let started = Instant::now();
+        self.pick_readback.poll(&self.device);
+        self.occlusion_readback.poll(&self.device);
+        self.texture_readbacks.poll(&self.device);
 
-        let mut draw_offset = 0;
-        for pass in buf.passes.iter() {
-            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &self.texture_views[pass.target.0],
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: match pass.clear_color {
-                            Some(color) => wgpu::LoadOp::Clear(color.into()),
-                            None => wgpu::LoadOp::Load,
-                        },
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
+        let (mut command_buffers, mut encoder, mut stats, slowest_pass) =
+            self.encode_draws(data, &mut buf);
+        let flushed = std::mem::take(&mut self.flushed);
+        stats += flushed.0;
+        let slowest_pass = slowest(slowest_pass, flushed.1);
 
-            for draw in &buf.draws[draw_offset..pass.draw_count] {
-                rpass.set_pipeline(&self.render_pipelines[draw.pipeline.0]);
-                rpass.set_vertex_buffer(0, self.buffers[draw.vbo.0].slice(..));
-                rpass.set_bind_group(0, &self.bgs[draw.globals_bg.0], &[]);
-                rpass.set_index_buffer(
-                    self.buffers[draw.ibo.0].slice(..),
-                    wgpu::IndexFormat::Uint16,
-                );
-                rpass.set_push_constants(
-                    // todo: can we move push constant to Graphics so that not all pipelines are aware of it?
-                    wgpu::ShaderStages::VERTEX_FRAGMENT,
-                    0,
-                    cast_slice(&[PushConstantBuffer {
-                        color: draw.color.to_array_f32(), // todo: Can we create all the push constant buffers ahead of time? Benefit?
-                        model: draw.model.to_cols_array(),
-                        globals_idx: draw.globals_idx as u32,
-                    }]),
-                );
-                rpass.draw_indexed(0..draw.index_count as u32, 0, 0..1);
+        if let Some(ref outline) = buf.outline {
+            self.encode_outline_pass(&mut encoder, outline, backbuffer.texture_view, &mut stats);
+        }
+        if let Some(id) = backbuffer_readback {
+            let src = backbuffer.texture;
+            let size = self.textures[src.0].size();
+            self.texture_readbacks
+                .create(&self.device, id, (size.width, size.height));
+            self.encode_texture_op(&mut encoder, &TextureOp::Read { src, id });
+        }
+        let read_pick = match buf.pick {
+            Some(ref pick) => self.encode_pick_pass(&mut encoder, pick, &mut stats),
+            None => false,
+        };
+        self.draw_stats = stats;
+        self.last_texture_binds = std::mem::take(&mut self.texture_binds);
+        let recreated = self.resources.end_frame();
+        if cfg!(debug_assertions) {
+            for warning in recreated {
+                eprintln!("{warning}");
             }
-            draw_offset += pass.draw_count;
         }
-
-        let view = surface.acquire();
-        {
+        let read_occlusion = self
+            .occlusion_readback
+            .resolve(&mut encoder, &buf.occlusion_queries);
+
+        let acquiring = Instant::now();
+        let view = surface.acquire(&self.device);
+        let acquire = acquiring.elapsed();
+        if let Some(view) = view {
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                     view: &view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(Color::BLUE.into()),
+                        load: wgpu::LoadOp::Clear(surface.clear_color.into()),
                         store: wgpu::StoreOp::Store,
                     },
                 })],
@@ -906,22 +1731,761 @@ impl Renderer {
                 occlusion_query_set: None,
             });
 
-            rpass.set_pipeline(&self.render_pipelines[self.backbuffer_pipeline.0]);
+            let crt = self.crt_effect.and_then(|crt| self.quality.apply(crt));
+            match crt {
+                Some(_) => rpass.set_pipeline(&self.render_pipelines[self.crt_pipeline.0]),
+                None => rpass.set_pipeline(&self.render_pipelines[self.backbuffer_pipeline.0]),
+            }
+            let constants = PresentConstants {
+                crt: crt.unwrap_or_default(),
+                region: zoom_region(self.ui_zoom, self.zoom_focus),
+            };
+            rpass.set_push_constants(
+                wgpu::ShaderStages::VERTEX_FRAGMENT,
+                0,
+                cast_slice(&[constants]),
+            );
             rpass.set_bind_group(0, &self.bgs[backbuffer.bg.0], &[]);
             rpass.draw(0..3, 0..1);
         }
 
         self.belt.finish();
-        self.queue.submit([encoder.finish()]);
+        command_buffers.push(encoder.finish());
+        let encode = started.elapsed() - acquire;
+        let submitting = Instant::now();
+        self.queue.submit(command_buffers);
         self.belt.recall();
-    }
+        self.frame_stats = FrameStats {
+            encode,
+            submit: submitting.elapsed(),
+            present: acquire,
+            slowest_pass,
+            ..Default::default()
+        };
+
+        if read_pick {
+            self.pick_readback.map();
+        }
+        if read_occlusion {
+            self.occlusion_readback.map();
+        }
+        self.texture_readbacks.map();
+    }
+
+    /// Submits the draws recorded so far this frame, without presenting them. Their draw stats
+    /// are added to those of the frame when it is submitted.
+    pub(crate) fn flush(&mut self, data: RenderData, mut buf: CommandBuffer) {
+        let (mut command_buffers, encoder, stats, slowest_pass) = self.encode_draws(data, &mut buf);
+        self.belt.finish();
+        command_buffers.push(encoder.finish());
+        self.queue.submit(command_buffers);
+        self.belt.recall();
+
+        self.flushed.0 += stats;
+        self.flushed.1 = slowest(self.flushed.1, slowest_pass);
+        self.texture_readbacks.map();
+    }
+
+    /// Encodes the render passes of `buf` along with its texture operations, returning the
+    /// encoded command buffers and an encoder for anything to be submitted after them.
+    fn encode_draws(
+        &mut self,
+        data: RenderData,
+        buf: &mut CommandBuffer,
+    ) -> (
+        Vec<wgpu::CommandBuffer>,
+        wgpu::CommandEncoder,
+        DrawStats,
+        Option<PassStats>,
+    ) {
+        for (_, op) in &buf.ops {
+            match *op {
+                TextureOp::Read { src, id } => {
+                    let size = self.textures[src.0].size();
+                    self.texture_readbacks
+                        .create(&self.device, id, (size.width, size.height));
+                }
+                // Blits such as the light map's happen every frame, so their bind groups are
+                // created once and kept until the view is destroyed.
+                TextureOp::Blit { src, filter, .. } => self.create_blit_bind_group(src, filter),
+                TextureOp::Copy { .. } => {}
+            }
+        }
+
+        for pass in &buf.passes {
+            sort_draws(&mut buf.draws[pass.draws.clone()]);
+        }
+        if cfg!(debug_assertions) {
+            count_texture_binds(buf, &mut self.texture_binds);
+        }
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("submit"),
+            });
+
+        // todo: can we pass staging belt to graphics rather than clone twice?
+        // There are no views to write when nothing has been drawn yet, e.g. while loading.
+        if let Some(size) = NonZeroU64::new(data.size as u64) {
+            let buffer = &mut self.buffers[data.dest.0];
+            if buffer.size() < size.get() {
+                // Each view drawn with this frame takes a slot, so grow to fit them all and point
+                // the bind group at the new buffer, keeping its id for the recorded draws.
+                *buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("globals"),
+                    size: size.get().next_power_of_two(),
+                    usage: buffer.usage(),
+                    mapped_at_creation: false,
+                });
+                self.bgs[data.globals_bg.0] =
+                    self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: Some("globals"),
+                        layout: &self.bgls[data.globals_bgl.0],
+                        entries: &[wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: self.buffers[data.dest.0].as_entire_binding(),
+                        }],
+                    });
+            }
+            self.belt
+                .write_buffer(
+                    &mut encoder,
+                    &self.buffers[data.dest.0],
+                    0,
+                    size,
+                    &self.device,
+                )
+                .clone_from_slice(&data.data);
+        }
+        if let Some(size) = NonZeroU64::new(data.vertices.len() as u64) {
+            let buffer = &mut self.buffers[data.vertices_dest.0];
+            if buffer.size() < size.get() {
+                // Draws already submitted keep the old buffer alive until they are done with it.
+                *buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("deformed vertices"),
+                    size: size.get().next_power_of_two(),
+                    usage: buffer.usage(),
+                    mapped_at_creation: false,
+                });
+            }
+            self.belt
+                .write_buffer(
+                    &mut encoder,
+                    &self.buffers[data.vertices_dest.0],
+                    0,
+                    size,
+                    &self.device,
+                )
+                .clone_from_slice(&data.vertices);
+        }
+
+        // Each group of passes is encoded into its own command buffer, in parallel if enabled.
+        // The buffers are submitted in order, so passes still see the results of earlier ones.
+        let buf = &*buf;
+        let groups = pass_groups(&buf.passes);
+        let encoded = if self.parallel_encoding && groups.len() > 1 {
+            std::thread::scope(|scope| {
+                let handles = groups
+                    .iter()
+                    .map(|group| scope.spawn(|| self.encode_passes(group.clone(), buf)))
+                    .collect::<Vec<_>>();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().expect("encoding thread panicked"))
+                    .collect::<Vec<_>>()
+            })
+        } else {
+            groups
+                .iter()
+                .map(|group| self.encode_passes(group.clone(), buf))
+                .collect()
+        };
+        let mut command_buffers = vec![encoder.finish()];
+        let mut stats = DrawStats::default();
+        let mut slowest_pass: Option<PassStats> = None;
+        for (command_buffer, group_stats, group_slowest) in encoded {
+            command_buffers.push(command_buffer);
+            stats += group_stats;
+            slowest_pass = slowest(slowest_pass, group_slowest);
+        }
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("submit"),
+            });
+
+        for (_, op) in buf
+            .ops
+            .iter()
+            .filter(|(before, _)| *before == buf.passes.len())
+        {
+            self.encode_texture_op(&mut encoder, op);
+        }
+
+        (command_buffers, encoder, stats, slowest_pass)
+    }
+
+    /// Encodes the render passes in `passes`, along with the texture operations recorded before
+    /// each of them.
+    fn encode_passes(
+        &self,
+        passes: Range<usize>,
+        buf: &CommandBuffer,
+    ) -> (wgpu::CommandBuffer, DrawStats, Option<PassStats>) {
+        let mut stats = DrawStats::default();
+        let mut slowest_pass: Option<PassStats> = None;
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("passes"),
+            });
+
+        for i in passes {
+            let started = Instant::now();
+            let pass = &buf.passes[i];
+            for (_, op) in buf.ops.iter().filter(|(before, _)| *before == i) {
+                self.encode_texture_op(&mut encoder, op);
+            }
+            let color_attachments = pass
+                .target
+                .color_targets()
+                .iter()
+                .map(|target| {
+                    Some(wgpu::RenderPassColorAttachment {
+                        view: &self.texture_views[target.texture_view.0],
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: match target.clear_color {
+                                Some(color) => wgpu::LoadOp::Clear(color.into()),
+                                None => wgpu::LoadOp::Load,
+                            },
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let draws = &buf.draws[pass.draws.clone()];
+            let queried = draws.iter().any(|draw| draw.occlusion_query.is_some());
+
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &color_attachments,
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: queried.then_some(&self.occlusion_readback.query_set),
+            });
+
+            let mut query = None;
+            let mut state = DrawState::default();
+            for draw in draws {
+                if draw.occlusion_query != query {
+                    if query.is_some() {
+                        rpass.end_occlusion_query();
+                    }
+                    if let Some(slot) = draw.occlusion_query {
+                        rpass.begin_occlusion_query(slot);
+                    }
+                    query = draw.occlusion_query;
+                }
+                self.encode_draw(&mut rpass, draw, &mut state, &mut stats);
+            }
+            if query.is_some() {
+                rpass.end_occlusion_query();
+            }
+
+            // The pass is only encoded once it is dropped.
+            drop(rpass);
+            let encode = started.elapsed();
+            if slowest_pass.is_none_or(|slowest| encode > slowest.encode) {
+                slowest_pass = Some(PassStats {
+                    index: i,
+                    draws: draws.len(),
+                    encode,
+                });
+            }
+        }
+
+        (encoder.finish(), stats, slowest_pass)
+    }
+
+    /// Draws the outlined draws of this frame into the outline mask, then draws the outlines
+    /// around them onto `target`.
+    fn encode_outline_pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        outline: &OutlinePass,
+        target: TextureViewId,
+        stats: &mut DrawStats,
+    ) {
+        if outline.draws.is_empty() {
+            return;
+        }
+
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("outline mask"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.texture_views[outline.view.0],
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            let mut state = DrawState::default();
+            for draw in outline.draws.iter() {
+                self.encode_draw(&mut rpass, draw, &mut state, stats);
+            }
+        }
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("outline"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.texture_views[target.0],
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rpass.set_pipeline(&self.render_pipelines[self.outline_pipeline.0]);
+        rpass.set_bind_group(0, &self.bgs[outline.bg.0], &[]);
+        rpass.draw(0..3, 0..1);
+    }
+
+    /// Draws the pickable draws of this frame into the id buffer, then copies the id under the
+    /// requested pick position for reading back, returning whether a copy was made.
+    fn encode_pick_pass(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        pick: &PickPass,
+        stats: &mut DrawStats,
+    ) -> bool {
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("pick"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.texture_views[pick.view.0],
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            let mut state = DrawState::default();
+            for draw in pick.draws.iter() {
+                self.encode_draw(&mut rpass, draw, &mut state, stats);
+            }
+        }
+
+        let (x, y) = match pick.read_at {
+            Some(position) => position,
+            None => return false,
+        };
+        if self.pick_readback.in_flight || x >= pick.size.0 || y >= pick.size.1 {
+            return false;
+        }
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.textures[pick.texture.0],
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.pick_readback.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        true
+    }
+
+    /// Encodes `draw`, only setting the state that differs from the previous draw in the pass.
+    fn encode_draw<'pass>(
+        &'pass self,
+        rpass: &mut wgpu::RenderPass<'pass>,
+        draw: &DrawCommand,
+        state: &mut DrawState,
+        stats: &mut DrawStats,
+    ) {
+        // Bind groups and buffers stay bound when the pipeline changes.
+        if state.pipeline != Some(draw.pipeline) {
+            rpass.set_pipeline(&self.render_pipelines[draw.pipeline.0]);
+            state.pipeline = Some(draw.pipeline);
+            stats.pipeline_switches += 1;
+        }
+        if state.vbo != Some(draw.vbo) || state.ibo != Some(draw.ibo) {
+            rpass.set_vertex_buffer(0, self.buffers[draw.vbo.0].slice(..));
+            rpass.set_index_buffer(
+                self.buffers[draw.ibo.0].slice(..),
+                wgpu::IndexFormat::Uint16,
+            );
+            state.vbo = Some(draw.vbo);
+            state.ibo = Some(draw.ibo);
+            stats.buffer_switches += 1;
+        }
+        if let Some((instances, _)) = draw.instances {
+            if state.instances != Some(instances) {
+                rpass.set_vertex_buffer(1, self.buffers[instances.0].slice(..));
+                state.instances = Some(instances);
+                stats.buffer_switches += 1;
+            }
+        }
+        if state.globals_bg != Some(draw.globals_bg) {
+            rpass.set_bind_group(0, &self.bgs[draw.globals_bg.0], &[]);
+            state.globals_bg = Some(draw.globals_bg);
+            stats.bind_group_switches += 1;
+        }
+        if state.texture_bg != Some(draw.texture_bg) {
+            rpass.set_bind_group(1, &self.bgs[draw.texture_bg.0], &[]);
+            state.texture_bg = Some(draw.texture_bg);
+            stats.bind_group_switches += 1;
+            stats.texture_binds += 1;
+        }
+        stats.draw_calls += 1;
+
+        rpass.set_push_constants(
+            // todo: can we move push constant to Graphics so that not all pipelines are aware of it?
+            wgpu::ShaderStages::VERTEX_FRAGMENT,
+            0,
+            cast_slice(&[PushConstantBuffer {
+                color: draw.color.to_array_f32(), // todo: Can we create all the push constant buffers ahead of time? Benefit?
+                model: draw.model.to_cols_array(),
+                globals_idx: draw.globals_idx as u32,
+                pick_id: draw.pick_id,
+            }]),
+        );
+        let instance_count = draw.instances.map_or(1, |(_, count)| count);
+        rpass.draw_indexed(
+            0..draw.index_count as u32,
+            draw.base_vertex,
+            0..instance_count,
+        );
+    }
+
+    /// The id of the pickable draw under the most recently read back pick position, or `None`
+    /// if nothing pickable was drawn there.
+    pub fn last_pick(&self) -> Option<u32> {
+        self.pick_readback.last
+    }
+
+    /// The pixels of a texture read back with [`Graphics::read_texture`](crate::Graphics::read_texture),
+    /// once they have arrived from the GPU a frame or more after the request. Each result can
+    /// only be taken once.
+    pub fn take_texture_readback(&mut self, id: TextureReadbackId) -> Option<Image> {
+        self.texture_readbacks.finished.remove(&id)
+    }
+
+    /// Blocks until every texture readback submitted so far has arrived from the GPU, e.g. after
+    /// [`Engine::flush`](crate::Engine::flush) to read back a texture within the same frame.
+    pub fn wait_for_texture_readbacks(&mut self) {
+        self.device.poll(wgpu::Maintain::Wait);
+        self.texture_readbacks.poll(&self.device);
+    }
+
+    /// A report of the textures, buffers and bind groups that have not been destroyed, counted by
+    /// label, if there are any.
+    pub(crate) fn live_resources_report(&self) -> Option<String> {
+        self.resources.live_report()
+    }
+
+    /// Leaves the resources that are live now, which belong to the engine, out of
+    /// [`Renderer::live_resources_report`].
+    pub(crate) fn mark_engine_resources(&mut self) {
+        self.resources.ignore_live();
+    }
+
+    /// When enabled, the render passes for each draw target are encoded on their own thread.
+    pub(crate) fn set_parallel_encoding(&mut self, enabled: bool) {
+        self.parallel_encoding = enabled;
+    }
+
+    /// The draw calls made and state changed to draw the last frame.
+    pub fn draw_stats(&self) -> DrawStats {
+        self.draw_stats
+    }
+
+    /// The labels of the texture bind groups bound most often in the last frame, with how often
+    /// they were bound, most first. Only counted in debug builds.
+    pub(crate) fn most_bound_textures(&self, count: usize) -> Vec<(String, usize)> {
+        let mut by_label = HashMap::<&str, usize>::new();
+        for (id, binds) in &self.last_texture_binds {
+            let label = self
+                .resources
+                .label(ResourceKind::BindGroup, id.0)
+                .unwrap_or("destroyed");
+            *by_label.entry(label).or_default() += binds;
+        }
+
+        let mut labels = by_label
+            .into_iter()
+            .map(|(label, binds)| (label.to_string(), binds))
+            .collect::<Vec<_>>();
+        labels.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        labels.truncate(count);
+        labels
+    }
+
+    /// How long encoding, submitting and acquiring a surface texture took for the last frame.
+    /// The time spent on the whole frame is left for the caller to fill in.
+    pub(crate) fn frame_stats(&self) -> FrameStats {
+        self.frame_stats
+    }
+
+    /// The number of samples drawn by the draws of occlusion query `id` in the most recently read
+    /// back frame, or `None` if that frame had no such query.
+    pub fn occlusion_samples(&self, id: u32) -> Option<u64> {
+        self.occlusion_readback.last.get(&id).copied()
+    }
+
+    fn encode_texture_op(&self, encoder: &mut wgpu::CommandEncoder, op: &TextureOp) {
+        match *op {
+            TextureOp::Copy {
+                src,
+                src_origin,
+                dst,
+                dst_origin,
+                size,
+            } => {
+                let origin = |(x, y)| wgpu::Origin3d { x, y, z: 0 };
+                encoder.copy_texture_to_texture(
+                    wgpu::ImageCopyTexture {
+                        texture: &self.textures[src.0],
+                        mip_level: 0,
+                        origin: origin(src_origin),
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    wgpu::ImageCopyTexture {
+                        texture: &self.textures[dst.0],
+                        mip_level: 0,
+                        origin: origin(dst_origin),
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    wgpu::Extent3d {
+                        width: size.0,
+                        height: size.1,
+                        depth_or_array_layers: 1,
+                    },
+                );
+            }
+            TextureOp::Read { src, id } => {
+                let readback = &self.texture_readbacks.pending[&id];
+                encoder.copy_texture_to_buffer(
+                    wgpu::ImageCopyTexture {
+                        texture: &self.textures[src.0],
+                        mip_level: 0,
+                        origin: wgpu::Origin3d::ZERO,
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    wgpu::ImageCopyBuffer {
+                        buffer: &readback.buffer,
+                        layout: wgpu::ImageDataLayout {
+                            offset: 0,
+                            bytes_per_row: Some(readback.bytes_per_row),
+                            rows_per_image: None,
+                        },
+                    },
+                    wgpu::Extent3d {
+                        width: readback.size.0,
+                        height: readback.size.1,
+                        depth_or_array_layers: 1,
+                    },
+                );
+            }
+
+            TextureOp::Blit {
+                src,
+                dst,
+                filter,
+                blend,
+            } => {
+                let bg = &self.blit_bgs[&(src, filter)];
+                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("blit"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &self.texture_views[dst.0],
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                rpass.set_pipeline(&self.render_pipelines[self.blit_pipelines[blend as usize].0]);
+                rpass.set_bind_group(0, bg, &[]);
+                rpass.draw(0..3, 0..1);
+            }
+        }
+    }
+
+    fn create_blit_bind_group(&mut self, src: TextureViewId, filter: FilterMode) {
+        if self.blit_bgs.contains_key(&(src, filter)) {
+            return;
+        }
+
+        let sampler = match filter {
+            FilterMode::Linear => self.blit_linear_sampler,
+            FilterMode::Nearest => self.blit_nearest_sampler,
+        };
+        let bg = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("blit"),
+            layout: &self.bgls[self.backbuffer_bgl.0],
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(&self.samplers[sampler.0]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&self.texture_views[src.0]),
+                },
+            ],
+        });
+        self.blit_bgs.insert((src, filter), bg);
+    }
+
+    /// Uploads tightly packed 8-bit RGBA pixels to the whole of `texture`. Fails if `data` is not
+    /// the size of the texture.
+    pub fn write_texture(&self, texture: TextureId, data: &[u8]) -> Result<(), Error> {
+        let size = self.textures[texture.0].size();
+        self.write_texture_region(texture, (0, 0), (size.width, size.height), data)
+    }
+
+    /// Uploads tightly packed 8-bit RGBA pixels to the region of `texture` with its top-left corner
+    /// at `origin`, leaving the rest of the texture unchanged. Fails if the region does not fit in
+    /// the texture or `data` is not the size of the region.
+    pub fn write_texture_region(
+        &self,
+        texture: TextureId,
+        origin: (u32, u32),
+        size: (u32, u32),
+        data: &[u8],
+    ) -> Result<(), Error> {
+        let texture = &self.textures[texture.0];
+        if !region_fits(origin, size, (texture.width(), texture.height())) {
+            return Err(Error::new("region is outside of the texture").with_kind(ErrorKind::Gpu));
+        }
+        let expected = (size.0 as usize)
+            .checked_mul(size.1 as usize)
+            .and_then(|pixels| pixels.checked_mul(4));
+        if expected != Some(data.len()) {
+            return Err(Error::new(format!(
+                "{} bytes of data do not match the size of the {}x{} region",
+                data.len(),
+                size.0,
+                size.1
+            ))
+            .with_kind(ErrorKind::Gpu));
+        }
+
+        self.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: origin.0,
+                    y: origin.1,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(size.0 * 4),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width: size.0,
+                height: size.1,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        Ok(())
+    }
 
     pub fn write_buffer<T: Copy>(&self, buffer: BufferId, data: &[T]) {
+        self.write_buffer_at(buffer, 0, data);
+    }
+
+    /// Writes `data` to `buffer` starting `offset` bytes in, leaving the rest of the buffer
+    /// unchanged. The offset and size of the data must be multiples of 4 bytes.
+    pub fn write_buffer_at<T: Copy>(&self, buffer: BufferId, offset: usize, data: &[T]) {
         self.queue
-            .write_buffer(&self.buffers[buffer.0], 0, cast_slice(data));
+            .write_buffer(&self.buffers[buffer.0], offset as u64, cast_slice(data));
+    }
+
+    pub fn buffer_size(&self, buffer: BufferId) -> usize {
+        self.buffers[buffer.0].size() as usize
+    }
+
+    /// Copies the first `size` bytes of `src` to the start of `dst`, after any pending writes to
+    /// `src`.
+    fn copy_buffer(&self, src: BufferId, dst: BufferId, size: usize) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("copy buffer"),
+            });
+        encoder.copy_buffer_to_buffer(
+            &self.buffers[src.0],
+            0,
+            &self.buffers[dst.0],
+            0,
+            size as u64,
+        );
+        self.queue.submit([encoder.finish()]);
     }
 }
 
+const BACKENDS: wgpu::Backends = wgpu::Backends::VULKAN; //DX12
+
+fn create_instance() -> wgpu::Instance {
+    let flags = if cfg!(debug_assertions) {
+        wgpu::InstanceFlags::DEBUG | wgpu::InstanceFlags::VALIDATION
+    } else {
+        wgpu::InstanceFlags::empty()
+    };
+
+    wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: BACKENDS,
+        flags,
+        ..Default::default()
+    })
+}
+
 pub(crate) fn cast_slice<T: Copy>(s: &[T]) -> &[u8] {
     let len = std::mem::size_of_val(s);
     let data = s.as_ptr() as *const u8;
@@ -930,21 +2494,154 @@ pub(crate) fn cast_slice<T: Copy>(s: &[T]) -> &[u8] {
 
 impl From<wgpu::CreateSurfaceError> for Error {
     fn from(value: wgpu::CreateSurfaceError) -> Self {
-        Error::new("failed to create a window surface").with_source(value)
+        Error::new("failed to create a window surface")
+            .with_kind(ErrorKind::Gpu)
+            .with_source(value)
+    }
+}
+
+/// The most color targets a [`DrawTarget`] can have.
+pub const MAX_COLOR_TARGETS: usize = 4;
+
+/// When a texture is cleared as a draw target without [`Graphics::clear`](crate::Graphics::clear)
+/// being called, set on a [`RenderTexture`](crate::RenderTexture).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClearPolicy {
+    /// Cleared to the color each time it is set as the draw target and then drawn to.
+    Always(Color),
+    /// Cleared to the color the first time it is drawn to each frame, so nothing is left over
+    /// from the last frame.
+    OnFirstUse(Color),
+    /// Never cleared, so it keeps what was drawn to it in earlier frames, e.g. for paint strokes.
+    Never,
+}
+
+impl Default for ClearPolicy {
+    fn default() -> Self {
+        ClearPolicy::OnFirstUse(Color::TRANSPARENT)
+    }
+}
+
+/// One of the textures drawn to by a [`DrawTarget`], usually created from a
+/// [`RenderTexture`](crate::RenderTexture).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorTarget {
+    texture_view: TextureViewId,
+    clear_color: Option<Color>,
+    clear_policy: ClearPolicy,
+}
+
+impl ColorTarget {
+    const INVALID: ColorTarget = ColorTarget {
+        texture_view: TextureViewId::INVALID,
+        clear_color: None,
+        clear_policy: ClearPolicy::Never,
+    };
+
+    pub(crate) fn new(texture_view: TextureViewId) -> Self {
+        Self {
+            texture_view,
+            clear_color: None,
+            clear_policy: ClearPolicy::Never,
+        }
+    }
+
+    pub(crate) fn with_clear_policy(mut self, policy: ClearPolicy) -> Self {
+        self.clear_policy = policy;
+        self
+    }
+
+    /// Clears the texture to `color` when it is first drawn to after being set as a draw target,
+    /// rather than drawing over what it already holds.
+    pub fn with_clear_color(mut self, color: Color) -> Self {
+        self.clear_color = Some(color);
+        self
+    }
+}
+
+/// What is drawn to, passed to [`Graphics::set_draw_target`](crate::Graphics::set_draw_target).
+/// Usually a single texture, but can be several that are drawn to at once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawTarget {
+    color_targets: [ColorTarget; MAX_COLOR_TARGETS],
+    count: usize,
+}
+
+impl DrawTarget {
+    pub(crate) const INVALID: DrawTarget = DrawTarget {
+        color_targets: [ColorTarget::INVALID; MAX_COLOR_TARGETS],
+        count: 1,
+    };
+
+    pub(crate) fn new(texture_view: TextureViewId) -> Self {
+        Self::with_color_targets(&[ColorTarget::new(texture_view)])
+    }
+
+    /// Draws to all of `targets` at once, e.g. to write color and an id or normal for each pixel
+    /// in a single pass. Fragment output `@location(i)` is written to `targets[i]`, so draws must
+    /// use a pipeline with a matching [`ColorTargetDesc`] for each target.
+    ///
+    /// Panics if there are no targets or more than [`MAX_COLOR_TARGETS`].
+    pub fn with_color_targets(targets: &[ColorTarget]) -> Self {
+        assert!(
+            (1..=MAX_COLOR_TARGETS).contains(&targets.len()),
+            "a draw target must have between 1 and {MAX_COLOR_TARGETS} color targets"
+        );
+
+        let mut color_targets = [ColorTarget::INVALID; MAX_COLOR_TARGETS];
+        color_targets[..targets.len()].copy_from_slice(targets);
+        Self {
+            color_targets,
+            count: targets.len(),
+        }
+    }
+
+    pub fn color_targets(&self) -> &[ColorTarget] {
+        &self.color_targets[..self.count]
+    }
+
+    /// The first color target, which is the one blitted to and cleared by
+    /// [`Graphics::clear`](crate::Graphics::clear).
+    pub(crate) fn texture_view(&self) -> TextureViewId {
+        self.color_targets[0].texture_view
+    }
+
+    /// The target with the first color target cleared to `color`, if given.
+    pub(crate) fn with_clear_color(mut self, color: Option<Color>) -> Self {
+        if color.is_some() {
+            self.color_targets[0].clear_color = color;
+        }
+        self
     }
-}
 
-pub struct DrawTarget {
-    texture_view: TextureViewId,
-}
+    /// Whether both targets draw to the same textures, whatever they are cleared to.
+    pub(crate) fn same_textures(&self, other: &DrawTarget) -> bool {
+        self.without_clear_colors() == other.without_clear_colors()
+    }
 
-impl DrawTarget {
-    pub(crate) const INVALID: DrawTarget = DrawTarget {
-        texture_view: TextureViewId::INVALID,
-    };
+    /// The target with each color target that has no clear color yet given one by its clear
+    /// policy. `drawn` holds the textures drawn to so far this frame, and has the textures of
+    /// this target added to it.
+    pub(crate) fn with_clear_policies(mut self, drawn: &mut HashSet<TextureViewId>) -> Self {
+        for target in &mut self.color_targets[..self.count] {
+            let first_use = drawn.insert(target.texture_view);
+            let color = match target.clear_policy {
+                ClearPolicy::Always(color) => Some(color),
+                ClearPolicy::OnFirstUse(color) if first_use => Some(color),
+                _ => None,
+            };
+            target.clear_color = target.clear_color.or(color);
+        }
+        self
+    }
 
-    pub(crate) fn texture_view(&self) -> TextureViewId {
-        self.texture_view
+    /// The target with nothing cleared, for drawing over what has already been drawn.
+    pub(crate) fn without_clear_colors(mut self) -> Self {
+        for target in &mut self.color_targets {
+            target.clear_color = None;
+            target.clear_policy = ClearPolicy::Never;
+        }
+        self
     }
 }
 
@@ -953,7 +2650,6 @@ pub(crate) struct Backbuffer {
     pipeline: RenderPipelineId,
     #[allow(dead_code)]
     sampler: SamplerId,
-    #[allow(dead_code)]
     texture: TextureId,
     texture_view: TextureViewId,
     bg: BindGroupId,
@@ -1009,9 +2705,7 @@ impl Backbuffer {
 
 impl From<&Backbuffer> for DrawTarget {
     fn from(backbuffer: &Backbuffer) -> Self {
-        DrawTarget {
-            texture_view: backbuffer.texture_view,
-        }
+        DrawTarget::new(backbuffer.texture_view)
     }
 }
 
@@ -1020,46 +2714,142 @@ pub(crate) struct CommandBuffer {
     next_pass: usize,
     draws: Vec<DrawCommand>,
     passes: Vec<RenderPass>,
+    ops: Vec<(usize, TextureOp)>, // Index of the render pass each op is encoded before.
+    pub(crate) pick: Option<PickPass>,
+    pub(crate) outline: Option<OutlinePass>,
+    pub(crate) occlusion_query: Option<u32>, // Id of the query that recorded draws are part of.
+    occlusion_queries: Vec<u32>,             // Id of the query counted in each slot.
 }
 
 impl CommandBuffer {
     pub(crate) fn clear(&mut self) {
+        self.clear_passes();
+        self.occlusion_query = None;
+        self.occlusion_queries.clear();
+        if let Some(ref mut pick) = self.pick {
+            pick.draws.clear();
+            pick.read_at = None;
+        }
+        if let Some(ref mut outline) = self.outline {
+            outline.draws.clear();
+        }
+    }
+
+    /// Clears the render passes and texture operations once they have been flushed, keeping the
+    /// pick, outline and occlusion query draws that are only encoded with the whole frame.
+    pub(crate) fn clear_passes(&mut self) {
         self.next_pass = 0;
         self.draws.clear();
         self.passes.clear();
+        self.ops.clear();
     }
 
-    pub(crate) fn record(&mut self, draw: DrawCommand) {
+    /// Records a texture operation to be encoded after the render passes recorded so far.
+    pub(crate) fn record_op(&mut self, op: TextureOp) {
+        self.ops.push((self.passes.len(), op));
+    }
+
+    pub(crate) fn record(&mut self, mut draw: DrawCommand) {
         if self.next_pass == 0 {
             panic!("cannot record draw command without a render pass");
         }
 
+        draw.occlusion_query = self.occlusion_query.and_then(|id| self.occlusion_slot(id));
         self.draws.push(draw);
-        self.passes[self.next_pass - 1].draw_count += 1;
+        self.passes[self.next_pass - 1].draws.end += 1;
+    }
+
+    /// The query slot to count a draw for query `id` in. Each slot can only be used by one run of
+    /// draws within a render pass, so a query split by other draws or passes takes several slots
+    /// whose results are added together.
+    fn occlusion_slot(&mut self, id: u32) -> Option<u32> {
+        let pass_draws = &self.passes[self.next_pass - 1].draws;
+        let previous = self.draws.last().filter(|_| !pass_draws.is_empty());
+        if let Some(slot) = previous.and_then(|draw| draw.occlusion_query) {
+            if self.occlusion_queries[slot as usize] == id {
+                return Some(slot);
+            }
+        }
+
+        if self.occlusion_queries.len() == MAX_OCCLUSION_QUERIES as usize {
+            return None;
+        }
+        self.occlusion_queries.push(id);
+        Some(self.occlusion_queries.len() as u32 - 1)
     }
 
-    pub(crate) fn set_render_pass(&mut self, target: TextureViewId, clear_color: Option<Color>) {
+    pub(crate) fn set_render_pass(&mut self, target: DrawTarget) {
         self.next_pass += 1;
         self.passes.push(RenderPass {
             target,
-            clear_color,
-            draw_count: 0,
+            draws: self.draws.len()..self.draws.len(),
         });
     }
 }
 
+#[derive(Debug, Clone)]
+pub(crate) enum TextureOp {
+    Copy {
+        src: TextureId,
+        src_origin: (u32, u32),
+        dst: TextureId,
+        dst_origin: (u32, u32),
+        size: (u32, u32),
+    },
+    Blit {
+        src: TextureViewId,
+        dst: TextureViewId,
+        filter: FilterMode,
+        blend: BlendMode,
+    },
+    Read {
+        src: TextureId,
+        id: TextureReadbackId,
+    },
+}
+
+/// Pickable draws, which are drawn again into an id buffer after everything else in the frame.
+#[derive(Debug, Clone)]
+pub(crate) struct PickPass {
+    pub(crate) texture: TextureId,
+    pub(crate) view: TextureViewId,
+    pub(crate) size: (u32, u32),
+    pub(crate) draws: Vec<DrawCommand>,
+    pub(crate) read_at: Option<(u32, u32)>,
+}
+
+/// Outlined draws, which are drawn again into a mask after everything else in the frame so that
+/// outlines can be drawn around them.
+#[derive(Debug, Clone)]
+pub(crate) struct OutlinePass {
+    pub(crate) texture: TextureId,
+    pub(crate) view: TextureViewId,
+    pub(crate) bg: BindGroupId, // Samples the mask when drawing the outlines.
+    pub(crate) draws: Vec<DrawCommand>,
+}
+
 #[derive(Clone)]
 pub(crate) struct RenderPass {
-    pub(crate) target: TextureViewId,
-    pub(crate) clear_color: Option<Color>,
-    pub(crate) draw_count: usize,
+    pub(crate) target: DrawTarget,
+    pub(crate) draws: Range<usize>,
+}
+
+/// Whether the `size` region at `origin` lies within a texture of `texture_size`.
+pub(crate) fn region_fits(origin: (u32, u32), size: (u32, u32), texture_size: (u32, u32)) -> bool {
+    let fits =
+        |origin: u32, size: u32, limit| origin.checked_add(size).is_some_and(|end| end <= limit);
+    fits(origin.0, size.0, texture_size.0) && fits(origin.1, size.1, texture_size.1)
 }
 
 #[derive(Debug, Default, Clone)]
 pub(crate) struct RenderData {
-    pub(crate) dest: BufferId,
+    pub(crate) dest: BufferId, // Grown to fit `data` if it is too small.
+    pub(crate) globals_bgl: BindGroupLayoutId,
+    pub(crate) globals_bg: BindGroupId, // Rebuilt over `dest` when it grows.
     pub(crate) size: usize,
     pub(crate) data: Vec<u8>,
+    pub(crate) vertices_dest: BufferId, // Grown to fit `vertices` if it is too small.
+    pub(crate) vertices: Vec<u8>,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -1068,38 +2858,876 @@ pub(crate) struct DrawCommand {
     pub(crate) vbo: BufferId,
     pub(crate) ibo: BufferId,
     pub(crate) index_count: usize,
+    pub(crate) base_vertex: i32,
     pub(crate) color: Color,
     pub(crate) model: Mat4,
     pub(crate) globals_bg: BindGroupId,
+    pub(crate) texture_bg: BindGroupId,
     pub(crate) globals_idx: usize, // Index of data in global sbo.
+    pub(crate) pick_id: u32,       // Zero if the draw cannot be picked.
+    pub(crate) instances: Option<(BufferId, u32)>, // Instance buffer and count, else one instance.
+    pub(crate) occlusion_query: Option<u32>, // Query slot the draw is counted in.
+    pub(crate) sortable: bool,     // Whether the draw can be reordered by state.
+}
+
+/// Sorts each run of sortable draws by the state they use, so that draws sharing a pipeline,
+/// textures and buffers are encoded together. Draws that are not sortable stay where they are and
+/// keep the draws either side of them apart.
+fn sort_draws(draws: &mut [DrawCommand]) {
+    for run in draws.split_mut(|draw| !draw.sortable) {
+        run.sort_by_key(|draw| {
+            (
+                draw.occlusion_query,
+                draw.pipeline.0.idx(),
+                draw.texture_bg.0.idx(),
+                draw.globals_bg.0.idx(),
+                draw.vbo.0.idx(),
+                draw.ibo.0.idx(),
+                draw.instances.map(|(buffer, _)| buffer.0.idx()),
+            )
+        });
+    }
+}
+
+/// The state bound by the previous draw in a render pass.
+#[derive(Default)]
+struct DrawState {
+    pipeline: Option<RenderPipelineId>,
+    globals_bg: Option<BindGroupId>,
+    texture_bg: Option<BindGroupId>,
+    vbo: Option<BufferId>,
+    ibo: Option<BufferId>,
+    instances: Option<BufferId>,
+}
+
+/// The draw calls made in a frame and how often the state they use had to be changed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DrawStats {
+    pub draw_calls: usize,
+    pub pipeline_switches: usize,
+    pub bind_group_switches: usize,
+    pub buffer_switches: usize,
+    /// How many of the bind group switches bound a different texture.
+    pub texture_binds: usize,
+}
+
+impl DrawStats {
+    /// A warning listing the limits of `budget` that were exceeded, along with the textures that
+    /// were bound most often, if any limit was exceeded.
+    pub(crate) fn budget_warning(
+        &self,
+        budget: &DrawBudget,
+        most_bound: &[(String, usize)],
+    ) -> Option<String> {
+        let over = |limit: Option<usize>, value: usize| limit.filter(|&limit| value > limit);
+        let draw_calls = over(budget.max_draw_calls, self.draw_calls);
+        let texture_binds = over(budget.max_texture_binds, self.texture_binds);
+        if draw_calls.is_none() && texture_binds.is_none() {
+            return None;
+        }
+
+        let mut warning = String::from("draws over budget:");
+        if let Some(limit) = draw_calls {
+            let _ = write!(warning, " draw_calls={} (max {})", self.draw_calls, limit);
+        }
+        if let Some(limit) = texture_binds {
+            let _ = write!(
+                warning,
+                " texture_binds={} (max {})",
+                self.texture_binds, limit
+            );
+        }
+        if !most_bound.is_empty() {
+            let labels = most_bound
+                .iter()
+                .map(|(label, binds)| format!("'{label}' x{binds}"))
+                .collect::<Vec<_>>();
+            let _ = write!(warning, " most_bound=[{}]", labels.join(", "));
+        }
+        Some(warning)
+    }
+}
+
+impl AddAssign for DrawStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.draw_calls += rhs.draw_calls;
+        self.pipeline_switches += rhs.pipeline_switches;
+        self.bind_group_switches += rhs.bind_group_switches;
+        self.buffer_switches += rhs.buffer_switches;
+        self.texture_binds += rhs.texture_binds;
+    }
+}
+
+/// Soft limits on the draws made in a frame, given to
+/// [`Engine::set_draw_budget`](crate::Engine::set_draw_budget). Going over a limit only logs a
+/// warning, which helps to catch draws that were meant to be batched, or resources that are
+/// recreated each frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DrawBudget {
+    pub max_draw_calls: Option<usize>,
+    pub max_texture_binds: Option<usize>,
+}
+
+/// Counts how often each texture bind group is bound when encoding the passes of `buf`, as in
+/// [`Renderer::encode_draw`].
+fn count_texture_binds(buf: &CommandBuffer, binds: &mut HashMap<BindGroupId, usize>) {
+    for pass in &buf.passes {
+        let mut bound = None;
+        for draw in &buf.draws[pass.draws.clone()] {
+            if bound != Some(draw.texture_bg) {
+                *binds.entry(draw.texture_bg).or_default() += 1;
+                bound = Some(draw.texture_bg);
+            }
+        }
+    }
+}
+
+/// Splits `passes` into runs that draw to the same target, which are encoded together.
+fn pass_groups(passes: &[RenderPass]) -> Vec<Range<usize>> {
+    let mut groups: Vec<Range<usize>> = Vec::new();
+    for (i, pass) in passes.iter().enumerate() {
+        match groups.last_mut() {
+            Some(group) if passes[group.start].target.same_textures(&pass.target) => group.end += 1,
+            _ => groups.push(i..i + 1),
+        }
+    }
+    groups
 }
 
 #[derive(Debug, Default, Clone, Copy)]
 #[repr(C)]
 pub struct GeometryVertex {
     pub pos: [f32; 2],
+    pub uv: [f32; 2],
+}
+
+impl_vertex!(GeometryVertex, Geometry, {
+    pos: Float32x2 => 0,
+    uv: Float32x2 => 1,
+});
+
+/// Settings for the CRT effect applied when presenting the backbuffer. Each intensity is in the
+/// range `0.0..=1.0`, where `0.0` disables that part of the effect.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct CrtEffect {
+    pub curvature: f32,
+    pub scanline_intensity: f32,
+    pub mask_intensity: f32,
+    pub bloom_intensity: f32,
+}
+
+impl Default for CrtEffect {
+    fn default() -> Self {
+        Self {
+            curvature: 0.05,
+            scanline_intensity: 0.3,
+            mask_intensity: 0.2,
+            bloom_intensity: 0.25,
+        }
+    }
+}
+
+/// How the window surface is composited with the desktop behind it. Modes other than `Opaque`
+/// only have an effect on a transparent window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CompositeAlphaMode {
+    #[default]
+    Auto,
+    Opaque,
+    PreMultiplied,
+    PostMultiplied,
+    Inherit,
+}
+
+impl From<CompositeAlphaMode> for wgpu::CompositeAlphaMode {
+    fn from(value: CompositeAlphaMode) -> Self {
+        match value {
+            CompositeAlphaMode::Auto => wgpu::CompositeAlphaMode::Auto,
+            CompositeAlphaMode::Opaque => wgpu::CompositeAlphaMode::Opaque,
+            CompositeAlphaMode::PreMultiplied => wgpu::CompositeAlphaMode::PreMultiplied,
+            CompositeAlphaMode::PostMultiplied => wgpu::CompositeAlphaMode::PostMultiplied,
+            CompositeAlphaMode::Inherit => wgpu::CompositeAlphaMode::Inherit,
+        }
+    }
+}
+
+impl From<wgpu::CompositeAlphaMode> for CompositeAlphaMode {
+    fn from(value: wgpu::CompositeAlphaMode) -> Self {
+        match value {
+            wgpu::CompositeAlphaMode::Auto => CompositeAlphaMode::Auto,
+            wgpu::CompositeAlphaMode::Opaque => CompositeAlphaMode::Opaque,
+            wgpu::CompositeAlphaMode::PreMultiplied => CompositeAlphaMode::PreMultiplied,
+            wgpu::CompositeAlphaMode::PostMultiplied => CompositeAlphaMode::PostMultiplied,
+            wgpu::CompositeAlphaMode::Inherit => CompositeAlphaMode::Inherit,
+        }
+    }
+}
+
+/// Which graphics adapter to render with, for systems that have more than one, such as laptops
+/// with both integrated and discrete GPUs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum GpuPreference {
+    #[default]
+    HighPerformance,
+    LowPower,
+    /// The first adapter whose name contains the given text, ignoring case, that supports the
+    /// features the renderer needs.
+    ByName(String),
+}
+
+/// The capabilities of the graphics adapter, probed at startup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuCapabilities {
+    pub adapter_name: String,
+    pub backend: String,
+    pub device_type: DeviceType,
+    pub max_texture_size: u32,
+    /// The MSAA sample counts supported by the backbuffer format, in ascending order.
+    pub msaa_sample_counts: Vec<u32>,
+    pub present_modes: Vec<PresentMode>,
+}
+
+impl GpuCapabilities {
+    fn probe(adapter: &wgpu::Adapter) -> Self {
+        let info = adapter.get_info();
+        let format_features = adapter.get_texture_format_features(TextureFormat::Rgba8Unorm.into());
+
+        Self {
+            adapter_name: info.name,
+            backend: format!("{:?}", info.backend),
+            device_type: info.device_type.into(),
+            max_texture_size: adapter.limits().max_texture_dimension_2d,
+            msaa_sample_counts: format_features.flags.supported_sample_counts(),
+            present_modes: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeviceType {
+    Cpu,
+    DiscreteGpu,
+    IntegratedGpu,
+    VirtualGpu,
+    Other,
+}
+
+impl From<wgpu::DeviceType> for DeviceType {
+    fn from(value: wgpu::DeviceType) -> Self {
+        match value {
+            wgpu::DeviceType::Cpu => DeviceType::Cpu,
+            wgpu::DeviceType::DiscreteGpu => DeviceType::DiscreteGpu,
+            wgpu::DeviceType::IntegratedGpu => DeviceType::IntegratedGpu,
+            wgpu::DeviceType::VirtualGpu => DeviceType::VirtualGpu,
+            wgpu::DeviceType::Other => DeviceType::Other,
+        }
+    }
 }
 
-impl GeometryVertex {
-    const ATTRIBS: [VertexAttribute; 1] = [VertexAttribute {
-        format: VertexFormat::Float32x2,
-        offset: 0,
-        location: 0,
-    }];
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PresentMode {
+    AutoVsync,
+    AutoNoVsync,
+    Fifo,
+    FifoRelaxed,
+    Immediate,
+    Mailbox,
+}
+
+impl From<wgpu::PresentMode> for PresentMode {
+    fn from(value: wgpu::PresentMode) -> Self {
+        match value {
+            wgpu::PresentMode::AutoVsync => PresentMode::AutoVsync,
+            wgpu::PresentMode::AutoNoVsync => PresentMode::AutoNoVsync,
+            wgpu::PresentMode::Fifo => PresentMode::Fifo,
+            wgpu::PresentMode::FifoRelaxed => PresentMode::FifoRelaxed,
+            wgpu::PresentMode::Immediate => PresentMode::Immediate,
+            wgpu::PresentMode::Mailbox => PresentMode::Mailbox,
+        }
+    }
+}
+
+/// Trades visual quality for performance. `Low` disables post effects, `Medium` keeps them but
+/// skips bloom, and `High` applies them in full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum QualityPreset {
+    Low,
+    Medium,
+    High,
+}
+
+impl QualityPreset {
+    /// The preset that suits the graphics adapter described by `capabilities`.
+    pub fn recommended(capabilities: &GpuCapabilities) -> Self {
+        match capabilities.device_type {
+            DeviceType::DiscreteGpu => QualityPreset::High,
+            DeviceType::Cpu => QualityPreset::Low,
+            DeviceType::IntegratedGpu | DeviceType::VirtualGpu | DeviceType::Other => {
+                QualityPreset::Medium
+            }
+        }
+    }
 
-    pub fn layout() -> VertexBufferLayoutDesc<'static> {
-        VertexBufferLayoutDesc {
-            stride: std::mem::size_of::<Self>(),
-            buffer_type: VertexBufferType::Geometry,
-            attributes: &Self::ATTRIBS,
+    fn apply(self, crt: CrtEffect) -> Option<CrtEffect> {
+        match self {
+            QualityPreset::Low => None,
+            QualityPreset::Medium => Some(CrtEffect {
+                bloom_intensity: 0.0,
+                ..crt
+            }),
+            QualityPreset::High => Some(crt),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+struct PresentConstants {
+    crt: CrtEffect,
+    region: [f32; 4],
+}
+
+/// The offset and size, in uv coordinates, of the region of the backbuffer that is shown when it
+/// is magnified by `zoom` around `focus`, which stays at the same place in the window.
+fn zoom_region(zoom: f32, focus: [f32; 2]) -> [f32; 4] {
+    let size = 1.0 / zoom;
+    [focus[0] * (1.0 - size), focus[1] * (1.0 - size), size, size]
+}
+
 #[derive(Debug, Clone, Copy)]
 #[repr(C)]
 struct PushConstantBuffer {
     color: [f32; 4],
     model: [f32; 16],
     globals_idx: u32,
+    pick_id: u32,
+}
+
+/// Reads a single pick id back from the GPU, which completes asynchronously a frame or more after
+/// the id was copied.
+struct PickReadback {
+    buffer: wgpu::Buffer,
+    mapped: Arc<AtomicBool>,
+    in_flight: bool,
+    last: Option<u32>,
+}
+
+impl PickReadback {
+    fn new(device: &wgpu::Device) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("pick readback"),
+            size: wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            buffer,
+            mapped: Arc::new(AtomicBool::new(false)),
+            in_flight: false,
+            last: None,
+        }
+    }
+
+    fn map(&mut self) {
+        let mapped = self.mapped.clone();
+        self.buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                mapped.store(result.is_ok(), Ordering::Release);
+            });
+        self.in_flight = true;
+    }
+
+    fn poll(&mut self, device: &wgpu::Device) {
+        if !self.in_flight {
+            return;
+        }
+
+        device.poll(wgpu::Maintain::Poll);
+        if !self.mapped.swap(false, Ordering::Acquire) {
+            return;
+        }
+
+        let id = {
+            let bytes = self.buffer.slice(..4).get_mapped_range();
+            u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+        };
+        self.buffer.unmap();
+        self.in_flight = false;
+        self.last = (id != 0).then_some(id);
+    }
+}
+
+/// Identifies a texture being read back from the GPU, returned by
+/// [`Graphics::read_texture`](crate::Graphics::read_texture).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureReadbackId(pub(crate) u64);
+
+/// Reads whole textures back from the GPU, each of which completes asynchronously a frame or more
+/// after it was copied.
+#[derive(Default)]
+struct TextureReadbacks {
+    pending: HashMap<TextureReadbackId, PendingTextureReadback>,
+    finished: HashMap<TextureReadbackId, Image>,
+}
+
+struct PendingTextureReadback {
+    buffer: wgpu::Buffer,
+    size: (u32, u32),
+    bytes_per_row: u32, // Rows are padded to the copy alignment.
+    mapped: Arc<AtomicBool>,
+    in_flight: bool,
+}
+
+impl TextureReadbacks {
+    fn create(&mut self, device: &wgpu::Device, id: TextureReadbackId, size: (u32, u32)) {
+        let bytes_per_row = (size.0 * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("texture readback"),
+            size: bytes_per_row as u64 * size.1 as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        self.pending.insert(
+            id,
+            PendingTextureReadback {
+                buffer,
+                size,
+                bytes_per_row,
+                mapped: Arc::new(AtomicBool::new(false)),
+                in_flight: false,
+            },
+        );
+    }
+
+    /// Starts mapping the readbacks copied in the frame that has just been submitted.
+    fn map(&mut self) {
+        for readback in self
+            .pending
+            .values_mut()
+            .filter(|readback| !readback.in_flight)
+        {
+            let mapped = readback.mapped.clone();
+            readback
+                .buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    mapped.store(result.is_ok(), Ordering::Release);
+                });
+            readback.in_flight = true;
+        }
+    }
+
+    fn poll(&mut self, device: &wgpu::Device) {
+        if self.pending.is_empty() {
+            return;
+        }
+
+        device.poll(wgpu::Maintain::Poll);
+        let mapped = self
+            .pending
+            .iter()
+            .filter(|(_, readback)| readback.mapped.load(Ordering::Acquire))
+            .map(|(&id, _)| id)
+            .collect::<Vec<_>>();
+        for id in mapped {
+            let readback = self.pending.remove(&id).expect("readback is pending");
+            let pixels = {
+                let bytes = readback.buffer.slice(..).get_mapped_range();
+                unpad_rows(&bytes, readback.size, readback.bytes_per_row)
+            };
+            readback.buffer.unmap();
+            let image = Image::new(readback.size.0, readback.size.1, pixels)
+                .expect("readback pixels match the texture size");
+            self.finished.insert(id, image);
+        }
+    }
+}
+
+/// The slower of two passes, if either is given.
+fn slowest(a: Option<PassStats>, b: Option<PassStats>) -> Option<PassStats> {
+    match (a, b) {
+        (Some(a), Some(b)) if a.encode >= b.encode => Some(a),
+        (a, b) => b.or(a),
+    }
+}
+
+/// Copies the pixels of each row out of rows padded to `bytes_per_row`.
+fn unpad_rows(bytes: &[u8], size: (u32, u32), bytes_per_row: u32) -> Vec<u8> {
+    let row = size.0 as usize * 4;
+    bytes
+        .chunks(bytes_per_row as usize)
+        .take(size.1 as usize)
+        .flat_map(|padded| &padded[..row])
+        .copied()
+        .collect()
+}
+
+/// The most occlusion query slots that can be used in a frame. Draws past the limit are not
+/// counted.
+const MAX_OCCLUSION_QUERIES: u32 = 256;
+
+struct OcclusionReadback {
+    query_set: wgpu::QuerySet,
+    resolve: wgpu::Buffer,
+    buffer: wgpu::Buffer,
+    mapped: Arc<AtomicBool>,
+    in_flight: Option<Vec<u32>>, // Id of the query counted in each slot being read back.
+    last: HashMap<u32, u64>,
+}
+
+impl OcclusionReadback {
+    fn new(device: &wgpu::Device) -> Self {
+        let size = MAX_OCCLUSION_QUERIES as u64 * std::mem::size_of::<u64>() as u64;
+
+        Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("occlusion"),
+                ty: wgpu::QueryType::Occlusion,
+                count: MAX_OCCLUSION_QUERIES,
+            }),
+            resolve: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("occlusion resolve"),
+                size,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("occlusion readback"),
+                size,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            mapped: Arc::new(AtomicBool::new(false)),
+            in_flight: None,
+            last: HashMap::new(),
+        }
+    }
+
+    /// Copies the results of the queries in `slots` for reading back, returning whether a copy
+    /// was made. Nothing is copied while an earlier frame's results are still being read.
+    fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder, slots: &[u32]) -> bool {
+        if slots.is_empty() || self.in_flight.is_some() {
+            return false;
+        }
+
+        let count = slots.len() as u32;
+        let size = count as u64 * std::mem::size_of::<u64>() as u64;
+        encoder.resolve_query_set(&self.query_set, 0..count, &self.resolve, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve, 0, &self.buffer, 0, size);
+        self.in_flight = Some(slots.to_vec());
+        true
+    }
+
+    fn map(&mut self) {
+        let mapped = self.mapped.clone();
+        self.buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                mapped.store(result.is_ok(), Ordering::Release);
+            });
+    }
+
+    fn poll(&mut self, device: &wgpu::Device) {
+        let Some(ref slots) = self.in_flight else {
+            return;
+        };
+
+        device.poll(wgpu::Maintain::Poll);
+        if !self.mapped.swap(false, Ordering::Acquire) {
+            return;
+        }
+
+        self.last.clear();
+        {
+            let bytes = self.buffer.slice(..).get_mapped_range();
+            for (id, samples) in slots.iter().zip(bytes.chunks_exact(8)) {
+                let samples = u64::from_ne_bytes(samples.try_into().unwrap());
+                *self.last.entry(*id).or_default() += samples;
+            }
+        }
+        self.buffer.unmap();
+        self.in_flight = None;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shader_errors_have_location() {
+        let source = "@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n    return vec4(1.0, missing, 0.0, 1.0);\n}\n";
+        let err = ShaderError::check(source, "broken.wgsl").unwrap_err();
+
+        assert_eq!((err.line, err.column), (3, 22));
+        assert!(err.snippet.contains("broken.wgsl:3:22"), "{}", err.snippet);
+        assert!(ShaderError::check(include_str!("default.wgsl"), "default").is_ok());
+        assert!(ShaderError::check(include_str!("error.wgsl"), "error").is_ok());
+        assert!(ShaderError::check(include_str!("outline.wgsl"), "outline").is_ok());
+        assert!(ShaderError::check(include_str!("outline_mask.wgsl"), "outline mask").is_ok());
+    }
+
+    #[test]
+    fn zoom_region_keeps_focus_in_place() {
+        assert_eq!(zoom_region(1.0, [0.3, 0.7]), [0.0, 0.0, 1.0, 1.0]);
+
+        let [x, y, w, h] = zoom_region(4.0, [0.2, 0.8]);
+        assert_eq!((w, h), (0.25, 0.25));
+        assert!((x + 0.2 * w - 0.2).abs() < 1e-6);
+        assert!((y + 0.8 * h - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn impl_vertex_uses_field_offsets() {
+        #[repr(C)]
+        struct Particle {
+            position: [f32; 2],
+            _age: f32,
+            color: [u8; 4],
+        }
+
+        impl_vertex!(Particle, Instance, {
+            position: Float32x2 => 2,
+            color: Unorm8x4 => 5,
+        });
+
+        let layout = Particle::layout();
+        assert_eq!(layout.stride, 16);
+        assert_eq!(layout.buffer_type, VertexBufferType::Instance);
+        assert_eq!(
+            layout.attributes,
+            [
+                VertexAttribute::new(VertexFormat::Float32x2, 0, 2),
+                VertexAttribute::new(VertexFormat::Unorm8x4, 12, 5),
+            ]
+        );
+        assert_eq!(VertexFormat::Unorm8x4.size(), 4);
+        assert_eq!(VertexFormat::Float32x3.size(), 12);
+    }
+
+    #[test]
+    fn regions_must_fit_in_the_texture() {
+        assert!(region_fits((0, 0), (64, 32), (64, 32)));
+        assert!(region_fits((60, 30), (4, 2), (64, 32)));
+        assert!(!region_fits((60, 30), (5, 2), (64, 32)));
+        assert!(!region_fits((u32::MAX, 0), (2, 1), (64, 32)));
+    }
+
+    #[test]
+    fn texture_writes_must_match_the_region() {
+        // Writing needs a graphics adapter, so there is nothing to check without one.
+        let Ok(mut renderer) = Renderer::new(&GpuPreference::LowPower) else {
+            return;
+        };
+        let texture = renderer.create_texture(&TextureDesc {
+            label: None,
+            width: 4,
+            height: 4,
+            format: TextureFormat::Rgba8Unorm,
+        });
+
+        assert!(renderer.write_texture(texture, &[0; 64]).is_ok());
+        let err = renderer.write_texture(texture, &[0; 60]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Gpu);
+        let err = renderer
+            .write_texture_region(texture, (u32::MAX, 0), (2, 2), &[0; 16])
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Gpu);
+    }
+
+    #[test]
+    fn draw_target_clears_each_color_target() {
+        let albedo = ColorTarget::new(TextureViewId::INVALID);
+        let ids = ColorTarget::new(TextureViewId::INVALID).with_clear_color(Color::BLACK);
+        let target = DrawTarget::with_color_targets(&[albedo, ids]);
+        assert_eq!(target.color_targets().len(), 2);
+
+        let cleared = target.with_clear_color(Some(Color::BLUE));
+        assert_eq!(
+            cleared
+                .color_targets()
+                .iter()
+                .map(|target| target.clear_color)
+                .collect::<Vec<_>>(),
+            [Some(Color::BLUE), Some(Color::BLACK)]
+        );
+        assert!(cleared
+            .without_clear_colors()
+            .color_targets()
+            .iter()
+            .all(|target| target.clear_color.is_none()));
+    }
+
+    #[test]
+    fn clear_policies_fill_in_clear_colors() {
+        let view = |i| TextureViewId(GenIdx::new(i, 0));
+        let always = ColorTarget::new(view(0)).with_clear_policy(ClearPolicy::Always(Color::RED));
+        let first_use =
+            ColorTarget::new(view(1)).with_clear_policy(ClearPolicy::OnFirstUse(Color::BLUE));
+        let target = DrawTarget::with_color_targets(&[always, first_use]);
+        let clear_colors = |target: DrawTarget| {
+            target
+                .color_targets()
+                .iter()
+                .map(|target| target.clear_color)
+                .collect::<Vec<_>>()
+        };
+
+        let mut drawn = HashSet::new();
+        let first = target.with_clear_policies(&mut drawn);
+        assert_eq!(clear_colors(first), [Some(Color::RED), Some(Color::BLUE)]);
+        let second = target.with_clear_policies(&mut drawn);
+        assert_eq!(clear_colors(second), [Some(Color::RED), None]);
+
+        // An explicit clear wins, and nothing is cleared once the first pass has been made.
+        let cleared = target.with_clear_color(Some(Color::GREEN));
+        assert_eq!(
+            clear_colors(cleared.with_clear_policies(&mut drawn)),
+            [Some(Color::GREEN), None]
+        );
+        let drawn_over = first.without_clear_colors();
+        assert_eq!(
+            clear_colors(drawn_over.with_clear_policies(&mut HashSet::new())),
+            [None, None]
+        );
+    }
+
+    #[test]
+    fn occlusion_queries_take_a_slot_per_run_of_draws() {
+        let mut buf = CommandBuffer::default();
+        buf.set_render_pass(DrawTarget::INVALID);
+        buf.occlusion_query = Some(7);
+        buf.record(DrawCommand::default());
+        buf.record(DrawCommand::default());
+        buf.occlusion_query = None;
+        buf.record(DrawCommand::default());
+        buf.occlusion_query = Some(7);
+        buf.record(DrawCommand::default());
+        buf.set_render_pass(DrawTarget::INVALID);
+        buf.record(DrawCommand::default());
+
+        let slots = buf
+            .draws
+            .iter()
+            .map(|draw| draw.occlusion_query)
+            .collect::<Vec<_>>();
+        assert_eq!(slots, [Some(0), Some(0), None, Some(1), Some(2)]);
+        assert_eq!(buf.occlusion_queries, [7, 7, 7]);
+    }
+
+    #[test]
+    fn draw_budget_warns_with_most_bound_textures() {
+        let texture = |i| DrawCommand {
+            texture_bg: BindGroupId(GenIdx::new(i, 0)),
+            ..Default::default()
+        };
+        let mut buf = CommandBuffer::default();
+        buf.set_render_pass(DrawTarget::INVALID);
+        for i in [1, 1, 2, 1] {
+            buf.record(texture(i));
+        }
+        let mut binds = HashMap::new();
+        count_texture_binds(&buf, &mut binds);
+        assert_eq!(binds[&BindGroupId(GenIdx::new(1, 0))], 2);
+        assert_eq!(binds[&BindGroupId(GenIdx::new(2, 0))], 1);
+
+        let stats = DrawStats {
+            draw_calls: 4,
+            texture_binds: 3,
+            ..Default::default()
+        };
+        let budget = DrawBudget {
+            max_draw_calls: Some(10),
+            max_texture_binds: Some(2),
+        };
+        let most_bound = [("player".to_string(), 2), ("camera".to_string(), 1)];
+        assert_eq!(
+            stats.budget_warning(&budget, &most_bound).as_deref(),
+            Some(
+                "draws over budget: texture_binds=3 (max 2) most_bound=['player' x2, 'camera' x1]"
+            )
+        );
+        assert_eq!(
+            stats.budget_warning(&DrawBudget::default(), &most_bound),
+            None
+        );
+    }
+
+    #[test]
+    fn flushed_passes_keep_occlusion_slots() {
+        let mut buf = CommandBuffer::default();
+        buf.set_render_pass(DrawTarget::INVALID);
+        buf.occlusion_query = Some(3);
+        buf.record(DrawCommand::default());
+        buf.clear_passes();
+        assert!(buf.passes.is_empty() && buf.draws.is_empty());
+
+        // Slots counted before the flush are resolved with the rest of the frame.
+        buf.set_render_pass(DrawTarget::INVALID);
+        buf.record(DrawCommand::default());
+        assert_eq!(buf.draws[0].occlusion_query, Some(1));
+        assert_eq!(buf.occlusion_queries, [3, 3]);
+    }
+
+    #[test]
+    fn sorting_keeps_unsortable_draws_in_place() {
+        let draw = |pipeline: usize, sortable: bool| DrawCommand {
+            pipeline: RenderPipelineId(GenIdx::new(pipeline, 0)),
+            sortable,
+            ..Default::default()
+        };
+        let mut draws = [
+            draw(2, true),
+            draw(1, true),
+            draw(2, true),
+            draw(0, false),
+            draw(3, true),
+            draw(1, true),
+        ];
+
+        sort_draws(&mut draws);
+        let pipelines = draws
+            .iter()
+            .map(|draw| draw.pipeline.0.split().0)
+            .collect::<Vec<_>>();
+        assert_eq!(pipelines, [1, 2, 2, 0, 1, 3]);
+    }
+
+    #[test]
+    fn sorting_groups_draws_by_instance_buffer() {
+        let draw = |instances: Option<usize>| DrawCommand {
+            instances: instances.map(|i| (BufferId(GenIdx::new(i, 0)), 10)),
+            sortable: true,
+            ..Default::default()
+        };
+        let mut draws = [draw(Some(2)), draw(None), draw(Some(1)), draw(Some(2))];
+
+        sort_draws(&mut draws);
+        let instances = draws
+            .iter()
+            .map(|draw| draw.instances.map(|(buffer, _)| buffer.0.split().0))
+            .collect::<Vec<_>>();
+        assert_eq!(instances, [None, Some(1), Some(2), Some(2)]);
+    }
+
+    #[test]
+    fn passes_are_grouped_by_target() {
+        let target = |view: usize| DrawTarget::new(TextureViewId(GenIdx::new(view, 0)));
+        let pass = |target: DrawTarget| RenderPass {
+            target,
+            draws: 0..0,
+        };
+        let passes = [
+            pass(target(1).with_clear_color(Some(Color::BLACK))),
+            pass(target(1)),
+            pass(target(2)),
+            pass(target(1)),
+        ];
+
+        assert_eq!(pass_groups(&passes), [0..2, 2..3, 3..4]);
+    }
 }
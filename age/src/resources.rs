@@ -0,0 +1,159 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
+};
+
+use crate::gen_vec::GenIdx;
+
+/// The kinds of GPU resource whose lifetimes are tracked by a [`ResourceTracker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) enum ResourceKind {
+    Texture,
+    Buffer,
+    BindGroup,
+}
+
+impl Display for ResourceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResourceKind::Texture => write!(f, "texture"),
+            ResourceKind::Buffer => write!(f, "buffer"),
+            ResourceKind::BindGroup => write!(f, "bind group"),
+        }
+    }
+}
+
+type Label = (ResourceKind, String);
+
+/// Keeps the label of each live resource, so that resources which are destroyed and created
+/// again every frame, and resources that are never destroyed, can be reported by label. Nothing
+/// is tracked in release builds, where the reports are not made.
+#[derive(Default)]
+pub(crate) struct ResourceTracker {
+    live: HashMap<(ResourceKind, GenIdx), String>,
+    ignored: HashSet<(ResourceKind, GenIdx)>, // Left out of the live report.
+    created: HashSet<Label>,                  // Since the start of the frame.
+    created_last_frame: HashSet<Label>,
+    destroyed: HashSet<Label>, // Since the start of the last frame.
+    destroyed_this_frame: HashSet<Label>,
+    reported: HashSet<Label>,
+}
+
+impl ResourceTracker {
+    pub(crate) fn created(&mut self, kind: ResourceKind, idx: GenIdx, label: Option<&str>) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+
+        let label = label.unwrap_or("unlabelled").to_string();
+        self.created.insert((kind, label.clone()));
+        self.live.insert((kind, idx), label);
+    }
+
+    pub(crate) fn destroyed(&mut self, kind: ResourceKind, idx: GenIdx) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+
+        self.ignored.remove(&(kind, idx));
+        if let Some(label) = self.live.remove(&(kind, idx)) {
+            self.destroyed_this_frame.insert((kind, label));
+        }
+    }
+
+    /// The label of a live resource.
+    pub(crate) fn label(&self, kind: ResourceKind, idx: GenIdx) -> Option<&str> {
+        self.live.get(&(kind, idx)).map(String::as_str)
+    }
+
+    /// Ends the frame, returning a warning for each label that was created in both this frame
+    /// and the last with one destroyed in between, which suggests the resource is recreated every
+    /// frame. Each label is only warned about once.
+    pub(crate) fn end_frame(&mut self) -> Vec<String> {
+        self.destroyed
+            .extend(self.destroyed_this_frame.iter().cloned());
+        let mut recreated = self
+            .created
+            .iter()
+            .filter(|label| self.created_last_frame.contains(*label))
+            .filter(|label| self.destroyed.contains(*label))
+            .filter(|label| !self.reported.contains(*label))
+            .cloned()
+            .collect::<Vec<_>>();
+        recreated.sort();
+        self.reported.extend(recreated.iter().cloned());
+
+        self.created_last_frame = std::mem::take(&mut self.created);
+        self.destroyed = std::mem::take(&mut self.destroyed_this_frame);
+
+        recreated
+            .into_iter()
+            .map(|(kind, label)| {
+                format!("{kind} '{label}' is recreated every frame, consider keeping it instead")
+            })
+            .collect()
+    }
+
+    /// Leaves the resources that are live now out of the live report.
+    pub(crate) fn ignore_live(&mut self) {
+        self.ignored.extend(self.live.keys().copied());
+    }
+
+    /// A report of the resources that are still live, counted by label, if there are any.
+    pub(crate) fn live_report(&self) -> Option<String> {
+        let mut counts = BTreeMap::<(ResourceKind, &str), usize>::new();
+        for (&(kind, idx), label) in &self.live {
+            if !self.ignored.contains(&(kind, idx)) {
+                *counts.entry((kind, label.as_str())).or_default() += 1;
+            }
+        }
+        if counts.is_empty() {
+            return None;
+        }
+
+        let live = counts
+            .into_iter()
+            .map(|((kind, label), count)| format!("{kind} '{label}' x{count}"))
+            .collect::<Vec<_>>();
+        Some(format!("resources never destroyed: {}", live.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn recreated_resources_are_reported_once() {
+        let mut tracker = ResourceTracker::default();
+        let camera = |frame| GenIdx::new(frame, 0);
+        tracker.created(ResourceKind::Texture, GenIdx::new(99, 0), Some("white"));
+        tracker.ignore_live();
+        tracker.created(ResourceKind::Texture, GenIdx::new(100, 0), Some("atlas"));
+
+        for frame in 0..3 {
+            if frame > 0 {
+                tracker.destroyed(ResourceKind::Buffer, camera(frame - 1));
+            }
+            tracker.created(ResourceKind::Buffer, camera(frame), Some("camera"));
+            let warnings = tracker.end_frame();
+            match frame {
+                1 => assert_eq!(
+                    warnings,
+                    ["buffer 'camera' is recreated every frame, consider keeping it instead"]
+                ),
+                _ => assert!(warnings.is_empty(), "{warnings:?}"),
+            }
+        }
+
+        assert_eq!(
+            tracker.label(ResourceKind::Buffer, camera(2)),
+            Some("camera")
+        );
+        assert_eq!(tracker.label(ResourceKind::Buffer, camera(1)), None);
+        assert_eq!(
+            tracker.live_report().as_deref(),
+            Some("resources never destroyed: texture 'atlas' x1, buffer 'camera' x1")
+        );
+    }
+}
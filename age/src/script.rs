@@ -0,0 +1,336 @@
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::SystemTime,
+};
+
+use rhai::{CallFnOptions, Dynamic, Scope, AST, FLOAT};
+
+use crate::{
+    math::{v2, Rect, Vec2f},
+    Color, Engine, Error, ErrorKind,
+};
+
+/// Game behavior written in [Rhai](https://rhai.rs), loaded from a file and reloaded whenever
+/// the file changes, so it can be iterated on without recompiling the game.
+///
+/// A script may define `fn init()`, called once when it is first loaded, and `fn update()`,
+/// called by [`Script::update`] each frame. Both are called with `this` bound to an object map
+/// that keeps the script's state, including across reloads. Scripts can call:
+///
+/// - `time()` and `delta_time()`, as on [`Engine`].
+/// - `key_held(name)`, where `name` is a key's [`display_name`](crate::KeyCode::display_name),
+///   ignoring case, e.g. `key_held("space")`.
+/// - `mouse_x()` and `mouse_y()`, the mouse position in logical pixels.
+/// - `rgb(r, g, b)` and `rgba(r, g, b, a)` to make colors.
+/// - `draw_line(x1, y1, x2, y2, width, color)` and `fill_rect(x, y, width, height, color)`.
+/// - `after(seconds, "name")` to call the script function `name` once after a delay.
+pub struct Script {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    engine: rhai::Engine,
+    ast: AST,
+    this: Dynamic,
+    context: Rc<RefCell<ScriptContext>>,
+}
+
+/// What a script can see of the engine during a call, and what it asked the engine to do.
+#[derive(Default)]
+struct ScriptContext {
+    time: f32,
+    delta_time: f32,
+    keys_held: Vec<String>,
+    mouse_position: Vec2f,
+    draws: Vec<ScriptDraw>,
+    timers: Vec<ScriptTimer>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScriptDraw {
+    Line(Vec2f, Vec2f, f32, Color),
+    Rect(Rect, Color),
+}
+
+struct ScriptTimer {
+    remaining: f32,
+    function: String,
+}
+
+impl Script {
+    /// Loads the script at `path` and calls its `init` function, if it has one.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let context = Rc::new(RefCell::new(ScriptContext::default()));
+        let engine = create_engine(&context);
+        let modified = modified_time(&path);
+        let ast = compile(&engine, &path)?;
+
+        let mut script = Self {
+            path,
+            modified,
+            engine,
+            ast,
+            this: Dynamic::from_map(rhai::Map::new()),
+            context,
+        };
+        script.call_if_defined("init")?;
+
+        Ok(script)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reloads the script if its file has changed since it was last loaded, returning whether it
+    /// was. If the changed script does not compile, the error is returned once and the previous
+    /// script keeps running until the file changes again, as it does while the file is missing,
+    /// e.g. part way through an editor saving it.
+    pub fn reload_if_changed(&mut self) -> Result<bool, Error> {
+        let modified = modified_time(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return Ok(false);
+        }
+
+        self.modified = modified;
+        self.ast = compile(&self.engine, &self.path)?;
+
+        Ok(true)
+    }
+
+    /// Reloads the script if it has changed, fires any timers that are due, then calls the
+    /// script's `update` function and draws what it asked for.
+    pub fn update(&mut self, age: &mut Engine) -> Result<(), Error> {
+        let keys_held = age.keyboard.held_keys().map(|key| key.display_name());
+        {
+            let mut context = self.context.borrow_mut();
+            context.time = age.time();
+            context.delta_time = age.delta_time();
+            context.keys_held = keys_held.collect();
+            context.mouse_position = age.mouse_position_logical();
+        }
+
+        let result = self.tick();
+        for draw in self.context.borrow_mut().draws.drain(..) {
+            match draw {
+                ScriptDraw::Line(from, to, width, color) => {
+                    age.graphics.draw_line(from, to, width, color)
+                }
+                ScriptDraw::Rect(rect, color) => age.graphics.fill_rect(rect, color),
+            }
+        }
+
+        result
+    }
+
+    fn tick(&mut self) -> Result<(), Error> {
+        self.reload_if_changed()?;
+
+        let due = {
+            let mut context = self.context.borrow_mut();
+            let delta_time = context.delta_time;
+            let mut due = Vec::new();
+            context.timers.retain_mut(|timer| {
+                timer.remaining -= delta_time;
+                if timer.remaining <= 0.0 {
+                    due.push(std::mem::take(&mut timer.function));
+                }
+                timer.remaining > 0.0
+            });
+            due
+        };
+        for function in due {
+            self.call_if_defined(&function)?;
+        }
+
+        self.call_if_defined("update")
+    }
+
+    fn call_if_defined(&mut self, name: &str) -> Result<(), Error> {
+        let defined = self
+            .ast
+            .iter_functions()
+            .any(|function| function.name == name && function.params.is_empty());
+        if !defined {
+            return Ok(());
+        }
+
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.this);
+        self.engine
+            .call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &self.ast, name, ())
+            .map(|_| ())
+            .map_err(|err| {
+                Error::new(format!("script function `{name}` failed"))
+                    .with_kind(ErrorKind::Other)
+                    .with_source(err)
+            })
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn compile(engine: &rhai::Engine, path: &Path) -> Result<AST, Error> {
+    let source = std::fs::read_to_string(path).map_err(|err| {
+        Error::new(format!("failed to read script `{}`", path.display()))
+            .with_kind(ErrorKind::Asset)
+            .with_source(err)
+    })?;
+
+    engine.compile(source).map_err(|err| {
+        Error::new(format!("failed to compile script `{}`", path.display()))
+            .with_kind(ErrorKind::Asset)
+            .with_source(err)
+    })
+}
+
+fn create_engine(context: &Rc<RefCell<ScriptContext>>) -> rhai::Engine {
+    let mut engine = rhai::Engine::new();
+    engine.register_type_with_name::<Color>("Color");
+    engine.register_fn("rgb", |r: FLOAT, g: FLOAT, b: FLOAT| {
+        Color::rgb(r as f32, g as f32, b as f32)
+    });
+    engine.register_fn("rgba", |r: FLOAT, g: FLOAT, b: FLOAT, a: FLOAT| {
+        Color::rgba(r as f32, g as f32, b as f32, a as f32)
+    });
+
+    let ctx = context.clone();
+    engine.register_fn("time", move || ctx.borrow().time as FLOAT);
+    let ctx = context.clone();
+    engine.register_fn("delta_time", move || ctx.borrow().delta_time as FLOAT);
+    let ctx = context.clone();
+    engine.register_fn("key_held", move |name: &str| {
+        let context = ctx.borrow();
+        context
+            .keys_held
+            .iter()
+            .any(|key| key.eq_ignore_ascii_case(name))
+    });
+    let ctx = context.clone();
+    engine.register_fn("mouse_x", move || ctx.borrow().mouse_position.x as FLOAT);
+    let ctx = context.clone();
+    engine.register_fn("mouse_y", move || ctx.borrow().mouse_position.y as FLOAT);
+
+    let ctx = context.clone();
+    engine.register_fn(
+        "draw_line",
+        move |x1: FLOAT, y1: FLOAT, x2: FLOAT, y2: FLOAT, width: FLOAT, color: Color| {
+            let from = v2(x1 as f32, y1 as f32);
+            let to = v2(x2 as f32, y2 as f32);
+            let draw = ScriptDraw::Line(from, to, width as f32, color);
+            ctx.borrow_mut().draws.push(draw);
+        },
+    );
+    let ctx = context.clone();
+    engine.register_fn(
+        "fill_rect",
+        move |x: FLOAT, y: FLOAT, width: FLOAT, height: FLOAT, color: Color| {
+            let rect = Rect::new(v2(x as f32, y as f32), v2(width as f32, height as f32));
+            ctx.borrow_mut().draws.push(ScriptDraw::Rect(rect, color));
+        },
+    );
+
+    let ctx = context.clone();
+    engine.register_fn("after", move |seconds: FLOAT, function: &str| {
+        ctx.borrow_mut().timers.push(ScriptTimer {
+            remaining: seconds as f32,
+            function: function.to_string(),
+        });
+    });
+
+    engine
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn write_script(name: &str, source: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("age-{}-{}.rhai", name, std::process::id()));
+        std::fs::write(&path, source).unwrap();
+        path
+    }
+
+    #[test]
+    fn scripts_draw_from_input_and_keep_state() {
+        let path = write_script(
+            "draw",
+            r#"
+            fn init() { this.count = 0; }
+            fn update() {
+                this.count += 1;
+                if key_held("space") {
+                    fill_rect(mouse_x(), mouse_y(), 2.0, 3.0, rgb(1.0, 0.0, 0.0));
+                }
+            }
+            "#,
+        );
+        let mut script = Script::load(&path).unwrap();
+        script.context.borrow_mut().keys_held = vec!["Space".to_string()];
+        script.context.borrow_mut().mouse_position = v2(4.0, 5.0);
+        script.tick().unwrap();
+        script.tick().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let rect = Rect::new(v2(4.0, 5.0), v2(2.0, 3.0));
+        let expected = ScriptDraw::Rect(rect, Color::rgb(1.0, 0.0, 0.0));
+        assert_eq!(script.context.borrow().draws, vec![expected, expected]);
+        let count = script.this.read_lock::<rhai::Map>().unwrap()["count"].as_int();
+        assert_eq!(count, Ok(2));
+    }
+
+    #[test]
+    fn timers_call_functions_once_due() {
+        let path = write_script(
+            "timers",
+            r#"
+            fn init() { this.fired = 0; after(1.0, "fire"); }
+            fn fire() { this.fired += 1; }
+            "#,
+        );
+        let mut script = Script::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        script.context.borrow_mut().delta_time = 0.6;
+        let fired =
+            |script: &Script| script.this.read_lock::<rhai::Map>().unwrap()["fired"].as_int();
+
+        script.tick().unwrap();
+        assert_eq!(fired(&script), Ok(0));
+        script.tick().unwrap();
+        assert_eq!(fired(&script), Ok(1));
+        script.tick().unwrap();
+        assert_eq!(fired(&script), Ok(1));
+    }
+
+    #[test]
+    fn changed_scripts_are_reloaded() {
+        let path = write_script("reload", "fn update() { this.version = 1; }");
+        let mut script = Script::load(&path).unwrap();
+        assert!(!script.reload_if_changed().unwrap());
+
+        // Make sure the change is seen even on file systems with coarse modification times.
+        std::fs::write(&path, "fn update() { this.version = 2; }").unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        script.tick().unwrap();
+        let version = script.this.read_lock::<rhai::Map>().unwrap()["version"].as_int();
+        assert_eq!(version, Ok(2));
+
+        std::fs::write(&path, "fn update( {").unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(20))
+            .unwrap();
+        let err = script.reload_if_changed().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Asset);
+        assert!(!script.reload_if_changed().unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
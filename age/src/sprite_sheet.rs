@@ -0,0 +1,250 @@
+use crate::{
+    graphics::MaterialId, renderer::Renderer, Color, Engine, Graphics, Sprite, Texture,
+    TextureOptions, TransientTextureDesc, View,
+};
+
+/// A texture divided into equally sized cells, numbered from left to right and top to bottom,
+/// such as the frames of a character animation.
+#[derive(Debug, Clone)]
+pub struct SpriteSheet {
+    texture: Texture,
+    cell_width: u32,
+    cell_height: u32,
+    columns: u32,
+    rows: u32,
+    margin: u32,
+    spacing: u32,
+}
+
+impl SpriteSheet {
+    /// Divides `texture` into as many whole cells as fit, where `margin` is the border around the
+    /// edge of the texture and `spacing` is the gap between neighbouring cells, both in pixels.
+    pub fn from_grid(
+        texture: &Texture,
+        cell_width: u32,
+        cell_height: u32,
+        margin: u32,
+        spacing: u32,
+    ) -> Self {
+        let (columns, rows) = grid_size(texture.size(), (cell_width, cell_height), margin, spacing);
+
+        Self {
+            texture: *texture,
+            cell_width,
+            cell_height,
+            columns,
+            rows,
+            margin,
+            spacing,
+        }
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    pub fn cell_size(&self) -> (u32, u32) {
+        (self.cell_width, self.cell_height)
+    }
+
+    pub fn len(&self) -> usize {
+        (self.columns * self.rows) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The index of the cell at `column` and `row`.
+    pub fn index(&self, column: u32, row: u32) -> usize {
+        (row * self.columns + column) as usize
+    }
+
+    /// The top-left corner of the cell at `index`, in pixels, or `None` if there is no such cell.
+    pub fn origin(&self, index: usize) -> Option<(u32, u32)> {
+        if index >= self.len() {
+            return None;
+        }
+
+        let (column, row) = (index as u32 % self.columns, index as u32 / self.columns);
+        Some((
+            self.margin + column * (self.cell_width + self.spacing),
+            self.margin + row * (self.cell_height + self.spacing),
+        ))
+    }
+
+    /// Bakes `frames` frames of `cell_size` pixels into a new sprite sheet by calling `draw` for
+    /// each of them, e.g. to turn a procedural explosion into a flipbook at load time rather than
+    /// drawing it from scratch every frame. `draw` is given the index of the frame and how far
+    /// through the animation it is, from `0.0` for the first frame to `1.0` for the last, and
+    /// draws with the top-left of the cell at the world origin onto a transparent background.
+    ///
+    /// The frames are drawn when the current frame is submitted, so the sheet can be used
+    /// straight away. The returned animation plays the frames in order, each for
+    /// `frame_duration` seconds.
+    pub fn bake<F>(
+        age: &mut Engine,
+        frames: usize,
+        cell_size: (u32, u32),
+        frame_duration: f32,
+        mut draw: F,
+    ) -> (Self, Animation)
+    where
+        F: FnMut(&mut Graphics, usize, f32),
+    {
+        assert!(frames > 0, "a flipbook needs at least one frame");
+
+        let (columns, rows) = flipbook_grid(frames);
+        let options = TextureOptions {
+            label: Some("flipbook"),
+            ..Default::default()
+        };
+        let sheet = age.graphics.create_render_texture(
+            &mut age.renderer,
+            columns * cell_size.0,
+            rows * cell_size.1,
+            &options,
+        );
+        let cell = age.graphics.acquire_transient_texture(
+            &mut age.renderer,
+            &TransientTextureDesc::new(cell_size.0, cell_size.1),
+        );
+
+        let view = View::new(cell_size.0, cell_size.1);
+        let last = (frames - 1).max(1) as f32;
+        for frame in 0..frames {
+            age.graphics
+                .draw_offscreen((&cell).into(), view, |graphics| {
+                    graphics.clear(Color::TRANSPARENT);
+                    draw(graphics, frame, frame as f32 / last);
+                });
+
+            let (column, row) = (frame as u32 % columns, frame as u32 / columns);
+            age.graphics
+                .copy_texture(
+                    cell.texture(),
+                    (0, 0),
+                    sheet.texture(),
+                    (column * cell_size.0, row * cell_size.1),
+                    cell_size,
+                )
+                .expect("every cell fits in the sheet");
+        }
+
+        let sheet = Self::from_grid(sheet.texture(), cell_size.0, cell_size.1, 0, 0);
+        let animation = Animation::new((0..frames).collect(), frame_duration);
+        (sheet, animation)
+    }
+
+    /// Creates a sprite that draws the cell at `index`.
+    pub fn sprite(&self, renderer: &mut Renderer, index: usize, material: MaterialId) -> Sprite {
+        let origin = self
+            .origin(index)
+            .unwrap_or_else(|| panic!("sprite sheet has no cell {}", index));
+
+        Sprite::from_texture_region(renderer, &self.texture, origin, self.cell_size(), material)
+    }
+}
+
+/// A sequence of sprite sheet cells, each shown for the same length of time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    frames: Vec<usize>,
+    frame_duration: f32,
+    looping: bool,
+}
+
+impl Animation {
+    /// Plays the cells at `frames` in order, each for `frame_duration` seconds, once.
+    pub fn new(frames: Vec<usize>, frame_duration: f32) -> Self {
+        assert!(!frames.is_empty(), "an animation needs at least one frame");
+        Self {
+            frames,
+            frame_duration,
+            looping: false,
+        }
+    }
+
+    /// Starts again from the first frame after the last one, rather than stopping on it.
+    pub fn with_looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    pub fn frames(&self) -> &[usize] {
+        &self.frames
+    }
+
+    /// How long it takes to play every frame once.
+    pub fn duration(&self) -> f32 {
+        self.frames.len() as f32 * self.frame_duration
+    }
+
+    /// The sprite sheet cell to show `elapsed` seconds after the animation started.
+    pub fn frame_at(&self, elapsed: f32) -> usize {
+        let frame = (elapsed.max(0.0) / self.frame_duration.max(f32::EPSILON)) as usize;
+        let frame = match self.looping {
+            true => frame % self.frames.len(),
+            false => frame.min(self.frames.len() - 1),
+        };
+        self.frames[frame]
+    }
+
+    pub fn is_finished(&self, elapsed: f32) -> bool {
+        !self.looping && elapsed >= self.duration()
+    }
+}
+
+/// The columns and rows of the smallest near-square grid with room for `frames` cells.
+fn flipbook_grid(frames: usize) -> (u32, u32) {
+    let columns = (frames as f32).sqrt().ceil() as u32;
+    (columns, (frames as u32).div_ceil(columns))
+}
+
+fn grid_size(size: (u32, u32), cell: (u32, u32), margin: u32, spacing: u32) -> (u32, u32) {
+    let count = |size: u32, cell: u32| {
+        let available = size.saturating_sub(2 * margin) + spacing;
+        available / (cell + spacing).max(1)
+    };
+
+    (count(size.0, cell.0), count(size.1, cell.1))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn grid_counts_whole_cells() {
+        assert_eq!(grid_size((64, 32), (16, 16), 0, 0), (4, 2));
+        assert_eq!(grid_size((70, 32), (16, 16), 0, 0), (4, 2));
+    }
+
+    #[test]
+    fn flipbooks_fill_a_near_square_grid() {
+        assert_eq!(flipbook_grid(1), (1, 1));
+        assert_eq!(flipbook_grid(10), (4, 3));
+        assert_eq!(flipbook_grid(16), (4, 4));
+
+        let animation = Animation::new(vec![4, 5, 6], 0.1);
+        assert_eq!(animation.frame_at(0.15), 5);
+        assert_eq!(animation.frame_at(1.0), 6);
+        assert!(animation.is_finished(0.3));
+        assert_eq!(animation.with_looping(true).frame_at(0.35), 4);
+    }
+
+    #[test]
+    fn grid_allows_for_margin_and_spacing() {
+        // 1 + 16 + 2 + 16 + 2 + 16 + 1 = 54
+        assert_eq!(grid_size((54, 18), (16, 16), 1, 2), (3, 1));
+        assert_eq!(grid_size((53, 18), (16, 16), 1, 2), (2, 1));
+    }
+}
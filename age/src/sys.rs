@@ -1,8 +1,19 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use winit::{dpi::LogicalSize, event_loop::ControlFlow};
+use winit::{
+    dpi::{LogicalPosition, LogicalSize},
+    event_loop::ControlFlow,
+};
 
-use crate::error::Error;
+use crate::{
+    error::{Error, ErrorKind},
+    i18n::Localization,
+    math::{v2, Vec2f},
+};
 
 pub(crate) struct Sys {
     el: Option<winit::event_loop::EventLoop<()>>,
@@ -14,11 +25,18 @@ impl Sys {
         Ok(Self { el })
     }
 
-    pub(crate) fn create_window(&self, width: u32, height: u32) -> Result<Window, Error> {
+    pub(crate) fn create_window(
+        &self,
+        width: u32,
+        height: u32,
+        title: &str,
+        transparent: bool,
+    ) -> Result<Window, Error> {
         let size = LogicalSize::new(width, height);
         let w = winit::window::WindowBuilder::new()
-            .with_title("age")
+            .with_title(title)
             .with_inner_size(size)
+            .with_transparent(transparent)
             .with_visible(false)
             .build(self.el.as_ref().unwrap())?;
         Ok(Window { w: Arc::new(w) })
@@ -32,20 +50,91 @@ impl Sys {
         let mut platform = Platform::default();
         let mut result = Ok(());
         el.run(|e, el| {
-            el.set_control_flow(ControlFlow::Poll);
-
             #[allow(clippy::collapsible_match)]
             let event = match e {
                 winit::event::Event::WindowEvent { event: e, .. } => match e {
                     winit::event::WindowEvent::CloseRequested => Some(Event::ExitRequested),
 
+                    winit::event::WindowEvent::KeyboardInput { event, .. } => {
+                        // Use the text produced by the key rather than the logical key, as this has
+                        // already been composed by the platform with any preceding dead keys.
+                        let text = match &event.text {
+                            Some(text) if event.state.is_pressed() => {
+                                text.chars().filter(|c| !c.is_control()).collect()
+                            }
+                            _ => String::new(),
+                        };
+
+                        Some(Event::KeyboardInput(KeyboardInput {
+                            key: KeyCode::try_from(&event.logical_key).ok(),
+                            scancode: ScanCode(event.physical_key),
+                            pressed: event.state.is_pressed(),
+                            repeat: event.repeat,
+                            text,
+                        }))
+                    }
+
+                    winit::event::WindowEvent::CursorMoved { position, .. } => {
+                        Some(Event::MouseMoved {
+                            x: position.x,
+                            y: position.y,
+                        })
+                    }
+
+                    winit::event::WindowEvent::MouseInput { state, button, .. } => {
+                        Some(Event::MouseInput {
+                            button: button.into(),
+                            pressed: state.is_pressed(),
+                        })
+                    }
+
+                    winit::event::WindowEvent::MouseWheel { delta, .. } => match delta {
+                        winit::event::MouseScrollDelta::LineDelta(x, y) => {
+                            Some(Event::MouseScrolled(ScrollDelta::Lines { x, y }))
+                        }
+                        winit::event::MouseScrollDelta::PixelDelta(position) => {
+                            Some(Event::MouseScrolled(ScrollDelta::Pixels {
+                                x: position.x,
+                                y: position.y,
+                            }))
+                        }
+                    },
+
+                    winit::event::WindowEvent::Touch(touch) => Some(Event::Touch {
+                        id: TouchId(touch.id),
+                        phase: touch.phase,
+                        x: touch.location.x,
+                        y: touch.location.y,
+                    }),
+
                     winit::event::WindowEvent::RedrawRequested => Some(Event::Update),
 
+                    winit::event::WindowEvent::Focused(false) => Some(Event::FocusLost),
+
+                    winit::event::WindowEvent::Resized(size) => Some(Event::Resized {
+                        width: size.width,
+                        height: size.height,
+                    }),
+
+                    winit::event::WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                        Some(Event::ScaleFactorChanged(scale_factor))
+                    }
+
                     _ => None,
                 },
 
                 winit::event::Event::Resumed => Some(Event::PlatformReady),
 
+                winit::event::Event::Suspended => Some(Event::PlatformSuspended),
+
+                winit::event::Event::AboutToWait => match platform.wake_at {
+                    Some(at) if Instant::now() >= at => {
+                        platform.wake_at = None;
+                        Some(Event::WakeUp)
+                    }
+                    _ => None,
+                },
+
                 _ => None,
             };
 
@@ -55,6 +144,11 @@ impl Sys {
                     el.exit();
                 }
             }
+
+            match platform.wake_at {
+                Some(at) => el.set_control_flow(ControlFlow::WaitUntil(at)),
+                None => el.set_control_flow(ControlFlow::Poll),
+            }
         })?;
 
         result
@@ -64,12 +158,18 @@ impl Sys {
 #[derive(Default)]
 pub(crate) struct Platform {
     exit: bool,
+    wake_at: Option<Instant>,
 }
 
 impl Platform {
     pub(crate) fn exit(&mut self) {
         self.exit = true;
     }
+
+    /// Keeps handling events until `at`, then sends [`Event::WakeUp`].
+    pub(crate) fn wake_at(&mut self, at: Instant) {
+        self.wake_at = Some(at);
+    }
 }
 
 #[derive(Clone)]
@@ -90,10 +190,58 @@ impl Window {
         self.w.request_redraw();
     }
 
+    pub(crate) fn request_redraw(&self) {
+        self.w.request_redraw();
+    }
+
     pub(crate) fn pre_present(&self) {
         self.w.pre_present_notify();
     }
 
+    pub(crate) fn scale_factor(&self) -> f64 {
+        self.w.scale_factor()
+    }
+
+    /// The refresh rate in hertz of the monitor the window is on, if the platform reports it.
+    pub(crate) fn refresh_rate(&self) -> Option<f32> {
+        let millihertz = self.w.current_monitor()?.refresh_rate_millihertz()?;
+        Some(millihertz as f32 / 1000.0)
+    }
+
+    pub(crate) fn set_cursor_position(&self, x: f32, y: f32) -> Result<(), Error> {
+        self.w.set_cursor_position(LogicalPosition::new(x, y))?;
+        Ok(())
+    }
+
+    pub(crate) fn request_user_attention(
+        &self,
+        attention: Option<winit::window::UserAttentionType>,
+    ) {
+        self.w.request_user_attention(attention);
+    }
+
+    #[cfg(feature = "notifications")]
+    pub(crate) fn title(&self) -> String {
+        self.w.title()
+    }
+
+    pub(crate) fn set_title(&self, title: &str) {
+        self.w.set_title(title);
+    }
+
+    pub(crate) fn set_decorations(&self, decorations: bool) {
+        self.w.set_decorations(decorations);
+    }
+
+    pub(crate) fn set_resizable(&self, resizable: bool) {
+        self.w.set_resizable(resizable);
+    }
+
+    pub(crate) fn drag(&self) -> Result<(), Error> {
+        self.w.drag_window()?;
+        Ok(())
+    }
+
     pub(crate) fn set_visible(&self, visible: bool) {
         self.w.set_visible(visible);
     }
@@ -115,21 +263,1230 @@ impl raw_window_handle::HasWindowHandle for Window {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) enum Event {
     ExitRequested,
+    KeyboardInput(KeyboardInput),
+    MouseInput {
+        button: MouseButton,
+        pressed: bool,
+    },
+    MouseMoved {
+        x: f64,
+        y: f64,
+    },
+    MouseScrolled(ScrollDelta),
+    Touch {
+        id: TouchId,
+        phase: winit::event::TouchPhase,
+        x: f64,
+        y: f64,
+    },
+    FocusLost,
     PlatformReady,
+    PlatformSuspended,
+    Resized {
+        width: u32,
+        height: u32,
+    },
+    ScaleFactorChanged(f64),
     Update,
+    WakeUp,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct KeyboardInput {
+    pub(crate) key: Option<KeyCode>,
+    pub(crate) scancode: ScanCode,
+    pub(crate) pressed: bool,
+    pub(crate) repeat: bool,
+    pub(crate) text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ScrollDelta {
+    Lines { x: f32, y: f32 },
+    Pixels { x: f64, y: f64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Back,
+    Forward,
+    Other(u16),
+}
+
+impl From<winit::event::MouseButton> for MouseButton {
+    fn from(button: winit::event::MouseButton) -> Self {
+        match button {
+            winit::event::MouseButton::Left => MouseButton::Left,
+            winit::event::MouseButton::Right => MouseButton::Right,
+            winit::event::MouseButton::Middle => MouseButton::Middle,
+            winit::event::MouseButton::Back => MouseButton::Back,
+            winit::event::MouseButton::Forward => MouseButton::Forward,
+            winit::event::MouseButton::Other(id) => MouseButton::Other(id),
+        }
+    }
+}
+
+pub struct Mouse {
+    held: HashSet<MouseButton>,
+    pressed: HashSet<MouseButton>,
+    released: HashSet<MouseButton>,
+    position: Vec2f,
+    scale_factor: f64,
+    scroll_lines: Vec2f,
+    scroll_pixels: Vec2f,
+    scroll_smoothed: Vec2f,
+    scroll_smoothing: f32,
+    pixels_per_line: f32,
+}
+
+impl Mouse {
+    pub(crate) fn new(scale_factor: f64) -> Self {
+        Self {
+            held: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+            position: Vec2f::ZERO,
+            scale_factor,
+            scroll_lines: Vec2f::ZERO,
+            scroll_pixels: Vec2f::ZERO,
+            scroll_smoothed: Vec2f::ZERO,
+            scroll_smoothing: 0.0,
+            pixels_per_line: 20.0,
+        }
+    }
+
+    pub(crate) fn on_event(&mut self, event: &Event) {
+        match *event {
+            Event::MouseInput { button, pressed } => {
+                if pressed {
+                    if self.held.insert(button) {
+                        self.pressed.insert(button);
+                    }
+                } else if self.held.remove(&button) {
+                    self.released.insert(button);
+                }
+            }
+            Event::MouseMoved { x, y } => self.position = v2(x as f32, y as f32),
+            Event::MouseScrolled(ScrollDelta::Lines { x, y }) => self.scroll_lines += v2(x, y),
+            Event::MouseScrolled(ScrollDelta::Pixels { x, y }) => {
+                self.scroll_pixels += v2(x as f32, y as f32)
+            }
+            Event::ScaleFactorChanged(scale_factor) => self.scale_factor = scale_factor,
+            _ => {}
+        }
+    }
+
+    pub(crate) fn update(&mut self) {
+        let s = self.scroll_smoothing;
+        self.scroll_smoothed = self.scroll_smoothed * s + self.scroll_delta() * (1.0 - s);
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        self.scroll_lines = Vec2f::ZERO;
+        self.scroll_pixels = Vec2f::ZERO;
+    }
+
+    /// Whether `button` is currently held down.
+    pub fn held(&self, button: MouseButton) -> bool {
+        self.held.contains(&button)
+    }
+
+    /// Whether `button` was pressed this frame.
+    pub fn pressed(&self, button: MouseButton) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    /// Whether `button` was released this frame.
+    pub fn released(&self, button: MouseButton) -> bool {
+        self.released.contains(&button)
+    }
+
+    pub fn held_buttons(&self) -> impl Iterator<Item = MouseButton> + '_ {
+        self.held.iter().copied()
+    }
+
+    /// The position of the mouse cursor in logical pixels, relative to the top-left of the window.
+    pub fn position_logical(&self) -> Vec2f {
+        self.position / self.scale_factor as f32
+    }
+
+    /// The position of the mouse cursor in physical pixels, relative to the top-left of the window.
+    pub fn position_physical(&self) -> Vec2f {
+        self.position
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// The distance scrolled this frame in lines. Pixel based scrolling, such as from a trackpad,
+    /// is converted to lines using the pixels per line setting.
+    pub fn scroll_delta(&self) -> Vec2f {
+        self.scroll_lines + self.scroll_pixels / self.pixels_per_line
+    }
+
+    /// The distance scrolled this frame in physical pixels. Line based scrolling, such as from a
+    /// mouse wheel, is converted to pixels using the pixels per line setting.
+    pub fn scroll_delta_pixels(&self) -> Vec2f {
+        self.scroll_pixels + self.scroll_lines * self.pixels_per_line
+    }
+
+    /// The distance scrolled in lines, smoothed across frames according to the scroll smoothing
+    /// setting.
+    pub fn scroll_delta_smoothed(&self) -> Vec2f {
+        self.scroll_smoothed
+    }
+
+    pub fn get_pixels_per_line(&self) -> f32 {
+        self.pixels_per_line
+    }
+
+    /// Sets how many pixels a line of scrolling is, which is at least one.
+    pub fn set_pixels_per_line(&mut self, pixels_per_line: f32) {
+        self.pixels_per_line = pixels_per_line.max(1.0);
+    }
+
+    pub fn get_scroll_smoothing(&self) -> f32 {
+        self.scroll_smoothing
+    }
+
+    /// Sets how much of the previous frame's scroll is carried into the smoothed scroll delta, in
+    /// the range `0.0..1.0`. A value of `0.0` disables smoothing.
+    pub fn set_scroll_smoothing(&mut self, smoothing: f32) {
+        self.scroll_smoothing = smoothing.clamp(0.0, 0.99);
+    }
+
+    pub(crate) fn set_position_logical(&mut self, position: Vec2f) {
+        self.position = position * self.scale_factor as f32;
+    }
+}
+
+/// Identifies a finger on a touch screen from when it touches the screen until it is lifted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TouchId(u64);
+
+/// The fingers on a touch screen, which are tracked separately from the mouse.
+pub struct Touches {
+    positions: HashMap<TouchId, Vec2f>, // In physical pixels, of fingers on the screen.
+    started: HashSet<TouchId>,
+    ended: HashMap<TouchId, Vec2f>, // Where fingers were lifted this frame.
+    scale_factor: f64,
+}
+
+impl Touches {
+    pub(crate) fn new(scale_factor: f64) -> Self {
+        Self {
+            positions: HashMap::new(),
+            started: HashSet::new(),
+            ended: HashMap::new(),
+            scale_factor,
+        }
+    }
+
+    pub(crate) fn on_event(&mut self, event: &Event) {
+        match *event {
+            Event::Touch { id, phase, x, y } => {
+                let position = v2(x as f32, y as f32);
+                match phase {
+                    winit::event::TouchPhase::Started => {
+                        self.positions.insert(id, position);
+                        self.started.insert(id);
+                    }
+                    winit::event::TouchPhase::Moved => {
+                        self.positions.insert(id, position);
+                    }
+                    // A cancelled touch, e.g. one taken over by a system gesture, is still ended
+                    // so that anything it started is finished.
+                    winit::event::TouchPhase::Ended | winit::event::TouchPhase::Cancelled => {
+                        self.positions.remove(&id);
+                        self.ended.insert(id, position);
+                    }
+                }
+            }
+            Event::ScaleFactorChanged(scale_factor) => self.scale_factor = scale_factor,
+            _ => {}
+        }
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.started.clear();
+        self.ended.clear();
+    }
+
+    /// The fingers currently on the screen and their positions in physical pixels, relative to
+    /// the top-left of the window.
+    pub fn held(&self) -> impl Iterator<Item = (TouchId, Vec2f)> + '_ {
+        self.positions.iter().map(|(id, position)| (*id, *position))
+    }
+
+    /// Whether `id` touched the screen this frame.
+    pub fn started(&self, id: TouchId) -> bool {
+        self.started.contains(&id)
+    }
+
+    /// Where `id` was lifted from the screen this frame, in physical pixels, if it was.
+    pub fn ended(&self, id: TouchId) -> Option<Vec2f> {
+        self.ended.get(&id).copied()
+    }
+
+    /// The fingers lifted from the screen this frame and where they were lifted, in physical
+    /// pixels.
+    pub fn ended_touches(&self) -> impl Iterator<Item = (TouchId, Vec2f)> + '_ {
+        self.ended.iter().map(|(id, position)| (*id, *position))
+    }
+
+    /// The position of `id` in logical pixels, relative to the top-left of the window.
+    pub fn position_logical(&self, id: TouchId) -> Option<Vec2f> {
+        self.position_physical(id)
+            .map(|position| position / self.scale_factor as f32)
+    }
+
+    /// The position of `id` in physical pixels, relative to the top-left of the window.
+    pub fn position_physical(&self, id: TouchId) -> Option<Vec2f> {
+        self.positions.get(&id).copied()
+    }
+
+    pub fn count(&self) -> usize {
+        self.positions.len()
+    }
+}
+
+/// A logical key, i.e. the key as it is interpreted by the current keyboard layout.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum KeyCode {
+    /// A key that produces a single character. Letters are always lower case.
+    Char(char),
+    /// A key that produces more than one character, which happens with some layouts and IMEs.
+    Str(String),
+    /// A dead key, which combines with the following key to produce a character.
+    Dead(Option<char>),
+    Alt,
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+    ArrowUp,
+    Backspace,
+    CapsLock,
+    Control,
+    Delete,
+    End,
+    Enter,
+    Escape,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    Home,
+    Insert,
+    PageDown,
+    PageUp,
+    Shift,
+    Space,
+    Super,
+    Tab,
+}
+
+impl TryFrom<&winit::keyboard::Key> for KeyCode {
+    type Error = Error;
+
+    fn try_from(key: &winit::keyboard::Key) -> Result<Self, Self::Error> {
+        use winit::keyboard::{Key, NamedKey};
+
+        let key = match key {
+            Key::Character(s) => {
+                let mut lower = s.chars().flat_map(char::to_lowercase);
+                match (lower.next(), lower.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    (Some(_), Some(_)) => KeyCode::Str(s.to_lowercase()),
+                    (None, _) => return Err("key does not produce any characters".into()),
+                }
+            }
+            Key::Dead(c) => KeyCode::Dead(*c),
+            Key::Named(named) => match named {
+                NamedKey::Alt => KeyCode::Alt,
+                NamedKey::ArrowDown => KeyCode::ArrowDown,
+                NamedKey::ArrowLeft => KeyCode::ArrowLeft,
+                NamedKey::ArrowRight => KeyCode::ArrowRight,
+                NamedKey::ArrowUp => KeyCode::ArrowUp,
+                NamedKey::Backspace => KeyCode::Backspace,
+                NamedKey::CapsLock => KeyCode::CapsLock,
+                NamedKey::Control => KeyCode::Control,
+                NamedKey::Delete => KeyCode::Delete,
+                NamedKey::End => KeyCode::End,
+                NamedKey::Enter => KeyCode::Enter,
+                NamedKey::Escape => KeyCode::Escape,
+                NamedKey::F1 => KeyCode::F1,
+                NamedKey::F2 => KeyCode::F2,
+                NamedKey::F3 => KeyCode::F3,
+                NamedKey::F4 => KeyCode::F4,
+                NamedKey::F5 => KeyCode::F5,
+                NamedKey::F6 => KeyCode::F6,
+                NamedKey::F7 => KeyCode::F7,
+                NamedKey::F8 => KeyCode::F8,
+                NamedKey::F9 => KeyCode::F9,
+                NamedKey::F10 => KeyCode::F10,
+                NamedKey::F11 => KeyCode::F11,
+                NamedKey::F12 => KeyCode::F12,
+                NamedKey::Home => KeyCode::Home,
+                NamedKey::Insert => KeyCode::Insert,
+                NamedKey::PageDown => KeyCode::PageDown,
+                NamedKey::PageUp => KeyCode::PageUp,
+                NamedKey::Shift => KeyCode::Shift,
+                NamedKey::Space => KeyCode::Space,
+                NamedKey::Super => KeyCode::Super,
+                NamedKey::Tab => KeyCode::Tab,
+                _ => return Err(Error::new(format!("key {:?} is not supported", named))),
+            },
+            _ => return Err(Error::new(format!("key {:?} is not supported", key))),
+        };
+
+        Ok(key)
+    }
+}
+
+impl KeyCode {
+    /// The name of the key to show to players, e.g. in a key binding menu. Keys that type
+    /// characters are named by the character in upper case, e.g. `A`, and dead keys by their
+    /// accent, e.g. `´`. To name a key by where it is on the keyboard, use
+    /// [`ScanCode::display_name`].
+    pub fn display_name(&self) -> String {
+        match self {
+            KeyCode::Char(c) => c.to_uppercase().collect(),
+            KeyCode::Str(s) => s.to_uppercase(),
+            KeyCode::Dead(Some(c)) => c.to_string(),
+            KeyCode::Dead(None) => "Dead Key".to_string(),
+            KeyCode::Control => "Ctrl".to_string(),
+            KeyCode::ArrowDown => "Down".to_string(),
+            KeyCode::ArrowLeft => "Left".to_string(),
+            KeyCode::ArrowRight => "Right".to_string(),
+            KeyCode::ArrowUp => "Up".to_string(),
+            named => split_words(&format!("{named:?}")),
+        }
+    }
+
+    /// The display name translated with the message `key.<name>` from `i18n`, where `<name>` is
+    /// the key's name in snake case, e.g. `key.page_up`. Keys that type characters are not
+    /// translated.
+    pub fn localized_name(&self, i18n: &Localization) -> String {
+        let name = self.display_name();
+        match self {
+            KeyCode::Char(_) | KeyCode::Str(_) | KeyCode::Dead(_) => name,
+            named => i18n.tr_or(&message_key(&format!("{named:?}")), name),
+        }
+    }
+}
+
+/// A physical key, i.e. the location of the key on the keyboard regardless of layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScanCode(pub(crate) winit::keyboard::PhysicalKey);
+
+impl ScanCode {
+    /// The name of the key at this location on a US keyboard, e.g. `A` or `Left Shift`.
+    pub fn display_name(&self) -> String {
+        match self.code_name() {
+            Some(code) => code_display_name(&code),
+            None => "Unknown Key".to_string(),
+        }
+    }
+
+    /// The display name translated with the message `key.<name>` from `i18n`, where `<name>` is
+    /// the key's code in snake case, e.g. `key.shift_left`.
+    pub fn localized_name(&self, i18n: &Localization) -> String {
+        match self.code_name() {
+            Some(code) => i18n.tr_or(&message_key(&code), code_display_name(&code)),
+            None => i18n.tr_or("key.unknown", self.display_name()),
+        }
+    }
+
+    fn code_name(&self) -> Option<String> {
+        match self.0 {
+            winit::keyboard::PhysicalKey::Code(code) => Some(format!("{code:?}")),
+            winit::keyboard::PhysicalKey::Unidentified(_) => None,
+        }
+    }
+}
+
+/// The display name of a key code named like `KeyA`, `Digit1` or `ShiftLeft`.
+fn code_display_name(code: &str) -> String {
+    let symbol = match code {
+        "Minus" => Some("-"),
+        "Equal" => Some("="),
+        "BracketLeft" => Some("["),
+        "BracketRight" => Some("]"),
+        "Backslash" => Some("\\"),
+        "Semicolon" => Some(";"),
+        "Quote" => Some("'"),
+        "Backquote" => Some("`"),
+        "Comma" => Some(","),
+        "Period" => Some("."),
+        "Slash" => Some("/"),
+        "ArrowDown" => Some("Down"),
+        "ArrowLeft" => Some("Left"),
+        "ArrowRight" => Some("Right"),
+        "ArrowUp" => Some("Up"),
+        _ => None,
+    };
+    if let Some(symbol) = symbol {
+        return symbol.to_string();
+    }
+
+    if let Some(rest) = code
+        .strip_prefix("Key")
+        .or_else(|| code.strip_prefix("Digit"))
+    {
+        if rest.len() == 1 {
+            return rest.to_string();
+        }
+    }
+    for side in ["Left", "Right"] {
+        match code.strip_suffix(side) {
+            Some("Control") => return format!("{side} Ctrl"),
+            Some(modifier @ ("Shift" | "Alt" | "Super")) => return format!("{side} {modifier}"),
+            _ => {}
+        }
+    }
+    split_words(code)
+}
+
+/// Splits a name in camel case into words, e.g. `PageUp` into `Page Up`.
+fn split_words(name: &str) -> String {
+    let mut words = String::with_capacity(name.len() + 4);
+    let mut previous: Option<char> = None;
+    for c in name.chars() {
+        if c.is_uppercase() && previous.is_some_and(|p| p.is_lowercase()) {
+            words.push(' ');
+        }
+        words.push(c);
+        previous = Some(c);
+    }
+    words
+}
+
+/// The i18n message key for a key named in camel case, e.g. `key.page_up` for `PageUp`.
+fn message_key(name: &str) -> String {
+    format!("key.{}", split_words(name).replace(' ', "_").to_lowercase())
+}
+
+pub struct Keyboard {
+    held: HashMap<ScanCode, Option<KeyCode>>, // The key each held scancode was pressed as.
+    pressed: HashSet<KeyCode>,
+    released: HashSet<KeyCode>,
+    press_times: HashMap<KeyCode, Instant>,
+    frame_start: Instant,
+}
+
+impl Default for Keyboard {
+    fn default() -> Self {
+        Self {
+            held: HashMap::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+            press_times: HashMap::new(),
+            frame_start: Instant::now(),
+        }
+    }
+}
+
+impl Keyboard {
+    // Held keys are tracked by scancode, as the key a scancode produces can change between the
+    // press and the release, e.g. `!` is released as `1` if shift is released first.
+    pub(crate) fn on_event(&mut self, event: &Event) {
+        match event {
+            Event::KeyboardInput(input) if input.pressed => {
+                if input.repeat || self.held.contains_key(&input.scancode) {
+                    return;
+                }
+                self.held.insert(input.scancode, input.key.clone());
+                if let Some(key) = &input.key {
+                    self.pressed.insert(key.clone());
+                    self.press_times.insert(key.clone(), Instant::now());
+                }
+            }
+            Event::KeyboardInput(input) => {
+                if let Some(Some(key)) = self.held.remove(&input.scancode) {
+                    self.released.insert(key);
+                }
+            }
+            // Keys released while the window is unfocused are never reported.
+            Event::FocusLost => {
+                for (_, key) in self.held.drain() {
+                    self.released.extend(key);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn update(&mut self) {
+        self.frame_start = Instant::now();
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+    }
+
+    /// Whether `key` was pressed within `seconds` of the start of this frame. This allows for some
+    /// leniency in the timing of inputs, e.g. to buffer a jump that is pressed just before landing.
+    /// Negative or NaN `seconds` are treated as zero, and `seconds` too large for a duration, such
+    /// as infinity, as any time since the key was pressed.
+    pub fn pressed_within(&self, key: &KeyCode, seconds: f32) -> bool {
+        let window = Duration::try_from_secs_f32(seconds.max(0.0)).unwrap_or(Duration::MAX);
+        self.press_times
+            .get(key)
+            .is_some_and(|time| self.frame_start.saturating_duration_since(*time) <= window)
+    }
+
+    /// Forgets the most recent press of `key` so that it is no longer reported by
+    /// [`Keyboard::pressed_within`], returning whether it had been pressed within `seconds`.
+    pub fn consume_press(&mut self, key: &KeyCode, seconds: f32) -> bool {
+        let pressed = self.pressed_within(key, seconds);
+        self.press_times.remove(key);
+        pressed
+    }
+
+    /// Whether `key` is currently held down.
+    pub fn held(&self, key: &KeyCode) -> bool {
+        self.held.values().any(|held| held.as_ref() == Some(key))
+    }
+
+    /// Whether `key` was pressed this frame.
+    pub fn pressed(&self, key: &KeyCode) -> bool {
+        self.pressed.contains(key)
+    }
+
+    /// Whether `key` was released this frame.
+    pub fn released(&self, key: &KeyCode) -> bool {
+        self.released.contains(key)
+    }
+
+    pub fn held_keys(&self) -> impl Iterator<Item = &KeyCode> {
+        self.held.values().flatten()
+    }
+
+    pub fn held_scancodes(&self) -> impl Iterator<Item = ScanCode> + '_ {
+        self.held.keys().copied()
+    }
+}
+
+/// Identifies a connected gamepad. Ids are not reused while the game is running, so a gamepad
+/// that is unplugged and plugged back in may be given a new one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GamepadId(usize);
+
+/// Gamepad buttons, named by their position on the pad, e.g. `South` is the Ⓐ button on Xbox
+/// pads and the Cross button on PlayStation pads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    Mode,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+/// Analog gamepad inputs. Sticks range from -1 to 1 and triggers from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamepadAxis {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+/// A change to a gamepad, given to [`Game::on_gamepad_event`](crate::Game::on_gamepad_event).
+#[derive(Debug, Clone, PartialEq)]
+pub enum GamepadEvent {
+    Connected {
+        name: String,
+    },
+    Disconnected,
+    ButtonPressed(GamepadButton),
+    ButtonReleased(GamepadButton),
+    /// The raw value of an axis, before any deadzone is applied. Stick y axes are positive down.
+    AxisChanged(GamepadAxis, f32),
+}
+
+/// The state of a connected gamepad.
+#[derive(Debug, Clone)]
+pub struct Gamepad {
+    id: GamepadId,
+    name: String,
+    held: HashSet<GamepadButton>,
+    pressed: HashSet<GamepadButton>,
+    released: HashSet<GamepadButton>,
+    axes: HashMap<GamepadAxis, f32>,
+    stick_deadzone: f32,
+    trigger_deadzone: f32,
+}
+
+impl Gamepad {
+    fn new(id: GamepadId, stick_deadzone: f32, trigger_deadzone: f32) -> Self {
+        Self {
+            id,
+            name: String::new(),
+            held: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+            axes: HashMap::new(),
+            stick_deadzone,
+            trigger_deadzone,
+        }
+    }
+
+    fn on_event(&mut self, event: &GamepadEvent) {
+        match event {
+            GamepadEvent::Connected { name } => self.name = name.clone(),
+            GamepadEvent::Disconnected => {}
+            GamepadEvent::ButtonPressed(button) => {
+                if self.held.insert(*button) {
+                    self.pressed.insert(*button);
+                }
+            }
+            GamepadEvent::ButtonReleased(button) => {
+                if self.held.remove(button) {
+                    self.released.insert(*button);
+                }
+            }
+            GamepadEvent::AxisChanged(axis, value) => {
+                self.axes.insert(*axis, *value);
+            }
+        }
+    }
+
+    pub fn id(&self) -> GamepadId {
+        self.id
+    }
+
+    /// The name the gamepad reports, e.g. `Xbox Controller`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether `button` is currently held down.
+    pub fn held(&self, button: GamepadButton) -> bool {
+        self.held.contains(&button)
+    }
+
+    /// Whether `button` was pressed this frame.
+    pub fn pressed(&self, button: GamepadButton) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    /// Whether `button` was released this frame.
+    pub fn released(&self, button: GamepadButton) -> bool {
+        self.released.contains(&button)
+    }
+
+    pub fn held_buttons(&self) -> impl Iterator<Item = GamepadButton> + '_ {
+        self.held.iter().copied()
+    }
+
+    /// The raw value of `axis`, without a deadzone applied.
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes.get(&axis).copied().unwrap_or_default()
+    }
+
+    /// The position of the left stick, with y positive down. Positions within the stick
+    /// deadzone are zero and the rest are rescaled to still reach a length of one.
+    pub fn left_stick(&self) -> Vec2f {
+        let stick = v2(
+            self.axis(GamepadAxis::LeftStickX),
+            self.axis(GamepadAxis::LeftStickY),
+        );
+        apply_deadzone(stick, self.stick_deadzone)
+    }
+
+    /// The position of the right stick, like [`Gamepad::left_stick`].
+    pub fn right_stick(&self) -> Vec2f {
+        let stick = v2(
+            self.axis(GamepadAxis::RightStickX),
+            self.axis(GamepadAxis::RightStickY),
+        );
+        apply_deadzone(stick, self.stick_deadzone)
+    }
+
+    /// How far the left trigger is pulled, from 0 to 1, with values within the trigger deadzone
+    /// being zero.
+    pub fn left_trigger(&self) -> f32 {
+        let trigger = v2(self.axis(GamepadAxis::LeftTrigger), 0.0);
+        apply_deadzone(trigger, self.trigger_deadzone).x
+    }
+
+    /// How far the right trigger is pulled, like [`Gamepad::left_trigger`].
+    pub fn right_trigger(&self) -> f32 {
+        let trigger = v2(self.axis(GamepadAxis::RightTrigger), 0.0);
+        apply_deadzone(trigger, self.trigger_deadzone).x
+    }
+}
+
+/// Zeroes `value` if its length is within `deadzone`, otherwise rescales it so that its length
+/// runs from zero at the edge of the deadzone to one.
+fn apply_deadzone(value: Vec2f, deadzone: f32) -> Vec2f {
+    let length = value.len();
+    if length <= deadzone {
+        return Vec2f::ZERO;
+    }
+    let rescaled = ((length - deadzone) / (1.0 - deadzone)).min(1.0);
+    value * (rescaled / length)
+}
+
+/// The connected gamepads, along with the deadzones applied to their sticks and triggers.
+#[derive(Debug, Clone)]
+pub struct Gamepads {
+    pads: HashMap<GamepadId, Gamepad>,
+    stick_deadzone: f32,
+    trigger_deadzone: f32,
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Self {
+            pads: HashMap::new(),
+            stick_deadzone: 0.15,
+            trigger_deadzone: 0.05,
+        }
+    }
+}
+
+impl Gamepads {
+    pub(crate) fn on_event(&mut self, id: GamepadId, event: &GamepadEvent) {
+        if *event == GamepadEvent::Disconnected {
+            self.pads.remove(&id);
+            return;
+        }
+
+        self.pads
+            .entry(id)
+            .or_insert_with(|| Gamepad::new(id, self.stick_deadzone, self.trigger_deadzone))
+            .on_event(event);
+    }
+
+    pub(crate) fn reset(&mut self) {
+        for pad in self.pads.values_mut() {
+            pad.pressed.clear();
+            pad.released.clear();
+        }
+    }
+
+    pub fn get(&self, id: GamepadId) -> Option<&Gamepad> {
+        self.pads.get(&id)
+    }
+
+    /// The connected gamepads, in the order they were connected.
+    pub fn iter(&self) -> impl Iterator<Item = &Gamepad> {
+        let mut pads = self.pads.values().collect::<Vec<_>>();
+        pads.sort_by_key(|pad| pad.id);
+        pads.into_iter()
+    }
+
+    /// The gamepad connected first, for single player games.
+    pub fn first(&self) -> Option<&Gamepad> {
+        self.pads.values().min_by_key(|pad| pad.id)
+    }
+
+    pub fn get_stick_deadzone(&self) -> f32 {
+        self.stick_deadzone
+    }
+
+    /// Sets how far, from 0 to 1, the sticks must be moved before they register, to hide the
+    /// drift of worn sticks.
+    pub fn set_stick_deadzone(&mut self, deadzone: f32) {
+        self.stick_deadzone = deadzone.clamp(0.0, 0.99);
+        for pad in self.pads.values_mut() {
+            pad.stick_deadzone = self.stick_deadzone;
+        }
+    }
+
+    pub fn get_trigger_deadzone(&self) -> f32 {
+        self.trigger_deadzone
+    }
+
+    /// Sets how far, from 0 to 1, the triggers must be pulled before they register.
+    pub fn set_trigger_deadzone(&mut self, deadzone: f32) {
+        self.trigger_deadzone = deadzone.clamp(0.0, 0.99);
+        for pad in self.pads.values_mut() {
+            pad.trigger_deadzone = self.trigger_deadzone;
+        }
+    }
+}
+
+/// Reads gamepad events from the platform. Without the `gamepad` feature there is no backend and
+/// no gamepads are ever connected.
+#[derive(Default)]
+pub(crate) struct GamepadInput {
+    #[cfg(feature = "gamepad")]
+    gilrs: Option<gilrs::Gilrs>,
+    #[cfg(feature = "gamepad")]
+    connected: Vec<(GamepadId, GamepadEvent)>, // Gamepads already connected at startup.
+}
+
+impl GamepadInput {
+    #[cfg(feature = "gamepad")]
+    pub(crate) fn new() -> Self {
+        let gilrs = match gilrs::Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(err) => {
+                eprintln!("gamepads are not available: {err}");
+                None
+            }
+        };
+        let connected = gilrs
+            .iter()
+            .flat_map(|gilrs| gilrs.gamepads())
+            .map(|(id, pad)| {
+                let name = pad.name().to_string();
+                (GamepadId(id.into()), GamepadEvent::Connected { name })
+            })
+            .collect();
+
+        Self { gilrs, connected }
+    }
+
+    #[cfg(not(feature = "gamepad"))]
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// The gamepad events since the last poll.
+    #[cfg(feature = "gamepad")]
+    pub(crate) fn poll(&mut self) -> Vec<(GamepadId, GamepadEvent)> {
+        use gilrs::{Axis, Button, EventType};
+
+        let mut events = std::mem::take(&mut self.connected);
+        let Some(gilrs) = self.gilrs.as_mut() else {
+            return events;
+        };
+
+        let button = |button| match button {
+            Button::South => Some(GamepadButton::South),
+            Button::East => Some(GamepadButton::East),
+            Button::North => Some(GamepadButton::North),
+            Button::West => Some(GamepadButton::West),
+            Button::LeftTrigger => Some(GamepadButton::LeftBumper),
+            Button::RightTrigger => Some(GamepadButton::RightBumper),
+            Button::LeftTrigger2 => Some(GamepadButton::LeftTrigger),
+            Button::RightTrigger2 => Some(GamepadButton::RightTrigger),
+            Button::Select => Some(GamepadButton::Select),
+            Button::Start => Some(GamepadButton::Start),
+            Button::Mode => Some(GamepadButton::Mode),
+            Button::LeftThumb => Some(GamepadButton::LeftStick),
+            Button::RightThumb => Some(GamepadButton::RightStick),
+            Button::DPadUp => Some(GamepadButton::DPadUp),
+            Button::DPadDown => Some(GamepadButton::DPadDown),
+            Button::DPadLeft => Some(GamepadButton::DPadLeft),
+            Button::DPadRight => Some(GamepadButton::DPadRight),
+            Button::C | Button::Z | Button::Unknown => None,
+        };
+
+        while let Some(gilrs::Event { id, event, .. }) = gilrs.next_event() {
+            let event = match event {
+                EventType::Connected => Some(GamepadEvent::Connected {
+                    name: gilrs.gamepad(id).name().to_string(),
+                }),
+                EventType::Disconnected => Some(GamepadEvent::Disconnected),
+                EventType::ButtonPressed(b, _) => button(b).map(GamepadEvent::ButtonPressed),
+                EventType::ButtonReleased(b, _) => button(b).map(GamepadEvent::ButtonReleased),
+                // Analog triggers are reported as buttons with a value.
+                EventType::ButtonChanged(Button::LeftTrigger2, value, _) => {
+                    Some(GamepadEvent::AxisChanged(GamepadAxis::LeftTrigger, value))
+                }
+                EventType::ButtonChanged(Button::RightTrigger2, value, _) => {
+                    Some(GamepadEvent::AxisChanged(GamepadAxis::RightTrigger, value))
+                }
+                EventType::AxisChanged(axis, value, _) => match axis {
+                    Axis::LeftStickX => Some((GamepadAxis::LeftStickX, value)),
+                    Axis::LeftStickY => Some((GamepadAxis::LeftStickY, -value)),
+                    Axis::RightStickX => Some((GamepadAxis::RightStickX, value)),
+                    Axis::RightStickY => Some((GamepadAxis::RightStickY, -value)),
+                    _ => None,
+                }
+                .map(|(axis, value)| GamepadEvent::AxisChanged(axis, value)),
+                _ => None,
+            };
+            if let Some(event) = event {
+                events.push((GamepadId(id.into()), event));
+            }
+        }
+
+        events
+    }
+
+    #[cfg(not(feature = "gamepad"))]
+    pub(crate) fn poll(&mut self) -> Vec<(GamepadId, GamepadEvent)> {
+        Vec::new()
+    }
 }
 
 impl From<winit::error::EventLoopError> for Error {
     fn from(value: winit::error::EventLoopError) -> Self {
-        Error::new("failed to create event loop").with_source(value)
+        Error::new("failed to create event loop")
+            .with_kind(ErrorKind::Os)
+            .with_source(value)
+    }
+}
+
+impl From<winit::error::ExternalError> for Error {
+    fn from(value: winit::error::ExternalError) -> Self {
+        Error::new("the requested operation is not supported by the platform")
+            .with_kind(ErrorKind::Os)
+            .with_source(value)
+    }
+}
+
+impl From<raw_window_handle::HandleError> for Error {
+    fn from(value: raw_window_handle::HandleError) -> Self {
+        Error::new("the window handle is not available")
+            .with_kind(ErrorKind::Os)
+            .with_source(value)
     }
 }
 
 impl From<winit::error::OsError> for Error {
     fn from(value: winit::error::OsError) -> Self {
-        Error::new("failed to complete the requested operation").with_source(value)
+        Error::new("failed to complete the requested operation")
+            .with_kind(ErrorKind::Os)
+            .with_source(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use winit::keyboard::{Key, SmolStr};
+
+    use super::*;
+
+    #[test]
+    fn gamepad_sticks_have_deadzones() {
+        let mut gamepads = Gamepads::default();
+        let id = GamepadId(0);
+        gamepads.on_event(id, &GamepadEvent::ButtonPressed(GamepadButton::South));
+        gamepads.on_event(id, &GamepadEvent::AxisChanged(GamepadAxis::LeftStickX, 0.1));
+        gamepads.on_event(
+            id,
+            &GamepadEvent::AxisChanged(GamepadAxis::RightStickY, 0.575),
+        );
+        gamepads.on_event(
+            id,
+            &GamepadEvent::AxisChanged(GamepadAxis::LeftTrigger, 1.0),
+        );
+
+        let pad = gamepads.first().unwrap();
+        assert!(pad.pressed(GamepadButton::South) && pad.held(GamepadButton::South));
+        assert_eq!(pad.left_stick(), Vec2f::ZERO);
+        assert!((pad.right_stick() - v2(0.0, 0.5)).len() < 1e-6);
+        assert_eq!(pad.left_trigger(), 1.0);
+
+        gamepads.reset();
+        gamepads.on_event(id, &GamepadEvent::ButtonReleased(GamepadButton::South));
+        let pad = gamepads.get(id).unwrap();
+        assert!(!pad.pressed(GamepadButton::South) && pad.released(GamepadButton::South));
+
+        gamepads.on_event(id, &GamepadEvent::Disconnected);
+        assert!(gamepads.first().is_none());
+    }
+
+    #[test]
+    fn pixels_per_line_is_at_least_one() {
+        let mut mouse = Mouse::new(1.0);
+        for pixels_per_line in [0.0, -20.0, f32::NAN] {
+            mouse.set_pixels_per_line(pixels_per_line);
+            assert_eq!(mouse.get_pixels_per_line(), 1.0);
+        }
+        mouse.set_pixels_per_line(16.0);
+        assert_eq!(mouse.get_pixels_per_line(), 16.0);
+    }
+
+    #[test]
+    fn touches_are_tracked_until_lifted() {
+        use winit::event::TouchPhase;
+
+        let touch = |id, phase, x| Event::Touch {
+            id: TouchId(id),
+            phase,
+            x,
+            y: 10.0,
+        };
+        let mut touches = Touches::new(2.0);
+        touches.on_event(&touch(1, TouchPhase::Started, 4.0));
+        touches.on_event(&touch(2, TouchPhase::Started, 8.0));
+        touches.on_event(&touch(1, TouchPhase::Moved, 6.0));
+        assert_eq!(touches.count(), 2);
+        assert!(touches.started(TouchId(1)));
+        assert_eq!(touches.position_logical(TouchId(1)), Some(v2(3.0, 5.0)));
+
+        touches.reset();
+        touches.on_event(&touch(2, TouchPhase::Cancelled, 9.0));
+        assert!(!touches.started(TouchId(1)));
+        assert_eq!(touches.ended(TouchId(2)), Some(v2(9.0, 10.0)));
+        assert_eq!(touches.position_physical(TouchId(2)), None);
+        assert_eq!(
+            touches.held().collect::<Vec<_>>(),
+            vec![(TouchId(1), v2(6.0, 10.0))]
+        );
+
+        touches.reset();
+        assert_eq!(touches.ended_touches().count(), 0);
+    }
+
+    #[test]
+    fn single_char_key_is_lower_case() {
+        let key = Key::Character(SmolStr::new("A"));
+        assert_eq!(KeyCode::try_from(&key).unwrap(), KeyCode::Char('a'));
+    }
+
+    #[test]
+    fn multi_char_key_is_str() {
+        let key = Key::Character(SmolStr::new("ǅa"));
+        assert_eq!(
+            KeyCode::try_from(&key).unwrap(),
+            KeyCode::Str("ǆa".to_string())
+        );
+    }
+
+    #[test]
+    fn keys_have_display_names() {
+        use winit::keyboard::{KeyCode as Code, PhysicalKey};
+
+        assert_eq!(KeyCode::Char('a').display_name(), "A");
+        assert_eq!(KeyCode::Dead(Some('´')).display_name(), "´");
+        assert_eq!(KeyCode::PageUp.display_name(), "Page Up");
+        assert_eq!(KeyCode::Control.display_name(), "Ctrl");
+
+        let scan = |code| ScanCode(PhysicalKey::Code(code)).display_name();
+        assert_eq!(scan(Code::KeyA), "A");
+        assert_eq!(scan(Code::Digit7), "7");
+        assert_eq!(scan(Code::ShiftLeft), "Left Shift");
+        assert_eq!(scan(Code::ControlRight), "Right Ctrl");
+        assert_eq!(scan(Code::Slash), "/");
+        assert_eq!(scan(Code::NumpadAdd), "Numpad Add");
+        assert_eq!(scan(Code::F11), "F11");
+
+        let mut bundle = crate::i18n::Bundle::new("de");
+        bundle.insert("key.shift_left", "Umschalt links");
+        bundle.insert("key.page_up", "Bild auf");
+        let mut i18n = Localization::default();
+        i18n.add_bundle(bundle);
+        assert_eq!(
+            ScanCode(PhysicalKey::Code(Code::ShiftLeft)).localized_name(&i18n),
+            "Umschalt links"
+        );
+        assert_eq!(KeyCode::PageUp.localized_name(&i18n), "Bild auf");
+        assert_eq!(KeyCode::Home.localized_name(&i18n), "Home");
+    }
+
+    #[test]
+    fn key_is_pressed_once_while_held() {
+        let input = |pressed, repeat| {
+            Event::KeyboardInput(KeyboardInput {
+                key: Some(KeyCode::Space),
+                scancode: ScanCode(winit::keyboard::PhysicalKey::Code(
+                    winit::keyboard::KeyCode::Space,
+                )),
+                pressed,
+                repeat,
+                text: String::new(),
+            })
+        };
+        let mut keyboard = Keyboard::default();
+
+        keyboard.on_event(&input(true, false));
+        assert!(keyboard.pressed(&KeyCode::Space));
+        keyboard.reset();
+        keyboard.on_event(&input(true, true));
+        assert!(!keyboard.pressed(&KeyCode::Space));
+        assert!(keyboard.held(&KeyCode::Space));
+        keyboard.on_event(&input(false, false));
+        assert!(keyboard.released(&KeyCode::Space));
+        assert!(!keyboard.held(&KeyCode::Space));
+    }
+
+    #[test]
+    fn key_press_is_buffered_until_consumed() {
+        let mut keyboard = Keyboard::default();
+        keyboard.on_event(&Event::KeyboardInput(KeyboardInput {
+            key: Some(KeyCode::Space),
+            scancode: ScanCode(winit::keyboard::PhysicalKey::Code(
+                winit::keyboard::KeyCode::Space,
+            )),
+            pressed: true,
+            repeat: false,
+            text: String::new(),
+        }));
+        keyboard.reset();
+        keyboard.update();
+
+        // Pretend the frame started a little after the press, so a zero window misses it.
+        keyboard.frame_start = keyboard.press_times[&KeyCode::Space] + Duration::from_millis(100);
+
+        assert!(keyboard.pressed_within(&KeyCode::Space, 1.0));
+        assert!(!keyboard.pressed_within(&KeyCode::Enter, 1.0));
+        assert!(!keyboard.pressed_within(&KeyCode::Space, 0.05));
+        // Windows that are not a valid duration are treated as the nearest one.
+        assert!(keyboard.pressed_within(&KeyCode::Space, f32::INFINITY));
+        assert!(keyboard.pressed_within(&KeyCode::Space, f32::MAX));
+        assert!(!keyboard.pressed_within(&KeyCode::Space, -1.0));
+        assert!(!keyboard.pressed_within(&KeyCode::Space, f32::NAN));
+        assert!(keyboard.consume_press(&KeyCode::Space, 1.0));
+        assert!(!keyboard.pressed_within(&KeyCode::Space, 1.0));
+    }
+
+    #[test]
+    fn keys_are_released_by_scancode_and_on_focus_loss() {
+        let digit1 = ScanCode(winit::keyboard::PhysicalKey::Code(
+            winit::keyboard::KeyCode::Digit1,
+        ));
+        let input = |key: char, pressed| {
+            Event::KeyboardInput(KeyboardInput {
+                key: Some(KeyCode::Char(key)),
+                scancode: digit1,
+                pressed,
+                repeat: false,
+                text: String::new(),
+            })
+        };
+        let mut keyboard = Keyboard::default();
+
+        // Shift is released between pressing and releasing the key.
+        keyboard.on_event(&input('!', true));
+        keyboard.on_event(&input('1', false));
+        assert!(!keyboard.held(&KeyCode::Char('!')));
+        assert!(keyboard.released(&KeyCode::Char('!')));
+
+        keyboard.reset();
+        keyboard.on_event(&input('1', true));
+        keyboard.on_event(&Event::FocusLost);
+        assert_eq!(keyboard.held_scancodes().count(), 0);
+        assert!(keyboard.released(&KeyCode::Char('1')));
     }
 }
@@ -0,0 +1,286 @@
+use std::ops::Range;
+
+use crate::sys::{KeyCode, Keyboard};
+
+/// The editing state of a single line of text entry, such as a name field. Typed text is passed
+/// to [`TextInput::insert`] from [`Game::on_text_entered`](crate::Game::on_text_entered), and
+/// key presses to [`TextInput::handle_key`] from
+/// [`Game::on_key_pressed`](crate::Game::on_key_pressed) so that held keys repeat.
+///
+/// Positions are byte offsets into the text that always fall on a character boundary.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextInput {
+    text: String,
+    caret: usize,
+    anchor: usize,
+    max_chars: Option<usize>,
+}
+
+impl TextInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts with `text`, with the caret at the end of it.
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.set_text(text);
+        self
+    }
+
+    /// Limits the text to `max_chars` characters, discarding anything typed or pasted past it.
+    pub fn with_max_chars(mut self, max_chars: usize) -> Self {
+        self.max_chars = Some(max_chars);
+        self.set_text(&self.text.clone());
+        self
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replaces the text, moving the caret to the end of it.
+    pub fn set_text(&mut self, text: &str) {
+        self.text = match self.max_chars {
+            Some(max) => text.chars().take(max).collect(),
+            None => text.to_string(),
+        };
+        self.caret = self.text.len();
+        self.anchor = self.caret;
+    }
+
+    pub fn caret(&self) -> usize {
+        self.caret
+    }
+
+    /// The selected range of the text, or `None` if nothing is selected.
+    pub fn selection(&self) -> Option<Range<usize>> {
+        match self.anchor.cmp(&self.caret) {
+            std::cmp::Ordering::Less => Some(self.anchor..self.caret),
+            std::cmp::Ordering::Equal => None,
+            std::cmp::Ordering::Greater => Some(self.caret..self.anchor),
+        }
+    }
+
+    pub fn selected_text(&self) -> &str {
+        self.selection().map_or("", |range| &self.text[range])
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = 0;
+        self.caret = self.text.len();
+    }
+
+    /// Inserts `text` at the caret, replacing any selection.
+    pub fn insert(&mut self, text: &str) {
+        self.delete_selection();
+
+        let text = text.chars().filter(|c| !c.is_control());
+        let inserted: String = match self.max_chars {
+            Some(max) => text
+                .take(max.saturating_sub(self.text.chars().count()))
+                .collect(),
+            None => text.collect(),
+        };
+
+        self.text.insert_str(self.caret, &inserted);
+        self.caret += inserted.len();
+        self.anchor = self.caret;
+    }
+
+    /// The selected text, for the game to put on the clipboard.
+    pub fn copy(&self) -> Option<String> {
+        self.selection().map(|range| self.text[range].to_string())
+    }
+
+    /// Removes the selected text, returning it for the game to put on the clipboard.
+    pub fn cut(&mut self) -> Option<String> {
+        let cut = self.copy();
+        self.delete_selection();
+        cut
+    }
+
+    /// Inserts text from the clipboard at the caret, replacing any selection.
+    pub fn paste(&mut self, text: &str) {
+        self.insert(text);
+    }
+
+    /// Moves the caret or edits the text in response to `key`, using the modifiers held on
+    /// `keyboard`. Shift extends the selection and Control moves or deletes by word. Returns
+    /// whether the key was used.
+    pub fn handle_key(&mut self, key: &KeyCode, keyboard: &Keyboard) -> bool {
+        let select = keyboard.held(&KeyCode::Shift);
+        let word = keyboard.held(&KeyCode::Control);
+
+        match key {
+            KeyCode::ArrowLeft => {
+                let to = match (self.selection(), select, word) {
+                    (Some(range), false, _) => range.start,
+                    (_, _, true) => self.word_start(self.caret),
+                    (_, _, false) => self.prev_char(self.caret),
+                };
+                self.move_caret(to, select);
+            }
+            KeyCode::ArrowRight => {
+                let to = match (self.selection(), select, word) {
+                    (Some(range), false, _) => range.end,
+                    (_, _, true) => self.word_end(self.caret),
+                    (_, _, false) => self.next_char(self.caret),
+                };
+                self.move_caret(to, select);
+            }
+            KeyCode::Home | KeyCode::ArrowUp => self.move_caret(0, select),
+            KeyCode::End | KeyCode::ArrowDown => self.move_caret(self.text.len(), select),
+            KeyCode::Backspace => {
+                if self.selection().is_none() {
+                    self.anchor = match word {
+                        true => self.word_start(self.caret),
+                        false => self.prev_char(self.caret),
+                    };
+                }
+                self.delete_selection();
+            }
+            KeyCode::Delete => {
+                if self.selection().is_none() {
+                    self.anchor = match word {
+                        true => self.word_end(self.caret),
+                        false => self.next_char(self.caret),
+                    };
+                }
+                self.delete_selection();
+            }
+            KeyCode::Char('a') if word => self.select_all(),
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn move_caret(&mut self, to: usize, select: bool) {
+        self.caret = to;
+        if !select {
+            self.anchor = to;
+        }
+    }
+
+    fn delete_selection(&mut self) {
+        if let Some(range) = self.selection() {
+            self.text.replace_range(range.clone(), "");
+            self.caret = range.start;
+            self.anchor = range.start;
+        }
+    }
+
+    fn prev_char(&self, from: usize) -> usize {
+        self.text[..from]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_char(&self, from: usize) -> usize {
+        self.text[from..]
+            .chars()
+            .next()
+            .map_or(from, |c| from + c.len_utf8())
+    }
+
+    /// The start of the word before `from`, skipping any whitespace between them.
+    fn word_start(&self, from: usize) -> usize {
+        let before = self.text[..from].trim_end();
+        before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8())
+    }
+
+    /// The end of the word after `from`, skipping any whitespace between them.
+    fn word_end(&self, from: usize) -> usize {
+        let after = &self.text[from..];
+        let word = after.len() - after.trim_start().len();
+        after[word..]
+            .char_indices()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(self.text.len(), |(i, _)| from + word + i)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sys::{Event, KeyboardInput, ScanCode};
+
+    fn press(input: &mut TextInput, key: KeyCode, modifiers: &[KeyCode]) {
+        let mut keyboard = Keyboard::default();
+        for modifier in modifiers {
+            let code = match modifier {
+                KeyCode::Shift => winit::keyboard::KeyCode::ShiftLeft,
+                KeyCode::Control => winit::keyboard::KeyCode::ControlLeft,
+                _ => winit::keyboard::KeyCode::AltLeft,
+            };
+            keyboard.on_event(&Event::KeyboardInput(KeyboardInput {
+                key: Some(modifier.clone()),
+                scancode: ScanCode(winit::keyboard::PhysicalKey::Code(code)),
+                pressed: true,
+                repeat: false,
+                text: String::new(),
+            }));
+        }
+        assert!(input.handle_key(&key, &keyboard));
+    }
+
+    #[test]
+    fn typing_replaces_selection() {
+        let mut input = TextInput::new().with_text("hello world");
+        press(
+            &mut input,
+            KeyCode::ArrowLeft,
+            &[KeyCode::Shift, KeyCode::Control],
+        );
+        assert_eq!(input.selected_text(), "world");
+
+        input.insert("there");
+        assert_eq!(input.text(), "hello there");
+        assert_eq!(input.caret(), 11);
+        assert_eq!(input.selection(), None);
+    }
+
+    #[test]
+    fn word_jumps_skip_whitespace() {
+        let mut input = TextInput::new().with_text("one  two three");
+        press(&mut input, KeyCode::ArrowLeft, &[KeyCode::Control]);
+        assert_eq!(input.caret(), 9);
+        press(&mut input, KeyCode::ArrowLeft, &[KeyCode::Control]);
+        assert_eq!(input.caret(), 5);
+        press(&mut input, KeyCode::Home, &[]);
+        press(&mut input, KeyCode::ArrowRight, &[KeyCode::Control]);
+        assert_eq!(input.caret(), 3);
+        press(&mut input, KeyCode::Delete, &[KeyCode::Control]);
+        assert_eq!(input.text(), "one three");
+    }
+
+    #[test]
+    fn backspace_removes_whole_characters() {
+        let mut input = TextInput::new().with_text("café");
+        press(&mut input, KeyCode::Backspace, &[]);
+        assert_eq!(input.text(), "caf");
+        press(&mut input, KeyCode::Backspace, &[KeyCode::Control]);
+        assert_eq!(input.text(), "");
+        press(&mut input, KeyCode::Backspace, &[]);
+        assert_eq!(input.caret(), 0);
+    }
+
+    #[test]
+    fn clipboard_and_max_chars() {
+        let mut input = TextInput::new().with_max_chars(8).with_text("player one");
+        assert_eq!(input.text(), "player o");
+
+        press(&mut input, KeyCode::Char('a'), &[KeyCode::Control]);
+        assert_eq!(input.cut().as_deref(), Some("player o"));
+        assert_eq!(input.text(), "");
+
+        input.paste("ada lovelace");
+        assert_eq!(input.text(), "ada love");
+        assert_eq!(input.copy(), None);
+    }
+}
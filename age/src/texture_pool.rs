@@ -0,0 +1,178 @@
+use crate::renderer::TextureFormat;
+
+/// The size and format of a transient texture, which must match exactly for a pooled texture to
+/// be reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransientTextureDesc {
+    pub width: u32,
+    pub height: u32,
+    pub format: TextureFormat,
+}
+
+impl TransientTextureDesc {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            format: TextureFormat::Rgba8Unorm,
+        }
+    }
+
+    pub fn with_format(mut self, format: TextureFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    fn bytes(&self) -> u64 {
+        // Every supported format has four bytes per pixel.
+        self.width as u64 * self.height as u64 * 4
+    }
+}
+
+/// How the transient texture pool was used over the last frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TransientTextureStats {
+    /// Textures handed out during the frame.
+    pub acquired: usize,
+    /// Textures that had to be created because none in the pool matched.
+    pub created: usize,
+    /// Textures held by the pool at the end of the frame, whether used or not.
+    pub pooled: usize,
+    /// The GPU memory held by the pool at the end of the frame.
+    pub pooled_bytes: u64,
+}
+
+struct Entry<T> {
+    desc: TransientTextureDesc,
+    texture: T,
+    in_use: bool,
+    unused_frames: u32,
+}
+
+/// Textures that are only needed within a frame, which are handed back at the end of each frame
+/// and reused by later frames rather than created again.
+pub(crate) struct TexturePool<T> {
+    entries: Vec<Entry<T>>,
+    frame: TransientTextureStats,
+    last_frame: TransientTextureStats,
+}
+
+impl<T> Default for TexturePool<T> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            frame: TransientTextureStats::default(),
+            last_frame: TransientTextureStats::default(),
+        }
+    }
+}
+
+impl<T: Copy> TexturePool<T> {
+    /// Textures that go unused for this many frames are released, e.g. those left at an old size
+    /// after the window has been resized.
+    const MAX_UNUSED_FRAMES: u32 = 60;
+
+    /// A texture matching `desc` that is not already in use this frame. If there is none, one
+    /// should be created and added with [`TexturePool::insert`].
+    pub(crate) fn acquire(&mut self, desc: &TransientTextureDesc) -> Option<T> {
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|entry| !entry.in_use && entry.desc == *desc)?;
+        entry.in_use = true;
+        self.frame.acquired += 1;
+        Some(entry.texture)
+    }
+
+    /// Adds a newly created texture to the pool, in use for the rest of this frame.
+    pub(crate) fn insert(&mut self, desc: &TransientTextureDesc, texture: T) {
+        self.frame.acquired += 1;
+        self.frame.created += 1;
+        self.entries.push(Entry {
+            desc: *desc,
+            texture,
+            in_use: true,
+            unused_frames: 0,
+        });
+    }
+
+    /// Returns every texture to the pool, removing and returning those that have gone unused for
+    /// too long so that they can be destroyed.
+    pub(crate) fn end_frame(&mut self) -> Vec<T> {
+        let mut released = Vec::new();
+        self.entries.retain_mut(|entry| {
+            match entry.in_use {
+                true => entry.unused_frames = 0,
+                false => entry.unused_frames += 1,
+            }
+            entry.in_use = false;
+
+            let keep = entry.unused_frames < Self::MAX_UNUSED_FRAMES;
+            if !keep {
+                released.push(entry.texture);
+            }
+            keep
+        });
+
+        self.frame.pooled = self.entries.len();
+        self.frame.pooled_bytes = self.entries.iter().map(|entry| entry.desc.bytes()).sum();
+        self.last_frame = std::mem::take(&mut self.frame);
+        released
+    }
+
+    pub(crate) fn stats(&self) -> TransientTextureStats {
+        self.last_frame
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn textures_are_reused_across_frames() {
+        let mut pool = TexturePool::default();
+        let mut next = 0;
+        let mut acquire = |pool: &mut TexturePool<i32>, desc: &TransientTextureDesc| {
+            pool.acquire(desc).unwrap_or_else(|| {
+                next += 1;
+                pool.insert(desc, next);
+                next
+            })
+        };
+        let small = TransientTextureDesc::new(64, 64);
+        let large = TransientTextureDesc::new(128, 128);
+
+        let a = acquire(&mut pool, &small);
+        let b = acquire(&mut pool, &small);
+        assert_ne!(a, b);
+        assert_eq!(acquire(&mut pool, &large), 3);
+        assert!(pool.end_frame().is_empty());
+        assert_eq!(
+            pool.stats(),
+            TransientTextureStats {
+                acquired: 3,
+                created: 3,
+                pooled: 3,
+                pooled_bytes: 2 * 64 * 64 * 4 + 128 * 128 * 4,
+            }
+        );
+
+        assert_eq!(acquire(&mut pool, &small), a);
+        assert_eq!(
+            acquire(&mut pool, &small.with_format(TextureFormat::Bgra8Unorm)),
+            4
+        );
+        pool.end_frame();
+        assert_eq!(pool.stats().created, 1);
+
+        let released = (0..TexturePool::<i32>::MAX_UNUSED_FRAMES)
+            .flat_map(|_| {
+                acquire(&mut pool, &small);
+                pool.end_frame()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(released, [b, 3, 4]);
+        assert_eq!(pool.stats().pooled, 1);
+    }
+}
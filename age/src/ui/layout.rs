@@ -0,0 +1,403 @@
+use crate::math::{v2, Rect, Vec2f};
+
+/// How wide or tall an element is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Size {
+    /// A size in pixels.
+    Fixed(f32),
+    /// A fraction of the space inside the parent, in the range `0.0..=1.0`.
+    Percent(f32),
+    /// All of the space inside the parent. In a stack, the space left by the other children is
+    /// shared equally between those that fill.
+    Fill,
+}
+
+/// Where an element sits within its parent. In a stack, only the part across the stack is used,
+/// e.g. `Left`, `Center` or `Right` for the children of a vertical stack.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Anchor {
+    #[default]
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// How far across the free space the element is placed, from `0.0` for the top or left to
+    /// `1.0` for the bottom or right.
+    fn alignment(self) -> Vec2f {
+        match self {
+            Anchor::TopLeft => v2(0.0, 0.0),
+            Anchor::Top => v2(0.5, 0.0),
+            Anchor::TopRight => v2(1.0, 0.0),
+            Anchor::Left => v2(0.0, 0.5),
+            Anchor::Center => v2(0.5, 0.5),
+            Anchor::Right => v2(1.0, 0.5),
+            Anchor::BottomLeft => v2(0.0, 1.0),
+            Anchor::Bottom => v2(0.5, 1.0),
+            Anchor::BottomRight => v2(1.0, 1.0),
+        }
+    }
+}
+
+/// Distances in from each edge of a rectangle.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Insets {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl Insets {
+    pub const fn new(left: f32, top: f32, right: f32, bottom: f32) -> Self {
+        Self {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    pub const fn uniform(inset: f32) -> Self {
+        Self::new(inset, inset, inset, inset)
+    }
+
+    /// `rect` with these insets taken off its edges, never smaller than nothing.
+    pub fn shrink(&self, rect: Rect) -> Rect {
+        Rect::new(
+            rect.position + v2(self.left, self.top),
+            v2(
+                (rect.size.x - self.left - self.right).max(0.0),
+                (rect.size.y - self.top - self.bottom).max(0.0),
+            ),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Horizontal,
+    Vertical,
+}
+
+/// An element of a [`Layout`], describing how it is sized and placed within its parent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Element {
+    width: Size,
+    height: Size,
+    anchor: Anchor,
+    offset: Vec2f,
+    padding: f32,
+    stack: Option<(Direction, f32)>,
+    full_screen: bool,
+}
+
+impl Element {
+    pub fn new(width: Size, height: Size) -> Self {
+        Self {
+            width,
+            height,
+            anchor: Anchor::default(),
+            offset: Vec2f::ZERO,
+            padding: 0.0,
+            stack: None,
+            full_screen: false,
+        }
+    }
+
+    pub fn with_anchor(mut self, anchor: Anchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    /// Moves the element by `offset` pixels from where its anchor places it. Ignored in a stack.
+    pub fn with_offset(mut self, offset: Vec2f) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Leaves `padding` pixels between the edges of the element and its children.
+    pub fn with_padding(mut self, padding: f32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Places a child of the root within the whole screen rather than the safe area, e.g. for a
+    /// background that should reach the edges of the display.
+    pub fn with_full_screen(mut self, full_screen: bool) -> Self {
+        self.full_screen = full_screen;
+        self
+    }
+
+    /// Places the children of the element one after another in `direction`, with `spacing`
+    /// pixels between them, rather than each by its own anchor.
+    pub fn with_stack(mut self, direction: Direction, spacing: f32) -> Self {
+        self.stack = Some((direction, spacing));
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ElementId(usize);
+
+struct Node {
+    element: Element,
+    children: Vec<ElementId>,
+    rect: Rect,
+}
+
+/// A tree of UI elements whose rectangles are worked out from the size of the screen, so that a
+/// HUD keeps its shape when the window is resized. The root fills the screen, and the layout is
+/// recalculated by calling [`Layout::update`], e.g. from
+/// [`Game::on_size_changed`](crate::Game::on_size_changed).
+pub struct Layout {
+    nodes: Vec<Node>,
+    safe_area: Rect,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Layout {
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![Node {
+                element: Element::new(Size::Fill, Size::Fill),
+                children: Vec::new(),
+                rect: Rect::default(),
+            }],
+            safe_area: Rect::default(),
+        }
+    }
+
+    /// The element that fills the screen, which all other elements are inside.
+    pub fn root(&self) -> ElementId {
+        ElementId(0)
+    }
+
+    /// Changes the root element, e.g. to give it padding or make it a stack. Its size is ignored.
+    pub fn set_root(&mut self, element: Element) {
+        self.nodes[0].element = element;
+    }
+
+    /// Adds `element` as the last child of `parent`. Its rectangle is worked out on the next
+    /// update.
+    pub fn add(&mut self, parent: ElementId, element: Element) -> ElementId {
+        let id = ElementId(self.nodes.len());
+        self.nodes.push(Node {
+            element,
+            children: Vec::new(),
+            rect: Rect::default(),
+        });
+        self.nodes[parent.0].children.push(id);
+        id
+    }
+
+    /// Where `id` was placed by the last update.
+    pub fn rect(&self, id: ElementId) -> Rect {
+        self.nodes[id.0].rect
+    }
+
+    /// Works out the rectangle of every element, with the root filling `screen`.
+    pub fn update(&mut self, screen: Rect) {
+        self.update_with_safe_area(screen, screen);
+    }
+
+    /// Works out the rectangle of every element, with the root filling `screen` and its children
+    /// kept within `safe_area`, e.g. [`Engine::safe_area`](crate::Engine::safe_area), unless they
+    /// are full screen.
+    pub fn update_with_safe_area(&mut self, screen: Rect, safe_area: Rect) {
+        self.safe_area = safe_area;
+        self.place(self.root(), screen);
+    }
+
+    fn place(&mut self, id: ElementId, rect: Rect) {
+        let node = &mut self.nodes[id.0];
+        node.rect = rect;
+        let element = node.element;
+        let children = node.children.clone();
+
+        let bounds = if id == self.root() {
+            self.safe_area
+        } else {
+            rect
+        };
+        let inner = Insets::uniform(element.padding).shrink(bounds);
+
+        match element.stack {
+            Some((direction, spacing)) => self.place_stack(&children, inner, direction, spacing),
+            None => {
+                for child in children {
+                    let element = self.nodes[child.0].element;
+                    let inner = match element.full_screen && id == self.root() {
+                        true => rect,
+                        false => inner,
+                    };
+                    let size = v2(
+                        resolve(element.width, inner.size.x, inner.size.x),
+                        resolve(element.height, inner.size.y, inner.size.y),
+                    );
+                    let position = inner.position
+                        + (inner.size - size) * element.anchor.alignment()
+                        + element.offset;
+                    self.place(child, Rect::new(position, size));
+                }
+            }
+        }
+    }
+
+    fn place_stack(
+        &mut self,
+        children: &[ElementId],
+        inner: Rect,
+        direction: Direction,
+        spacing: f32,
+    ) {
+        // Work in (along, across) the stack, swapping back to (x, y) when placing.
+        let swap = |v: Vec2f| match direction {
+            Direction::Horizontal => v,
+            Direction::Vertical => v2(v.y, v.x),
+        };
+        let space = swap(inner.size);
+
+        let along = |element: &Element| match direction {
+            Direction::Horizontal => element.width,
+            Direction::Vertical => element.height,
+        };
+        let across = |element: &Element| match direction {
+            Direction::Horizontal => element.height,
+            Direction::Vertical => element.width,
+        };
+
+        let gaps = spacing * children.len().saturating_sub(1) as f32;
+        let (used, fills) = children.iter().fold((gaps, 0), |(used, fills), child| {
+            match along(&self.nodes[child.0].element) {
+                Size::Fill => (used, fills + 1),
+                size => (used + resolve(size, space.x, 0.0), fills),
+            }
+        });
+        let fill = ((space.x - used) / fills.max(1) as f32).max(0.0);
+
+        let mut cursor = 0.0;
+        for &child in children {
+            let element = self.nodes[child.0].element;
+            let size = v2(
+                resolve(along(&element), space.x, fill),
+                resolve(across(&element), space.y, space.y),
+            );
+            let alignment = swap(element.anchor.alignment());
+            let position = v2(cursor, (space.y - size.y) * alignment.y);
+            cursor += size.x + spacing;
+
+            self.place(
+                child,
+                Rect::new(inner.position + swap(position), swap(size)),
+            );
+        }
+    }
+}
+
+fn resolve(size: Size, space: f32, fill: f32) -> f32 {
+    match size {
+        Size::Fixed(pixels) => pixels,
+        Size::Percent(fraction) => space * fraction,
+        Size::Fill => fill,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn screen(width: f32, height: f32) -> Rect {
+        Rect::new(Vec2f::ZERO, v2(width, height))
+    }
+
+    #[test]
+    fn anchors_follow_screen_size() {
+        let mut layout = Layout::new();
+        let minimap = layout.add(
+            layout.root(),
+            Element::new(Size::Fixed(100.0), Size::Fixed(100.0))
+                .with_anchor(Anchor::TopRight)
+                .with_offset(v2(-10.0, 10.0)),
+        );
+        let bar = layout.add(
+            layout.root(),
+            Element::new(Size::Percent(0.5), Size::Fixed(20.0)).with_anchor(Anchor::Bottom),
+        );
+
+        layout.update(screen(800.0, 600.0));
+        assert_eq!(
+            layout.rect(minimap),
+            Rect::new(v2(690.0, 10.0), v2(100.0, 100.0))
+        );
+        assert_eq!(
+            layout.rect(bar),
+            Rect::new(v2(200.0, 580.0), v2(400.0, 20.0))
+        );
+
+        layout.update(screen(1000.0, 500.0));
+        assert_eq!(layout.rect(minimap).position, v2(890.0, 10.0));
+        assert_eq!(
+            layout.rect(bar),
+            Rect::new(v2(250.0, 480.0), v2(500.0, 20.0))
+        );
+    }
+
+    #[test]
+    fn children_of_root_stay_in_safe_area() {
+        let mut layout = Layout::new();
+        let background = layout.add(
+            layout.root(),
+            Element::new(Size::Fill, Size::Fill).with_full_screen(true),
+        );
+        let score = layout.add(
+            layout.root(),
+            Element::new(Size::Fixed(50.0), Size::Fixed(10.0)).with_anchor(Anchor::TopRight),
+        );
+
+        let screen = screen(800.0, 600.0);
+        layout.update_with_safe_area(screen, Insets::new(0.0, 40.0, 20.0, 0.0).shrink(screen));
+        assert_eq!(layout.rect(background), screen);
+        assert_eq!(
+            layout.rect(score),
+            Rect::new(v2(730.0, 40.0), v2(50.0, 10.0))
+        );
+    }
+
+    #[test]
+    fn stack_shares_remaining_space_between_fills() {
+        let mut layout = Layout::new();
+        let menu = layout.add(
+            layout.root(),
+            Element::new(Size::Fixed(200.0), Size::Fixed(110.0))
+                .with_padding(5.0)
+                .with_stack(Direction::Vertical, 10.0),
+        );
+        let title = layout.add(
+            menu,
+            Element::new(Size::Fixed(100.0), Size::Fixed(20.0)).with_anchor(Anchor::Center),
+        );
+        let play = layout.add(menu, Element::new(Size::Fill, Size::Fill));
+        let quit = layout.add(menu, Element::new(Size::Fill, Size::Fill));
+
+        layout.update(screen(800.0, 600.0));
+        assert_eq!(
+            layout.rect(title),
+            Rect::new(v2(50.0, 5.0), v2(100.0, 20.0))
+        );
+        assert_eq!(layout.rect(play), Rect::new(v2(5.0, 35.0), v2(190.0, 30.0)));
+        assert_eq!(layout.rect(quit), Rect::new(v2(5.0, 75.0), v2(190.0, 30.0)));
+    }
+}
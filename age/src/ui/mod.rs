@@ -0,0 +1,5 @@
+//! Building blocks for game UI that are independent of how it is drawn.
+
+pub use layout::*;
+
+mod layout;
@@ -1,6 +1,6 @@
 use std::process::ExitCode;
 
-use age::{Color, Engine, Error, Game, Sprite};
+use age::{math::v2, Color, Engine, Error, Game, Sprite};
 
 struct Sandbox {
     sprite: Sprite,
@@ -16,7 +16,10 @@ impl Game for Sandbox {
 
     fn on_update(&mut self, age: &mut Engine) {
         age.graphics.clear(Color::RED);
-        age.graphics.draw_sprite(&self.sprite);
+        age.draw(&self.sprite)
+            .at(v2(100.0, 100.0))
+            .origin_center()
+            .submit();
     }
 }
 